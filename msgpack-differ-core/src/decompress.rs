@@ -0,0 +1,189 @@
+use std::io::Read;
+
+use crate::loader::{LoadError, LoadProgress};
+
+/// Compression formats that are recognized by their magic bytes and decompressed before parsing.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Lz4,
+}
+
+impl std::fmt::Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Lz4 => "lz4",
+        })
+    }
+}
+
+impl Compression {
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else if data.starts_with(&[0x04, 0x22, 0x4d, 0x18]) {
+            Some(Compression::Lz4)
+        } else {
+            None
+        }
+    }
+
+    /// Decompresses `data`, failing once that comes to more than `limit` bytes or `progress` is
+    /// cancelled.
+    pub fn decompress(
+        self,
+        data: &[u8],
+        limit: u64,
+        progress: &LoadProgress,
+    ) -> Result<Vec<u8>, LoadError> {
+        let decoder: Box<dyn Read + '_> = match self {
+            Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(data)),
+            Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(data)?),
+            Compression::Lz4 => Box::new(lz4_flex::frame::FrameDecoder::new(data)),
+        };
+        // one byte more than the limit is enough to tell that it's too much
+        let mut decoder = decoder.take(limit.saturating_add(1));
+        let mut decompressed = Vec::new();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            progress.check_cancelled()?;
+            let read = match decoder.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
+            decompressed.extend_from_slice(&buffer[..read]);
+            if decompressed.len() as u64 > limit {
+                return Err(LoadError::TooLarge {
+                    what: "decompressed data",
+                    limit,
+                });
+            }
+            progress.set_bytes_decompressed(decompressed.len() as u64);
+        }
+        Ok(decompressed)
+    }
+}
+
+/// Decompresses `data` if it looks compressed, or returns `None` if it doesn't; see
+/// [`Compression::decompress`].
+///
+/// The magic bytes of these formats are also valid msgpack, so data that fails to decompress is
+/// assumed to be uncompressed msgpack after all.
+pub fn maybe_decompress(
+    data: &[u8],
+    limit: u64,
+    progress: &LoadProgress,
+) -> Result<Option<(Vec<u8>, Compression)>, LoadError> {
+    let Some(compression) = Compression::detect(data) else {
+        return Ok(None);
+    };
+    match compression.decompress(data, limit, progress) {
+        Ok(decompressed) => Ok(Some((decompressed, compression))),
+        Err(LoadError::Io(err)) => {
+            log::info!("Data looked {compression}-compressed but failed to decompress: {err}");
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Names the format of `data` if it looks like one that is known not to be msgpack, such as an archive
+/// or compression that isn't undone, e.g. `xz compression`.
+pub fn unsupported_format(data: &[u8]) -> Option<&'static str> {
+    const FORMATS: [(&[u8], &str); 4] = [
+        (b"BZh", "bzip2 compression"),
+        (&[0xfd, b'7', b'z', b'X', b'Z', 0x00], "xz compression"),
+        (b"PK\x03\x04", "a zip archive"),
+        (&[b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c], "a 7z archive"),
+    ];
+    FORMATS
+        .iter()
+        .find(|(magic, _)| data.starts_with(magic))
+        .map(|&(_, format)| format)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn compress(compression: Compression, data: &[u8]) -> Vec<u8> {
+        match compression {
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Zstd => zstd::encode_all(data, 0).unwrap(),
+            Compression::Lz4 => {
+                let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            }
+        }
+    }
+
+    const ALL: [Compression; 3] = [Compression::Gzip, Compression::Zstd, Compression::Lz4];
+
+    #[test]
+    fn decompresses_up_to_the_limit() {
+        let data = vec![0x90; 100_000];
+        for compression in ALL {
+            let compressed = compress(compression, &data);
+            let progress = LoadProgress::default();
+            let (decompressed, detected) = maybe_decompress(&compressed, 100_000, &progress)
+                .unwrap()
+                .unwrap();
+            assert_eq!(detected, compression);
+            assert_eq!(decompressed, data);
+            assert_eq!(progress.bytes_decompressed(), 100_000);
+        }
+    }
+
+    #[test]
+    fn too_much_is_too_large() {
+        let bomb = vec![0; 1024 * 1024];
+        for compression in ALL {
+            let compressed = compress(compression, &bomb);
+            let err = maybe_decompress(&compressed, 1000, &LoadProgress::default()).unwrap_err();
+            assert!(
+                matches!(err, LoadError::TooLarge { limit: 1000, .. }),
+                "{compression}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn cancelling_stops_decompressing() {
+        let progress = LoadProgress::default();
+        progress.cancel();
+        for compression in ALL {
+            let compressed = compress(compression, b"data");
+            let err = maybe_decompress(&compressed, 1000, &progress).unwrap_err();
+            assert!(matches!(err, LoadError::Cancelled), "{compression}: {err}");
+        }
+    }
+
+    #[test]
+    fn what_fails_to_decompress_is_left_as_it_is() {
+        let mut compressed = compress(Compression::Gzip, b"data");
+        compressed.truncate(12);
+        let progress = LoadProgress::default();
+        assert!(maybe_decompress(&compressed, 1000, &progress)
+            .unwrap()
+            .is_none());
+        // a positive fixint that isn't compressed at all
+        assert!(maybe_decompress(&[0x01], 1000, &progress)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::ext::ExtRegistry;
+use crate::path::{PathSegment, ValuePath};
+use crate::value::{child_of, same_value, segment_of, HashableValue};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DiffStatus {
+    Unchanged,
+    /// Only present in B.
+    Added,
+    /// Only present in A.
+    Removed,
+    /// Present in both but with a different value (or a different type).
+    Changed,
+    /// A container present in both whose children differ.
+    ContainsChanges,
+    /// At a path that was excluded from the comparison.
+    Ignored,
+}
+
+impl DiffStatus {
+    pub fn is_difference(self) -> bool {
+        matches!(self, Self::Added | Self::Removed | Self::Changed)
+    }
+}
+
+/// Structural diff of two values, mirroring the shape of the union of both documents.
+///
+/// Nodes don't hold on to the values they compare; instead `a_index`/`b_index` give the position of the
+/// node within its parent array or map on each side (see [`child_of`]), so the diff can be walked in
+/// lockstep with the documents it was computed from. For the root node they are `Some(0)` for each
+/// side that is present.
+#[derive(Debug, Clone)]
+pub struct DiffNode {
+    pub status: DiffStatus,
+    pub a_index: Option<usize>,
+    pub b_index: Option<usize>,
+    pub children: Vec<DiffNode>,
+    /// Number of added, removed or changed nodes in this subtree (including this node itself).
+    pub difference_count: usize,
+}
+
+impl DiffNode {
+    fn leaf(status: DiffStatus, a_index: Option<usize>, b_index: Option<usize>) -> Self {
+        Self {
+            status,
+            a_index,
+            b_index,
+            children: Vec::new(),
+            difference_count: usize::from(status.is_difference()),
+        }
+    }
+}
+
+/// How [`diff_values_with`] compares values.
+#[derive(Debug, Clone, Copy)]
+pub struct DiffOptions<'a> {
+    /// Paths whose values are [`DiffStatus::Ignored`] rather than compared.
+    pub ignored: &'a [ValuePath],
+    /// How to decode extension values, which are compared by what they decode to.
+    pub ext_types: &'a ExtRegistry,
+}
+
+impl Default for DiffOptions<'_> {
+    fn default() -> Self {
+        Self {
+            ignored: &[],
+            ext_types: ExtRegistry::builtin(),
+        }
+    }
+}
+
+/// Diffs `a` and `b` with the default [`DiffOptions`]: ignoring nothing, and decoding only the
+/// built-in extension types.
+pub fn diff_values(a: Option<&rmpv::Value>, b: Option<&rmpv::Value>) -> DiffNode {
+    diff_values_with(a, b, &DiffOptions::default())
+}
+
+pub fn diff_values_with(
+    a: Option<&rmpv::Value>,
+    b: Option<&rmpv::Value>,
+    options: &DiffOptions,
+) -> DiffNode {
+    let ignored = options
+        .ignored
+        .iter()
+        .map(ValuePath::segments)
+        .collect::<Vec<_>>();
+    let ext_types = options.ext_types;
+    diff_node(a, b, a.map(|_| 0), b.map(|_| 0), &ignored, ext_types)
+}
+
+/// What's left of the `ignored` paths below the child at `segment`.
+fn ignored_below<'p>(
+    ignored: &[&'p [PathSegment]],
+    segment: &PathSegment,
+) -> Vec<&'p [PathSegment]> {
+    ignored
+        .iter()
+        .filter_map(|path| match path.split_first() {
+            Some((first, rest)) if first == segment => Some(rest),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `ignored` holds the rest of each ignored path that leads through this node, relative to it.
+fn diff_node(
+    a: Option<&rmpv::Value>,
+    b: Option<&rmpv::Value>,
+    a_index: Option<usize>,
+    b_index: Option<usize>,
+    ignored: &[&[PathSegment]],
+    ext_types: &ExtRegistry,
+) -> DiffNode {
+    if ignored.iter().any(|path| path.is_empty()) {
+        return DiffNode::leaf(DiffStatus::Ignored, a_index, b_index);
+    }
+    let (a, b) = match (a, b) {
+        (Some(a), Some(b)) => (a, b),
+        (Some(_), None) => return DiffNode::leaf(DiffStatus::Removed, a_index, None),
+        (None, Some(_)) => return DiffNode::leaf(DiffStatus::Added, None, b_index),
+        (None, None) => return DiffNode::leaf(DiffStatus::Unchanged, None, None),
+    };
+
+    let children = match (a, b) {
+        (rmpv::Value::Array(a_items), rmpv::Value::Array(b_items)) => {
+            (0..a_items.len().max(b_items.len()))
+                .map(|i| {
+                    let a_index = (i < a_items.len()).then_some(i);
+                    let b_index = (i < b_items.len()).then_some(i);
+                    let ignored = ignored_below(ignored, &PathSegment::Index(i));
+                    diff_node(
+                        a_items.get(i),
+                        b_items.get(i),
+                        a_index,
+                        b_index,
+                        &ignored,
+                        ext_types,
+                    )
+                })
+                .collect::<Vec<_>>()
+        }
+        (rmpv::Value::Map(a_entries), rmpv::Value::Map(b_entries)) => {
+            // duplicate keys are legal in msgpack, so pair up the nth occurrence in A with the nth in B
+            let mut b_by_key: HashMap<HashableValue, VecDeque<usize>> = HashMap::new();
+            for (i, (key, _)) in b_entries.iter().enumerate() {
+                b_by_key.entry(HashableValue(key)).or_default().push_back(i);
+            }
+            let mut matched_b = vec![false; b_entries.len()];
+            let mut children = Vec::with_capacity(a_entries.len().max(b_entries.len()));
+            for (a_i, (key, a_value)) in a_entries.iter().enumerate() {
+                let b_i = b_by_key
+                    .get_mut(&HashableValue(key))
+                    .and_then(|indexes| indexes.pop_front());
+                if let Some(b_i) = b_i {
+                    matched_b[b_i] = true;
+                }
+                let ignored = ignored_below(ignored, &PathSegment::Key(key.clone()));
+                children.push(diff_node(
+                    Some(a_value),
+                    b_i.map(|b_i| &b_entries[b_i].1),
+                    Some(a_i),
+                    b_i,
+                    &ignored,
+                    ext_types,
+                ));
+            }
+            for (b_i, (key, b_value)) in b_entries.iter().enumerate() {
+                if !matched_b[b_i] {
+                    let ignored = ignored_below(ignored, &PathSegment::Key(key.clone()));
+                    children.push(diff_node(
+                        None,
+                        Some(b_value),
+                        None,
+                        Some(b_i),
+                        &ignored,
+                        ext_types,
+                    ));
+                }
+            }
+            children
+        }
+        _ => {
+            let status = if scalars_equal(a, b, ext_types) {
+                DiffStatus::Unchanged
+            } else {
+                DiffStatus::Changed
+            };
+            return DiffNode::leaf(status, a_index, b_index);
+        }
+    };
+
+    let difference_count = children.iter().map(|c| c.difference_count).sum();
+    DiffNode {
+        status: if difference_count == 0 {
+            DiffStatus::Unchanged
+        } else {
+            DiffStatus::ContainsChanges
+        },
+        a_index,
+        b_index,
+        children,
+        difference_count,
+    }
+}
+
+/// Compares values that aren't both arrays or both maps, extension values by what they decode to
+/// (see [`ExtRegistry::decode`]) as that can be encoded in several ways, e.g. timestamps. Strings are
+/// compared by their bytes, as they needn't be valid UTF-8, and floats by their bits, so that a NaN is
+/// unchanged.
+fn scalars_equal(a: &rmpv::Value, b: &rmpv::Value, ext_types: &ExtRegistry) -> bool {
+    if let (rmpv::Value::String(a), rmpv::Value::String(b)) = (a, b) {
+        return a.as_bytes() == b.as_bytes();
+    }
+    match (ext_types.decode(a), ext_types.decode(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => same_value(a, b),
+    }
+}
+
+/// A single added, removed or changed value.
+#[derive(Debug, Clone)]
+pub struct Difference<'a> {
+    pub path: ValuePath,
+    pub status: DiffStatus,
+    pub a: Option<&'a rmpv::Value>,
+    pub b: Option<&'a rmpv::Value>,
+}
+
+/// Flattens a diff into the list of its differences, in document order.
+pub fn differences<'a>(
+    node: &'a DiffNode,
+    a: Option<&'a rmpv::Value>,
+    b: Option<&'a rmpv::Value>,
+) -> Vec<Difference<'a>> {
+    let mut out = Vec::with_capacity(node.difference_count);
+    let mut path = ValuePath::root();
+    collect_differences(node, a, b, &mut path, &mut out);
+    out
+}
+
+fn collect_differences<'a>(
+    node: &'a DiffNode,
+    a: Option<&'a rmpv::Value>,
+    b: Option<&'a rmpv::Value>,
+    path: &mut ValuePath,
+    out: &mut Vec<Difference<'a>>,
+) {
+    if node.difference_count == 0 {
+        return;
+    }
+    if node.status.is_difference() {
+        out.push(Difference {
+            path: path.clone(),
+            status: node.status,
+            a,
+            b,
+        });
+        return;
+    }
+    for (segment, child) in children(node, a, b) {
+        path.push(segment);
+        collect_differences(child.node, child.a, child.b, path, out);
+        path.pop();
+    }
+}
+
+/// A node of a diff along with the values it compares.
+#[derive(Debug, Copy, Clone)]
+pub struct DiffEntry<'a> {
+    pub node: &'a DiffNode,
+    pub a: Option<&'a rmpv::Value>,
+    pub b: Option<&'a rmpv::Value>,
+}
+
+impl<'a> DiffEntry<'a> {
+    /// The `index`th child of this node, with the path segment leading to it.
+    pub fn child(&self, index: usize) -> (PathSegment, DiffEntry<'a>) {
+        let child = &self.node.children[index];
+        let child_a = self.a.zip(child.a_index).and_then(|(a, i)| child_of(a, i));
+        let child_b = self.b.zip(child.b_index).and_then(|(b, i)| child_of(b, i));
+        let segment = self
+            .a
+            .zip(child.a_index)
+            .and_then(|(a, i)| segment_of(a, i))
+            .or_else(|| {
+                self.b
+                    .zip(child.b_index)
+                    .and_then(|(b, i)| segment_of(b, i))
+            })
+            .expect("diff child should exist on at least one side");
+        let entry = DiffEntry {
+            node: child,
+            a: child_a,
+            b: child_b,
+        };
+        (segment, entry)
+    }
+}
+
+/// The children of `node`, each with the path segment leading to it.
+pub fn children<'a>(
+    node: &'a DiffNode,
+    a: Option<&'a rmpv::Value>,
+    b: Option<&'a rmpv::Value>,
+) -> impl Iterator<Item = (PathSegment, DiffEntry<'a>)> {
+    let entry = DiffEntry { node, a, b };
+    (0..node.children.len()).map(move |i| entry.child(i))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nans_are_unchanged() {
+        let nan_key = rmpv::Value::F64(f64::NAN);
+        let doc = rmpv::Value::Map(vec![
+            ("f32".into(), rmpv::Value::F32(f32::NAN)),
+            ("f64".into(), rmpv::Value::F64(f64::NAN)),
+            (
+                "items".into(),
+                rmpv::Value::Array(vec![1.into(), rmpv::Value::F64(f64::NAN), 2.into()]),
+            ),
+            (nan_key, "keyed by NaN".into()),
+        ]);
+        let root = diff_values(Some(&doc), Some(&doc.clone()));
+        assert_eq!(root.difference_count, 0);
+        assert_eq!(root.status, DiffStatus::Unchanged);
+    }
+
+    #[test]
+    fn extension_values_are_compared_with_the_registry_given() {
+        let a = rmpv::Value::Ext(5, b"12.50".to_vec());
+        let b = rmpv::Value::Ext(5, b"+12.5".to_vec());
+        let decimals = ExtRegistry::with_mappings([crate::ext::ExtMapping {
+            type_id: 5,
+            name: String::new(),
+            decoder: crate::ext::ExtDecoder::Decimal,
+        }]);
+        let options = DiffOptions {
+            ext_types: &decimals,
+            ..Default::default()
+        };
+        let root = diff_values_with(Some(&a), Some(&b), &options);
+        assert_eq!(root.status, DiffStatus::Unchanged);
+        assert_eq!(diff_values(Some(&a), Some(&b)).status, DiffStatus::Changed);
+    }
+
+    #[test]
+    fn extension_values_of_different_types_are_different() {
+        let text = |type_id| crate::ext::ExtMapping {
+            type_id,
+            name: "Text".to_owned(),
+            decoder: crate::ext::ExtDecoder::Text,
+        };
+        let ext_types = ExtRegistry::with_mappings([text(1), text(2)]);
+        let options = DiffOptions {
+            ext_types: &ext_types,
+            ..Default::default()
+        };
+        let a = rmpv::Value::Ext(1, b"same".to_vec());
+        let b = rmpv::Value::Ext(2, b"same".to_vec());
+        let root = diff_values_with(Some(&a), Some(&b), &options);
+        assert_eq!(root.status, DiffStatus::Changed);
+        let root = diff_values_with(Some(&a), Some(&a.clone()), &options);
+        assert_eq!(root.status, DiffStatus::Unchanged);
+    }
+}
@@ -0,0 +1,584 @@
+use crate::payload::{decode_hex, encode_hex};
+use crate::value::{child_of, value_at_mut};
+
+/// A change made by hand to the document of a file. Entries are at the positions of the children
+/// leading to them, see [`crate::value::child_of`].
+#[derive(Debug, Clone)]
+pub enum Edit {
+    /// Replaces the value of the entry.
+    Set {
+        indexes: Vec<usize>,
+        value: rmpv::Value,
+    },
+    /// Adds an entry to an array or map, which ends up at `indexes`; entries of maps need a key.
+    Insert {
+        indexes: Vec<usize>,
+        key: Option<rmpv::Value>,
+        value: rmpv::Value,
+    },
+    Remove {
+        indexes: Vec<usize>,
+    },
+    /// Adds a copy of the entry right after it, under `key` if it's given and it's a map entry.
+    Duplicate {
+        indexes: Vec<usize>,
+        key: Option<rmpv::Value>,
+    },
+    /// Moves the entry within what holds it, to be its `to`th entry.
+    Move {
+        indexes: Vec<usize>,
+        to: usize,
+    },
+}
+
+impl Edit {
+    /// The positions of the entry the edit is made to, or for an insert, of the entry added.
+    pub fn indexes(&self) -> &[usize] {
+        match self {
+            Edit::Set { indexes, .. }
+            | Edit::Insert { indexes, .. }
+            | Edit::Remove { indexes }
+            | Edit::Duplicate { indexes, .. }
+            | Edit::Move { indexes, .. } => indexes,
+        }
+    }
+
+    pub fn apply(&self, document: &mut rmpv::Value) -> Result<(), String> {
+        const GONE: &str = "the value edited is no longer there";
+        let (&position, parent) = match self {
+            Edit::Set { indexes, value } => {
+                *value_at_mut(document, indexes).ok_or(GONE)? = value.clone();
+                return Ok(());
+            }
+            _ => self
+                .indexes()
+                .split_last()
+                .ok_or("the whole document can't be added or removed")?,
+        };
+        let container = value_at_mut(document, parent).ok_or(GONE)?;
+        match (self, container) {
+            (Edit::Insert { value, .. }, rmpv::Value::Array(items)) if position <= items.len() => {
+                items.insert(position, value.clone());
+            }
+            (Edit::Insert { key, value, .. }, rmpv::Value::Map(entries))
+                if position <= entries.len() =>
+            {
+                let key = key.clone().ok_or("entries of maps need a key")?;
+                entries.insert(position, (key, value.clone()));
+            }
+            (Edit::Remove { .. }, rmpv::Value::Array(items)) if position < items.len() => {
+                items.remove(position);
+            }
+            (Edit::Remove { .. }, rmpv::Value::Map(entries)) if position < entries.len() => {
+                entries.remove(position);
+            }
+            (Edit::Duplicate { .. }, rmpv::Value::Array(items)) if position < items.len() => {
+                items.insert(position + 1, items[position].clone());
+            }
+            (Edit::Duplicate { key, .. }, rmpv::Value::Map(entries))
+                if position < entries.len() =>
+            {
+                let mut entry = entries[position].clone();
+                if let Some(key) = key {
+                    entry.0 = key.clone();
+                }
+                entries.insert(position + 1, entry);
+            }
+            (Edit::Move { to, .. }, rmpv::Value::Array(items))
+                if position < items.len() && *to < items.len() =>
+            {
+                let item = items.remove(position);
+                items.insert(*to, item);
+            }
+            (Edit::Move { to, .. }, rmpv::Value::Map(entries))
+                if position < entries.len() && *to < entries.len() =>
+            {
+                let entry = entries.remove(position);
+                entries.insert(*to, entry);
+            }
+            (_, rmpv::Value::Array(_) | rmpv::Value::Map(_)) => return Err(GONE.to_owned()),
+            _ => return Err("only arrays and maps have entries to add, remove or move".to_owned()),
+        }
+        Ok(())
+    }
+
+    /// The same edit made to the value at `root` within the document, rather than to the document
+    /// as a whole.
+    pub fn within(mut self, root: &[usize]) -> Self {
+        let (Edit::Set { indexes, .. }
+        | Edit::Insert { indexes, .. }
+        | Edit::Remove { indexes }
+        | Edit::Duplicate { indexes, .. }
+        | Edit::Move { indexes, .. }) = &mut self;
+        indexes.splice(..0, root.iter().copied());
+        self
+    }
+
+    /// The edit undoing this one, given `document` as it was before this one was made to it, or
+    /// `None` if this one can't be made to it.
+    pub fn inverse(&self, document: &rmpv::Value) -> Option<Edit> {
+        let value_at = |indexes: &[usize]| {
+            indexes
+                .iter()
+                .try_fold(document, |value, &index| child_of(value, index))
+        };
+        let indexes = self.indexes().to_vec();
+        Some(match self {
+            Edit::Set { .. } => Edit::Set {
+                value: value_at(&indexes)?.clone(),
+                indexes,
+            },
+            Edit::Insert { .. } => Edit::Remove { indexes },
+            Edit::Remove { .. } => {
+                let (&position, parent) = indexes.split_last()?;
+                let (key, value) = match value_at(parent)? {
+                    rmpv::Value::Array(items) => (None, items.get(position)?.clone()),
+                    rmpv::Value::Map(entries) => {
+                        let (key, value) = entries.get(position)?;
+                        (Some(key.clone()), value.clone())
+                    }
+                    _ => return None,
+                };
+                Edit::Insert {
+                    indexes,
+                    key,
+                    value,
+                }
+            }
+            Edit::Duplicate { .. } => {
+                let mut copy = indexes;
+                *copy.last_mut()? += 1;
+                Edit::Remove { indexes: copy }
+            }
+            Edit::Move { to, .. } => {
+                let (&from, parent) = indexes.split_last()?;
+                Edit::Move {
+                    indexes: [parent, &[*to]].concat(),
+                    to: from,
+                }
+            }
+        })
+    }
+
+    /// Where whatever was at `indexes` is once the edit is made: nowhere if it was removed, and in
+    /// two places if it was duplicated.
+    pub fn moved(&self, indexes: &[usize]) -> Vec<Vec<usize>> {
+        let (at, shift) = match self {
+            Edit::Set { .. } => return vec![indexes.to_vec()],
+            Edit::Insert { indexes: at, .. } => (at, 1),
+            Edit::Remove { indexes: at } if indexes.starts_with(at) => return Vec::new(),
+            Edit::Remove { indexes: at } => (at, -1),
+            Edit::Duplicate { indexes: at, .. } if indexes.starts_with(at) => {
+                let mut copy = indexes.to_vec();
+                copy[at.len() - 1] += 1;
+                return vec![indexes.to_vec(), copy];
+            }
+            Edit::Duplicate { indexes: at, .. } => (at, 1),
+            Edit::Move { indexes: at, to } => {
+                let Some((_, parent)) = at.split_last() else {
+                    return vec![indexes.to_vec()];
+                };
+                if indexes.starts_with(at) {
+                    let mut moved = indexes.to_vec();
+                    moved[parent.len()] = *to;
+                    return vec![moved];
+                }
+                // the same as taking it out and putting it back in where it goes
+                let removed = Edit::Remove {
+                    indexes: at.clone(),
+                };
+                let inserted = Edit::Insert {
+                    indexes: [parent, &[*to]].concat(),
+                    key: None,
+                    value: rmpv::Value::Nil,
+                };
+                return removed
+                    .moved(indexes)
+                    .iter()
+                    .flat_map(|indexes| inserted.moved(indexes))
+                    .collect();
+            }
+        };
+        let Some((&position, parent)) = at.split_last() else {
+            return vec![indexes.to_vec()];
+        };
+        // the entries after the one added or removed, and what's in them, move along with it
+        let first_moved = match self {
+            Edit::Insert { .. } => position,
+            _ => position + 1,
+        };
+        let mut moved = indexes.to_vec();
+        if indexes.len() > parent.len()
+            && indexes.starts_with(parent)
+            && indexes[parent.len()] >= first_moved
+        {
+            moved[parent.len()] = moved[parent.len()].saturating_add_signed(shift);
+        }
+        vec![moved]
+    }
+}
+
+/// The types a value can be edited into, each written as text in its own way.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ValueKind {
+    Nil,
+    Boolean,
+    Integer,
+    F32,
+    F64,
+    String,
+    /// Written as hex.
+    Binary,
+    /// Written as its type, a colon and its data in hex, e.g. `-1:00000000`.
+    Ext,
+    /// An empty array, to add entries to afterwards.
+    Array,
+    Map,
+}
+
+impl ValueKind {
+    pub const ALL: [ValueKind; 10] = [
+        ValueKind::Nil,
+        ValueKind::Boolean,
+        ValueKind::Integer,
+        ValueKind::F32,
+        ValueKind::F64,
+        ValueKind::String,
+        ValueKind::Binary,
+        ValueKind::Ext,
+        ValueKind::Array,
+        ValueKind::Map,
+    ];
+
+    /// The kind of `value`, or `None` for arrays and maps, which aren't edited as a whole.
+    pub fn of(value: &rmpv::Value) -> Option<Self> {
+        Some(match value {
+            rmpv::Value::Nil => ValueKind::Nil,
+            rmpv::Value::Boolean(_) => ValueKind::Boolean,
+            rmpv::Value::Integer(_) => ValueKind::Integer,
+            rmpv::Value::F32(_) => ValueKind::F32,
+            rmpv::Value::F64(_) => ValueKind::F64,
+            rmpv::Value::String(_) => ValueKind::String,
+            rmpv::Value::Binary(_) => ValueKind::Binary,
+            rmpv::Value::Ext(_, _) => ValueKind::Ext,
+            rmpv::Value::Array(_) | rmpv::Value::Map(_) => return None,
+        })
+    }
+
+    /// Whether values of this kind are read from text, which all but some with only one value are.
+    pub fn takes_text(self) -> bool {
+        !matches!(self, ValueKind::Nil | ValueKind::Array | ValueKind::Map)
+    }
+
+    /// Reads `text` as a value of this kind, saying what's wrong with it if it can't be.
+    pub fn parse(self, text: &str) -> Result<rmpv::Value, String> {
+        let trimmed = text.trim();
+        match self {
+            ValueKind::Nil => Ok(rmpv::Value::Nil),
+            ValueKind::Array => Ok(rmpv::Value::Array(Vec::new())),
+            ValueKind::Map => Ok(rmpv::Value::Map(Vec::new())),
+            ValueKind::Boolean => match trimmed {
+                "true" => Ok(rmpv::Value::Boolean(true)),
+                "false" => Ok(rmpv::Value::Boolean(false)),
+                _ => Err("expected true or false".to_owned()),
+            },
+            ValueKind::Integer => parse_integer(trimmed).map(rmpv::Value::Integer),
+            ValueKind::F32 => trimmed
+                .parse::<f32>()
+                .map(rmpv::Value::F32)
+                .map_err(|_| "expected a number, e.g. 1.5, 1e-3, inf or NaN".to_owned()),
+            ValueKind::F64 => trimmed
+                .parse::<f64>()
+                .map(rmpv::Value::F64)
+                .map_err(|_| "expected a number, e.g. 1.5, 1e-3, inf or NaN".to_owned()),
+            // strings are taken as they're typed, spaces and all
+            ValueKind::String => Ok(rmpv::Value::from(text)),
+            ValueKind::Binary => parse_bytes(trimmed).map(rmpv::Value::Binary),
+            ValueKind::Ext => {
+                let (type_id, data) = trimmed
+                    .split_once(':')
+                    .ok_or("expected the type, a colon and the data in hex, e.g. 1:0a0b")?;
+                let type_id = type_id
+                    .trim()
+                    .parse::<i8>()
+                    .map_err(|_| "the type must be from -128 to 127")?;
+                Ok(rmpv::Value::Ext(type_id, parse_bytes(data.trim())?))
+            }
+        }
+    }
+
+    /// `value` written as text the way [`Self::parse`] reads it back.
+    pub fn text_of(value: &rmpv::Value) -> String {
+        match value {
+            rmpv::Value::Nil => String::new(),
+            rmpv::Value::String(text) => String::from_utf8_lossy(text.as_bytes()).into_owned(),
+            rmpv::Value::Binary(bytes) => encode_hex(bytes),
+            rmpv::Value::Ext(type_id, data) => format!("{type_id}:{}", encode_hex(data)),
+            value => value.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ValueKind::Nil => "nil",
+            ValueKind::Boolean => "boolean",
+            ValueKind::Integer => "integer",
+            ValueKind::F32 => "float32",
+            ValueKind::F64 => "float64",
+            ValueKind::String => "string",
+            ValueKind::Binary => "binary",
+            ValueKind::Ext => "extension",
+            ValueKind::Array => "empty array",
+            ValueKind::Map => "empty map",
+        })
+    }
+}
+
+/// Reads an integer written in decimal, or in hex after `0x`, with `_` allowed between digits.
+fn parse_integer(text: &str) -> Result<rmpv::Integer, String> {
+    let digits = text.replace('_', "");
+    let (negative, digits) = match digits.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, digits.as_str()),
+    };
+    let magnitude = match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => digits.parse::<u64>(),
+    }
+    .map_err(|_| "expected a whole number, e.g. 42, -7 or 0xff".to_owned())?;
+    if !negative {
+        return Ok(magnitude.into());
+    }
+    0i64.checked_sub_unsigned(magnitude)
+        .map(rmpv::Integer::from)
+        .ok_or_else(|| format!("{text} is less than the least integer, {}", i64::MIN))
+}
+
+fn parse_bytes(text: &str) -> Result<Vec<u8>, String> {
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+    decode_hex(text).ok_or_else(|| "expected an even number of hex digits".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A map with a duplicate key, holding an array and another map.
+    fn document() -> rmpv::Value {
+        rmpv::Value::Map(vec![
+            ("k".into(), 1.into()),
+            (
+                "items".into(),
+                rmpv::Value::Array(vec![10.into(), 20.into(), 30.into()]),
+            ),
+            ("k".into(), 2.into()),
+            (
+                "nested".into(),
+                rmpv::Value::Map(vec![("a".into(), true.into())]),
+            ),
+        ])
+    }
+
+    /// Makes `edit` to `before`, checks that its inverse makes it `before` again, and returns what
+    /// the edit made of it.
+    fn round_trip(before: &rmpv::Value, edit: Edit) -> rmpv::Value {
+        let inverse = edit.inverse(before).expect("the edit can be made");
+        let mut after = before.clone();
+        edit.apply(&mut after).unwrap();
+        let mut undone = after.clone();
+        inverse.apply(&mut undone).unwrap();
+        assert_eq!(&undone, before, "{inverse:?} didn't undo {edit:?}");
+        after
+    }
+
+    fn entries(value: &rmpv::Value) -> &[(rmpv::Value, rmpv::Value)] {
+        value.as_map().unwrap()
+    }
+
+    #[test]
+    fn set_round_trips() {
+        let doc = document();
+        let after = round_trip(
+            &doc,
+            Edit::Set {
+                indexes: vec![1, 2],
+                value: "thirty".into(),
+            },
+        );
+        assert_eq!(entries(&after)[1].1[2], rmpv::Value::from("thirty"));
+        // the second of the duplicate keys
+        let after = round_trip(
+            &doc,
+            Edit::Set {
+                indexes: vec![2],
+                value: 3.into(),
+            },
+        );
+        assert_eq!(entries(&after)[0].1, rmpv::Value::from(1));
+        assert_eq!(entries(&after)[2].1, rmpv::Value::from(3));
+    }
+
+    #[test]
+    fn insert_and_remove_round_trip() {
+        let doc = document();
+        let after = round_trip(
+            &doc,
+            Edit::Insert {
+                indexes: vec![1, 3],
+                key: None,
+                value: 40.into(),
+            },
+        );
+        assert_eq!(entries(&after)[1].1.as_array().unwrap().len(), 4);
+        // yet another of a duplicate key
+        let after = round_trip(
+            &doc,
+            Edit::Insert {
+                indexes: vec![1],
+                key: Some("k".into()),
+                value: 0.into(),
+            },
+        );
+        let keys = entries(&after).iter().map(|(key, _)| key.as_str().unwrap());
+        assert_eq!(keys.collect::<Vec<_>>(), ["k", "k", "items", "k", "nested"]);
+
+        let after = round_trip(
+            &doc,
+            Edit::Remove {
+                indexes: vec![1, 0],
+            },
+        );
+        assert_eq!(
+            entries(&after)[1].1,
+            rmpv::Value::Array(vec![20.into(), 30.into()])
+        );
+        let after = round_trip(&doc, Edit::Remove { indexes: vec![2] });
+        assert_eq!(entries(&after).len(), 3);
+        assert_eq!(entries(&after)[0], ("k".into(), 1.into()));
+    }
+
+    #[test]
+    fn duplicate_round_trips() {
+        let doc = document();
+        let after = round_trip(
+            &doc,
+            Edit::Duplicate {
+                indexes: vec![1, 1],
+                key: None,
+            },
+        );
+        assert_eq!(
+            entries(&after)[1].1,
+            rmpv::Value::Array(vec![10.into(), 20.into(), 20.into(), 30.into()])
+        );
+        // under a key of its own
+        let after = round_trip(
+            &doc,
+            Edit::Duplicate {
+                indexes: vec![3],
+                key: Some("copy".into()),
+            },
+        );
+        assert_eq!(entries(&after)[4].0, rmpv::Value::from("copy"));
+        assert_eq!(entries(&after)[4].1, entries(&doc)[3].1);
+    }
+
+    #[test]
+    fn move_round_trips() {
+        let doc = document();
+        let after = round_trip(
+            &doc,
+            Edit::Move {
+                indexes: vec![1, 0],
+                to: 2,
+            },
+        );
+        assert_eq!(
+            entries(&after)[1].1,
+            rmpv::Value::Array(vec![20.into(), 30.into(), 10.into()])
+        );
+        let after = round_trip(
+            &doc,
+            Edit::Move {
+                indexes: vec![2],
+                to: 0,
+            },
+        );
+        assert_eq!(entries(&after)[0], ("k".into(), 2.into()));
+        assert_eq!(entries(&after)[1], ("k".into(), 1.into()));
+        round_trip(
+            &doc,
+            Edit::Move {
+                indexes: vec![0],
+                to: 3,
+            },
+        );
+    }
+
+    #[test]
+    fn out_of_range_edits_fail_and_have_no_inverse() {
+        let doc = document();
+        for edit in [
+            Edit::Set {
+                indexes: vec![1, 3],
+                value: 0.into(),
+            },
+            Edit::Insert {
+                indexes: vec![1, 4],
+                key: None,
+                value: 0.into(),
+            },
+            Edit::Insert {
+                indexes: vec![5],
+                key: Some("k".into()),
+                value: 0.into(),
+            },
+            Edit::Remove {
+                indexes: vec![1, 3],
+            },
+            Edit::Remove { indexes: vec![4] },
+            Edit::Duplicate {
+                indexes: vec![4],
+                key: None,
+            },
+            Edit::Move {
+                indexes: vec![1, 0],
+                to: 3,
+            },
+            Edit::Move {
+                indexes: vec![4],
+                to: 0,
+            },
+            Edit::Remove {
+                indexes: vec![0, 0],
+            },
+            Edit::Remove { indexes: vec![] },
+        ] {
+            let mut edited = doc.clone();
+            assert!(edit.apply(&mut edited).is_err(), "{edit:?} was made");
+            assert_eq!(edited, doc, "{edit:?} changed the document");
+        }
+        assert!(Edit::Remove { indexes: vec![4] }.inverse(&doc).is_none());
+        assert!(Edit::Set {
+            indexes: vec![1, 3],
+            value: 0.into(),
+        }
+        .inverse(&doc)
+        .is_none());
+    }
+
+    #[test]
+    fn inserts_into_maps_need_a_key() {
+        let mut doc = document();
+        let edit = Edit::Insert {
+            indexes: vec![0],
+            key: None,
+            value: 0.into(),
+        };
+        assert!(edit.apply(&mut doc).is_err());
+    }
+}
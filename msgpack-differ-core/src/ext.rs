@@ -0,0 +1,232 @@
+use std::path::Path;
+use std::sync::LazyLock;
+
+use jiff::SignedDuration;
+use serde::{Deserialize, Serialize};
+
+/// Extension type of the standard msgpack timestamp.
+pub const TIMESTAMP: i8 = -1;
+
+/// Decodes the data of a timestamp extension value in any of its three formats: 32-bit seconds,
+/// 30-bit nanoseconds with 34-bit seconds, or 32-bit nanoseconds with signed 64-bit seconds.
+///
+/// Returns `None` for data of any other length and for instants out of the range of
+/// [`jiff::Timestamp`].
+pub fn decode_timestamp(data: &[u8]) -> Option<jiff::Timestamp> {
+    let (seconds, nanoseconds) = match data.len() {
+        4 => (i64::from(u32::from_be_bytes(data.try_into().ok()?)), 0),
+        8 => {
+            let packed = u64::from_be_bytes(data.try_into().ok()?);
+            ((packed & 0x3_ffff_ffff) as i64, (packed >> 34) as u32)
+        }
+        12 => (
+            i64::from_be_bytes(data[4..].try_into().ok()?),
+            u32::from_be_bytes(data[..4].try_into().ok()?),
+        ),
+        _ => return None,
+    };
+    if nanoseconds >= 1_000_000_000 {
+        return None;
+    }
+    jiff::Timestamp::new(seconds, nanoseconds as i32).ok()
+}
+
+/// How to turn the data of an extension value into something readable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExtDecoder {
+    /// The standard msgpack timestamp, see [`decode_timestamp`].
+    Timestamp,
+    /// 16 bytes, written in the usual hyphenated form.
+    Uuid,
+    /// A decimal number written out as UTF-8 text, e.g. `-12.50`.
+    Decimal,
+    /// UTF-8 text.
+    Text,
+    /// A big-endian signed integer of 1, 2, 4 or 8 bytes.
+    Integer,
+    /// An integer as for [`ExtDecoder::Integer`] counting nanoseconds.
+    DurationNanos,
+    DurationMicros,
+    DurationMillis,
+    DurationSeconds,
+}
+
+impl ExtDecoder {
+    /// Decodes `data` into text that's the same for values that mean the same thing, e.g. `12.5`
+    /// for both `12.50` and `+12.5` as decimals.
+    fn decode(self, data: &[u8]) -> Option<String> {
+        let integer = || -> Option<i64> {
+            Some(match data.len() {
+                1 => i64::from(data[0] as i8),
+                2 => i64::from(i16::from_be_bytes(data.try_into().ok()?)),
+                4 => i64::from(i32::from_be_bytes(data.try_into().ok()?)),
+                8 => i64::from_be_bytes(data.try_into().ok()?),
+                _ => return None,
+            })
+        };
+        match self {
+            ExtDecoder::Timestamp => decode_timestamp(data).map(|timestamp| timestamp.to_string()),
+            ExtDecoder::Uuid => {
+                if data.len() != 16 {
+                    return None;
+                }
+                let hex = crate::payload::encode_hex(data);
+                Some(format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                ))
+            }
+            ExtDecoder::Decimal => normalize_decimal(std::str::from_utf8(data).ok()?),
+            ExtDecoder::Text => std::str::from_utf8(data).ok().map(str::to_owned),
+            ExtDecoder::Integer => integer().map(|i| i.to_string()),
+            ExtDecoder::DurationNanos => {
+                integer().map(|i| SignedDuration::from_nanos(i).to_string())
+            }
+            ExtDecoder::DurationMicros => {
+                integer().map(|i| SignedDuration::from_micros(i).to_string())
+            }
+            ExtDecoder::DurationMillis => {
+                integer().map(|i| SignedDuration::from_millis(i).to_string())
+            }
+            ExtDecoder::DurationSeconds => {
+                integer().map(|i| SignedDuration::from_secs(i).to_string())
+            }
+        }
+    }
+}
+
+/// Checks that `text` is a decimal number, with an optional sign, fraction and exponent, and writes
+/// it without a `+` sign, leading zeros or trailing zeros in its fraction.
+fn normalize_decimal(text: &str) -> Option<String> {
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (text, None),
+    };
+    let unsigned = mantissa.trim_start_matches(['+', '-']);
+    let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    let exponent_valid = exponent.is_none_or(|e| {
+        let digits = e.strip_prefix(['+', '-']).unwrap_or(e);
+        !digits.is_empty() && is_digits(digits)
+    });
+    if mantissa.len() - unsigned.len() > 1
+        || whole.is_empty() && fraction.is_empty()
+        || !is_digits(whole)
+        || !is_digits(fraction)
+        || !exponent_valid
+    {
+        return None;
+    }
+    let mut normalized = String::new();
+    if mantissa.starts_with('-') {
+        normalized.push('-');
+    }
+    let whole = whole.trim_start_matches('0');
+    normalized.push_str(if whole.is_empty() { "0" } else { whole });
+    let fraction = fraction.trim_end_matches('0');
+    if !fraction.is_empty() {
+        normalized.push('.');
+        normalized.push_str(fraction);
+    }
+    if let Some(exponent) = exponent {
+        normalized.push('e');
+        normalized.push_str(exponent.trim_start_matches('+'));
+    }
+    Some(normalized)
+}
+
+/// Which decoder to use for extension values of a type, as given in an extension types file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtMapping {
+    #[serde(rename = "type")]
+    pub type_id: i8,
+    /// What the values are, e.g. `Duration`; the decoder's name if left out.
+    #[serde(default)]
+    pub name: String,
+    pub decoder: ExtDecoder,
+}
+
+/// The decoders to use for each extension type.
+///
+/// An extension types file is a JSON list of [`ExtMapping`]s, e.g.
+/// `[{"type": 1, "name": "UUID", "decoder": "uuid"}, {"type": 3, "decoder": "duration-nanos"}]`.
+#[derive(Debug, Clone)]
+pub struct ExtRegistry {
+    /// Later mappings take precedence over earlier ones for the same type.
+    mappings: Vec<ExtMapping>,
+}
+
+impl Default for ExtRegistry {
+    fn default() -> Self {
+        Self {
+            mappings: vec![ExtMapping {
+                type_id: TIMESTAMP,
+                name: "Timestamp".to_owned(),
+                decoder: ExtDecoder::Timestamp,
+            }],
+        }
+    }
+}
+
+impl ExtRegistry {
+    /// The built-in mappings along with those in the extension types file at `path`.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mappings: Vec<ExtMapping> = serde_json::from_slice(&std::fs::read(path)?)?;
+        Ok(Self::with_mappings(mappings))
+    }
+
+    /// The built-in mappings along with `mappings`.
+    pub fn with_mappings(mappings: impl IntoIterator<Item = ExtMapping>) -> Self {
+        let mut registry = Self::default();
+        registry.mappings.extend(mappings);
+        registry
+    }
+
+    /// Just the built-in mappings, shared.
+    pub fn builtin() -> &'static Self {
+        static BUILTIN: LazyLock<ExtRegistry> = LazyLock::new(ExtRegistry::default);
+        &BUILTIN
+    }
+
+    pub fn mappings(&self) -> &[ExtMapping] {
+        &self.mappings
+    }
+
+    /// Decodes `value`, if it's an extension value of a type there's a decoder for and the decoder
+    /// accepts its data.
+    pub fn decode(&self, value: &rmpv::Value) -> Option<DecodedExt> {
+        let rmpv::Value::Ext(type_id, data) = value else {
+            return None;
+        };
+        let mapping = self
+            .mappings
+            .iter()
+            .rev()
+            .find(|mapping| mapping.type_id == *type_id)?;
+        let name = match mapping.name.as_str() {
+            "" => format!("{:?}", mapping.decoder),
+            name => name.to_owned(),
+        };
+        Some(DecodedExt {
+            type_id: *type_id,
+            name,
+            text: mapping.decoder.decode(data)?,
+        })
+    }
+}
+
+/// An extension value as decoded by the registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedExt {
+    /// The values of different types are different, even if they decode to the same text.
+    pub type_id: i8,
+    /// What the value is, from its type's mapping.
+    pub name: String,
+    /// The same for values of a type that mean the same thing, so this is how they're compared.
+    pub text: String,
+}
@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+
+use crate::recover::DecodeFailure;
+
+/// How the messages in a file are delimited.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Framing {
+    /// Values simply follow one another, which is how msgpack streams usually work.
+    #[default]
+    Concatenated,
+    /// Concatenated msgpack-RPC requests, responses and notifications.
+    MsgpackRpc,
+    /// Each value is preceded by its length in bytes as a big-endian u32.
+    LengthPrefixedBigEndian,
+    /// Each value is preceded by its length in bytes as a little-endian u32.
+    LengthPrefixedLittleEndian,
+}
+
+impl Framing {
+    pub const ALL: [Framing; 4] = [
+        Framing::Concatenated,
+        Framing::MsgpackRpc,
+        Framing::LengthPrefixedBigEndian,
+        Framing::LengthPrefixedLittleEndian,
+    ];
+}
+
+impl std::fmt::Display for Framing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Framing::Concatenated => "concatenated values",
+            Framing::MsgpackRpc => "msgpack-RPC",
+            Framing::LengthPrefixedBigEndian => "u32 length prefix (big-endian)",
+            Framing::LengthPrefixedLittleEndian => "u32 length prefix (little-endian)",
+        })
+    }
+}
+
+/// Splits length-prefixed `data` into its frames, each along with its offset within `data`, stopping
+/// early if a frame is cut short.
+pub fn split_length_prefixed(
+    data: &[u8],
+    big_endian: bool,
+) -> (Vec<(usize, &[u8])>, Option<DecodeFailure>) {
+    let mut frames = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let Some(prefix) = data.get(offset..offset + 4) else {
+            let failure = DecodeFailure {
+                offset,
+                message: format!("truncated length prefix of frame {}", frames.len()),
+            };
+            return (frames, Some(failure));
+        };
+        let prefix = <[u8; 4]>::try_from(prefix).expect("slice has length 4");
+        let length = if big_endian {
+            u32::from_be_bytes(prefix)
+        } else {
+            u32::from_le_bytes(prefix)
+        } as usize;
+        let start = offset + 4;
+        let Some(frame) = data.get(start..start.saturating_add(length)) else {
+            let failure = DecodeFailure {
+                offset: data.len(),
+                message: format!(
+                    "frame {} at offset {offset} claims {length} bytes but only {} remain",
+                    frames.len(),
+                    data.len() - start
+                ),
+            };
+            return (frames, Some(failure));
+        };
+        frames.push((start, frame));
+        offset = start + length;
+    }
+    (frames, None)
+}
+
+/// Checks that `value` is a msgpack-RPC request `[0, msgid, method, params]`, response
+/// `[1, msgid, error, result]` or notification `[2, method, params]`.
+pub fn check_rpc_message(value: &rmpv::Value) -> Result<(), String> {
+    let Some(fields) = value.as_array() else {
+        return Err("is not an array".to_owned());
+    };
+    let expected_len = match fields.first().and_then(rmpv::Value::as_u64) {
+        Some(0 | 1) => 4,
+        Some(2) => 3,
+        _ => return Err("does not start with a message type of 0, 1 or 2".to_owned()),
+    };
+    if fields.len() != expected_len {
+        return Err(format!(
+            "has {} elements rather than {expected_len}",
+            fields.len()
+        ));
+    }
+    Ok(())
+}
+
+/// One-line summary of a msgpack-RPC message, e.g. `request #3 "get_user"`.
+pub fn describe_rpc_message(value: &rmpv::Value) -> Option<String> {
+    check_rpc_message(value).ok()?;
+    let fields = value.as_array()?;
+    Some(match fields[0].as_u64()? {
+        0 => format!("request #{} {}", fields[1], fields[2]),
+        1 if fields[2].is_nil() => format!("response #{}", fields[1]),
+        1 => format!("error response #{}", fields[1]),
+        _ => format!("notification {}", fields[1]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(data: &[u8], big_endian: bool) -> Vec<(usize, &[u8])> {
+        let (frames, failure) = split_length_prefixed(data, big_endian);
+        assert_eq!(failure, None);
+        frames
+    }
+
+    #[test]
+    fn splits_by_big_and_little_endian_prefixes() {
+        let data = [0, 0, 0, 1, 0x01, 0, 0, 0, 2, 0x92, 0x02];
+        assert_eq!(
+            frames(&data, true),
+            [(4, &[0x01][..]), (9, &[0x92, 0x02][..])]
+        );
+        let data = [1, 0, 0, 0, 0x01, 0, 0, 0, 0, 2, 0, 0, 0, 0x92, 0x02];
+        assert_eq!(
+            frames(&data, false),
+            [(4, &[0x01][..]), (9, &[][..]), (13, &[0x92, 0x02][..])]
+        );
+        assert!(frames(&[], true).is_empty());
+    }
+
+    #[test]
+    fn truncated_prefixes_fail_where_they_start() {
+        let data = [0, 0, 0, 1, 0x01, 0, 0];
+        let (frames, failure) = split_length_prefixed(&data, true);
+        assert_eq!(frames, [(4, &[0x01][..])]);
+        let failure = failure.unwrap();
+        assert_eq!(failure.offset, 5);
+        assert_eq!(failure.message, "truncated length prefix of frame 1");
+    }
+
+    #[test]
+    fn frames_cut_short_fail_at_the_end() {
+        let data = [0, 0, 0, 3, 0x92, 0x01];
+        let (frames, failure) = split_length_prefixed(&data, true);
+        assert!(frames.is_empty());
+        let failure = failure.unwrap();
+        assert_eq!(failure.offset, data.len());
+        assert_eq!(
+            failure.message,
+            "frame 0 at offset 0 claims 3 bytes but only 2 remain"
+        );
+        // claiming 4 GiB
+        let (_, failure) = split_length_prefixed(&[0xff; 8], false);
+        assert_eq!(failure.unwrap().offset, 8);
+    }
+
+    fn array(items: Vec<rmpv::Value>) -> rmpv::Value {
+        rmpv::Value::Array(items)
+    }
+
+    #[test]
+    fn rpc_messages_are_checked_and_described() {
+        let params = || array(vec![1.into()]);
+        let request = array(vec![0.into(), 3.into(), "get_user".into(), params()]);
+        let response = array(vec![1.into(), 3.into(), rmpv::Value::Nil, "ok".into()]);
+        let error = array(vec![1.into(), 4.into(), "nope".into(), rmpv::Value::Nil]);
+        let notification = array(vec![2.into(), "ping".into(), params()]);
+        for (message, description) in [
+            (request, r#"request #3 "get_user""#),
+            (response, "response #3"),
+            (error, "error response #4"),
+            (notification, r#"notification "ping""#),
+        ] {
+            assert_eq!(check_rpc_message(&message), Ok(()));
+            assert_eq!(describe_rpc_message(&message).unwrap(), description);
+        }
+    }
+
+    #[test]
+    fn what_isnt_an_rpc_message_says_why() {
+        for (value, reason) in [
+            (rmpv::Value::from(5), "is not an array"),
+            (
+                array(vec![3.into(), "x".into(), array(vec![])]),
+                "does not start with a message type of 0, 1 or 2",
+            ),
+            (
+                array(vec![0.into(), 1.into(), "ping".into()]),
+                "has 3 elements rather than 4",
+            ),
+            (
+                array(vec![2.into(), "ping".into()]),
+                "has 2 elements rather than 3",
+            ),
+        ] {
+            assert_eq!(check_rpc_message(&value), Err(reason.to_owned()));
+            assert_eq!(describe_rpc_message(&value), None);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+
+use crate::ext::ExtRegistry;
+use crate::payload::encode_hex;
+
+/// How binary and extension values, which JSON has nothing like, are converted to it.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JsonConversion {
+    pub binary: BinaryConversion,
+    pub ext: ExtConversion,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryConversion {
+    #[default]
+    Base64,
+    Hex,
+    /// As objects like `{"$binary": "<base64>"}`, so that they can be told apart from strings.
+    Tagged,
+    /// As arrays of the values of the bytes.
+    Bytes,
+}
+
+impl BinaryConversion {
+    pub const ALL: [BinaryConversion; 4] = [
+        BinaryConversion::Base64,
+        BinaryConversion::Hex,
+        BinaryConversion::Tagged,
+        BinaryConversion::Bytes,
+    ];
+}
+
+impl std::fmt::Display for BinaryConversion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BinaryConversion::Base64 => "base64 strings",
+            BinaryConversion::Hex => "hex strings",
+            BinaryConversion::Tagged => "{\"$binary\": base64} objects",
+            BinaryConversion::Bytes => "arrays of bytes",
+        })
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExtConversion {
+    /// As objects like `{"type": 1, "data": "<base64>"}`.
+    #[default]
+    Tagged,
+    /// As what [`crate::ext::ExtRegistry::decode`] makes of them, e.g. timestamps as dates, or else tagged.
+    Decoded,
+    /// As base64 strings of their data, losing their types.
+    Base64,
+}
+
+impl ExtConversion {
+    pub const ALL: [ExtConversion; 3] = [
+        ExtConversion::Tagged,
+        ExtConversion::Decoded,
+        ExtConversion::Base64,
+    ];
+}
+
+impl std::fmt::Display for ExtConversion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ExtConversion::Tagged => "{\"type\", \"data\"} objects",
+            ExtConversion::Decoded => "what they decode to",
+            ExtConversion::Base64 => "base64 strings of their data",
+        })
+    }
+}
+
+/// Converts a JSON value to the closest msgpack equivalent.
+///
+/// Integers that fit in `i64`/`u64` stay integers; everything else numeric becomes an F64.
+pub fn json_to_msgpack(value: &serde_json::Value) -> rmpv::Value {
+    match value {
+        serde_json::Value::Null => rmpv::Value::Nil,
+        serde_json::Value::Bool(b) => rmpv::Value::Boolean(*b),
+        serde_json::Value::Number(n) => {
+            if let Some(i) = n.as_u64() {
+                rmpv::Value::from(i)
+            } else if let Some(i) = n.as_i64() {
+                rmpv::Value::from(i)
+            } else {
+                rmpv::Value::F64(n.as_f64().unwrap_or(f64::NAN))
+            }
+        }
+        serde_json::Value::String(s) => rmpv::Value::from(s.as_str()),
+        serde_json::Value::Array(a) => rmpv::Value::Array(a.iter().map(json_to_msgpack).collect()),
+        serde_json::Value::Object(o) => rmpv::Value::Map(
+            o.iter()
+                .map(|(k, v)| (rmpv::Value::from(k.as_str()), json_to_msgpack(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Converts a msgpack value to JSON, for copying it elsewhere.
+///
+/// Binary and extension data is written as base64, map keys that aren't strings as they're
+/// displayed, and floats that JSON can't represent (NaN and infinities) as `null`.
+pub fn msgpack_to_json(value: &rmpv::Value) -> serde_json::Value {
+    msgpack_to_json_with(value, JsonConversion::default(), ExtRegistry::builtin())
+}
+
+/// Like [`msgpack_to_json`], with binary and extension values converted as `conversion` says,
+/// decoding extension values with `ext_types` if they're to be converted to what they decode to.
+pub fn msgpack_to_json_with(
+    value: &rmpv::Value,
+    conversion: JsonConversion,
+    ext_types: &ExtRegistry,
+) -> serde_json::Value {
+    let base64 = |data: &[u8]| base64::engine::general_purpose::STANDARD.encode(data);
+    let convert = |value| msgpack_to_json_with(value, conversion, ext_types);
+    match value {
+        rmpv::Value::Nil => serde_json::Value::Null,
+        rmpv::Value::Boolean(b) => serde_json::Value::Bool(*b),
+        rmpv::Value::Integer(i) => match (i.as_u64(), i.as_i64()) {
+            (Some(u), _) => u.into(),
+            (_, Some(i)) => i.into(),
+            _ => serde_json::Value::Null,
+        },
+        rmpv::Value::F32(f) => float_to_json(f64::from(*f)),
+        rmpv::Value::F64(f) => float_to_json(*f),
+        rmpv::Value::String(s) => {
+            serde_json::Value::String(String::from_utf8_lossy(s.as_bytes()).into_owned())
+        }
+        rmpv::Value::Binary(data) => match conversion.binary {
+            BinaryConversion::Base64 => serde_json::Value::String(base64(data)),
+            BinaryConversion::Hex => serde_json::Value::String(encode_hex(data)),
+            BinaryConversion::Tagged => serde_json::json!({ "$binary": base64(data) }),
+            BinaryConversion::Bytes => data.iter().copied().collect(),
+        },
+        rmpv::Value::Array(items) => items.iter().map(convert).collect(),
+        rmpv::Value::Map(entries) => serde_json::Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| (json_key(key), convert(value)))
+                .collect(),
+        ),
+        rmpv::Value::Ext(type_id, data) => {
+            let decoded = match conversion.ext {
+                ExtConversion::Decoded => ext_types.decode(value),
+                _ => None,
+            };
+            match (conversion.ext, decoded) {
+                (_, Some(decoded)) => serde_json::Value::String(decoded.text),
+                (ExtConversion::Base64, None) => serde_json::Value::String(base64(data)),
+                _ => serde_json::json!({
+                    "type": type_id,
+                    "data": base64(data),
+                }),
+            }
+        }
+    }
+}
+
+/// Describes what of `value` doesn't survive [`msgpack_to_json`] unchanged, once each.
+pub fn json_conversion_losses(value: &rmpv::Value) -> Vec<&'static str> {
+    fn visit(value: &rmpv::Value, losses: &mut Vec<&'static str>) {
+        let mut note = |loss| {
+            if !losses.contains(&loss) {
+                losses.push(loss);
+            }
+        };
+        match value {
+            rmpv::Value::F32(f) if !f.is_finite() => note("NaN and infinite floats become null"),
+            rmpv::Value::F64(f) if !f.is_finite() => note("NaN and infinite floats become null"),
+            rmpv::Value::String(s) if s.as_str().is_none() => {
+                note("invalid UTF-8 in strings is replaced")
+            }
+            rmpv::Value::Binary(_) => note("binary data becomes base64 strings"),
+            rmpv::Value::Ext(..) => note("extension values become objects with base64 data"),
+            rmpv::Value::Array(items) => items.iter().for_each(|item| visit(item, losses)),
+            rmpv::Value::Map(entries) => {
+                if entries.iter().any(|(key, _)| !key.is_str()) {
+                    note("keys that aren't strings are written as strings");
+                }
+                let keys = entries
+                    .iter()
+                    .map(|(key, _)| json_key(key))
+                    .collect::<std::collections::HashSet<_>>();
+                if keys.len() < entries.len() {
+                    note("of duplicate keys, only the last one is kept");
+                }
+                for (_, value) in entries {
+                    visit(value, losses);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut losses = Vec::new();
+    visit(value, &mut losses);
+    losses
+}
+
+/// A map key as it's written in JSON, where keys are always strings.
+pub fn json_key(key: &rmpv::Value) -> String {
+    match key {
+        rmpv::Value::String(s) => String::from_utf8_lossy(s.as_bytes()).into_owned(),
+        key => key.to_string(),
+    }
+}
+
+fn float_to_json(f: f64) -> serde_json::Value {
+    serde_json::Number::from_f64(f).map_or(serde_json::Value::Null, serde_json::Value::Number)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn convert(
+        value: &rmpv::Value,
+        binary: BinaryConversion,
+        ext: ExtConversion,
+    ) -> serde_json::Value {
+        let conversion = JsonConversion { binary, ext };
+        msgpack_to_json_with(value, conversion, ExtRegistry::builtin())
+    }
+
+    #[test]
+    fn binary_is_converted_as_asked() {
+        let binary = rmpv::Value::Binary(vec![0x00, 0xfe, 0xff]);
+        for (conversion, expected) in [
+            (BinaryConversion::Base64, json!("AP7/")),
+            (BinaryConversion::Hex, json!("00feff")),
+            (BinaryConversion::Tagged, json!({ "$binary": "AP7/" })),
+            (BinaryConversion::Bytes, json!([0, 254, 255])),
+        ] {
+            let converted = convert(&binary, conversion, ExtConversion::default());
+            assert_eq!(converted, expected, "{conversion}");
+        }
+    }
+
+    #[test]
+    fn ext_values_are_converted_as_asked() {
+        // the timestamp 0, and a type nothing decodes
+        let timestamp = rmpv::Value::Ext(-1, vec![0; 4]);
+        let unknown = rmpv::Value::Ext(42, vec![0x01, 0x02]);
+        for (conversion, expected_timestamp, expected_unknown) in [
+            (
+                ExtConversion::Tagged,
+                json!({ "type": -1, "data": "AAAAAA==" }),
+                json!({ "type": 42, "data": "AQI=" }),
+            ),
+            (
+                ExtConversion::Decoded,
+                json!("1970-01-01T00:00:00Z"),
+                json!({ "type": 42, "data": "AQI=" }),
+            ),
+            (ExtConversion::Base64, json!("AAAAAA=="), json!("AQI=")),
+        ] {
+            let binary = BinaryConversion::default();
+            assert_eq!(convert(&timestamp, binary, conversion), expected_timestamp);
+            assert_eq!(convert(&unknown, binary, conversion), expected_unknown);
+        }
+    }
+
+    #[test]
+    fn keys_that_arent_strings_are_written_as_shown() {
+        let map = rmpv::Value::Map(vec![
+            (1.into(), "int".into()),
+            (true.into(), "bool".into()),
+            (rmpv::Value::Nil, "nil".into()),
+            (rmpv::Value::F64(f64::NAN), rmpv::Value::F32(f32::INFINITY)),
+        ]);
+        let expected = json!({ "1": "int", "true": "bool", "nil": "nil", "NaN": null });
+        assert_eq!(msgpack_to_json(&map), expected);
+        assert_eq!(
+            json_conversion_losses(&map),
+            [
+                "keys that aren't strings are written as strings",
+                "NaN and infinite floats become null"
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_keys_are_a_loss() {
+        let map = rmpv::Value::Map(vec![
+            ("1".into(), rmpv::Value::Binary(vec![])),
+            (1.into(), rmpv::Value::Ext(1, vec![])),
+        ]);
+        assert_eq!(
+            json_conversion_losses(&map),
+            [
+                "keys that aren't strings are written as strings",
+                "of duplicate keys, only the last one is kept",
+                "binary data becomes base64 strings",
+                "extension values become objects with base64 data",
+            ]
+        );
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let value = json_to_msgpack(&json!({ "a": [1, -2, 1.5, u64::MAX, null, true, "text"] }));
+        let items = value.as_map().unwrap()[0].1.as_array().unwrap().clone();
+        assert_eq!(
+            items,
+            [
+                rmpv::Value::from(1),
+                rmpv::Value::from(-2),
+                rmpv::Value::F64(1.5),
+                rmpv::Value::from(u64::MAX),
+                rmpv::Value::Nil,
+                rmpv::Value::Boolean(true),
+                rmpv::Value::from("text"),
+            ]
+        );
+        assert_eq!(json_to_msgpack(&msgpack_to_json(&value)), value);
+        assert!(json_conversion_losses(&value).is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+//! The parts of msgpack-differ that don't need a GUI: loading msgpack files while keeping track of
+//! where each value is encoded, diffing two documents, and turning a diff into patches and other
+//! exports.
+//!
+//! Values are [`rmpv::Value`]s, which is re-exported so that the same version is used. A diff is a
+//! tree of [`diff::DiffNode`]s mirroring both documents, which is walked along with the values it
+//! compares as [`diff::DiffEntry`]s:
+//!
+//! ```
+//! use msgpack_differ_core::diff::{diff_values, differences, DiffEntry, DiffStatus};
+//! use msgpack_differ_core::rmpv::Value;
+//!
+//! let a = Value::Map(vec![("hp".into(), 10.into()), ("name".into(), "orc".into())]);
+//! let b = Value::Map(vec![("hp".into(), 7.into()), ("name".into(), "orc".into())]);
+//! let root = diff_values(Some(&a), Some(&b));
+//! let found = differences(&root, Some(&a), Some(&b));
+//! assert_eq!(found.len(), 1);
+//! assert_eq!(found[0].path.to_string(), "hp");
+//! assert_eq!(found[0].status, DiffStatus::Changed);
+//!
+//! // and the patch that turns A into B, which is checked against the bytes A was decoded from
+//! let mut a_bytes = Vec::new();
+//! msgpack_differ_core::rmpv::encode::write_value(&mut a_bytes, &a).unwrap();
+//! let entry = DiffEntry { node: &root, a: Some(&a), b: Some(&b) };
+//! let patch = msgpack_differ_core::patch::msgpack_patch(entry, &a_bytes, false);
+//! let mut patched = a.clone();
+//! msgpack_differ_core::patch::apply_msgpack_patch(&mut patched, &patch, &a_bytes).unwrap();
+//! assert_eq!(patched, b);
+//! ```
+//!
+//! Files are loaded with [`loader::LoadedFile::load`], which decompresses them, finds the msgpack
+//! in them, splits them into their values however they're framed, and keeps the bytes so that
+//! [`loader::LoadedFile::value_span`] can say where any value came from.
+
+pub use rmpv;
+
+/// Recognizing and undoing compression of whole files.
+pub mod decompress;
+/// Structural diffs of two values.
+pub mod diff;
+/// Edits made by hand to a document.
+pub mod edit;
+/// Decoding extension values, such as timestamps.
+pub mod ext;
+/// Splitting files of several values into them.
+pub mod framing;
+/// Converting values to JSON and back.
+pub mod json;
+/// Reading files and decoding them, along with where each value is encoded.
+pub mod loader;
+/// Binary values that hold msgpack themselves.
+pub mod nested;
+/// Patches turning one document into another, made from diffs.
+pub mod patch;
+/// Paths to values within documents.
+pub mod path;
+/// Payloads pasted as hex or base64.
+pub mod payload;
+/// Making what can be made of files that fail to decode.
+pub mod recover;
+/// Where values are encoded within the bytes decoded.
+pub mod spans;
+/// Walking and describing values.
+pub mod value;
+/// Writing values as YAML.
+pub mod yaml;
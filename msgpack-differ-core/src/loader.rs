@@ -0,0 +1,980 @@
+use std::collections::HashSet;
+use std::ops::{Deref, Range};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::decompress::{maybe_decompress, unsupported_format, Compression};
+use crate::framing::{check_rpc_message, split_length_prefixed, Framing};
+use crate::nested::decode_nested;
+use crate::recover::{decode_prefix, hex_context, DecodeFailure, PartialValue};
+use crate::spans::{describe_encoding, size_tree, span_tree, value_span, SizeTree, SpanTree};
+use crate::value::value_at_mut;
+
+/// Where the bytes of a file come from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Source {
+    File(PathBuf),
+    /// An HTTP(S) URL, downloaded when loading.
+    Url(String),
+    /// A payload pasted from the clipboard, which only exists as the bytes cached from pasting it.
+    Pasted {
+        name: String,
+    },
+    /// A copy of another file as it was at some point, which only exists in memory.
+    Snapshot {
+        name: String,
+    },
+}
+
+impl Source {
+    /// Interprets a command line argument or pasted text as a URL if it looks like one, else as a path.
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        if text.starts_with("http://") || text.starts_with("https://") {
+            Source::Url(text.to_owned())
+        } else {
+            Source::File(PathBuf::from(text))
+        }
+    }
+
+    pub fn as_path(&self) -> Option<&Path> {
+        match self {
+            Source::File(path) => Some(path),
+            Source::Url(_) | Source::Pasted { .. } | Source::Snapshot { .. } => None,
+        }
+    }
+
+    /// Short name for headings: the file name, or the last segment of the URL's path.
+    pub fn short_name(&self) -> String {
+        match self {
+            Source::File(path) => path
+                .file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .into_owned(),
+            Source::Url(url) => {
+                let without_query = url.split(['?', '#']).next().unwrap_or(url);
+                match without_query.trim_end_matches('/').rsplit_once('/') {
+                    Some((_, name)) if !name.is_empty() => name.to_owned(),
+                    _ => url.clone(),
+                }
+            }
+            Source::Pasted { name } | Source::Snapshot { name } => name.clone(),
+        }
+    }
+
+    fn fetch(&self, progress: &LoadProgress) -> Result<FileBytes, LoadError> {
+        match self {
+            Source::File(path) => {
+                progress.set_stage(LoadStage::Reading);
+                Ok(FileBytes::read(path)?)
+            }
+            Source::Url(url) => download(url, progress),
+            Source::Pasted { .. } => Err(LoadError::Unavailable("pasted data")),
+            Source::Snapshot { .. } => Err(LoadError::Unavailable("snapshot")),
+        }
+    }
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Url(url) => f.write_str(url),
+            Source::Pasted { name } => write!(f, "{name} (from clipboard)"),
+            Source::Snapshot { name } => write!(f, "{name} (snapshot)"),
+        }
+    }
+}
+
+fn download(url: &str, progress: &LoadProgress) -> Result<FileBytes, LoadError> {
+    progress.set_stage(LoadStage::Downloading);
+    let response = ureq::get(url).call().map_err(Box::new)?;
+    let size = response
+        .header("Content-Length")
+        .and_then(|len| len.parse::<u64>().ok());
+    if size.is_some_and(|size| size > LoadedFile::MAX_SIZE) {
+        return Err(LoadError::TooLarge {
+            what: "download",
+            limit: LoadedFile::MAX_SIZE,
+        });
+    }
+    progress
+        .download_size
+        .store(size.unwrap_or(0), Ordering::Relaxed);
+
+    let mut reader = response.into_reader();
+    // don't trust the server's claimed size too much when allocating
+    let mut data = Vec::with_capacity(size.unwrap_or(0).min(64 * 1024 * 1024) as usize);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        progress.check_cancelled()?;
+        let read = std::io::Read::read(&mut reader, &mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        data.extend_from_slice(&buffer[..read]);
+        if data.len() as u64 > LoadedFile::MAX_SIZE {
+            return Err(LoadError::TooLarge {
+                what: "download",
+                limit: LoadedFile::MAX_SIZE,
+            });
+        }
+        progress
+            .bytes_downloaded
+            .fetch_add(read as u64, Ordering::Relaxed);
+    }
+    Ok(FileBytes::from(data))
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct Crc32 {
+    pub result: u32,
+}
+
+impl std::fmt::Display for Crc32 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CRC32: {:08x}", self.result)
+    }
+}
+
+impl Crc32 {
+    pub fn calculate_hash_of(data: &[u8]) -> Self {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(data);
+        let result = hasher.finalize();
+        Self { result }
+    }
+}
+
+/// Bytes of a loaded file, cheaply shareable between everything that needs to look at them.
+#[derive(Clone)]
+pub struct FileBytes(Arc<Backing>);
+
+enum Backing {
+    Owned(Vec<u8>),
+    Mapped(memmap2::Mmap),
+}
+
+impl FileBytes {
+    /// Files at least this big are memory-mapped instead of read into memory.
+    const MMAP_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        if file.metadata()?.len() < Self::MMAP_THRESHOLD {
+            let mut data = Vec::new();
+            std::io::Read::read_to_end(&mut &file, &mut data)?;
+            return Ok(Self::from(data));
+        }
+        // SAFETY: mapping a file is only sound as long as nothing else modifies it while it's mapped,
+        // which we can't guarantee. That's deemed an acceptable risk for a viewer: in practice the
+        // files being compared are rewritten by replacing them, which leaves the mapping intact.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Self(Arc::new(Backing::Mapped(mmap))))
+    }
+
+    pub fn is_mapped(&self) -> bool {
+        matches!(*self.0, Backing::Mapped(_))
+    }
+}
+
+impl From<Vec<u8>> for FileBytes {
+    fn from(data: Vec<u8>) -> Self {
+        Self(Arc::new(Backing::Owned(data)))
+    }
+}
+
+impl Deref for FileBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &*self.0 {
+            Backing::Owned(data) => data,
+            Backing::Mapped(mmap) => mmap,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LoadedFile {
+    pub source: Source,
+    /// The bytes as read or downloaded, before any decompression.
+    pub raw: FileBytes,
+    /// The msgpack bytes, after decompression if the file was compressed.
+    pub data: FileBytes,
+    /// CRC of the file as stored on disk.
+    pub crc32: Crc32,
+    /// Compression format and size on disk, if the file was compressed.
+    pub compression: Option<(Compression, usize)>,
+    /// The decoded document. Files holding several concatenated values are presented as a virtual root
+    /// array of those values, see `value_count`. It's shared so that keeping earlier versions of the
+    /// file around doesn't copy it.
+    pub parsed: Arc<rmpv::Value>,
+    /// How many top-level values the file holds.
+    pub value_count: usize,
+    pub options: LoadOptions,
+    /// Where in `data` the msgpack starts, after skipping any header.
+    pub payload_offset: usize,
+    /// Where in `data` each top-level value starts.
+    pub value_offsets: Vec<usize>,
+    /// Values within `parsed` that were decoded from msgpack held in binary values, by the positions
+    /// leading to them; see [`LoadOptions::decode_nested`].
+    pub nested: HashSet<Vec<usize>>,
+    /// Where decoding stopped if the file is truncated or corrupt, in which case `parsed` holds
+    /// whatever came before that.
+    pub decode_failure: Option<DecodeFailure>,
+    pub load_time: jiff::Timestamp,
+    /// How long decompressing and decoding the file took, not counting reading it.
+    pub parse_duration: Duration,
+}
+impl LoadedFile {
+    /// The most bytes that are downloaded or decompressed, so that a file can't run the machine out
+    /// of memory. Files read from disk are mapped rather than held in memory, so they can be bigger.
+    pub const MAX_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+    /// Loads `source`, or re-parses `cached` bytes previously fetched from it if given.
+    pub fn load(
+        source: &Source,
+        cached: Option<FileBytes>,
+        options: &LoadOptions,
+        progress: &LoadProgress,
+    ) -> LoadResult {
+        let raw = match cached {
+            Some(raw) => raw,
+            None => source.fetch(progress)?,
+        };
+        progress.check_cancelled()?;
+        Self::parse(source, raw, options, progress)
+    }
+
+    fn parse(
+        source: &Source,
+        raw: FileBytes,
+        options: &LoadOptions,
+        progress: &LoadProgress,
+    ) -> LoadResult {
+        let started = Instant::now();
+        let crc32 = Crc32::calculate_hash_of(&raw);
+        let file_size = raw.len();
+        progress.set_stage(LoadStage::Decompressing);
+        let (data, compression) = match maybe_decompress(&raw, Self::MAX_SIZE, progress)? {
+            Some((decompressed, compression)) => (FileBytes::from(decompressed), Some(compression)),
+            None => (raw.clone(), None),
+        };
+        progress.check_cancelled()?;
+
+        progress.set_stage(LoadStage::Parsing);
+        let payload_offset = find_payload(&data, options, progress)?;
+        let payload = &data[payload_offset..];
+        progress
+            .total_bytes
+            .store(payload.len() as u64, Ordering::Relaxed);
+        let (values, mut decode_failure) = read_values(payload, options.framing, progress)?;
+        if decode_failure.is_some() {
+            // what decoded is most likely just its header happening to be valid msgpack
+            if let Some(format) = unsupported_format(&data) {
+                return Err(LoadError::UnsupportedFormat { format });
+            }
+        }
+        // report offsets within the whole file rather than within the payload
+        if let Some(failure) = &mut decode_failure {
+            failure.offset += payload_offset;
+        }
+        let (value_offsets, mut values): (Vec<_>, Vec<_>) = values
+            .into_iter()
+            .map(|(offset, value)| (payload_offset + offset, value))
+            .unzip();
+        let value_count = values.len();
+        let mut parsed = match value_count {
+            0 => match decode_failure {
+                Some(failure) => return Err(NothingDecoded { failure, data }.into()),
+                None => return Err(LoadError::Empty),
+            },
+            1 => values.pop().expect("just checked length"),
+            _ => rmpv::Value::Array(values),
+        };
+        let mut nested = HashSet::new();
+        if options.decode_nested {
+            // a multi-value file's values are children of `parsed`, which isn't binary itself
+            decode_nested(&mut parsed, &mut Vec::new(), &mut nested);
+        }
+        Ok(Self {
+            source: source.clone(),
+            raw,
+            data,
+            crc32,
+            compression: compression.map(|compression| (compression, file_size)),
+            parsed: Arc::new(parsed),
+            value_count,
+            options: options.clone(),
+            payload_offset,
+            value_offsets,
+            nested,
+            decode_failure,
+            load_time: jiff::Timestamp::now(),
+            parse_duration: started.elapsed(),
+        })
+    }
+
+    /// Encodes `document`, an edited copy of `parsed`, the way the file is encoded: after the same
+    /// header, with the values of a multi-value file framed the same way, and with what was decoded
+    /// from binary values (at the positions in `nested`) encoded back into them. It's left
+    /// uncompressed, though.
+    pub fn encode_document(
+        &self,
+        document: &rmpv::Value,
+        nested: &HashSet<Vec<usize>>,
+    ) -> Result<Vec<u8>, EncodeError> {
+        let mut document = document.clone();
+        // the innermost first, so that what holds them is encoded with them back in binary
+        let mut nested = nested.iter().collect::<Vec<_>>();
+        nested.sort_by_key(|indexes| std::cmp::Reverse(indexes.len()));
+        for indexes in nested {
+            let Some(value) = value_at_mut(&mut document, indexes) else {
+                continue;
+            };
+            let mut encoded = Vec::new();
+            rmpv::encode::write_value(&mut encoded, value).expect("writing to a Vec can't fail");
+            *value = rmpv::Value::Binary(encoded);
+        }
+        let values = match document {
+            rmpv::Value::Array(values) if self.is_multi_value() => values,
+            document => vec![document],
+        };
+        let mut data = self.data[..self.payload_offset].to_vec();
+        for (index, value) in values.iter().enumerate() {
+            let mut encoded = Vec::new();
+            rmpv::encode::write_value(&mut encoded, value).expect("writing to a Vec can't fail");
+            let length = || {
+                u32::try_from(encoded.len()).map_err(|_| EncodeError::TooLongToPrefix {
+                    index,
+                    len: encoded.len(),
+                })
+            };
+            match self.options.framing {
+                Framing::Concatenated | Framing::MsgpackRpc => {}
+                Framing::LengthPrefixedBigEndian => data.extend(length()?.to_be_bytes()),
+                Framing::LengthPrefixedLittleEndian => data.extend(length()?.to_le_bytes()),
+            }
+            data.extend(encoded);
+        }
+        Ok(data)
+    }
+
+    /// Whether the file holds several concatenated values rather than a single document.
+    pub fn is_multi_value(&self) -> bool {
+        self.value_count > 1
+    }
+
+    /// Where in `data` the value at `indexes` within `parsed` (see [`crate::value::child_of`]) is
+    /// encoded, unless decoding failed within it. The root of a multi-value file spans all of its
+    /// values, along with their framing.
+    pub fn value_span(&self, indexes: &[usize]) -> Option<Range<usize>> {
+        let (start, indexes) = match indexes.split_first() {
+            Some((&value, rest)) if self.is_multi_value() => {
+                (*self.value_offsets.get(value)?, rest)
+            }
+            None if self.is_multi_value() => {
+                let end = value_span(&self.data, *self.value_offsets.last()?, &[])?.end;
+                return Some(*self.value_offsets.first()?..end);
+            }
+            _ => (*self.value_offsets.first()?, indexes),
+        };
+        value_span(&self.data, start, indexes)
+    }
+
+    /// The bytes encoding the value at `indexes` within `parsed`, unless decoding failed within it.
+    pub fn value_bytes(&self, indexes: &[usize]) -> Option<&[u8]> {
+        self.data.get(self.value_span(indexes)?)
+    }
+
+    /// Works out how many bytes each value within `parsed` takes, unless decoding failed within it.
+    pub fn size_tree(&self) -> Option<SizeTree> {
+        if !self.is_multi_value() {
+            return Some(size_tree(&self.data, *self.value_offsets.first()?, &self.parsed)?.0);
+        }
+        let values = self.parsed.as_array()?;
+        let children = self
+            .value_offsets
+            .iter()
+            .zip(values)
+            .map(|(&offset, value)| Some(size_tree(&self.data, offset, value)?.0))
+            .collect::<Option<Vec<_>>>()?;
+        Some(SizeTree {
+            size: children.iter().map(|child| child.size).sum(),
+            children,
+        })
+    }
+
+    /// Works out where each value within `parsed` is encoded, unless decoding failed within it.
+    pub fn span_tree(&self) -> Option<SpanTree> {
+        if !self.is_multi_value() {
+            return span_tree(&self.data, *self.value_offsets.first()?, &self.parsed);
+        }
+        let values = self.parsed.as_array()?;
+        let children = self
+            .value_offsets
+            .iter()
+            .zip(values)
+            .map(|(&offset, value)| span_tree(&self.data, offset, value))
+            .collect::<Option<Vec<_>>>()?;
+        let start = children.first().map_or(0, |child| child.span.start);
+        let end = children.last().map_or(start, |child| child.span.end);
+        Some(SpanTree {
+            span: start..end,
+            entry_start: start,
+            children,
+        })
+    }
+
+    /// Describes how and where the value at `indexes` within `parsed` is encoded, e.g. `fixstr, 11
+    /// bytes at offset 0x1A3C, 12 bytes in all`.
+    pub fn describe_span(&self, indexes: &[usize]) -> String {
+        let Some(span) = self.value_span(indexes) else {
+            return "offset unknown, as decoding failed within it".to_owned();
+        };
+        let within = match self.compression {
+            Some((compression, _)) => format!(" of the data decompressed from {compression}"),
+            None => String::new(),
+        };
+        let encoding = describe_encoding(&self.data, span.start).unwrap_or_default();
+        format!(
+            "{encoding} at offset 0x{:X}{within}, {} bytes in all",
+            span.start,
+            span.len()
+        )
+    }
+}
+
+/// How to interpret the bytes of a file.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoadOptions {
+    pub framing: Framing,
+    /// Bytes to ignore at the start of the (decompressed) data, e.g. to get past a custom header.
+    pub skip_bytes: usize,
+    /// Whether to skip further ahead, to the first offset from which everything decodes cleanly
+    /// as a map or array (or as any value when length-prefixed).
+    pub scan_for_start: bool,
+    /// Whether to show binary values that hold msgpack as what they decode to.
+    pub decode_nested: bool,
+}
+
+impl LoadOptions {
+    /// How far [`find_payload`] looks for the start of the msgpack.
+    const MAX_SCAN: usize = 64 * 1024;
+}
+
+/// Finds where the msgpack starts in `data` according to `options`.
+///
+/// When scanning, that's the first offset from which everything decodes cleanly. Headers tend to
+/// be ASCII, which consists of valid msgpack integers, so unless the values are length-prefixed the
+/// first value must also be a map or an array.
+fn find_payload(
+    data: &[u8],
+    options: &LoadOptions,
+    progress: &LoadProgress,
+) -> Result<usize, LoadError> {
+    let start = options.skip_bytes;
+    if start > data.len() {
+        return Err(LoadError::SkippedPastEnd {
+            skip: start,
+            len: data.len(),
+        });
+    }
+    if !options.scan_for_start {
+        return Ok(start);
+    }
+    let scan_end = data.len().min(start + LoadOptions::MAX_SCAN);
+    let length_prefixed = matches!(
+        options.framing,
+        Framing::LengthPrefixedBigEndian | Framing::LengthPrefixedLittleEndian
+    );
+    // progress is only reported for decoding the payload once it's found
+    let scan_progress = LoadProgress::default();
+    for offset in start..scan_end {
+        progress.check_cancelled()?;
+        let candidate = &data[offset..];
+        if !length_prefixed && !matches!(candidate[0], 0x80..=0x9f | 0xdc..=0xdf) {
+            continue;
+        }
+        if let Ok((values, None)) = read_values(candidate, options.framing, &scan_progress) {
+            if !values.is_empty() {
+                return Ok(offset);
+            }
+        }
+    }
+    Err(LoadError::NoMsgpackFound {
+        start,
+        end: scan_end,
+    })
+}
+
+pub type LoadResult = Result<LoadedFile, LoadError>;
+
+/// Why a file failed to load.
+#[derive(Debug, thiserror::Error)]
+pub enum LoadError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("downloading failed: {0}")]
+    Download(#[from] Box<ureq::Error>),
+    /// Pasted data and snapshots only exist as the bytes they were loaded from, which are gone once
+    /// they're unloaded.
+    #[error("the {0} is no longer available")]
+    Unavailable(&'static str),
+    #[error("file is empty")]
+    Empty,
+    /// [`LoadOptions::skip_bytes`] is past the end of the data.
+    #[error("can't skip {skip} bytes of {len} bytes of data")]
+    SkippedPastEnd { skip: usize, len: usize },
+    /// Scanning for where the msgpack starts, see [`LoadOptions::scan_for_start`], found nothing.
+    #[error("no valid msgpack found between byte {start} and byte {end}")]
+    NoMsgpackFound { start: usize, end: usize },
+    /// The file was to be read as msgpack-RPC messages, and one wasn't one.
+    #[error("value {index} is not a msgpack-RPC message: it {reason}")]
+    NotRpcMessage { index: usize, reason: String },
+    /// Not even the start of the first value, at `offset`, could be decoded.
+    #[error("{details}")]
+    DecodeError {
+        offset: usize,
+        details: NothingDecoded,
+    },
+    /// The data is more than [`LoadedFile::MAX_SIZE`] bytes.
+    #[error("the {what} is bigger than {} MiB, the most that's loaded", limit / (1024 * 1024))]
+    TooLarge { what: &'static str, limit: u64 },
+    /// The file is in a known format that isn't msgpack, nor compression of it that can be undone.
+    #[error("{format} isn't supported")]
+    UnsupportedFormat { format: &'static str },
+    #[error("loading was cancelled")]
+    Cancelled,
+    /// The thread loading the file went away without saying how it went.
+    #[error("loading was aborted")]
+    Aborted,
+}
+
+/// Why [`LoadedFile::encode_document`] couldn't encode a document.
+#[derive(Debug, thiserror::Error)]
+pub enum EncodeError {
+    /// A value of a length-prefixed file is too long for its length to fit in the prefix.
+    #[error("value {index} is {len} bytes long, too long for a 32-bit length prefix")]
+    TooLongToPrefix { index: usize, len: usize },
+}
+
+/// Why a file failed to load when its very first value couldn't be decoded.
+pub struct NothingDecoded {
+    pub failure: DecodeFailure,
+    /// The msgpack bytes, to show where it failed.
+    pub data: FileBytes,
+}
+
+impl std::fmt::Debug for NothingDecoded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NothingDecoded")
+            .field("failure", &self.failure)
+            .finish_non_exhaustive()
+    }
+}
+
+impl std::fmt::Display for NothingDecoded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let context = hex_context(&self.data, self.failure.offset);
+        write!(f, "nothing could be decoded: {}\n{context}", self.failure)
+    }
+}
+
+impl std::error::Error for NothingDecoded {}
+
+impl From<NothingDecoded> for LoadError {
+    fn from(details: NothingDecoded) -> Self {
+        LoadError::DecodeError {
+            offset: details.failure.offset,
+            details,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[repr(u8)]
+pub enum LoadStage {
+    Reading,
+    Downloading,
+    Decompressing,
+    Parsing,
+}
+
+/// Progress of a load, shared between the loading thread and whoever is watching it.
+#[derive(Debug, Default)]
+pub struct LoadProgress {
+    stage: AtomicU8,
+    bytes_downloaded: AtomicU64,
+    /// Zero if unknown.
+    download_size: AtomicU64,
+    bytes_decompressed: AtomicU64,
+    bytes_parsed: AtomicU64,
+    total_bytes: AtomicU64,
+    values_parsed: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+impl LoadProgress {
+    pub fn stage(&self) -> LoadStage {
+        match self.stage.load(Ordering::Relaxed) {
+            0 => LoadStage::Reading,
+            1 => LoadStage::Downloading,
+            2 => LoadStage::Decompressing,
+            _ => LoadStage::Parsing,
+        }
+    }
+
+    fn set_stage(&self, stage: LoadStage) {
+        self.stage.store(stage as u8, Ordering::Relaxed);
+    }
+
+    /// Bytes downloaded so far and the download's size, if the server said.
+    pub fn download(&self) -> (u64, Option<u64>) {
+        let size = self.download_size.load(Ordering::Relaxed);
+        (
+            self.bytes_downloaded.load(Ordering::Relaxed),
+            (size > 0).then_some(size),
+        )
+    }
+
+    /// Bytes decompressed so far, if the file is compressed.
+    pub fn bytes_decompressed(&self) -> u64 {
+        self.bytes_decompressed.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_bytes_decompressed(&self, bytes: u64) {
+        self.bytes_decompressed.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Bytes parsed so far and the total to parse (which is only known once parsing starts).
+    pub fn bytes(&self) -> (u64, u64) {
+        (
+            self.bytes_parsed.load(Ordering::Relaxed),
+            self.total_bytes.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Number of top-level values parsed so far.
+    pub fn values_parsed(&self) -> u64 {
+        self.values_parsed.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn check_cancelled(&self) -> Result<(), LoadError> {
+        if self.is_cancelled() {
+            Err(LoadError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Reads from a byte slice while keeping a [`LoadProgress`] up to date, failing once it's cancelled.
+struct ProgressReader<'a> {
+    remaining: &'a [u8],
+    consumed: u64,
+    progress: &'a LoadProgress,
+}
+
+impl std::io::Read for ProgressReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.progress.is_cancelled() {
+            return Err(std::io::Error::other(LoadError::Cancelled));
+        }
+        let read = self.remaining.read(buf)?;
+        self.consumed += read as u64;
+        self.progress
+            .bytes_parsed
+            .store(self.consumed, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+/// A [`LoadedFile::load`] running on a worker thread.
+pub struct BackgroundLoad {
+    source: Source,
+    progress: Arc<LoadProgress>,
+    result: mpsc::Receiver<LoadResult>,
+}
+
+impl BackgroundLoad {
+    /// Starts loading `source` (see [`LoadedFile::load`]); `on_done` is called from the worker thread
+    /// once the result is ready.
+    pub fn start(
+        source: Source,
+        cached: Option<FileBytes>,
+        options: LoadOptions,
+        on_done: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        let (sender, result) = mpsc::channel();
+        let progress = Arc::new(LoadProgress::default());
+        let thread_source = source.clone();
+        let thread_progress = progress.clone();
+        let spawned = std::thread::Builder::new()
+            .name(format!("load {}", source.short_name()))
+            .spawn(move || {
+                let result = LoadedFile::load(&thread_source, cached, &options, &thread_progress);
+                // the receiver is gone if the load was abandoned, in which case nobody cares
+                let _ = sender.send(result);
+                on_done();
+            });
+        if let Err(err) = spawned {
+            log::error!("Could not start loading thread: {err}");
+        }
+        Self {
+            source,
+            progress,
+            result,
+        }
+    }
+
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+
+    pub fn progress(&self) -> &LoadProgress {
+        &self.progress
+    }
+
+    /// The result of the load, once it has finished.
+    pub fn poll(&self) -> Option<LoadResult> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err(LoadError::Aborted)),
+        }
+    }
+}
+
+/// Values decoded from a file, each with the offset it starts at, and where decoding failed if it
+/// didn't get to the end.
+type DecodedValues = (Vec<(usize, rmpv::Value)>, Option<DecodeFailure>);
+
+/// Decodes every message in `data`, delimited according to `framing`. If the data turns out to be
+/// corrupt, this returns everything up to that point and where it went wrong.
+fn read_values(
+    data: &[u8],
+    framing: Framing,
+    progress: &LoadProgress,
+) -> Result<DecodedValues, LoadError> {
+    let big_endian = match framing {
+        Framing::Concatenated => {
+            let concatenated = read_concatenated(data, 0, progress)?;
+            return Ok((concatenated.values, concatenated.failure));
+        }
+        Framing::MsgpackRpc => {
+            let Concatenated {
+                values,
+                failure,
+                partial,
+            } = read_concatenated(data, 0, progress)?;
+            // whatever is left of a broken message is unlikely to pass as one
+            let complete_count = values.len() - usize::from(partial);
+            for (i, (_, value)) in values.iter().enumerate().take(complete_count) {
+                check_rpc_message(value).map_err(|reason| LoadError::NotRpcMessage {
+                    index: i,
+                    reason: reason.to_string(),
+                })?;
+            }
+            return Ok((values, failure));
+        }
+        Framing::LengthPrefixedBigEndian => true,
+        Framing::LengthPrefixedLittleEndian => false,
+    };
+    let (frames, split_failure) = split_length_prefixed(data, big_endian);
+    let mut values = Vec::with_capacity(frames.len());
+    for (i, (offset, frame)) in frames.into_iter().enumerate() {
+        let Concatenated {
+            values: mut frame_values,
+            failure,
+            ..
+        } = read_concatenated(frame, offset, progress)?;
+        if failure.is_none() && frame_values.len() != 1 {
+            let message = match frame_values.len() {
+                0 => format!("frame {i} is empty"),
+                n => format!("frame {i} holds {n} values rather than one"),
+            };
+            return Ok((values, Some(DecodeFailure { offset, message })));
+        }
+        values.append(&mut frame_values);
+        if failure.is_some() {
+            return Ok((values, failure));
+        }
+    }
+    Ok((values, split_failure))
+}
+
+/// Values decoded by [`read_concatenated`].
+struct Concatenated {
+    values: Vec<(usize, rmpv::Value)>,
+    failure: Option<DecodeFailure>,
+    /// Whether the last of `values` is only what could be made of the value that failed to decode.
+    partial: bool,
+}
+
+/// Decodes concatenated values from `data`, which starts `base_offset` bytes into the whole file,
+/// until reaching either the end or something that doesn't decode.
+fn read_concatenated(
+    data: &[u8],
+    base_offset: usize,
+    progress: &LoadProgress,
+) -> Result<Concatenated, LoadError> {
+    let mut reader = ProgressReader {
+        remaining: data,
+        consumed: base_offset as u64,
+        progress,
+    };
+    let mut values = Vec::new();
+    while !reader.remaining.is_empty() {
+        let value_offset = data.len() - reader.remaining.len();
+        match rmpv::decode::read_value(&mut reader) {
+            Ok(value) => values.push((base_offset + value_offset, value)),
+            Err(_) if progress.is_cancelled() => return Err(LoadError::Cancelled),
+            Err(err) => {
+                let mut partial = false;
+                let failure = match decode_prefix(&data[value_offset..]) {
+                    Err(PartialValue { value, failure }) => {
+                        if let Some(value) = value {
+                            values.push((base_offset + value_offset, value));
+                            partial = true;
+                        }
+                        failure
+                    }
+                    // only happens if rmpv fails for some reason that decode_prefix doesn't know of
+                    Ok(_) => DecodeFailure {
+                        offset: 0,
+                        message: err.to_string(),
+                    },
+                };
+                let offset = base_offset + value_offset + failure.offset;
+                return Ok(Concatenated {
+                    values,
+                    failure: Some(DecodeFailure { offset, ..failure }),
+                    partial,
+                });
+            }
+        }
+        progress.values_parsed.fetch_add(1, Ordering::Relaxed);
+    }
+    Ok(Concatenated {
+        values,
+        failure: None,
+        partial: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(values: &[rmpv::Value]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for value in values {
+            rmpv::encode::write_value(&mut bytes, value).unwrap();
+        }
+        bytes
+    }
+
+    fn notification(method: &str) -> rmpv::Value {
+        rmpv::Value::Array(vec![2.into(), method.into(), rmpv::Value::Array(vec![])])
+    }
+
+    #[test]
+    fn rpc_with_truncated_header_decodes_nothing() {
+        // an array 16 header missing its length
+        let (values, failure) =
+            read_values(&[0xdc], Framing::MsgpackRpc, &LoadProgress::default()).unwrap();
+        assert!(values.is_empty());
+        assert_eq!(failure.unwrap().offset, 0);
+    }
+
+    #[test]
+    fn rpc_checks_the_message_before_a_truncated_header() {
+        let mut data = encode(&[notification("ping"), 5.into()]);
+        data.push(0xdc);
+        let err = read_values(&data, Framing::MsgpackRpc, &LoadProgress::default()).unwrap_err();
+        assert!(
+            matches!(err, LoadError::NotRpcMessage { index: 1, .. }),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn rpc_skips_checking_a_partly_decoded_message() {
+        let data = encode(&[notification("ping"), notification("pong")]);
+        let truncated = &data[..data.len() - 1];
+        let (values, failure) =
+            read_values(truncated, Framing::MsgpackRpc, &LoadProgress::default()).unwrap();
+        assert_eq!(values.len(), 2);
+        assert!(failure.is_some());
+    }
+
+    fn load_bytes(data: &[u8], options: &LoadOptions) -> LoadResult {
+        let source = Source::Pasted {
+            name: "test".to_owned(),
+        };
+        let cached = FileBytes::from(data.to_vec());
+        LoadedFile::load(&source, Some(cached), options, &LoadProgress::default())
+    }
+
+    #[test]
+    fn undecodable_data_says_where_it_failed() {
+        let options = LoadOptions {
+            skip_bytes: 2,
+            ..LoadOptions::default()
+        };
+        // an array 16 header missing its length
+        let err = load_bytes(&[0x01, 0x02, 0xdc], &options).err().unwrap();
+        assert!(
+            matches!(err, LoadError::DecodeError { offset: 2, .. }),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn archives_are_unsupported() {
+        let mut data = b"PK\x03\x04".to_vec();
+        data.extend([0x14, 0x00, 0xdc]);
+        let err = load_bytes(&data, &LoadOptions::default()).err().unwrap();
+        assert!(
+            matches!(
+                err,
+                LoadError::UnsupportedFormat {
+                    format: "a zip archive"
+                }
+            ),
+            "{err}"
+        );
+        // msgpack that happens to start like one still loads, here as three integers
+        assert!(load_bytes(b"BZh", &LoadOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn the_root_of_several_values_spans_them_all() {
+        let options = LoadOptions {
+            framing: Framing::LengthPrefixedBigEndian,
+            ..LoadOptions::default()
+        };
+        let data = [0, 0, 0, 1, 0x01, 0, 0, 0, 2, 0x91, 0x02];
+        let file = load_bytes(&data, &options).ok().unwrap();
+        assert_eq!(file.value_span(&[]), Some(4..11));
+        assert_eq!(file.value_span(&[1]), Some(9..11));
+        assert_eq!(file.value_span(&[1, 0]), Some(10..11));
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+
+/// Decodes `bytes` as msgpack, if they hold exactly one map or array.
+///
+/// Scalars don't count, as nearly any short blob decodes as one (e.g. any single byte below 0x80 is
+/// an integer).
+pub fn decode_msgpack(bytes: &[u8]) -> Option<rmpv::Value> {
+    let mut reader = bytes;
+    let value = rmpv::decode::read_value(&mut reader).ok()?;
+    let is_container = matches!(value, rmpv::Value::Array(_) | rmpv::Value::Map(_));
+    (is_container && reader.is_empty()).then_some(value)
+}
+
+/// Replaces the binary values within `value` that hold msgpack with what they decode to, and those
+/// within what they decode to and so on, adding the positions of the children leading to each (see
+/// [`crate::value::child_of`]) to `decoded`, starting from `indexes`.
+pub fn decode_nested(
+    value: &mut rmpv::Value,
+    indexes: &mut Vec<usize>,
+    decoded: &mut HashSet<Vec<usize>>,
+) {
+    if let rmpv::Value::Binary(bytes) = value {
+        let Some(nested) = decode_msgpack(bytes) else {
+            return;
+        };
+        *value = nested;
+        decoded.insert(indexes.clone());
+    }
+    let children: Box<dyn Iterator<Item = &mut rmpv::Value>> = match value {
+        rmpv::Value::Array(items) => Box::new(items.iter_mut()),
+        rmpv::Value::Map(entries) => Box::new(entries.iter_mut().map(|(_, value)| value)),
+        _ => return,
+    };
+    for (i, child) in children.enumerate() {
+        indexes.push(i);
+        decode_nested(child, indexes, decoded);
+        indexes.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(value: &rmpv::Value) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, value).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn only_a_whole_container_is_msgpack() {
+        let array = rmpv::Value::Array(vec![1.into()]);
+        assert_eq!(decode_msgpack(&encode(&array)), Some(array.clone()));
+        let mut trailing = encode(&array);
+        trailing.push(0x01);
+        assert_eq!(decode_msgpack(&trailing), None);
+        assert_eq!(decode_msgpack(&encode(&"text".into())), None);
+        assert_eq!(decode_msgpack(&[0x92, 0x01]), None);
+    }
+
+    #[test]
+    fn decodes_msgpack_within_msgpack_within_binary() {
+        let innermost = rmpv::Value::Map(vec![("deep".into(), true.into())]);
+        let inner = rmpv::Value::Array(vec![
+            "not msgpack".into(),
+            rmpv::Value::Binary(encode(&innermost)),
+        ]);
+        let mut value = rmpv::Value::Map(vec![
+            ("plain".into(), rmpv::Value::Binary(vec![0x01, 0x02])),
+            ("nested".into(), rmpv::Value::Binary(encode(&inner))),
+        ]);
+        let mut decoded = HashSet::new();
+        decode_nested(&mut value, &mut Vec::new(), &mut decoded);
+
+        let expected = rmpv::Value::Map(vec![
+            ("plain".into(), rmpv::Value::Binary(vec![0x01, 0x02])),
+            (
+                "nested".into(),
+                rmpv::Value::Array(vec!["not msgpack".into(), innermost]),
+            ),
+        ]);
+        assert_eq!(value, expected);
+        assert_eq!(decoded, HashSet::from([vec![1], vec![1, 1]]));
+    }
+}
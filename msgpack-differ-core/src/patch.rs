@@ -0,0 +1,968 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::diff::{children, DiffEntry, DiffStatus};
+use crate::json::{json_key, json_to_msgpack, msgpack_to_json};
+use crate::path::PathSegment;
+use crate::value::{key_counts, HashableValue};
+
+/// One operation of an RFC 6902 JSON Patch document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum JsonPatchOp {
+    Add {
+        path: String,
+        value: serde_json::Value,
+    },
+    Remove {
+        path: String,
+    },
+    Replace {
+        path: String,
+        value: serde_json::Value,
+    },
+    Move {
+        from: String,
+        path: String,
+    },
+    Copy {
+        from: String,
+        path: String,
+    },
+    Test {
+        path: String,
+        value: serde_json::Value,
+    },
+}
+
+#[derive(Debug)]
+pub struct PatchError {
+    /// Index of the failing operation within the patch.
+    pub op_index: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "patch operation {}: {}", self.op_index, self.message)
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+pub fn parse_json_patch(text: &str) -> Result<Vec<JsonPatchOp>, serde_json::Error> {
+    serde_json::from_str(text)
+}
+
+/// Applies a JSON Patch to a msgpack document.
+///
+/// JSON can only express string keys, so a pointer token that matches no string key of a map will also
+/// match an integer key with the same decimal representation.
+pub fn apply_json_patch(doc: &mut rmpv::Value, ops: &[JsonPatchOp]) -> Result<(), PatchError> {
+    for (op_index, op) in ops.iter().enumerate() {
+        apply_op(doc, op).map_err(|message| PatchError { op_index, message })?;
+    }
+    Ok(())
+}
+
+fn apply_op(doc: &mut rmpv::Value, op: &JsonPatchOp) -> Result<(), String> {
+    match op {
+        JsonPatchOp::Add { path, value } => add(doc, &parse_pointer(path)?, json_to_msgpack(value)),
+        JsonPatchOp::Remove { path } => remove(doc, &parse_pointer(path)?).map(|_| ()),
+        JsonPatchOp::Replace { path, value } => {
+            *resolve_mut(doc, &parse_pointer(path)?)? = json_to_msgpack(value);
+            Ok(())
+        }
+        JsonPatchOp::Move { from, path } => {
+            let from = parse_pointer(from)?;
+            let path = parse_pointer(path)?;
+            if path.len() > from.len() && path.starts_with(&from) {
+                return Err("cannot move a value into one of its own children".to_owned());
+            }
+            let value = remove(doc, &from)?;
+            add(doc, &path, value)
+        }
+        JsonPatchOp::Copy { from, path } => {
+            let value = resolve_mut(doc, &parse_pointer(from)?)?.clone();
+            add(doc, &parse_pointer(path)?, value)
+        }
+        JsonPatchOp::Test { path, value } => {
+            let expected = json_to_msgpack(value);
+            let actual = resolve_mut(doc, &parse_pointer(path)?)?;
+            if *actual == expected {
+                Ok(())
+            } else {
+                Err(format!("test failed: expected {expected}, found {actual}"))
+            }
+        }
+    }
+}
+
+/// The JSON Patch that turns A into B, for the diff at `root`. Values are converted as
+/// [`msgpack_to_json`] does, and ignored nodes are left as they are.
+pub fn json_patch(root: DiffEntry) -> Vec<JsonPatchOp> {
+    // JSON objects can't have duplicate keys, so there's no telling which one is meant
+    let pointer = |path: &[Step]| -> String {
+        path.iter()
+            .map(|step| match step {
+                Step::Index(i) => format!("/{i}"),
+                Step::Key { key, .. } => format!("/{}", escape_token(&json_key(key))),
+            })
+            .collect()
+    };
+    changes(root)
+        .into_iter()
+        .map(|change| match change {
+            Change::Add(path, value) => JsonPatchOp::Add {
+                path: pointer(&path),
+                value: msgpack_to_json(value),
+            },
+            Change::Remove(path) => JsonPatchOp::Remove {
+                path: pointer(&path),
+            },
+            Change::Replace(path, value) => JsonPatchOp::Replace {
+                path: pointer(&path),
+                value: msgpack_to_json(value),
+            },
+        })
+        .collect()
+}
+
+/// A change that turns A into B, at a path whose indexes are positions in arrays as patched by
+/// the changes before it.
+enum Change<'a> {
+    Add(Vec<Step>, &'a rmpv::Value),
+    Remove(Vec<Step>),
+    Replace(Vec<Step>, &'a rmpv::Value),
+}
+
+/// One step of the path of a [`Change`].
+#[derive(Clone)]
+enum Step {
+    Index(usize),
+    /// A key of a map along with, if it occurs more than once in A or B, which occurrence of it in
+    /// the map as patched by the changes before this is meant, counting from 0.
+    Key {
+        key: rmpv::Value,
+        occurrence: Option<usize>,
+    },
+}
+
+/// The changes that turn A into B, for the diff at `root`, leaving ignored nodes as they are.
+fn changes(root: DiffEntry) -> Vec<Change> {
+    let mut changes = Vec::new();
+    push_changes(root, &mut Vec::new(), &mut changes);
+    changes
+}
+
+fn push_changes<'a>(entry: DiffEntry<'a>, path: &mut Vec<Step>, changes: &mut Vec<Change<'a>>) {
+    match (entry.node.status, entry.b) {
+        (DiffStatus::Unchanged | DiffStatus::Ignored, _) => {}
+        (DiffStatus::Added, Some(b)) => changes.push(Change::Add(path.clone(), b)),
+        // the whole document can't be removed, and there's nothing for it to be replaced with
+        (DiffStatus::Removed, _) if !path.is_empty() => changes.push(Change::Remove(path.clone())),
+        (DiffStatus::Changed, Some(b)) => changes.push(Change::Replace(path.clone(), b)),
+        (DiffStatus::ContainsChanges, _) => {
+            let maps = match (entry.a, entry.b) {
+                (Some(rmpv::Value::Map(a)), Some(rmpv::Value::Map(b))) => Some((a, b)),
+                _ => None,
+            };
+            let counts = maps.map(|(a, b)| (key_counts(a), key_counts(b)));
+            // where the next item is in the array as patched so far, as additions and removals
+            // before it move it, and likewise how many of each key the map has so far
+            let mut position = 0;
+            let mut occurrences: HashMap<HashableValue, usize> = HashMap::new();
+            for (segment, child) in children(entry.node, entry.a, entry.b) {
+                let is_there = match child.node.status {
+                    DiffStatus::Removed => false,
+                    DiffStatus::Ignored => child.a.is_some(),
+                    _ => true,
+                };
+                let (a_index, b_index) = (child.node.a_index, child.node.b_index);
+                let step = match (segment, maps.zip(counts.as_ref())) {
+                    (PathSegment::Key(key), Some(((a, b), (a_counts, b_counts)))) => {
+                        let entry_key = match (a_index, b_index) {
+                            (Some(i), _) => &a[i].0,
+                            (None, Some(i)) => &b[i].0,
+                            (None, None) => unreachable!("diff child should exist on a side"),
+                        };
+                        let occurrence = occurrences.entry(HashableValue(entry_key)).or_default();
+                        let duplicated = a_index.is_some_and(|i| a_counts[i] > 1)
+                            || b_index.is_some_and(|i| b_counts[i] > 1);
+                        let step = Step::Key {
+                            key,
+                            occurrence: duplicated.then_some(*occurrence),
+                        };
+                        *occurrence += usize::from(is_there);
+                        step
+                    }
+                    (PathSegment::Key(key), None) => Step::Key {
+                        key,
+                        occurrence: None,
+                    },
+                    (PathSegment::Index(_), _) => {
+                        let step = Step::Index(position);
+                        position += usize::from(is_there);
+                        step
+                    }
+                };
+                path.push(step);
+                push_changes(child, path, changes);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The JSON Merge Patch (RFC 7396) that turns A into B, for the diff at `root`. That can only
+/// express changes to maps, so arrays that changed are replaced as a whole, and values that became
+/// nil can't be told from ones that were removed.
+pub fn json_merge_patch(root: DiffEntry) -> serde_json::Value {
+    match (root.node.status, root.a, root.b) {
+        (DiffStatus::Unchanged | DiffStatus::Ignored, _, _) => {
+            serde_json::Value::Object(serde_json::Map::new())
+        }
+        (DiffStatus::ContainsChanges, Some(rmpv::Value::Map(_)), Some(rmpv::Value::Map(_))) => {
+            let mut patch = serde_json::Map::new();
+            for (segment, child) in children(root.node, root.a, root.b) {
+                let PathSegment::Key(key) = segment else {
+                    continue;
+                };
+                let value = match child.node.status {
+                    DiffStatus::Unchanged | DiffStatus::Ignored => continue,
+                    DiffStatus::Removed => serde_json::Value::Null,
+                    _ => json_merge_patch(child),
+                };
+                patch.insert(json_key(&key), value);
+            }
+            serde_json::Value::Object(patch)
+        }
+        (_, _, b) => b.map_or(serde_json::Value::Null, msgpack_to_json),
+    }
+}
+
+/// A patch that turns one msgpack value into another, written as msgpack itself to ship small
+/// deltas of big files. Unlike a JSON Patch it keeps values and keys exactly as they are, binary
+/// and extension values included.
+///
+/// It's written as a map of `"msgpack-differ patch"` to the version of the format, `"base_crc32"`
+/// to the CRC32 of the bytes that the value it applies to was decoded from, `"decode_nested"` to
+/// whether it was, and `"ops"` to an array of operations, each `[code, path, value]` or, for a
+/// removal, `[code, path]`. Codes are those of [`MsgpackPatchOp`], and paths are arrays of indexes
+/// into arrays and keys of maps. Keys that occur more than once in their map, or that are arrays
+/// themselves, are written as `[key, n]` for the `n`th occurrence of the key in the map as patched
+/// by the operations before, counting from 0.
+///
+/// Version 1 of the format had no such pairs, and versions 1 and 2 had no `"decode_nested"` and the
+/// CRC32 of the value as encoded by rmpv instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgpackPatch {
+    pub base_crc32: u32,
+    /// Whether the value it applies to had the msgpack within its binary values decoded, as by
+    /// [`crate::loader::LoadOptions::decode_nested`], so that paths lead into it.
+    pub decode_nested: bool,
+    pub ops: Vec<MsgpackPatchOp>,
+}
+
+/// One operation of a [`MsgpackPatch`]. Indexes into arrays are positions in them as patched by
+/// the operations before.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MsgpackPatchOp {
+    /// Inserts into an array, or adds an entry to the end of a map even if it has the key already.
+    Add {
+        path: Vec<rmpv::Value>,
+        value: rmpv::Value,
+    },
+    Remove {
+        path: Vec<rmpv::Value>,
+    },
+    Replace {
+        path: Vec<rmpv::Value>,
+        value: rmpv::Value,
+    },
+}
+
+/// Key of a [`MsgpackPatch`] that tells it apart from other msgpack, along with its version.
+const MSGPACK_PATCH_FORMAT: &str = "msgpack-differ patch";
+const MSGPACK_PATCH_VERSION: u64 = 3;
+
+impl MsgpackPatchOp {
+    const ADD: u64 = 0;
+    const REMOVE: u64 = 1;
+    const REPLACE: u64 = 2;
+}
+
+/// The [`MsgpackPatch`] that turns A into B, for the diff at `root`, leaving ignored nodes as they
+/// are. `base` is the bytes that A was decoded from, with `decode_nested` as it was decoded.
+pub fn msgpack_patch(root: DiffEntry, base: &[u8], decode_nested: bool) -> MsgpackPatch {
+    let path = |path: Vec<Step>| -> Vec<rmpv::Value> {
+        path.into_iter()
+            .map(|step| match step {
+                Step::Index(i) => rmpv::Value::from(i),
+                Step::Key {
+                    key,
+                    occurrence: None,
+                } if !key.is_array() => key,
+                Step::Key { key, occurrence } => {
+                    rmpv::Value::Array(vec![key, occurrence.unwrap_or(0).into()])
+                }
+            })
+            .collect()
+    };
+    let ops = changes(root)
+        .into_iter()
+        .map(|change| match change {
+            Change::Add(at, value) => MsgpackPatchOp::Add {
+                path: path(at),
+                value: value.clone(),
+            },
+            Change::Remove(at) => MsgpackPatchOp::Remove { path: path(at) },
+            Change::Replace(at, value) => MsgpackPatchOp::Replace {
+                path: path(at),
+                value: value.clone(),
+            },
+        })
+        .collect();
+    MsgpackPatch {
+        base_crc32: crc32fast::hash(base),
+        decode_nested,
+        ops,
+    }
+}
+
+/// CRC32 of `value` as rmpv encodes it, which a [`MsgpackPatch`] of version 1 or 2 of the format
+/// is checked against before it's applied.
+fn value_crc32(value: &rmpv::Value) -> u32 {
+    let mut encoded = Vec::new();
+    rmpv::encode::write_value(&mut encoded, value).expect("writing to a Vec can't fail");
+    crc32fast::hash(&encoded)
+}
+
+pub fn encode_msgpack_patch(patch: &MsgpackPatch) -> Vec<u8> {
+    let path = |path: &[rmpv::Value]| rmpv::Value::Array(path.to_vec());
+    let ops = patch
+        .ops
+        .iter()
+        .map(|op| {
+            rmpv::Value::Array(match op {
+                MsgpackPatchOp::Add { path: at, value } => {
+                    vec![MsgpackPatchOp::ADD.into(), path(at), value.clone()]
+                }
+                MsgpackPatchOp::Remove { path: at } => {
+                    vec![MsgpackPatchOp::REMOVE.into(), path(at)]
+                }
+                MsgpackPatchOp::Replace { path: at, value } => {
+                    vec![MsgpackPatchOp::REPLACE.into(), path(at), value.clone()]
+                }
+            })
+        })
+        .collect();
+    let patch = rmpv::Value::Map(vec![
+        (MSGPACK_PATCH_FORMAT.into(), MSGPACK_PATCH_VERSION.into()),
+        ("base_crc32".into(), patch.base_crc32.into()),
+        ("decode_nested".into(), patch.decode_nested.into()),
+        ("ops".into(), rmpv::Value::Array(ops)),
+    ]);
+    let mut encoded = Vec::new();
+    rmpv::encode::write_value(&mut encoded, &patch).expect("writing to a Vec can't fail");
+    encoded
+}
+
+/// Whether `bytes` start like a [`MsgpackPatch`] rather than a JSON Patch, which is text.
+pub fn is_msgpack_patch(bytes: &[u8]) -> bool {
+    matches!(bytes.first(), Some(0x80..=0x8f | 0xde | 0xdf))
+}
+
+pub fn decode_msgpack_patch(mut bytes: &[u8]) -> Result<MsgpackPatch, String> {
+    let value = rmpv::decode::read_value(&mut bytes).map_err(|err| err.to_string())?;
+    let rmpv::Value::Map(entries) = value else {
+        return Err("not a msgpack patch: not a map".to_owned());
+    };
+    let field = |name: &str| {
+        entries
+            .iter()
+            .find(|(key, _)| key.as_str() == Some(name))
+            .map(|(_, value)| value)
+    };
+    let version = match field(MSGPACK_PATCH_FORMAT).and_then(rmpv::Value::as_u64) {
+        Some(version @ (1..=MSGPACK_PATCH_VERSION)) => version,
+        Some(version) => return Err(format!("unsupported msgpack patch version {version}")),
+        None => return Err("not a msgpack patch: no format version".to_owned()),
+    };
+    let base_crc32 = field("base_crc32")
+        .and_then(rmpv::Value::as_u64)
+        .and_then(|crc| u32::try_from(crc).ok())
+        .ok_or("msgpack patch has no base_crc32")?;
+    let decode_nested = match field("decode_nested") {
+        Some(decode_nested) => decode_nested
+            .as_bool()
+            .ok_or("msgpack patch has a decode_nested that isn't a boolean")?,
+        None => false,
+    };
+    let Some(rmpv::Value::Array(ops)) = field("ops") else {
+        return Err("msgpack patch has no ops".to_owned());
+    };
+    let ops = ops
+        .iter()
+        .enumerate()
+        .map(|(i, op)| {
+            let invalid = || format!("patch operation {i} is malformed");
+            let rmpv::Value::Array(op) = op else {
+                return Err(invalid());
+            };
+            let (code, mut path, value) = match op.as_slice() {
+                [code, rmpv::Value::Array(path)] => (code.as_u64(), path.clone(), None),
+                [code, rmpv::Value::Array(path), value] => {
+                    (code.as_u64(), path.clone(), Some(value.clone()))
+                }
+                _ => return Err(invalid()),
+            };
+            if version == 1 {
+                // arrays in paths could only be keys of maps
+                for segment in path.iter_mut().filter(|segment| segment.is_array()) {
+                    *segment = rmpv::Value::Array(vec![segment.clone(), 0.into()]);
+                }
+            }
+            match (code, value) {
+                (Some(MsgpackPatchOp::ADD), Some(value)) => Ok(MsgpackPatchOp::Add { path, value }),
+                (Some(MsgpackPatchOp::REMOVE), None) => Ok(MsgpackPatchOp::Remove { path }),
+                (Some(MsgpackPatchOp::REPLACE), Some(value)) => {
+                    Ok(MsgpackPatchOp::Replace { path, value })
+                }
+                _ => Err(invalid()),
+            }
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(MsgpackPatch {
+        base_crc32,
+        decode_nested,
+        ops,
+    })
+}
+
+/// Applies a [`MsgpackPatch`] to `doc`, decoded from `base` (with [`MsgpackPatch::decode_nested`]
+/// as the patch says), once it's checked that it's the value the patch was made for. Operations
+/// failing are reported as with [`apply_json_patch`], and a mismatched base as if the first one
+/// failed.
+pub fn apply_msgpack_patch(
+    doc: &mut rmpv::Value,
+    patch: &MsgpackPatch,
+    base: &[u8],
+) -> Result<(), PatchError> {
+    let crc32 = crc32fast::hash(base);
+    // patches made by earlier versions have the CRC32 of the value instead
+    if crc32 != patch.base_crc32 && value_crc32(doc) != patch.base_crc32 {
+        return Err(PatchError {
+            op_index: 0,
+            message: format!(
+                "the patch is for a value with CRC32 {:08x}, not {crc32:08x}",
+                patch.base_crc32
+            ),
+        });
+    }
+    for (op_index, op) in patch.ops.iter().enumerate() {
+        apply_msgpack_op(doc, op).map_err(|message| PatchError { op_index, message })?;
+    }
+    Ok(())
+}
+
+fn apply_msgpack_op(doc: &mut rmpv::Value, op: &MsgpackPatchOp) -> Result<(), String> {
+    let (path, value) = match op {
+        MsgpackPatchOp::Add { path, value } | MsgpackPatchOp::Replace { path, value } => {
+            (path, Some(value.clone()))
+        }
+        MsgpackPatchOp::Remove { path } => (path, None),
+    };
+    let Some((last, parent)) = path.split_last() else {
+        return match value {
+            Some(value) => {
+                *doc = value;
+                Ok(())
+            }
+            None => Err("cannot remove the whole document".to_owned()),
+        };
+    };
+    let mut current = doc;
+    for segment in parent {
+        current = child_mut(current, segment)?;
+    }
+    match (op, current) {
+        (MsgpackPatchOp::Add { .. }, rmpv::Value::Array(items)) => {
+            let index = msgpack_index(last, items.len() + 1)?;
+            items.insert(index, value.expect("adding a value"));
+        }
+        (MsgpackPatchOp::Add { .. }, rmpv::Value::Map(entries)) => {
+            let key = match last.as_array().map(Vec::as_slice) {
+                Some([key, _]) => key,
+                Some(_) => return Err(format!("malformed key {last}")),
+                None => last,
+            };
+            entries.push((key.clone(), value.expect("adding a value")));
+        }
+        (MsgpackPatchOp::Remove { .. }, rmpv::Value::Array(items)) => {
+            let index = msgpack_index(last, items.len())?;
+            items.remove(index);
+        }
+        (MsgpackPatchOp::Remove { .. }, rmpv::Value::Map(entries)) => {
+            let index = key_position(entries, last).ok_or_else(|| format!("no key {last}"))?;
+            entries.remove(index);
+        }
+        (MsgpackPatchOp::Replace { .. }, current) => {
+            *child_mut(current, last)? = value.expect("replacing with a value");
+        }
+        (_, other) => return Err(format!("cannot index into {other} with {last}")),
+    }
+    Ok(())
+}
+
+fn child_mut<'a>(
+    value: &'a mut rmpv::Value,
+    segment: &rmpv::Value,
+) -> Result<&'a mut rmpv::Value, String> {
+    match value {
+        rmpv::Value::Array(items) => {
+            let index = msgpack_index(segment, items.len())?;
+            Ok(&mut items[index])
+        }
+        rmpv::Value::Map(entries) => {
+            let index =
+                key_position(entries, segment).ok_or_else(|| format!("no key {segment}"))?;
+            Ok(&mut entries[index].1)
+        }
+        other => Err(format!("cannot index into {other} with {segment}")),
+    }
+}
+
+/// Where the entry `segment` of a [`MsgpackPatch`] path leads to is in a map: the first one with
+/// that key, or for `[key, n]` the `n`th.
+fn key_position(entries: &[(rmpv::Value, rmpv::Value)], segment: &rmpv::Value) -> Option<usize> {
+    let (key, occurrence) = match segment {
+        rmpv::Value::Array(pair) => match pair.as_slice() {
+            [key, occurrence] => (key, usize::try_from(occurrence.as_u64()?).ok()?),
+            _ => return None,
+        },
+        key => (key, 0),
+    };
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(_, (entry_key, _))| entry_key == key)
+        .nth(occurrence)
+        .map(|(index, _)| index)
+}
+
+fn msgpack_index(segment: &rmpv::Value, len: usize) -> Result<usize, String> {
+    segment
+        .as_u64()
+        .and_then(|index| usize::try_from(index).ok())
+        .filter(|&index| index < len)
+        .ok_or_else(|| format!("array index {segment} out of bounds (length {len})"))
+}
+
+/// Escapes a reference token of a JSON Pointer, the reverse of what [`parse_pointer`] does.
+fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Splits a JSON Pointer (RFC 6901) into its unescaped reference tokens.
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(format!(
+            "invalid JSON pointer {pointer:?}: must start with '/'"
+        ));
+    };
+    Ok(rest
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+fn find_key(entries: &[(rmpv::Value, rmpv::Value)], token: &str) -> Option<usize> {
+    entries
+        .iter()
+        .position(|(k, _)| k.as_str() == Some(token))
+        .or_else(|| {
+            entries.iter().position(|(k, _)| match k {
+                rmpv::Value::Integer(i) => i.to_string() == token,
+                _ => false,
+            })
+        })
+}
+
+fn parse_index(token: &str, len: usize) -> Result<usize, String> {
+    match token.parse::<usize>() {
+        Ok(index) if index < len && (token == "0" || !token.starts_with('0')) => Ok(index),
+        _ => Err(format!(
+            "array index {token:?} out of bounds (length {len})"
+        )),
+    }
+}
+
+fn resolve_mut<'a>(
+    doc: &'a mut rmpv::Value,
+    tokens: &[String],
+) -> Result<&'a mut rmpv::Value, String> {
+    let mut current = doc;
+    for token in tokens {
+        current = match current {
+            rmpv::Value::Array(items) => {
+                let index = parse_index(token, items.len())?;
+                &mut items[index]
+            }
+            rmpv::Value::Map(entries) => {
+                let index = find_key(entries, token).ok_or_else(|| format!("no key {token:?}"))?;
+                &mut entries[index].1
+            }
+            other => return Err(format!("cannot index into {other} with {token:?}")),
+        };
+    }
+    Ok(current)
+}
+
+fn add(doc: &mut rmpv::Value, tokens: &[String], value: rmpv::Value) -> Result<(), String> {
+    let Some((last, parent)) = tokens.split_last() else {
+        *doc = value;
+        return Ok(());
+    };
+    match resolve_mut(doc, parent)? {
+        rmpv::Value::Array(items) => {
+            let index = if last == "-" {
+                items.len()
+            } else {
+                // inserting directly after the last element is allowed
+                parse_index(last, items.len() + 1)?
+            };
+            items.insert(index, value);
+        }
+        rmpv::Value::Map(entries) => match find_key(entries, last) {
+            Some(index) => entries[index].1 = value,
+            None => entries.push((rmpv::Value::from(last.as_str()), value)),
+        },
+        other => return Err(format!("cannot add {last:?} to {other}")),
+    }
+    Ok(())
+}
+
+fn remove(doc: &mut rmpv::Value, tokens: &[String]) -> Result<rmpv::Value, String> {
+    let Some((last, parent)) = tokens.split_last() else {
+        return Err("cannot remove the whole document".to_owned());
+    };
+    match resolve_mut(doc, parent)? {
+        rmpv::Value::Array(items) => {
+            let index = parse_index(last, items.len())?;
+            Ok(items.remove(index))
+        }
+        rmpv::Value::Map(entries) => {
+            let index = find_key(entries, last).ok_or_else(|| format!("no key {last:?}"))?;
+            Ok(entries.remove(index).1)
+        }
+        other => Err(format!("cannot remove {last:?} from {other}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::diff_values;
+
+    fn map(entries: &[(rmpv::Value, i64)]) -> rmpv::Value {
+        rmpv::Value::Map(
+            entries
+                .iter()
+                .map(|(key, value)| (key.clone(), (*value).into()))
+                .collect(),
+        )
+    }
+
+    /// A patched with the msgpack patch made from its diff with B, by way of encoding the patch.
+    fn patched(a: &rmpv::Value, b: &rmpv::Value) -> rmpv::Value {
+        let root = diff_values(Some(a), Some(b));
+        let entry = DiffEntry {
+            node: &root,
+            a: Some(a),
+            b: Some(b),
+        };
+        let base = encode(a);
+        let patch = msgpack_patch(entry, &base, false);
+        let patch = decode_msgpack_patch(&encode_msgpack_patch(&patch)).unwrap();
+        let mut patched = a.clone();
+        apply_msgpack_patch(&mut patched, &patch, &base).unwrap();
+        patched
+    }
+
+    fn encode(value: &rmpv::Value) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        rmpv::encode::write_value(&mut encoded, value).unwrap();
+        encoded
+    }
+
+    fn assert_same(a: &rmpv::Value, b: &rmpv::Value) {
+        let root = diff_values(Some(a), Some(b));
+        assert_eq!(root.difference_count, 0, "{a} differs from {b}");
+    }
+
+    #[test]
+    fn msgpack_patch_of_duplicate_keys() {
+        let k = rmpv::Value::from("k");
+        let other = rmpv::Value::from("other");
+        let a = map(&[(k.clone(), 1), (k.clone(), 2), (other.clone(), 0)]);
+        let b = map(&[(other, 0), (k.clone(), 1), (k.clone(), 3), (k, 4)]);
+        assert_same(&patched(&a, &b), &b);
+    }
+
+    #[test]
+    fn msgpack_patch_removing_duplicate_keys() {
+        let k = rmpv::Value::from("k");
+        let a = map(&[
+            (k.clone(), 1),
+            ("x".into(), 0),
+            (k.clone(), 2),
+            (k.clone(), 3),
+        ]);
+        let b = map(&[(k.clone(), 1), ("x".into(), 0), (k, 5)]);
+        assert_eq!(patched(&a, &b), b);
+    }
+
+    #[test]
+    fn msgpack_patch_of_array_keys() {
+        let key = rmpv::Value::Array(vec![1.into(), 2.into()]);
+        let a = map(&[(key.clone(), 1), (rmpv::Value::from(1), 0)]);
+        let b = map(&[(key.clone(), 2), (rmpv::Value::from(1), 0)]);
+        assert_eq!(patched(&a, &b), b);
+        let root = diff_values(Some(&a), Some(&b));
+        let entry = DiffEntry {
+            node: &root,
+            a: Some(&a),
+            b: Some(&b),
+        };
+        let wrapped = rmpv::Value::Array(vec![key, 0.into()]);
+        assert_eq!(
+            msgpack_patch(entry, &encode(&a), false).ops,
+            [MsgpackPatchOp::Replace {
+                path: vec![wrapped],
+                value: 2.into()
+            }]
+        );
+    }
+
+    #[test]
+    fn msgpack_add_appends_to_maps() {
+        let k = rmpv::Value::from("k");
+        let mut doc = map(&[(k.clone(), 1)]);
+        let op = MsgpackPatchOp::Add {
+            path: vec![k.clone()],
+            value: 2.into(),
+        };
+        apply_msgpack_op(&mut doc, &op).unwrap();
+        assert_eq!(doc, map(&[(k.clone(), 1), (k, 2)]));
+    }
+
+    #[test]
+    fn version_1_msgpack_patches_still_apply() {
+        let key = rmpv::Value::Array(vec!["x".into()]);
+        let doc = map(&[(key.clone(), 1)]);
+        let patch = rmpv::Value::Map(vec![
+            (MSGPACK_PATCH_FORMAT.into(), 1.into()),
+            ("base_crc32".into(), value_crc32(&doc).into()),
+            (
+                "ops".into(),
+                rmpv::Value::Array(vec![rmpv::Value::Array(vec![
+                    MsgpackPatchOp::REPLACE.into(),
+                    rmpv::Value::Array(vec![key.clone()]),
+                    7.into(),
+                ])]),
+            ),
+        ]);
+        let patch = decode_msgpack_patch(&encode(&patch)).unwrap();
+        assert!(!patch.decode_nested);
+        let mut patched = doc.clone();
+        // however the value was encoded
+        apply_msgpack_patch(&mut patched, &patch, &[]).unwrap();
+        assert_eq!(patched, map(&[(key, 7)]));
+    }
+
+    #[test]
+    fn msgpack_patches_are_for_the_bytes_of_their_base() {
+        // [1, 2], with the 1 as a uint16
+        let base = [0x92, 0xcd, 0x00, 0x01, 0x02];
+        let a = rmpv::decode::read_value(&mut &base[..]).unwrap();
+        let b = rmpv::Value::Array(vec![1.into(), 3.into()]);
+        let root = diff_values(Some(&a), Some(&b));
+        let entry = DiffEntry {
+            node: &root,
+            a: Some(&a),
+            b: Some(&b),
+        };
+        let patch = msgpack_patch(entry, &base, true);
+        assert_eq!(patch.base_crc32, crc32fast::hash(&base));
+        let patch = decode_msgpack_patch(&encode_msgpack_patch(&patch)).unwrap();
+        assert!(patch.decode_nested);
+
+        let mut patched = a.clone();
+        apply_msgpack_patch(&mut patched, &patch, &base).unwrap();
+        assert_eq!(patched, b);
+        let mut patched = a.clone();
+        let err = apply_msgpack_patch(&mut patched, &patch, &encode(&a)).unwrap_err();
+        assert_eq!(err.op_index, 0);
+        assert_eq!(patched, a);
+    }
+
+    #[test]
+    fn msgpack_patches_of_nested_msgpack_apply_to_the_file() {
+        use crate::loader::{LoadOptions, LoadProgress, LoadedFile, Source};
+
+        let with_blob = |items: Vec<rmpv::Value>| {
+            let blob = rmpv::Value::Binary(encode(&rmpv::Value::Array(items)));
+            encode(&rmpv::Value::Map(vec![("blob".into(), blob)]))
+        };
+        let nested = LoadOptions {
+            decode_nested: true,
+            ..LoadOptions::default()
+        };
+        let load = |bytes: &[u8], options: &LoadOptions| {
+            let source = Source::Pasted {
+                name: "test".to_owned(),
+            };
+            let cached = Some(bytes.to_vec().into());
+            let loaded = LoadedFile::load(&source, cached, options, &LoadProgress::default());
+            loaded.ok().unwrap()
+        };
+        let file_a = load(&with_blob(vec![1.into()]), &nested);
+        let file_b = load(&with_blob(vec![2.into()]), &nested);
+        let root = diff_values(Some(&file_a.parsed), Some(&file_b.parsed));
+        let entry = DiffEntry {
+            node: &root,
+            a: Some(&file_a.parsed),
+            b: Some(&file_b.parsed),
+        };
+        let patch = msgpack_patch(entry, file_a.value_bytes(&[]).unwrap(), true);
+        let patch = decode_msgpack_patch(&encode_msgpack_patch(&patch)).unwrap();
+
+        let options = LoadOptions {
+            decode_nested: patch.decode_nested,
+            ..LoadOptions::default()
+        };
+        let base = load(&with_blob(vec![1.into()]), &options);
+        let mut doc = rmpv::Value::clone(&base.parsed);
+        apply_msgpack_patch(&mut doc, &patch, base.value_bytes(&[]).unwrap()).unwrap();
+        let patched = base.encode_document(&doc, &base.nested).unwrap();
+        assert_eq!(patched, with_blob(vec![2.into()]));
+    }
+
+    /// `json` as msgpack, for writing documents in tests as JSON.
+    fn doc(json: serde_json::Value) -> rmpv::Value {
+        json_to_msgpack(&json)
+    }
+
+    fn apply_json(doc: &mut rmpv::Value, ops: serde_json::Value) -> Result<(), PatchError> {
+        apply_json_patch(
+            doc,
+            &serde_json::from_value::<Vec<JsonPatchOp>>(ops).unwrap(),
+        )
+    }
+
+    #[test]
+    fn json_patch_round_trip() {
+        let a = doc(serde_json::json!({
+            "name": "x",
+            "items": [1, 2, 3, {"hp": 10}],
+            "gone": true,
+        }));
+        let b = doc(serde_json::json!({
+            "name": "y",
+            "items": [1, 3, {"hp": 7}, 4],
+            "new": {"a": [1]},
+        }));
+        let root = diff_values(Some(&a), Some(&b));
+        let entry = DiffEntry {
+            node: &root,
+            a: Some(&a),
+            b: Some(&b),
+        };
+        let mut patched = a.clone();
+        apply_json_patch(&mut patched, &json_patch(entry)).unwrap();
+        assert_same(&patched, &b);
+        assert_same(&self::patched(&a, &b), &b);
+    }
+
+    #[test]
+    fn pointers_escape_tildes_and_slashes() {
+        assert_eq!(escape_token("a/b~c"), "a~1b~0c");
+        assert_eq!(parse_pointer("/a~1b~0c").unwrap(), ["a/b~c"]);
+        // "~01" is an escaped tilde followed by a 1, not an escaped slash
+        assert_eq!(parse_pointer("/~01").unwrap(), ["~1"]);
+        assert_eq!(parse_pointer("").unwrap(), Vec::<String>::new());
+        assert_eq!(parse_pointer("/").unwrap(), [""]);
+        assert!(parse_pointer("a").is_err());
+
+        let a = doc(serde_json::json!({"a/b~c": 1, "~1": 2}));
+        let b = doc(serde_json::json!({"a/b~c": 3, "~1": 2}));
+        let root = diff_values(Some(&a), Some(&b));
+        let entry = DiffEntry {
+            node: &root,
+            a: Some(&a),
+            b: Some(&b),
+        };
+        let ops = json_patch(entry);
+        assert!(
+            matches!(&ops[..], [JsonPatchOp::Replace { path, .. }] if path == "/a~1b~0c"),
+            "{ops:?}"
+        );
+        let mut patched = a.clone();
+        apply_json_patch(&mut patched, &ops).unwrap();
+        assert_eq!(patched, b);
+    }
+
+    #[test]
+    fn json_add_at_dash_appends() {
+        let mut items = doc(serde_json::json!({"items": [1, 2]}));
+        apply_json(
+            &mut items,
+            serde_json::json!([
+                {"op": "add", "path": "/items/-", "value": 3},
+                // directly after the last item is the same as "-"
+                {"op": "add", "path": "/items/3", "value": 4},
+                {"op": "add", "path": "/items/0", "value": 0},
+            ]),
+        )
+        .unwrap();
+        assert_eq!(items, doc(serde_json::json!({"items": [0, 1, 2, 3, 4]})));
+    }
+
+    #[test]
+    fn out_of_range_indexes_are_errors() {
+        let original = doc(serde_json::json!({"items": [1, 2]}));
+        for op in [
+            serde_json::json!({"op": "add", "path": "/items/3", "value": 0}),
+            serde_json::json!({"op": "remove", "path": "/items/2"}),
+            serde_json::json!({"op": "remove", "path": "/items/-"}),
+            serde_json::json!({"op": "replace", "path": "/items/-", "value": 0}),
+            serde_json::json!({"op": "replace", "path": "/items/01", "value": 0}),
+            serde_json::json!({"op": "test", "path": "/items/-1", "value": 0}),
+        ] {
+            let mut items = original.clone();
+            let ops = serde_json::json!([{"op": "test", "path": "/items/0", "value": 1}, op]);
+            let err = apply_json(&mut items, ops).unwrap_err();
+            assert_eq!(err.op_index, 1, "{op}");
+            assert!(err.message.contains("out of bounds"), "{op}: {err}");
+        }
+
+        let mut items = rmpv::Value::Array(vec![1.into()]);
+        let add = |index: u64| MsgpackPatchOp::Add {
+            path: vec![index.into()],
+            value: 0.into(),
+        };
+        assert!(apply_msgpack_op(&mut items, &add(2)).is_err());
+        apply_msgpack_op(&mut items, &add(1)).unwrap();
+        let remove = MsgpackPatchOp::Remove {
+            path: vec![2.into()],
+        };
+        assert!(apply_msgpack_op(&mut items, &remove).is_err());
+        assert_eq!(items, rmpv::Value::Array(vec![1.into(), 0.into()]));
+    }
+}
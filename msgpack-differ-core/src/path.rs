@@ -0,0 +1,451 @@
+use std::fmt::Write;
+
+use crate::json::json_key;
+use crate::value::same_value;
+
+/// One step from a container value to one of its children.
+#[derive(Debug, Clone)]
+pub enum PathSegment {
+    Index(usize),
+    Key(rmpv::Value),
+}
+
+/// Keys are compared as by [`same_value`], so that paths through NaN keys are equal.
+impl PartialEq for PathSegment {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PathSegment::Index(a), PathSegment::Index(b)) => a == b,
+            (PathSegment::Key(a), PathSegment::Key(b)) => same_value(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// Location of a value within a document, e.g. `players[3].inventory.items[0].name`.
+///
+/// String keys that look like identifiers are written as `.key`, other string keys as `["some key"]`,
+/// and keys of any other type in braces, e.g. `{42}` or `{true}`. Float keys say which width they
+/// are, e.g. `{0.5f32}`, as a key of one doesn't find the entry of a key of the other.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValuePath {
+    segments: Vec<PathSegment>,
+}
+
+impl ValuePath {
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, segment: PathSegment) {
+        self.segments.push(segment);
+    }
+
+    pub fn pop(&mut self) -> Option<PathSegment> {
+        self.segments.pop()
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    /// Finds the value at this path within `root`. Keys resolve to the first map entry with that key,
+    /// compared as by [`same_value`].
+    pub fn resolve<'a>(&self, root: &'a rmpv::Value) -> Option<&'a rmpv::Value> {
+        self.segments
+            .iter()
+            .try_fold(root, |value, segment| match (segment, value) {
+                (PathSegment::Index(index), rmpv::Value::Array(items)) => items.get(*index),
+                (PathSegment::Key(key), rmpv::Value::Map(entries)) => entries
+                    .iter()
+                    .find(|(k, _)| same_value(k, key))
+                    .map(|(_, v)| v),
+                _ => None,
+            })
+    }
+
+    /// Like [`Self::resolve`], but finds the positions of the children leading to the value, as
+    /// used by [`crate::value::child_of`].
+    pub fn indexes(&self, root: &rmpv::Value) -> Option<Vec<usize>> {
+        let mut value = root;
+        let mut indexes = Vec::with_capacity(self.segments.len());
+        for segment in &self.segments {
+            let index = match (segment, value) {
+                (PathSegment::Index(index), rmpv::Value::Array(items)) if *index < items.len() => {
+                    *index
+                }
+                (PathSegment::Key(key), rmpv::Value::Map(entries)) => {
+                    entries.iter().position(|(k, _)| same_value(k, key))?
+                }
+                _ => return None,
+            };
+            value = crate::value::child_of(value, index)?;
+            indexes.push(index);
+        }
+        Some(indexes)
+    }
+}
+
+impl ValuePath {
+    /// Formats the path as a JSON Pointer (RFC 6901), e.g. `/config/servers/2/host`, for the path's
+    /// value as converted to JSON.
+    pub fn to_json_pointer(&self) -> String {
+        let mut pointer = String::new();
+        for segment in &self.segments {
+            pointer.push('/');
+            match segment {
+                PathSegment::Index(index) => pointer.push_str(&index.to_string()),
+                PathSegment::Key(key) => {
+                    pointer.push_str(&json_key(key).replace('~', "~0").replace('/', "~1"))
+                }
+            }
+        }
+        pointer
+    }
+
+    /// Formats the path as a jq expression, e.g. `.config.servers[2].host`, for the path's value as
+    /// converted to JSON.
+    pub fn to_jq(&self) -> String {
+        if self.segments.is_empty() {
+            return ".".to_owned();
+        }
+        let mut expression = String::new();
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Index(index) => write!(expression, "[{index}]"),
+                PathSegment::Key(key) => {
+                    let key = json_key(key);
+                    let mut chars = key.chars();
+                    let is_start = |c: char| c.is_ascii_alphabetic() || c == '_';
+                    let is_jq_identifier = chars.next().is_some_and(is_start)
+                        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+                    if is_jq_identifier {
+                        write!(expression, ".{key}")
+                    } else {
+                        // JSON string syntax is what jq takes too
+                        let quoted = serde_json::Value::String(key).to_string();
+                        write!(expression, "[{quoted}]")
+                    }
+                }
+            }
+            .expect("writing to a String can't fail");
+        }
+        // jq wants a `.` before the first bracket
+        if expression.starts_with('[') {
+            expression.insert(0, '.');
+        }
+        expression
+    }
+}
+
+impl FromIterator<PathSegment> for ValuePath {
+    fn from_iter<I: IntoIterator<Item = PathSegment>>(segments: I) -> Self {
+        Self {
+            segments: segments.into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePathError {
+    /// Character offset of the problem within the parsed text.
+    pub position: usize,
+    pub message: &'static str,
+}
+
+impl std::fmt::Display for ParsePathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParsePathError {}
+
+/// Parses the format produced by `Display`; an empty string (or `(root)`) is the root.
+impl std::str::FromStr for ValuePath {
+    type Err = ParsePathError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let mut path = ValuePath::root();
+        if text == "(root)" {
+            return Ok(path);
+        }
+        let chars = text.chars().collect::<Vec<_>>();
+        let error = |position, message| Err(ParsePathError { position, message });
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '[' => {
+                    let close = find_closing(&chars, i, ']').ok_or(ParsePathError {
+                        position: i,
+                        message: "unclosed '['",
+                    })?;
+                    let inner = chars[i + 1..close].iter().collect::<String>();
+                    let inner = inner.trim();
+                    if inner.starts_with('"') {
+                        let Some(key) = unquote(inner) else {
+                            return error(i + 1, "invalid quoted key");
+                        };
+                        path.push(PathSegment::Key(rmpv::Value::from(key)));
+                    } else {
+                        let Ok(index) = inner.parse::<usize>() else {
+                            return error(i + 1, "expected an array index or a quoted key");
+                        };
+                        path.push(PathSegment::Index(index));
+                    }
+                    i = close + 1;
+                }
+                '{' => {
+                    let close = find_closing(&chars, i, '}').ok_or(ParsePathError {
+                        position: i,
+                        message: "unclosed '{'",
+                    })?;
+                    let inner = chars[i + 1..close].iter().collect::<String>();
+                    let Some(key) = parse_key_literal(inner.trim()) else {
+                        return error(i + 1, "expected a key literal such as 42, true or nil");
+                    };
+                    path.push(PathSegment::Key(key));
+                    i = close + 1;
+                }
+                c if c == '.' || (i == 0 && (c.is_alphabetic() || c == '_')) => {
+                    let start = if c == '.' { i + 1 } else { i };
+                    let mut end = start;
+                    while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_') {
+                        end += 1;
+                    }
+                    if end == start {
+                        return error(start, "expected a key after '.'");
+                    }
+                    let key = chars[start..end].iter().collect::<String>();
+                    path.push(PathSegment::Key(rmpv::Value::from(key)));
+                    i = end;
+                }
+                _ => return error(i, "unexpected character"),
+            }
+        }
+        Ok(path)
+    }
+}
+
+/// Finds the `close` character ending the bracket at `open`, skipping over quoted strings.
+fn find_closing(chars: &[char], open: usize, close: char) -> Option<usize> {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, &c) in chars.iter().enumerate().skip(open + 1) {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == close {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Undoes the `{:?}` escaping of a double-quoted string.
+fn unquote(quoted: &str) -> Option<String> {
+    let inner = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            't' => out.push('\t'),
+            '0' => out.push('\0'),
+            'u' => {
+                let rest = chars.as_str().strip_prefix('{')?;
+                let (hex, rest) = rest.split_once('}')?;
+                out.push(char::from_u32(u32::from_str_radix(hex, 16).ok()?)?);
+                chars = rest.chars();
+            }
+            other => out.push(other),
+        }
+    }
+    Some(out)
+}
+
+fn parse_key_literal(text: &str) -> Option<rmpv::Value> {
+    match text {
+        "nil" => return Some(rmpv::Value::Nil),
+        "true" => return Some(rmpv::Value::Boolean(true)),
+        "false" => return Some(rmpv::Value::Boolean(false)),
+        _ => {}
+    }
+    if let Some(float) = text.strip_suffix("f32") {
+        return float.parse().ok().map(rmpv::Value::F32);
+    }
+    if let Some(float) = text.strip_suffix("f64") {
+        return float.parse().ok().map(rmpv::Value::F64);
+    }
+    if let Ok(i) = text.parse::<i64>() {
+        Some(rmpv::Value::from(i))
+    } else if let Ok(i) = text.parse::<u64>() {
+        Some(rmpv::Value::from(i))
+    } else if let Ok(f) = text.parse::<f64>() {
+        Some(rmpv::Value::F64(f))
+    } else {
+        unquote(text).map(rmpv::Value::from)
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn write_segment(f: &mut impl Write, segment: &PathSegment, is_first: bool) -> std::fmt::Result {
+    match segment {
+        PathSegment::Index(index) => write!(f, "[{index}]"),
+        PathSegment::Key(rmpv::Value::String(s)) => match s.as_str() {
+            Some(s) if is_identifier(s) => {
+                if !is_first {
+                    f.write_char('.')?;
+                }
+                f.write_str(s)
+            }
+            Some(s) => write!(f, "[{s:?}]"),
+            None => write!(f, "[{:?}]", String::from_utf8_lossy(s.as_bytes())),
+        },
+        PathSegment::Key(rmpv::Value::F32(float)) => write!(f, "{{{float}f32}}"),
+        PathSegment::Key(rmpv::Value::F64(float)) => write!(f, "{{{float}f64}}"),
+        PathSegment::Key(key) => write!(f, "{{{key}}}"),
+    }
+}
+
+/// Formats the segment as it would appear at the start of a path.
+impl std::fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_segment(f, self, true)
+    }
+}
+
+impl std::fmt::Display for ValuePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.segments.is_empty() {
+            return f.write_str("(root)");
+        }
+        for (i, segment) in self.segments.iter().enumerate() {
+            write_segment(f, segment, i == 0)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: impl Into<rmpv::Value>) -> PathSegment {
+        PathSegment::Key(key.into())
+    }
+
+    /// Checks that `path` is written as `text`, and that `text` is read as `path`.
+    fn assert_round_trips(path: &[PathSegment], text: &str) {
+        let path = path.iter().cloned().collect::<ValuePath>();
+        assert_eq!(path.to_string(), text);
+        assert_eq!(text.parse::<ValuePath>().unwrap(), path, "{text}");
+    }
+
+    #[test]
+    fn identifiers_round_trip() {
+        assert_round_trips(&[], "(root)");
+        assert_round_trips(&[key("players")], "players");
+        assert_round_trips(
+            &[key("players"), PathSegment::Index(3), key("_name2")],
+            "players[3]._name2",
+        );
+        assert_round_trips(&[PathSegment::Index(0), key("é")], "[0].é");
+    }
+
+    #[test]
+    fn quoted_keys_round_trip() {
+        assert_round_trips(&[key("some key")], r#"["some key"]"#);
+        assert_round_trips(&[key("a"), key("2nd")], r#"a["2nd"]"#);
+        assert_round_trips(&[key("")], r#"[""]"#);
+        assert_round_trips(
+            &[key("\"quoted\"], [closed]\n")],
+            r#"["\"quoted\"], [closed]\n"]"#,
+        );
+    }
+
+    #[test]
+    fn other_keys_round_trip() {
+        assert_round_trips(&[key(42)], "{42}");
+        assert_round_trips(&[key("a"), key(-7)], "a{-7}");
+        assert_round_trips(&[key(u64::MAX)], "{18446744073709551615}");
+        assert_round_trips(&[key(true), key(rmpv::Value::Nil)], "{true}{nil}");
+        assert_round_trips(&[key(rmpv::Value::F32(0.1))], "{0.1f32}");
+        assert_round_trips(&[key(rmpv::Value::F64(2.0))], "{2f64}");
+        assert_round_trips(&[key(rmpv::Value::F64(-0.0))], "{-0f64}");
+        // as typed by hand
+        assert_eq!(
+            "{1.5}".parse::<ValuePath>().unwrap(),
+            ValuePath::from_iter([key(rmpv::Value::F64(1.5))])
+        );
+    }
+
+    #[test]
+    fn float_keys_find_entries_of_their_width() {
+        let doc = rmpv::Value::Map(vec![
+            (rmpv::Value::F64(0.5), "f64".into()),
+            (rmpv::Value::F32(0.5), "f32".into()),
+            (rmpv::Value::F32(f32::NAN), "nan".into()),
+        ]);
+        for (text, found, index) in [
+            ("{0.5f32}", "f32", 1),
+            ("{0.5f64}", "f64", 0),
+            ("{NaNf32}", "nan", 2),
+        ] {
+            let path = text.parse::<ValuePath>().unwrap();
+            assert_eq!(path.resolve(&doc), Some(&found.into()), "{text}");
+            assert_eq!(path.indexes(&doc), Some(vec![index]), "{text}");
+        }
+    }
+
+    #[test]
+    fn paths_through_nan_keys_are_equal() {
+        let path = ValuePath::from_iter([key(rmpv::Value::F64(f64::NAN))]);
+        assert_eq!(path, path.clone());
+        assert_ne!(
+            path,
+            ValuePath::from_iter([key(rmpv::Value::F32(f32::NAN))])
+        );
+    }
+
+    #[test]
+    fn malformed_paths_say_where() {
+        for (text, position) in [("a[", 1), ("a[x]", 2), ("a.", 2), ("{nope}", 1), ("a b", 1)] {
+            let err = text.parse::<ValuePath>().unwrap_err();
+            assert_eq!(err.position, position, "{text}: {err}");
+        }
+    }
+
+    #[test]
+    fn json_pointers_escape_tildes_and_slashes() {
+        let path = ValuePath::from_iter([key("a/b"), PathSegment::Index(2), key("~c"), key(42)]);
+        assert_eq!(path.to_json_pointer(), "/a~1b/2/~0c/42");
+        assert_eq!(ValuePath::root().to_json_pointer(), "");
+    }
+
+    #[test]
+    fn jq_quotes_what_isnt_an_identifier() {
+        assert_eq!(ValuePath::root().to_jq(), ".");
+        let path = ValuePath::from_iter([key("config"), key("servers"), PathSegment::Index(2)]);
+        assert_eq!(path.to_jq(), ".config.servers[2]");
+        let path =
+            ValuePath::from_iter([PathSegment::Index(0), key("some key"), key("é"), key(42)]);
+        assert_eq!(path.to_jq(), r#".[0]["some key"]["é"]["42"]"#);
+    }
+}
@@ -0,0 +1,112 @@
+use base64::Engine as _;
+
+/// Text encodings that binary payloads get copied around in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PayloadEncoding {
+    Hex,
+    Base64,
+}
+
+impl std::fmt::Display for PayloadEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PayloadEncoding::Hex => "hex",
+            PayloadEncoding::Base64 => "base64",
+        })
+    }
+}
+
+/// Decodes a payload pasted as hex (optionally `0x`-prefixed or split by whitespace or commas) or as
+/// base64 (standard or URL-safe alphabet, padded or not).
+///
+/// Hex digits are all valid base64 too, so anything that decodes as hex is taken to be hex.
+pub fn decode_payload(text: &str) -> Result<(Vec<u8>, PayloadEncoding), String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("clipboard is empty".to_owned());
+    }
+    if let Some(bytes) = decode_hex(text) {
+        return Ok((bytes, PayloadEncoding::Hex));
+    }
+    let compact = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>();
+    let unpadded = compact.trim_end_matches('=');
+    base64::engine::general_purpose::STANDARD_NO_PAD
+        .decode(unpadded)
+        .or_else(|_| base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(unpadded))
+        .map(|bytes| (bytes, PayloadEncoding::Base64))
+        .map_err(|_| "clipboard contents are neither hex nor base64".to_owned())
+}
+
+/// Formats bytes as lowercase hex digits, the way [`decode_payload`] reads them back.
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Decodes hex digits, optionally `0x`-prefixed or split by whitespace or commas.
+pub fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let digits = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|chunk| chunk.strip_prefix("0x").unwrap_or(chunk))
+        .collect::<String>();
+    if digits.is_empty() || digits.len() % 2 != 0 || !digits.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_is_read_in_any_layout() {
+        for text in ["92c3c2", "0x92 0xc3 0xc2", "92, c3, c2", " 92C3\n c2 "] {
+            let decoded = decode_payload(text).unwrap();
+            assert_eq!(
+                decoded,
+                (vec![0x92, 0xc3, 0xc2], PayloadEncoding::Hex),
+                "{text}"
+            );
+        }
+        assert_eq!(
+            decode_hex(&encode_hex(&[0x00, 0xab, 0xff])),
+            Some(vec![0x00, 0xab, 0xff])
+        );
+    }
+
+    #[test]
+    fn what_isnt_hex_is_read_as_base64() {
+        // "kcM=" and "kcM" are [0x91, 0xc3], "-_8" is [0xfb, 0xff] in the URL-safe alphabet
+        for (text, bytes) in [
+            ("kcM=", &[0x91, 0xc3][..]),
+            ("kcM", &[0x91, 0xc3]),
+            ("kc\nM=", &[0x91, 0xc3]),
+            ("-_8", &[0xfb, 0xff]),
+            // an odd number of hex digits
+            ("abc", &[0x69, 0xb7]),
+        ] {
+            let decoded = decode_payload(text).unwrap();
+            assert_eq!(decoded, (bytes.to_vec(), PayloadEncoding::Base64), "{text}");
+        }
+    }
+
+    #[test]
+    fn what_is_neither_fails() {
+        assert_eq!(decode_payload("  ").unwrap_err(), "clipboard is empty");
+        for text in ["not base64!", "0x1?", "a"] {
+            assert_eq!(
+                decode_payload(text).unwrap_err(),
+                "clipboard contents are neither hex nor base64",
+                "{text}"
+            );
+        }
+        assert_eq!(decode_hex("0x"), None);
+    }
+}
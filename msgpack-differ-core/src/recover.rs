@@ -0,0 +1,267 @@
+/// Where and why decoding a file stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeFailure {
+    /// Offset of the byte that couldn't be decoded, which is the length of the data if it ended early.
+    pub offset: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for DecodeFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at byte offset {}", self.message, self.offset)
+    }
+}
+
+/// What could be made of a value that failed to decode.
+#[derive(Debug)]
+pub struct PartialValue {
+    /// Everything that was decoded before the failure: containers hold the elements preceding the
+    /// one that's broken. `None` if not even that much could be decoded.
+    pub value: Option<rmpv::Value>,
+    pub failure: DecodeFailure,
+}
+
+/// Decodes the value at the start of `data` for as far as it's intact, returning it along with the
+/// number of bytes it took up if it's intact all the way.
+///
+/// This is slower than [`rmpv::decode::read_value`], so it's only meant for investigating data
+/// that already failed to decode.
+pub fn decode_prefix(data: &[u8]) -> Result<(rmpv::Value, usize), PartialValue> {
+    let mut decoder = Decoder { data, pos: 0 };
+    match decoder.value(MAX_DEPTH) {
+        Ok(value) => Ok((value, decoder.pos)),
+        Err((value, message)) => Err(PartialValue {
+            value,
+            failure: DecodeFailure {
+                offset: decoder.pos,
+                message,
+            },
+        }),
+    }
+}
+
+/// Same as rmpv's default, so that anything it refuses for being nested too deeply fails here too.
+const MAX_DEPTH: usize = 1024;
+
+/// A decoded value, or whatever part of it was decoded along with what went wrong.
+type Decoded = Result<rmpv::Value, (Option<rmpv::Value>, String)>;
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    /// Where decoding is up to; on failure, where it failed.
+    pos: usize,
+}
+
+impl Decoder<'_> {
+    fn value(&mut self, depth: usize) -> Decoded {
+        let start = self.pos;
+        let Some(&marker) = self.data.get(start) else {
+            return Err((None, "unexpected end of data".to_owned()));
+        };
+        let uint = |width: usize| -> Result<usize, (Option<rmpv::Value>, String)> {
+            let bytes = self.data.get(start + 1..start + 1 + width).ok_or_else(|| {
+                (
+                    None,
+                    format!("truncated header of value with marker 0x{marker:02x}"),
+                )
+            })?;
+            Ok(bytes.iter().fold(0, |n, &b| (n << 8) | b as usize))
+        };
+        // containers: whether it's a map, the element count, and the header size
+        let container = match marker {
+            0x80..=0x8f => Some((true, (marker & 0x0f) as usize, 1)),
+            0x90..=0x9f => Some((false, (marker & 0x0f) as usize, 1)),
+            0xdc => Some((false, uint(2)?, 3)),
+            0xdd => Some((false, uint(4)?, 5)),
+            0xde => Some((true, uint(2)?, 3)),
+            0xdf => Some((true, uint(4)?, 5)),
+            _ => None,
+        };
+        if let Some((is_map, len, header)) = container {
+            if depth == 0 {
+                return Err((None, "values are nested too deeply".to_owned()));
+            }
+            self.pos = start + header;
+            // the length may be garbage, so don't trust it for allocating
+            let capacity = len.min(self.data.len() - self.pos);
+            return if is_map {
+                self.map_entries(len, capacity, depth - 1)
+            } else {
+                self.array_items(len, capacity, depth - 1)
+            };
+        }
+
+        let size = match marker {
+            0x00..=0x7f | 0xc0..=0xc3 | 0xe0..=0xff => 1,
+            0xa0..=0xbf => 1 + (marker & 0x1f) as usize,
+            0xc4 | 0xd9 => 2 + uint(1)?,
+            0xc5 | 0xda => 3 + uint(2)?,
+            0xc6 | 0xdb => 5 + uint(4)?,
+            0xc7 => 3 + uint(1)?,
+            0xc8 => 4 + uint(2)?,
+            0xc9 => 6 + uint(4)?,
+            0xca | 0xce | 0xd2 => 5,
+            0xcb | 0xcf | 0xd3 => 9,
+            0xcc | 0xd0 => 2,
+            0xcd | 0xd1 => 3,
+            0xd4 => 3,
+            0xd5 => 4,
+            0xd6 => 6,
+            0xd7 => 10,
+            0xd8 => 18,
+            _ => unreachable!("containers are handled above"),
+        };
+        let Some(mut bytes) = self.data.get(start..start.saturating_add(size)) else {
+            return Err((
+                None,
+                format!(
+                    "value with marker 0x{marker:02x} needs {size} bytes but only {} remain",
+                    self.data.len() - start
+                ),
+            ));
+        };
+        // now that it's known to be all there, let rmpv take care of the details
+        let value = rmpv::decode::read_value(&mut bytes).map_err(|err| (None, err.to_string()))?;
+        self.pos = start + size;
+        Ok(value)
+    }
+
+    fn array_items(&mut self, len: usize, capacity: usize, depth: usize) -> Decoded {
+        let mut items = Vec::with_capacity(capacity);
+        for _ in 0..len {
+            match self.value(depth) {
+                Ok(item) => items.push(item),
+                Err((item, message)) => {
+                    items.extend(item);
+                    return Err((Some(rmpv::Value::Array(items)), message));
+                }
+            }
+        }
+        Ok(rmpv::Value::Array(items))
+    }
+
+    fn map_entries(&mut self, len: usize, capacity: usize, depth: usize) -> Decoded {
+        let mut entries = Vec::with_capacity(capacity);
+        for _ in 0..len {
+            let key = match self.value(depth) {
+                Ok(key) => key,
+                // a partial key isn't much use without its value
+                Err((_, message)) => return Err((Some(rmpv::Value::Map(entries)), message)),
+            };
+            match self.value(depth) {
+                Ok(value) => entries.push((key, value)),
+                Err((value, message)) => {
+                    entries.extend(value.map(|value| (key, value)));
+                    return Err((Some(rmpv::Value::Map(entries)), message));
+                }
+            }
+        }
+        Ok(rmpv::Value::Map(entries))
+    }
+}
+
+/// Hex dump of the bytes around `offset`, with the byte at `offset` in brackets, e.g.
+/// `00000010  92 a3 66 6f [c1] 01`.
+pub fn hex_context(data: &[u8], offset: usize) -> String {
+    const ROW: usize = 16;
+    let first_row = offset.saturating_sub(ROW) / ROW * ROW;
+    let end = (offset + ROW + 1).min(data.len());
+    let mut out = String::new();
+    for row_start in (first_row..end.max(offset + 1)).step_by(ROW) {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("{row_start:08x} "));
+        for pos in row_start..(row_start + ROW).min(end.max(offset + 1)) {
+            match data.get(pos) {
+                Some(byte) if pos == offset => out.push_str(&format!(" [{byte:02x}]")),
+                Some(byte) => out.push_str(&format!(" {byte:02x}")),
+                None => out.push_str(" [end of data]"),
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partial(data: &[u8]) -> (Option<rmpv::Value>, DecodeFailure) {
+        let PartialValue { value, failure } = decode_prefix(data).unwrap_err();
+        (value, failure)
+    }
+
+    #[test]
+    fn intact_values_decode_with_their_length() {
+        let data = [0x92, 0x01, 0xa1, b'x', 0xc0];
+        let value = rmpv::Value::Array(vec![1.into(), "x".into()]);
+        assert_eq!(decode_prefix(&data).unwrap(), (value, 4));
+    }
+
+    #[test]
+    fn truncated_arrays_keep_the_items_before() {
+        // [1, [2, 3, <missing>], <missing>]
+        let (value, failure) = partial(&[0x93, 0x01, 0x93, 0x02, 0x03]);
+        let inner = rmpv::Value::Array(vec![2.into(), 3.into()]);
+        assert_eq!(value, Some(rmpv::Value::Array(vec![1.into(), inner])));
+        assert_eq!(failure.offset, 5);
+        assert_eq!(failure.message, "unexpected end of data");
+    }
+
+    #[test]
+    fn truncated_maps_keep_the_entries_before() {
+        // {"a": 1, "b": "hello" cut short}
+        let data = [0x82, 0xa1, b'a', 0x01, 0xa1, b'b', 0xa5, b'h', b'e'];
+        let (value, failure) = partial(&data);
+        let entries = vec![("a".into(), 1.into())];
+        assert_eq!(value, Some(rmpv::Value::Map(entries)));
+        assert_eq!(failure.offset, 6);
+        assert_eq!(
+            failure.message,
+            "value with marker 0xa5 needs 6 bytes but only 3 remain"
+        );
+        // a key without its value is dropped
+        let (value, failure) = partial(&[0x81, 0xa1, b'a']);
+        assert_eq!(value, Some(rmpv::Value::Map(vec![])));
+        assert_eq!(failure.offset, 3);
+    }
+
+    #[test]
+    fn truncated_headers_decode_nothing() {
+        let (value, failure) = partial(&[]);
+        assert!(value.is_none());
+        assert_eq!(failure.offset, 0);
+        let (value, failure) = partial(&[0xdc, 0x00]);
+        assert!(value.is_none());
+        assert_eq!(failure.offset, 0);
+        assert_eq!(
+            failure.message,
+            "truncated header of value with marker 0xdc"
+        );
+    }
+
+    #[test]
+    fn values_nested_too_deeply_fail() {
+        // unoptimized, recursing that deep takes more than the stack tests get
+        let failure = std::thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(|| partial(&[0x91; MAX_DEPTH + 1]).1)
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(failure.offset, MAX_DEPTH);
+        assert_eq!(failure.message, "values are nested too deeply");
+    }
+
+    #[test]
+    fn hex_context_marks_the_offset() {
+        let data = (0..20).collect::<Vec<u8>>();
+        assert_eq!(
+            hex_context(&data, 17),
+            "00000000  00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f\n\
+             00000010  10 [11] 12 13"
+        );
+        assert_eq!(hex_context(&data[..2], 2), "00000000  00 01 [end of data]");
+    }
+}
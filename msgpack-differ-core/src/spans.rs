@@ -0,0 +1,438 @@
+use std::ops::Range;
+
+/// What a value's first bytes say about how it's encoded.
+struct Header {
+    /// Size of the marker and whatever follows it other than child values, i.e. all of a scalar.
+    len: usize,
+    /// Number of values that follow as part of this one: the items of an array, or the keys and
+    /// values of a map.
+    children: usize,
+}
+
+/// Reads the header of the value encoded at `pos`, if all of it is there.
+fn header(data: &[u8], pos: usize) -> Option<Header> {
+    let marker = *data.get(pos)?;
+    let uint = |width: usize| {
+        let bytes = data.get(pos + 1..pos + 1 + width)?;
+        Some(bytes.iter().fold(0, |n, &b| (n << 8) | b as usize))
+    };
+    let scalar = |len| Some(Header { len, children: 0 });
+    match marker {
+        0x80..=0x8f => Some(Header {
+            len: 1,
+            children: 2 * (marker & 0x0f) as usize,
+        }),
+        0x90..=0x9f => Some(Header {
+            len: 1,
+            children: (marker & 0x0f) as usize,
+        }),
+        0xdc => Some(Header {
+            len: 3,
+            children: uint(2)?,
+        }),
+        0xdd => Some(Header {
+            len: 5,
+            children: uint(4)?,
+        }),
+        0xde => Some(Header {
+            len: 3,
+            children: 2 * uint(2)?,
+        }),
+        0xdf => Some(Header {
+            len: 5,
+            children: 2 * uint(4)?,
+        }),
+        0x00..=0x7f | 0xc0..=0xc3 | 0xe0..=0xff => scalar(1),
+        0xa0..=0xbf => scalar(1 + (marker & 0x1f) as usize),
+        0xc4 | 0xd9 => scalar(2 + uint(1)?),
+        0xc5 | 0xda => scalar(3 + uint(2)?),
+        0xc6 | 0xdb => scalar(5 + uint(4)?),
+        0xc7 => scalar(3 + uint(1)?),
+        0xc8 => scalar(4 + uint(2)?),
+        0xc9 => scalar(6 + uint(4)?),
+        0xca | 0xce | 0xd2 => scalar(5),
+        0xcb | 0xcf | 0xd3 => scalar(9),
+        0xcc | 0xd0 => scalar(2),
+        0xcd | 0xd1 => scalar(3),
+        0xd4 => scalar(3),
+        0xd5 => scalar(4),
+        0xd6 => scalar(6),
+        0xd7 => scalar(10),
+        0xd8 => scalar(18),
+    }
+}
+
+/// Names the msgpack format that a value starting with `marker` is encoded in, e.g. `uint16`.
+fn format_name(marker: u8) -> &'static str {
+    match marker {
+        0x00..=0x7f => "positive fixint",
+        0x80..=0x8f => "fixmap",
+        0x90..=0x9f => "fixarray",
+        0xa0..=0xbf => "fixstr",
+        0xc0 => "nil",
+        0xc1 => "(never used)",
+        0xc2 => "false",
+        0xc3 => "true",
+        0xc4 => "bin8",
+        0xc5 => "bin16",
+        0xc6 => "bin32",
+        0xc7 => "ext8",
+        0xc8 => "ext16",
+        0xc9 => "ext32",
+        0xca => "float32",
+        0xcb => "float64",
+        0xcc => "uint8",
+        0xcd => "uint16",
+        0xce => "uint32",
+        0xcf => "uint64",
+        0xd0 => "int8",
+        0xd1 => "int16",
+        0xd2 => "int32",
+        0xd3 => "int64",
+        0xd4 => "fixext1",
+        0xd5 => "fixext2",
+        0xd6 => "fixext4",
+        0xd7 => "fixext8",
+        0xd8 => "fixext16",
+        0xd9 => "str8",
+        0xda => "str16",
+        0xdb => "str32",
+        0xdc => "array16",
+        0xdd => "array32",
+        0xde => "map16",
+        0xdf => "map32",
+        0xe0..=0xff => "negative fixint",
+    }
+}
+
+/// Describes how the value at `pos` is encoded, e.g. `fixstr, 11 bytes` or `map16 with 300
+/// entries`, if all of its header is there.
+pub fn describe_encoding(data: &[u8], pos: usize) -> Option<String> {
+    let marker = *data.get(pos)?;
+    let name = format_name(marker);
+    let header = header(data, pos)?;
+    // size of the length that follows the marker, if there is one
+    let length_width = match marker {
+        0xc4 | 0xc7 | 0xd9 => 1,
+        0xc5 | 0xc8 | 0xda => 2,
+        0xc6 | 0xc9 | 0xdb => 4,
+        _ => 0,
+    };
+    Some(match marker {
+        0x80..=0x8f | 0xde | 0xdf => format!("{name} with {} entries", header.children / 2),
+        0x90..=0x9f | 0xdc | 0xdd => format!("{name} with {} items", header.children),
+        0xa0..=0xbf | 0xc4..=0xc6 | 0xd9..=0xdb => {
+            format!("{name}, {} bytes", header.len - 1 - length_width)
+        }
+        // the type comes before the data
+        0xc7..=0xc9 | 0xd4..=0xd8 => format!("{name}, {} bytes", header.len - 2 - length_width),
+        _ => name.to_owned(),
+    })
+}
+
+/// Size of everything before the payload of a binary value with this marker, or 0 for any other.
+fn binary_prefix(marker: u8) -> usize {
+    match marker {
+        0xc4 => 2,
+        0xc5 => 3,
+        0xc6 => 5,
+        _ => 0,
+    }
+}
+
+/// Finds where the value encoded at `start` ends, without decoding it.
+pub fn skip_value(data: &[u8], start: usize) -> Option<usize> {
+    let mut pos = start;
+    // values still to skip, which grows as containers are entered
+    let mut remaining = 1usize;
+    while remaining > 0 {
+        let header = header(data, pos)?;
+        pos = pos.checked_add(header.len)?;
+        remaining = remaining - 1 + header.children;
+    }
+    (pos <= data.len()).then_some(pos)
+}
+
+/// Finds the bytes encoding the value at `indexes` (see [`crate::value::child_of`]) within the value
+/// encoded at `start`.
+///
+/// This skips over everything that comes before it, so it takes time in proportion to that. Binary
+/// values along the way are stepped into, for msgpack nested within them.
+pub fn value_span(data: &[u8], start: usize, indexes: &[usize]) -> Option<Range<usize>> {
+    let mut pos = start;
+    for &index in indexes {
+        // a binary value the index leads into holds msgpack, as decoded by `LoadOptions::decode_nested`
+        pos += binary_prefix(*data.get(pos)?);
+        let marker = *data.get(pos)?;
+        let is_map = matches!(marker, 0x80..=0x8f | 0xde | 0xdf);
+        let header = header(data, pos)?;
+        let (len, skipped) = match is_map {
+            // skips the keys and values of the entries before, then the entry's key
+            true => (header.children / 2, 2 * index + 1),
+            false => (header.children, index),
+        };
+        if index >= len {
+            return None;
+        }
+        pos += header.len;
+        for _ in 0..skipped {
+            pos = skip_value(data, pos)?;
+        }
+    }
+    Some(pos..skip_value(data, pos)?)
+}
+
+/// How many bytes a value takes to encode, and the same for each of its children in the order of
+/// [`crate::value::child_of`]. An entry of a map counts the bytes of its key along with its value's.
+#[derive(Debug, Clone, Default)]
+pub struct SizeTree {
+    pub size: usize,
+    pub children: Vec<SizeTree>,
+}
+
+impl SizeTree {
+    /// The sizes of the value at `indexes` within this one.
+    pub fn get(&self, indexes: &[usize]) -> Option<&SizeTree> {
+        indexes
+            .iter()
+            .try_fold(self, |tree, &index| tree.children.get(index))
+    }
+}
+
+/// Works out the sizes within `value`, which was decoded from the bytes at `start`, returning them
+/// along with where the value ends.
+pub fn size_tree(data: &[u8], start: usize, value: &rmpv::Value) -> Option<(SizeTree, usize)> {
+    let mut pos = start;
+    let is_container = matches!(value, rmpv::Value::Array(_) | rmpv::Value::Map(_));
+    if is_container {
+        // a container where the bytes hold a binary value was decoded from the msgpack within it
+        pos += binary_prefix(*data.get(pos)?);
+    }
+    let header = header(data, pos)?;
+    pos = pos.checked_add(header.len)?;
+    let mut children = Vec::new();
+    match value {
+        rmpv::Value::Array(items) => {
+            for item in items {
+                let (child, end) = size_tree(data, pos, item)?;
+                children.push(child);
+                pos = end;
+            }
+        }
+        rmpv::Value::Map(entries) => {
+            for (_, value) in entries {
+                let key_start = pos;
+                let (mut child, end) = size_tree(data, skip_value(data, pos)?, value)?;
+                child.size = end - key_start;
+                children.push(child);
+                pos = end;
+            }
+        }
+        _ => {}
+    }
+    let tree = SizeTree {
+        size: pos - start,
+        children,
+    };
+    (pos <= data.len()).then_some((tree, pos))
+}
+
+/// Where a value is encoded, and the same for each of its children in the order of
+/// [`crate::value::child_of`].
+#[derive(Debug, Clone, Default)]
+pub struct SpanTree {
+    pub span: Range<usize>,
+    /// Where the entry holding the value starts, which for a map entry is where its key does.
+    pub entry_start: usize,
+    pub children: Vec<SpanTree>,
+}
+
+impl SpanTree {
+    /// The spans of the value at `indexes` within this one.
+    pub fn get(&self, indexes: &[usize]) -> Option<&SpanTree> {
+        indexes
+            .iter()
+            .try_fold(self, |tree, &index| tree.children.get(index))
+    }
+
+    /// The positions of the innermost value within this one whose entry covers the byte at
+    /// `offset`, if this one's does.
+    pub fn enclosing(&self, offset: usize) -> Option<Vec<usize>> {
+        if !(self.entry_start..self.span.end).contains(&offset) {
+            return None;
+        }
+        let mut indexes = Vec::new();
+        let mut tree = self;
+        loop {
+            // children are in the order they're encoded in
+            let i = tree
+                .children
+                .partition_point(|child| child.span.end <= offset);
+            match tree.children.get(i) {
+                Some(child) if child.entry_start <= offset => {
+                    indexes.push(i);
+                    tree = child;
+                }
+                _ => return Some(indexes),
+            }
+        }
+    }
+}
+
+/// Works out where everything within `value` is encoded, given that it was decoded from the bytes
+/// at `start`.
+pub fn span_tree(data: &[u8], start: usize, value: &rmpv::Value) -> Option<SpanTree> {
+    let mut pos = start;
+    if matches!(value, rmpv::Value::Array(_) | rmpv::Value::Map(_)) {
+        // a container where the bytes hold a binary value was decoded from the msgpack within it
+        pos += binary_prefix(*data.get(pos)?);
+    }
+    let header = header(data, pos)?;
+    pos = pos.checked_add(header.len)?;
+    let mut children = Vec::new();
+    match value {
+        rmpv::Value::Array(items) => {
+            for item in items {
+                let child = span_tree(data, pos, item)?;
+                pos = child.span.end;
+                children.push(child);
+            }
+        }
+        rmpv::Value::Map(entries) => {
+            for (_, value) in entries {
+                let key_start = pos;
+                let mut child = span_tree(data, skip_value(data, pos)?, value)?;
+                child.entry_start = key_start;
+                pos = child.span.end;
+                children.push(child);
+            }
+        }
+        _ => {}
+    }
+    (pos <= data.len()).then_some(SpanTree {
+        span: start..pos,
+        entry_start: start,
+        children,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `{"a": [1, 300], "bc": <bin8 holding [true]>}`
+    const DATA: [u8; 15] = [
+        0x82, // 0
+        0xa1, b'a', // 1
+        0x92, 0x01, 0xcd, 0x01, 0x2c, // 3
+        0xa2, b'b', b'c', // 8
+        0xc4, 0x02, 0x91, 0xc3, // 11
+    ];
+
+    #[test]
+    fn skips_whole_values() {
+        assert_eq!(skip_value(&DATA, 0), Some(15));
+        assert_eq!(skip_value(&DATA, 3), Some(8));
+        assert_eq!(skip_value(&DATA, 5), Some(8));
+        assert_eq!(skip_value(&DATA[..14], 0), None);
+    }
+
+    #[test]
+    fn finds_the_bytes_of_values() {
+        assert_eq!(value_span(&DATA, 0, &[]), Some(0..15));
+        assert_eq!(value_span(&DATA, 0, &[0]), Some(3..8));
+        assert_eq!(value_span(&DATA, 0, &[0, 1]), Some(5..8));
+        assert_eq!(value_span(&DATA, 0, &[1]), Some(11..15));
+        // within the binary value
+        assert_eq!(value_span(&DATA, 0, &[1, 0]), Some(14..15));
+        assert_eq!(value_span(&DATA, 0, &[2]), None);
+        assert_eq!(value_span(&DATA, 0, &[0, 2]), None);
+        assert_eq!(value_span(&DATA[..14], 0, &[1]), None);
+    }
+
+    /// [`DATA`] as decoded, with the binary value as the msgpack within it.
+    fn value() -> rmpv::Value {
+        rmpv::Value::Map(vec![
+            ("a".into(), rmpv::Value::Array(vec![1.into(), 300.into()])),
+            ("bc".into(), rmpv::Value::Array(vec![true.into()])),
+        ])
+    }
+
+    #[test]
+    fn sizes_count_the_keys_of_entries() {
+        let (tree, end) = size_tree(&DATA, 0, &value()).unwrap();
+        assert_eq!(end, 15);
+        assert_eq!(tree.size, 15);
+        let size = |indexes: &[usize]| tree.get(indexes).map(|tree| tree.size);
+        assert_eq!(size(&[0]), Some(7));
+        assert_eq!(size(&[0, 1]), Some(3));
+        assert_eq!(size(&[1]), Some(7));
+        assert_eq!(size(&[1, 0]), Some(1));
+        assert_eq!(size(&[2]), None);
+        assert!(size_tree(&DATA[..14], 0, &value()).is_none());
+    }
+
+    #[test]
+    fn spans_cover_the_bytes_of_values() {
+        let tree = span_tree(&DATA, 0, &value()).unwrap();
+        assert_eq!((tree.entry_start, tree.span.clone()), (0, 0..15));
+        let spans = |indexes: &[usize]| {
+            let tree = tree.get(indexes).unwrap();
+            (tree.entry_start, tree.span.clone())
+        };
+        assert_eq!(spans(&[0]), (1, 3..8));
+        assert_eq!(spans(&[0, 1]), (5, 5..8));
+        // the binary value holding it
+        assert_eq!(spans(&[1]), (8, 11..15));
+        assert_eq!(spans(&[1, 0]), (14, 14..15));
+        assert!(span_tree(&DATA[..14], 0, &value()).is_none());
+    }
+
+    #[test]
+    fn finds_the_innermost_value_at_an_offset() {
+        let tree = span_tree(&DATA, 0, &value()).unwrap();
+        assert_eq!(tree.enclosing(0), Some(vec![]));
+        // the key of an entry
+        assert_eq!(tree.enclosing(2), Some(vec![0]));
+        assert_eq!(tree.enclosing(6), Some(vec![0, 1]));
+        assert_eq!(tree.enclosing(9), Some(vec![1]));
+        assert_eq!(tree.enclosing(13), Some(vec![1]));
+        assert_eq!(tree.enclosing(14), Some(vec![1, 0]));
+        assert_eq!(tree.enclosing(15), None);
+    }
+
+    #[test]
+    fn describes_encodings() {
+        let describe = |data: &[u8]| describe_encoding(data, 0);
+        assert_eq!(
+            describe_encoding(&DATA, 0).unwrap(),
+            "fixmap with 2 entries"
+        );
+        assert_eq!(describe_encoding(&DATA, 1).unwrap(), "fixstr, 1 bytes");
+        assert_eq!(
+            describe_encoding(&DATA, 3).unwrap(),
+            "fixarray with 2 items"
+        );
+        assert_eq!(describe_encoding(&DATA, 5).unwrap(), "uint16");
+        assert_eq!(describe_encoding(&DATA, 11).unwrap(), "bin8, 2 bytes");
+        assert_eq!(
+            describe(&[0xdc, 0x01, 0x2c]).unwrap(),
+            "array16 with 300 items"
+        );
+        assert_eq!(
+            describe(&[0xdf, 0, 0, 0, 2]).unwrap(),
+            "map32 with 2 entries"
+        );
+        assert_eq!(describe(&[0xda, 0x00, 0x03]).unwrap(), "str16, 3 bytes");
+        assert_eq!(
+            describe(&[0xd6, 0x01, 0, 0, 0, 0]).unwrap(),
+            "fixext4, 4 bytes"
+        );
+        assert_eq!(describe(&[0xc7, 0x03, 0x05]).unwrap(), "ext8, 3 bytes");
+        assert_eq!(describe(&[0xff]).unwrap(), "negative fixint");
+        assert_eq!(describe(&[0xc1]).unwrap(), "(never used)");
+        // the header is cut short
+        assert_eq!(describe(&[0xdc, 0x01]), None);
+        assert_eq!(describe(&[]), None);
+    }
+}
@@ -0,0 +1,285 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::ext::ExtRegistry;
+use crate::path::{PathSegment, ValuePath};
+
+/// Looks up the `index`th child of an array or map (for a map, the value of its `index`th entry).
+pub fn child_of(value: &rmpv::Value, index: usize) -> Option<&rmpv::Value> {
+    match value {
+        rmpv::Value::Array(a) => a.get(index),
+        rmpv::Value::Map(m) => m.get(index).map(|(_, v)| v),
+        _ => None,
+    }
+}
+
+/// Like [`child_of`], but for changing the child.
+pub fn child_of_mut(value: &mut rmpv::Value, index: usize) -> Option<&mut rmpv::Value> {
+    match value {
+        rmpv::Value::Array(a) => a.get_mut(index),
+        rmpv::Value::Map(m) => m.get_mut(index).map(|(_, v)| v),
+        _ => None,
+    }
+}
+
+/// The value reached from `root` by the positions of the children leading to it, for changing it.
+pub fn value_at_mut<'a>(
+    root: &'a mut rmpv::Value,
+    indexes: &[usize],
+) -> Option<&'a mut rmpv::Value> {
+    indexes
+        .iter()
+        .try_fold(root, |value, &index| child_of_mut(value, index))
+}
+
+/// The path segment leading from `value` to its `index`th child.
+pub fn segment_of(value: &rmpv::Value, index: usize) -> Option<PathSegment> {
+    match value {
+        rmpv::Value::Array(a) if index < a.len() => Some(PathSegment::Index(index)),
+        rmpv::Value::Map(m) => m.get(index).map(|(k, _)| PathSegment::Key(k.clone())),
+        _ => None,
+    }
+}
+
+/// Says how many children a container has, e.g. `(127 items)` or `(14 keys)`.
+pub fn describe_children(value: &rmpv::Value) -> Option<String> {
+    let (count, noun) = match value {
+        rmpv::Value::Array(items) => (items.len(), "item"),
+        rmpv::Value::Map(entries) => (entries.len(), "key"),
+        _ => return None,
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    Some(format!("({count} {noun}{plural})"))
+}
+
+/// How many values there are within `value`, at any depth, counting map keys as well as values.
+pub fn count_values(value: &rmpv::Value) -> usize {
+    match value {
+        rmpv::Value::Array(items) => items.iter().map(|item| 1 + count_values(item)).sum(),
+        rmpv::Value::Map(entries) => entries
+            .iter()
+            .map(|(key, value)| 2 + count_values(key) + count_values(value))
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// Formats a value for a one-line summary, cutting it short if it's long. Extension values are
+/// written as what `ext_types` decodes them to.
+pub fn abbreviate(value: &rmpv::Value, ext_types: &ExtRegistry) -> String {
+    const MAX_CHARS: usize = 60;
+    let mut formatted = match (ext_types.decode(value), value) {
+        (Some(decoded), _) => decoded.text,
+        // rmpv would write out the bytes as a list of numbers
+        (None, rmpv::Value::String(s)) if s.as_str().is_none() => {
+            let text = String::from_utf8_lossy(s.as_bytes());
+            format!("{text:?} (invalid UTF-8)")
+        }
+        (None, value) => value.to_string(),
+    };
+    if let Some((cut, _)) = formatted.char_indices().nth(MAX_CHARS) {
+        formatted.truncate(cut);
+        formatted.push('…');
+    }
+    formatted
+}
+
+/// What a binary value holds as text, if it's text: valid UTF-8, or at least mostly printable with
+/// anything that isn't replaced. Only the first few KiB are looked at, and returned.
+pub fn binary_as_text(bytes: &[u8]) -> Option<String> {
+    const MAX_CHECKED: usize = 4096;
+    const MIN_PRINTABLE: f64 = 0.95;
+    if bytes.is_empty() {
+        return None;
+    }
+    // a character cut in half at the end of what's checked only counts as one unprintable one
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_CHECKED)]).into_owned();
+    let is_printable =
+        |c: char| c != char::REPLACEMENT_CHARACTER && (!c.is_control() || c.is_whitespace());
+    let (printable, total) = text.chars().fold((0, 0), |(printable, total), c| {
+        (printable + usize::from(is_printable(c)), total + 1)
+    });
+    (printable as f64 >= total as f64 * MIN_PRINTABLE).then_some(text)
+}
+
+/// Orders map keys for showing them sorted: by type (nil, booleans, numbers, strings, binary, and
+/// then everything else), then by value.
+pub fn compare_keys(a: &rmpv::Value, b: &rmpv::Value) -> Ordering {
+    fn rank(value: &rmpv::Value) -> u8 {
+        match value {
+            rmpv::Value::Nil => 0,
+            rmpv::Value::Boolean(_) => 1,
+            rmpv::Value::Integer(_) | rmpv::Value::F32(_) | rmpv::Value::F64(_) => 2,
+            rmpv::Value::String(_) => 3,
+            rmpv::Value::Binary(_) => 4,
+            rmpv::Value::Array(_) | rmpv::Value::Map(_) | rmpv::Value::Ext(..) => 5,
+        }
+    }
+
+    match (a, b) {
+        (rmpv::Value::Boolean(a), rmpv::Value::Boolean(b)) => a.cmp(b),
+        (rmpv::Value::Integer(a), rmpv::Value::Integer(b)) => {
+            let wide = |i: &rmpv::Integer| {
+                i.as_i64()
+                    .map_or(i.as_u64().map(i128::from), |i| Some(i.into()))
+            };
+            wide(a).cmp(&wide(b))
+        }
+        (rmpv::Value::String(a), rmpv::Value::String(b)) => a.as_bytes().cmp(b.as_bytes()),
+        (rmpv::Value::Binary(a), rmpv::Value::Binary(b)) => a.cmp(b),
+        _ if rank(a) == 2 && rank(b) == 2 => {
+            let float = |value: &rmpv::Value| value.as_f64().unwrap_or(f64::NAN);
+            float(a).total_cmp(&float(b))
+        }
+        _ => rank(a)
+            .cmp(&rank(b))
+            .then_with(|| a.to_string().cmp(&b.to_string())),
+    }
+}
+
+/// How many times the key of each entry of a map occurs in it, which is more than once for keys that
+/// are duplicated. Msgpack allows that, but it's almost always a bug in whatever encoded the map.
+pub fn key_counts(entries: &[(rmpv::Value, rmpv::Value)]) -> Vec<usize> {
+    let mut counts: HashMap<HashableValue, usize> = HashMap::new();
+    for (key, _) in entries {
+        *counts.entry(HashableValue(key)).or_default() += 1;
+    }
+    entries
+        .iter()
+        .map(|(key, _)| counts[&HashableValue(key)])
+        .collect()
+}
+
+/// Adds the paths of the maps within `value` that have duplicate keys to `found`, along with how
+/// many of their entries have one, starting from `path`.
+pub fn find_duplicate_keys(
+    value: &rmpv::Value,
+    path: &mut ValuePath,
+    found: &mut Vec<(ValuePath, usize)>,
+) {
+    if let rmpv::Value::Map(entries) = value {
+        let duplicated = key_counts(entries)
+            .iter()
+            .filter(|&&count| count > 1)
+            .count();
+        if duplicated > 0 {
+            found.push((path.clone(), duplicated));
+        }
+    }
+    let mut i = 0;
+    while let (Some(segment), Some(child)) = (segment_of(value, i), child_of(value, i)) {
+        path.push(segment);
+        find_duplicate_keys(child, path, found);
+        path.pop();
+        i += 1;
+    }
+}
+
+/// Whether `a` and `b` are the same value. Unlike with `==`, floats are compared by their bits, so
+/// that a NaN is the same as itself.
+pub fn same_value(a: &rmpv::Value, b: &rmpv::Value) -> bool {
+    match (a, b) {
+        (rmpv::Value::F32(a), rmpv::Value::F32(b)) => a.to_bits() == b.to_bits(),
+        (rmpv::Value::F64(a), rmpv::Value::F64(b)) => a.to_bits() == b.to_bits(),
+        (rmpv::Value::Array(a), rmpv::Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_value(a, b))
+        }
+        (rmpv::Value::Map(a), rmpv::Value::Map(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|((ka, va), (kb, vb))| same_value(ka, kb) && same_value(va, vb))
+        }
+        _ => a == b,
+    }
+}
+
+/// A value that can be hashed and compared with [`same_value`], e.g. for finding map keys.
+pub struct HashableValue<'a>(pub &'a rmpv::Value);
+impl<'a> std::hash::Hash for HashableValue<'a> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        match self.0 {
+            rmpv::Value::Nil => {
+                state.write_u8(0);
+            }
+            rmpv::Value::Boolean(b) => {
+                (*b).hash(state);
+            }
+            rmpv::Value::Integer(i) => {
+                if let Some(i) = i.as_i64() {
+                    i.hash(state);
+                } else if let Some(i) = i.as_u64() {
+                    i.hash(state);
+                } else if let Some(i) = i.as_f64() {
+                    i.to_bits().hash(state);
+                } else {
+                    panic!("unsupported rmpv integer type");
+                }
+            }
+            rmpv::Value::F32(f) => {
+                f.to_bits().hash(state);
+            }
+            rmpv::Value::F64(f) => {
+                f.to_bits().hash(state);
+            }
+            rmpv::Value::String(s) => {
+                s.as_bytes().hash(state);
+            }
+            rmpv::Value::Binary(b) => {
+                state.write_u8(6);
+                state.write_u64(b.len() as u64);
+                state.write(b);
+            }
+            rmpv::Value::Array(a) => {
+                state.write_u8(7);
+                state.write_u64(a.len() as u64);
+                for item in a {
+                    HashableValue(item).hash(state);
+                }
+            }
+            rmpv::Value::Map(m) => {
+                state.write_u8(8);
+                state.write_u64(m.len() as u64);
+                for (key, value) in m {
+                    HashableValue(key).hash(state);
+                    HashableValue(value).hash(state);
+                }
+            }
+            rmpv::Value::Ext(i8, bytes) => {
+                state.write_u8(9);
+                state.write_i8(*i8);
+                state.write_u64(bytes.len() as u64);
+                state.write(bytes);
+            }
+        }
+    }
+}
+impl<'a> PartialEq for HashableValue<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        same_value(self.0, other.0)
+    }
+}
+impl<'a> Eq for HashableValue<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nans_are_the_same_as_themselves() {
+        let nans = rmpv::Value::Array(vec![
+            rmpv::Value::F32(f32::NAN),
+            rmpv::Value::Map(vec![(
+                rmpv::Value::F64(f64::NAN),
+                rmpv::Value::F64(-f64::NAN),
+            )]),
+        ]);
+        assert!(same_value(&nans, &nans.clone()));
+        assert!(HashableValue(&nans) == HashableValue(&nans));
+        assert!(!same_value(
+            &rmpv::Value::F64(f64::NAN),
+            &rmpv::Value::F64(-f64::NAN)
+        ));
+        assert!(!same_value(&rmpv::Value::F32(1.0), &rmpv::Value::F64(1.0)));
+    }
+}
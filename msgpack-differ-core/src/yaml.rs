@@ -0,0 +1,150 @@
+/// Writes `value` as YAML in block style, for tools that take YAML rather than JSON. Strings are
+/// written as they are where YAML would read them back as the same string, and otherwise quoted
+/// like in JSON, which YAML reads the same way.
+pub fn to_yaml(value: &serde_json::Value) -> String {
+    let mut yaml = String::new();
+    if is_block(value) {
+        write_block(&mut yaml, value, 0);
+    } else {
+        yaml.push_str(&scalar(value));
+        yaml.push('\n');
+    }
+    yaml
+}
+
+/// Whether `value` is written over lines of its own, rather than after its key or dash.
+fn is_block(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Array(items) => !items.is_empty(),
+        serde_json::Value::Object(entries) => !entries.is_empty(),
+        _ => false,
+    }
+}
+
+/// Writes the entries or items of `value`, which [`is_block`], `depth` levels in.
+fn write_block(yaml: &mut String, value: &serde_json::Value, depth: usize) {
+    let indent = "  ".repeat(depth);
+    match value {
+        serde_json::Value::Object(entries) => {
+            for (key, value) in entries {
+                yaml.push_str(&indent);
+                yaml.push_str(&string(key));
+                yaml.push(':');
+                write_child(yaml, value, depth);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                yaml.push_str(&indent);
+                yaml.push('-');
+                write_child(yaml, item, depth);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Writes `value` after the key or dash of an entry or item `depth` levels in.
+fn write_child(yaml: &mut String, value: &serde_json::Value, depth: usize) {
+    if is_block(value) {
+        yaml.push('\n');
+        write_block(yaml, value, depth + 1);
+    } else {
+        yaml.push(' ');
+        yaml.push_str(&scalar(value));
+        yaml.push('\n');
+    }
+}
+
+fn scalar(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "null".to_owned(),
+        serde_json::Value::Bool(b) => b.to_string(),
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::String(text) => string(text),
+        serde_json::Value::Array(_) => "[]".to_owned(),
+        serde_json::Value::Object(_) => "{}".to_owned(),
+    }
+}
+
+/// `text` as it's written in YAML: as it is if that can't be mistaken for anything else, such as a
+/// number, a boolean or the start of a map, or else quoted.
+fn string(text: &str) -> String {
+    const RESERVED: [&str; 11] = [
+        "true", "false", "null", "yes", "no", "on", "off", "y", "n", "~", "",
+    ];
+    let is_plain = text
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && !text.ends_with(' ')
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_-./ ".contains(c))
+        && !RESERVED.contains(&text.to_lowercase().as_str());
+    match is_plain {
+        true => text.to_owned(),
+        false => serde_json::to_string(text).expect("strings always serialize"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::json::msgpack_to_json;
+
+    #[test]
+    fn writes_msgpack_as_block_yaml() {
+        let value = rmpv::Value::Map(vec![
+            ("name".into(), "a file.bin".into()),
+            ("data".into(), rmpv::Value::Binary(vec![0x00, 0xfe, 0xff])),
+            ("when".into(), rmpv::Value::Ext(-1, vec![0; 4])),
+            (
+                1.into(),
+                rmpv::Value::Array(vec![true.into(), rmpv::Value::Nil]),
+            ),
+            ("empty".into(), rmpv::Value::Map(vec![])),
+        ]);
+        let expected = "\
+name: a file.bin
+data: AP7/
+when:
+  type: -1
+  data: \"AAAAAA==\"
+\"1\":
+  - true
+  - null
+empty: {}
+";
+        assert_eq!(to_yaml(&msgpack_to_json(&value)), expected);
+    }
+
+    #[test]
+    fn quotes_strings_that_would_read_as_something_else() {
+        for (text, yaml) in [
+            ("plain text", "plain text"),
+            ("snake_case-and.dots/slashes", "snake_case-and.dots/slashes"),
+            ("true", "\"true\""),
+            ("No", "\"No\""),
+            ("~", "\"~\""),
+            ("", "\"\""),
+            ("12", "\"12\""),
+            ("key: value", "\"key: value\""),
+            ("- item", "\"- item\""),
+            ("trailing ", "\"trailing \""),
+            ("line\nbreak", "\"line\\nbreak\""),
+        ] {
+            assert_eq!(to_yaml(&json!(text)), format!("{yaml}\n"), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn writes_scalars_and_empty_containers_on_their_own() {
+        assert_eq!(to_yaml(&json!(null)), "null\n");
+        assert_eq!(to_yaml(&json!(-1.5)), "-1.5\n");
+        assert_eq!(to_yaml(&json!([])), "[]\n");
+        assert_eq!(to_yaml(&json!([[1], {}])), "-\n  - 1\n- {}\n");
+    }
+}
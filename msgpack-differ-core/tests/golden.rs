@@ -0,0 +1,220 @@
+//! Golden-file tests of the diff engine.
+//!
+//! Each directory in `tests/golden` is a case holding the two documents compared, `a.msgpack` and
+//! `b.msgpack`, optionally an `ignored.txt` of paths to leave out of the comparison (one per line),
+//! and what is expected of the diff of them:
+//!
+//! - `expected.txt`, the whole diff tree, one node per line, so that changes to how array items are
+//!   aligned or map entries matched show up as changes to which nodes are paired up;
+//! - `expected.json`, the JSON Patch and JSON Merge Patch made from the diff.
+//!
+//! However the nodes are paired up, patching A with the msgpack patch made from the diff has to
+//! make B, which is checked rather than recorded so that a broken patch can't become what's
+//! expected.
+//!
+//! Run with `UPDATE_GOLDEN=1` to write what the diff engine makes now as what's expected, for new
+//! cases or after changing it on purpose, and review the changes to the expected files like any
+//! other.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use msgpack_differ_core::diff::{
+    children, diff_values_with, differences, DiffEntry, DiffOptions, DiffStatus,
+};
+use msgpack_differ_core::patch::{
+    apply_msgpack_patch, decode_msgpack_patch, encode_msgpack_patch, json_merge_patch, json_patch,
+    msgpack_patch,
+};
+use msgpack_differ_core::path::ValuePath;
+use msgpack_differ_core::rmpv::Value;
+
+#[test]
+fn golden() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut cases = std::fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden"))
+        .expect("golden cases should be readable")
+        .map(|entry| entry.expect("golden cases should be readable").path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+    cases.sort();
+    assert!(!cases.is_empty(), "there should be golden cases");
+
+    let mut failures = Vec::new();
+    for case in &cases {
+        let name = case.file_name().unwrap().to_string_lossy();
+        match check_case(case, update) {
+            Ok(()) => {}
+            Err(failure) => failures.push(format!("{name}: {failure}")),
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} golden cases failed (run with UPDATE_GOLDEN=1 if the changes are expected):\n\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n\n")
+    );
+}
+
+/// Diffs the documents of the case in `directory`, comparing what's made of the diff with what's
+/// expected, or writing it as what's expected if `update`.
+fn check_case(directory: &Path, update: bool) -> Result<(), String> {
+    let (a, a_bytes) = read_document(&directory.join("a.msgpack"))?;
+    let (b, _) = read_document(&directory.join("b.msgpack"))?;
+    let ignored = read_ignored(&directory.join("ignored.txt"))?;
+    let options = DiffOptions {
+        ignored: &ignored,
+        ..Default::default()
+    };
+    let root = diff_values_with(Some(&a), Some(&b), &options);
+    let entry = DiffEntry {
+        node: &root,
+        a: Some(&a),
+        b: Some(&b),
+    };
+
+    // compared with the diff engine, as e.g. maps in a different order are the same to it
+    let patch = msgpack_patch(entry, &a_bytes, false);
+    let patch = decode_msgpack_patch(&encode_msgpack_patch(&patch))?;
+    let mut patched = a.clone();
+    apply_msgpack_patch(&mut patched, &patch, &a_bytes).map_err(|err| err.to_string())?;
+    let left = diff_values_with(Some(&patched), Some(&b), &options);
+    let left = differences(&left, Some(&patched), Some(&b));
+    if !left.is_empty() {
+        let paths = left.iter().map(|difference| difference.path.to_string());
+        return Err(format!(
+            "patching A leaves differences from B at {}",
+            paths.collect::<Vec<_>>().join(", ")
+        ));
+    }
+
+    let mut tree = String::new();
+    write_tree(&mut tree, entry, "(root)", 0);
+    let patches = serde_json::json!({
+        "json_patch": json_patch(entry),
+        "json_merge_patch": json_merge_patch(entry),
+    });
+    let json = serde_json::to_string_pretty(&patches).map_err(|err| err.to_string())? + "\n";
+
+    let mut mismatches = Vec::new();
+    for (file_name, actual) in [("expected.txt", tree), ("expected.json", json)] {
+        let path = directory.join(file_name);
+        if update {
+            std::fs::write(&path, &actual)
+                .map_err(|err| format!("couldn't write {}: {err}", path.display()))?;
+            continue;
+        }
+        match std::fs::read_to_string(&path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => mismatches.push(format!(
+                "{file_name} differs\n--- expected\n{expected}--- actual\n{actual}"
+            )),
+            Err(err) => mismatches.push(format!("couldn't read {file_name}: {err}")),
+        }
+    }
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(mismatches.join("\n"))
+    }
+}
+
+/// The one document in the file at `path`.
+/// The document in the file at `path`, along with the bytes of the file.
+fn read_document(path: &Path) -> Result<(Value, Vec<u8>), String> {
+    let bytes =
+        std::fs::read(path).map_err(|err| format!("couldn't read {}: {err}", path.display()))?;
+    let mut rest = bytes.as_slice();
+    let value = msgpack_differ_core::rmpv::decode::read_value(&mut rest)
+        .map_err(|err| format!("couldn't decode {}: {err}", path.display()))?;
+    if !rest.is_empty() {
+        return Err(format!("{} has bytes after its document", path.display()));
+    }
+    Ok((value, bytes))
+}
+
+/// The paths listed in the file at `path`, or none if there's no such file.
+fn read_ignored(path: &Path) -> Result<Vec<ValuePath>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("couldn't read {}: {err}", path.display()))?;
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.parse()
+                .map_err(|err| format!("bad path {line:?} to ignore: {err}"))
+        })
+        .collect()
+}
+
+/// Writes the node of `entry` as a line, e.g. `~ hp (A 1, B 1): 10 -> 7`, followed by its
+/// children indented below it.
+fn write_tree(out: &mut String, entry: DiffEntry, label: &str, depth: usize) {
+    let symbol = match entry.node.status {
+        DiffStatus::Unchanged => '=',
+        DiffStatus::Added => '+',
+        DiffStatus::Removed => '-',
+        DiffStatus::Changed => '~',
+        DiffStatus::ContainsChanges => '*',
+        DiffStatus::Ignored => '!',
+    };
+    let index = |index: Option<usize>| index.map_or("-".to_owned(), |index| index.to_string());
+    write!(
+        out,
+        "{:indent$}{symbol} {label} (A {}, B {})",
+        "",
+        index(entry.node.a_index),
+        index(entry.node.b_index),
+        indent = depth * 2
+    )
+    .unwrap();
+    // containers that are in both have their children listed instead
+    if entry.node.children.is_empty() {
+        match (entry.a, entry.b) {
+            (Some(a), Some(b)) if entry.node.status == DiffStatus::Changed => {
+                write!(out, ": {} -> {}", format_value(a), format_value(b)).unwrap()
+            }
+            (Some(value), _) | (None, Some(value)) => {
+                write!(out, ": {}", format_value(value)).unwrap()
+            }
+            (None, None) => {}
+        }
+    }
+    out.push('\n');
+    for (segment, child) in children(entry.node, entry.a, entry.b) {
+        write_tree(out, child, &segment.to_string(), depth + 1);
+    }
+}
+
+/// `value` written out in full, unlike [`Value`]'s `Display` telling apart floats from integers,
+/// floats of each size, and binary and extension values from arrays.
+fn format_value(value: &Value) -> String {
+    let hex = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+    };
+    match value {
+        Value::F32(float) => format!("{float:?}f32"),
+        Value::F64(float) => format!("{float:?}"),
+        Value::Binary(bytes) => format!("bin({})", hex(bytes)),
+        Value::Ext(type_id, data) => format!("ext({type_id}, {})", hex(data)),
+        Value::Array(items) => {
+            let items = items.iter().map(format_value).collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Map(entries) => {
+            let entries = entries
+                .iter()
+                .map(|(key, value)| format!("{}: {}", format_value(key), format_value(value)))
+                .collect::<Vec<_>>();
+            format!("{{{}}}", entries.join(", "))
+        }
+        _ => value.to_string(),
+    }
+}
@@ -0,0 +1,172 @@
+use eframe::egui;
+use egui::accesskit;
+
+use crate::diff::DiffStatus;
+use crate::diff_view::status_name;
+use crate::display::DisplayOptions;
+use crate::path::PathSegment;
+
+/// Senses clicks without being a stop when tabbing through widgets, for the rows of a tree and the
+/// buttons within them, which are gone through with the arrow keys instead.
+pub const CLICK_WITHOUT_FOCUS: egui::Sense = egui::Sense {
+    click: true,
+    drag: false,
+    focusable: false,
+};
+
+/// Names an entry of a tree the way a screen reader should read it out, e.g. `key host, string
+/// value example.com`, by what holds it there and its value if it's there.
+pub fn describe_entry(
+    segment: Option<&PathSegment>,
+    value: Option<&rmpv::Value>,
+    display: &DisplayOptions,
+) -> String {
+    let name = match segment {
+        Some(PathSegment::Index(i)) => format!("item {i}"),
+        Some(PathSegment::Key(rmpv::Value::String(key))) => {
+            format!("key {}", String::from_utf8_lossy(key.as_bytes()))
+        }
+        Some(PathSegment::Key(key)) => format!("key {}", display.abbreviate(key)),
+        None => "root".to_owned(),
+    };
+    match value {
+        Some(value) => format!("{name}, {}", describe_value(value, display)),
+        None => name,
+    }
+}
+
+/// Like [`describe_entry`] for a node of the diff, along with how it differs, e.g. `key port,
+/// changed from integer value 80 to integer value 8080`.
+pub fn describe_diff_entry(
+    segment: Option<&PathSegment>,
+    a: Option<&rmpv::Value>,
+    b: Option<&rmpv::Value>,
+    status: DiffStatus,
+    display: &DisplayOptions,
+) -> String {
+    match (status, a, b) {
+        (DiffStatus::Changed, Some(a), Some(b)) => format!(
+            "{}, changed from {} to {}",
+            describe_entry(segment, None, display),
+            describe_value(a, display),
+            describe_value(b, display)
+        ),
+        (DiffStatus::Unchanged, _, _) => describe_entry(segment, b.or(a), display),
+        (status, a, b) => format!(
+            "{}, {}",
+            describe_entry(segment, b.or(a), display),
+            status_name(status)
+        ),
+    }
+}
+
+/// `name` with `note` read out after it, if there's a note.
+pub fn with_note(name: String, note: Option<&str>) -> String {
+    match note {
+        Some(note) => format!("{name}, note: {note}"),
+        None => name,
+    }
+}
+
+/// A value as it's read out, e.g. `string value example.com` or `map of 14 keys`.
+fn describe_value(value: &rmpv::Value, display: &DisplayOptions) -> String {
+    match value {
+        rmpv::Value::Nil => "nil".to_owned(),
+        rmpv::Value::Boolean(b) => format!("boolean value {b}"),
+        rmpv::Value::Integer(_) | rmpv::Value::F32(_) | rmpv::Value::F64(_) => {
+            let kind = match value {
+                rmpv::Value::Integer(_) => "integer",
+                _ => "float",
+            };
+            format!("{kind} value {}", display.abbreviate(value))
+        }
+        rmpv::Value::String(text) => {
+            let text = String::from_utf8_lossy(text.as_bytes());
+            format!("string value {}", display.cut_short(&text))
+        }
+        rmpv::Value::Binary(bytes) => match bytes.len() {
+            1 => "binary value of 1 byte".to_owned(),
+            len => format!("binary value of {len} bytes"),
+        },
+        rmpv::Value::Array(items) => match items.len() {
+            1 => "array of 1 item".to_owned(),
+            len => format!("array of {len} items"),
+        },
+        rmpv::Value::Map(entries) => match entries.len() {
+            1 => "map of 1 key".to_owned(),
+            len => format!("map of {len} keys"),
+        },
+        rmpv::Value::Ext(type_id, _) => match crate::ext_types::decode(value) {
+            Some(decoded) => format!("{} value {}", decoded.name, decoded.text),
+            None => format!("extension value of type {type_id}"),
+        },
+    }
+}
+
+/// Makes the widget with `id` read out as a tree named `name`, whose items are those added within
+/// `add_contents`, with `active` the one that's selected, if one is.
+pub fn tree<R>(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    name: &str,
+    add_contents: impl FnOnce(&mut egui::Ui) -> (R, Option<egui::Id>),
+) -> R {
+    let ctx = ui.ctx().clone();
+    ctx.accesskit_node_builder(id, |builder| {
+        builder.set_role(accesskit::Role::Tree);
+        builder.set_name(name);
+    });
+    let (result, active) = ctx.with_accessibility_parent(id, || add_contents(ui));
+    if let Some(active) = active {
+        ctx.accesskit_node_builder(id, |builder| {
+            // egui gives each widget the node with the same id as it
+            builder.set_active_descendant(accesskit::NodeId::from(active.value()));
+        });
+    }
+    result
+}
+
+/// Makes `response`, covering a whole row of a tree, read out as an item of it named by `name` at
+/// `depth`, 1 being the top level, and shows the contents of the row within it.
+pub fn tree_item<R>(
+    ui: &mut egui::Ui,
+    response: &egui::Response,
+    depth: usize,
+    expanded: Option<bool>,
+    selected: bool,
+    name: impl FnOnce() -> String,
+    add_contents: impl FnOnce(&mut egui::Ui) -> R,
+) -> R {
+    // only named if a screen reader is listening, as that's not free
+    ui.ctx().accesskit_node_builder(response.id, |builder| {
+        builder.set_role(accesskit::Role::TreeItem);
+        builder.set_name(name());
+        builder.set_level(depth);
+        if let Some(expanded) = expanded {
+            builder.set_expanded(expanded);
+        }
+        builder.set_selected(selected);
+    });
+    let ctx = ui.ctx().clone();
+    ctx.with_accessibility_parent(response.id, || add_contents(ui))
+}
+
+/// Shows the button for expanding or collapsing an entry of a tree, returning whether it was
+/// clicked. It's left out when tabbing through widgets, like the row it's on.
+pub fn show_expander(ui: &mut egui::Ui, expanded: bool) -> bool {
+    let (icon, name) = match expanded {
+        true => ("▼", "Collapse"),
+        false => ("▶", "Expand"),
+    };
+    let response = ui.add(egui::Button::new(icon).small().sense(CLICK_WITHOUT_FOCUS));
+    response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, true, name));
+    response.clicked()
+}
+
+/// Shows a small button with just `icon` on it, which is read out and shown on hover as `name`.
+pub fn icon_button(ui: &mut egui::Ui, icon: &str, name: impl Into<String>) -> egui::Response {
+    let name = name.into();
+    let response = ui.small_button(icon);
+    response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, true, &name));
+    response.on_hover_text(name)
+}
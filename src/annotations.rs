@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::path::ValuePath;
+
+/// Notes attached to paths within the compared values, such as why a difference is expected, which
+/// are saved with the session.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Annotations {
+    /// By the path as written, like the ignored paths, so that they read well when saved.
+    notes: BTreeMap<String, String>,
+}
+
+impl Annotations {
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+    }
+
+    pub fn get(&self, path: &ValuePath) -> Option<&str> {
+        if self.notes.is_empty() {
+            return None;
+        }
+        self.notes.get(&path.to_string()).map(String::as_str)
+    }
+
+    /// The note on the value at `full_path` within a file whose compared value is at `root`.
+    pub fn get_within(&self, root: &ValuePath, full_path: &ValuePath) -> Option<&str> {
+        let rest = full_path.segments().strip_prefix(root.segments())?;
+        self.get(&rest.iter().cloned().collect())
+    }
+
+    /// Attaches `note` to `path`, replacing whatever was attached before, or removes the note if
+    /// it's blank.
+    pub fn set(&mut self, path: String, note: String) {
+        if note.trim().is_empty() {
+            self.notes.remove(&path);
+        } else {
+            self.notes.insert(path, note);
+        }
+    }
+
+    /// Paths with notes attached, in order, along with their notes.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.notes
+            .iter()
+            .map(|(path, note)| (path.as_str(), note.as_str()))
+    }
+}
+
+/// Shows a marker next to a value that has `note` attached, if it does, with the note on hover.
+pub fn show_note_marker(ui: &mut egui::Ui, note: Option<&str>) {
+    if let Some(note) = note {
+        ui.label(egui::RichText::new("🗒").color(ui.visuals().hyperlink_color))
+            .on_hover_text(note);
+    }
+}
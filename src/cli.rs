@@ -0,0 +1,336 @@
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::diff::DiffEntry;
+use crate::diff::{diff_values, differences, DiffStatus, Difference};
+use crate::ext::ExtRegistry;
+use crate::loader::{EncodeError, LoadError, LoadOptions, LoadProgress, LoadedFile, Source};
+use crate::patch::{
+    apply_json_patch, apply_msgpack_patch, decode_msgpack_patch, encode_msgpack_patch,
+    is_msgpack_patch, msgpack_patch, parse_json_patch, PatchError,
+};
+use crate::recover::DecodeFailure;
+use crate::value::abbreviate;
+
+const USAGE: &str = "\
+Usage:
+  msgpack-differ [A] [B]                         open the GUI
+  msgpack-differ diff-stream <A> <B>             diff two streams of concatenated msgpack values record by record
+  msgpack-differ make-patch <A> <B> [-o <OUT>]   write a msgpack patch turning A into B to OUT or stdout
+  msgpack-differ apply-patch <BASE> <PATCH> [-o <OUT>]
+                                                 apply a msgpack patch or a JSON Patch (RFC 6902) to BASE,
+                                                 writing msgpack to OUT or stdout
+
+Exit codes:
+  0  A and B are the same, or the patch was applied
+  1  A and B differ
+  2  the arguments weren't understood
+  3  a file couldn't be read or written
+  4  a file isn't msgpack, or is corrupt or truncated
+  5  a file, or what it decompresses to, is too large
+  6  a file is in a format that isn't supported, such as an archive
+  7  the patch isn't a patch, or doesn't apply to BASE";
+
+const EXIT_SAME: i32 = 0;
+const EXIT_DIFFERENT: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_IO: i32 = 3;
+const EXIT_NOT_MSGPACK: i32 = 4;
+const EXIT_TOO_LARGE: i32 = 5;
+const EXIT_UNSUPPORTED: i32 = 6;
+const EXIT_BAD_PATCH: i32 = 7;
+
+/// Why a subcommand failed.
+#[derive(Debug, thiserror::Error)]
+enum CliError {
+    #[error("{}: {source}", path.display())]
+    Load { path: PathBuf, source: LoadError },
+    /// The file loaded, but stopped decoding part way through.
+    #[error("{}: {failure}", path.display())]
+    Truncated {
+        path: PathBuf,
+        failure: DecodeFailure,
+    },
+    #[error("{}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{}: record {record}: {source}", path.display())]
+    Record {
+        path: PathBuf,
+        record: usize,
+        source: rmpv::decode::Error,
+    },
+    /// The patch file couldn't be read as either kind of patch.
+    #[error("{}: {message}", path.display())]
+    InvalidPatch { path: PathBuf, message: String },
+    #[error("{}: {source}", path.display())]
+    PatchFailed { path: PathBuf, source: PatchError },
+    /// The patched document couldn't be encoded like the file it came from.
+    #[error("{}: {source}", path.display())]
+    Encode { path: PathBuf, source: EncodeError },
+    #[error("couldn't write the output: {0}")]
+    Write(#[from] std::io::Error),
+}
+
+impl CliError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Load { source, .. } => match source {
+                LoadError::Io(_)
+                | LoadError::Download(_)
+                | LoadError::Unavailable(_)
+                | LoadError::Cancelled
+                | LoadError::Aborted => EXIT_IO,
+                LoadError::Empty
+                | LoadError::SkippedPastEnd { .. }
+                | LoadError::NoMsgpackFound { .. }
+                | LoadError::NotRpcMessage { .. }
+                | LoadError::DecodeError { .. } => EXIT_NOT_MSGPACK,
+                LoadError::TooLarge { .. } => EXIT_TOO_LARGE,
+                LoadError::UnsupportedFormat { .. } => EXIT_UNSUPPORTED,
+            },
+            CliError::Truncated { .. } | CliError::Record { .. } => EXIT_NOT_MSGPACK,
+            CliError::Read { .. } | CliError::Write(_) => EXIT_IO,
+            CliError::InvalidPatch { .. } | CliError::PatchFailed { .. } => EXIT_BAD_PATCH,
+            CliError::Encode { .. } => EXIT_TOO_LARGE,
+        }
+    }
+}
+
+/// Runs the subcommand named by `args` (excluding the program name), if any, returning its exit code.
+///
+/// Returns `None` when `args` don't name a subcommand, in which case the GUI should be started instead.
+pub fn run_subcommand(args: &[OsString]) -> Option<i32> {
+    let (command, rest) = args.split_first()?;
+    let code =
+        match command.to_str()? {
+            "diff-stream" => match rest {
+                [a, b] => report(diff_stream(Path::new(a), Path::new(b))),
+                _ => usage_error(),
+            },
+            "make-patch" => match rest {
+                [a, b] => report(make_patch(Path::new(a), Path::new(b), None)),
+                [a, b, flag, out] if flag == "-o" || flag == "--output" => report(make_patch(
+                    Path::new(a),
+                    Path::new(b),
+                    Some(PathBuf::from(out)),
+                )),
+                _ => usage_error(),
+            },
+            "apply-patch" => match rest {
+                [base, patch] => report(apply_patch(Path::new(base), Path::new(patch), None)),
+                [base, patch, flag, out] if flag == "-o" || flag == "--output" => report(
+                    apply_patch(Path::new(base), Path::new(patch), Some(PathBuf::from(out))),
+                ),
+                _ => usage_error(),
+            },
+            "help" | "--help" | "-h" => {
+                println!("{USAGE}");
+                EXIT_SAME
+            }
+            _ => return None,
+        };
+    Some(code)
+}
+
+fn usage_error() -> i32 {
+    eprintln!("{USAGE}");
+    EXIT_USAGE
+}
+
+fn report(result: Result<bool, CliError>) -> i32 {
+    match result {
+        Ok(true) => EXIT_DIFFERENT,
+        Ok(false) => EXIT_SAME,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            err.exit_code()
+        }
+    }
+}
+
+/// Reads the next value of a stream, or `None` at a clean end of stream.
+fn read_next(
+    reader: &mut impl BufRead,
+    path: &Path,
+    record: usize,
+) -> Result<Option<rmpv::Value>, CliError> {
+    let read_error = |source| CliError::Read {
+        path: path.to_owned(),
+        source,
+    };
+    if reader.fill_buf().map_err(read_error)?.is_empty() {
+        return Ok(None);
+    }
+    match rmpv::decode::read_value(reader) {
+        Ok(value) => Ok(Some(value)),
+        Err(source) => Err(CliError::Record {
+            path: path.to_owned(),
+            record,
+            source,
+        }),
+    }
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, CliError> {
+    match File::open(path) {
+        Ok(file) => Ok(BufReader::new(file)),
+        Err(source) => Err(CliError::Read {
+            path: path.to_owned(),
+            source,
+        }),
+    }
+}
+
+/// Diffs two msgpack streams one record at a time, printing one line per record.
+/// Returns whether any record differed.
+fn diff_stream(path_a: &Path, path_b: &Path) -> Result<bool, CliError> {
+    let mut reader_a = open(path_a)?;
+    let mut reader_b = open(path_b)?;
+    let mut out = std::io::stdout().lock();
+    let mut any_different = false;
+    for record in 0.. {
+        let a = read_next(&mut reader_a, path_a, record)?;
+        let b = read_next(&mut reader_b, path_b, record)?;
+        if a.is_none() && b.is_none() {
+            break;
+        }
+
+        let diff = diff_values(a.as_ref(), b.as_ref());
+        any_different |= diff.difference_count > 0;
+        match diff.status {
+            DiffStatus::Unchanged | DiffStatus::Ignored => writeln!(out, "{record}\tunchanged")?,
+            DiffStatus::Removed => writeln!(out, "{record}\tonly in A")?,
+            DiffStatus::Added => writeln!(out, "{record}\tonly in B")?,
+            DiffStatus::Changed | DiffStatus::ContainsChanges => {
+                let diffs = differences(&diff, a.as_ref(), b.as_ref());
+                let summary = diffs.iter().map(describe).collect::<Vec<_>>().join("; ");
+                let noun = if diffs.len() == 1 {
+                    "difference"
+                } else {
+                    "differences"
+                };
+                writeln!(out, "{record}\t{} {noun}: {summary}", diffs.len())?;
+            }
+        }
+    }
+    Ok(any_different)
+}
+
+/// Applies `patch_path` to the document in `base_path`. Never reports a difference.
+fn apply_patch(
+    base_path: &Path,
+    patch_path: &Path,
+    output_path: Option<PathBuf>,
+) -> Result<bool, CliError> {
+    let invalid = |message: String| CliError::InvalidPatch {
+        path: patch_path.to_owned(),
+        message,
+    };
+    let patch = std::fs::read(patch_path).map_err(|source| CliError::Read {
+        path: patch_path.to_owned(),
+        source,
+    })?;
+    let (base, doc, applied) = if is_msgpack_patch(&patch) {
+        let patch = decode_msgpack_patch(&patch).map_err(invalid)?;
+        // loaded the way the patch was made, so that its paths lead where they did
+        let options = LoadOptions {
+            decode_nested: patch.decode_nested,
+            ..LoadOptions::default()
+        };
+        let base = load(base_path, &options)?;
+        let mut doc = rmpv::Value::clone(&base.parsed);
+        let bytes = base.value_bytes(&[]).unwrap_or_default();
+        let applied = apply_msgpack_patch(&mut doc, &patch, bytes);
+        (base, doc, applied)
+    } else {
+        let patch = String::from_utf8(patch)
+            .map_err(|_| invalid("neither a msgpack patch nor UTF-8 JSON".to_owned()))?;
+        let ops = parse_json_patch(&patch).map_err(|e| invalid(e.to_string()))?;
+        let base = load(base_path, &LoadOptions::default())?;
+        let mut doc = rmpv::Value::clone(&base.parsed);
+        let applied = apply_json_patch(&mut doc, &ops);
+        (base, doc, applied)
+    };
+    applied.map_err(|source| CliError::PatchFailed {
+        path: patch_path.to_owned(),
+        source,
+    })?;
+
+    let encoded = base
+        .encode_document(&doc, &base.nested)
+        .map_err(|source| CliError::Encode {
+            path: base_path.to_owned(),
+            source,
+        })?;
+    write_output(output_path, &encoded)?;
+    Ok(false)
+}
+
+/// Writes the msgpack patch that turns the document in `path_a` into the one in `path_b`.
+/// Returns whether they differ.
+fn make_patch(
+    path_a: &Path,
+    path_b: &Path,
+    output_path: Option<PathBuf>,
+) -> Result<bool, CliError> {
+    let options = LoadOptions::default();
+    let (file_a, file_b) = (load(path_a, &options)?, load(path_b, &options)?);
+    let (a, b) = (&*file_a.parsed, &*file_b.parsed);
+    let diff = diff_values(Some(a), Some(b));
+    let entry = DiffEntry {
+        node: &diff,
+        a: Some(a),
+        b: Some(b),
+    };
+    let base = file_a.value_bytes(&[]).unwrap_or_default();
+    let patch = msgpack_patch(entry, base, options.decode_nested);
+    write_output(output_path, &encode_msgpack_patch(&patch))?;
+    Ok(diff.difference_count > 0)
+}
+
+/// Loads the document in `path` the way the GUI does with `options`, so decompressed and with the
+/// values of a file holding several as an array of them. Unlike the GUI, a file that stops decoding
+/// part way through is an error rather than whatever came before that.
+fn load(path: &Path, options: &LoadOptions) -> Result<LoadedFile, CliError> {
+    let file = LoadedFile::load(
+        &Source::File(path.to_owned()),
+        None,
+        options,
+        &LoadProgress::default(),
+    )
+    .map_err(|source| CliError::Load {
+        path: path.to_owned(),
+        source,
+    })?;
+    match file.decode_failure {
+        Some(failure) => Err(CliError::Truncated {
+            path: path.to_owned(),
+            failure,
+        }),
+        None => Ok(file),
+    }
+}
+
+fn write_output(output_path: Option<PathBuf>, bytes: &[u8]) -> std::io::Result<()> {
+    match output_path {
+        Some(output_path) => std::fs::write(output_path, bytes),
+        None => std::io::stdout().lock().write_all(bytes),
+    }
+}
+
+fn describe(difference: &Difference) -> String {
+    let path = &difference.path;
+    // the command line doesn't read the settings, so only the built-in extension types are known
+    let abbreviate = |value| abbreviate(value, ExtRegistry::builtin());
+    match (difference.status, difference.a, difference.b) {
+        (DiffStatus::Added, _, Some(b)) => format!("{path}: added {}", abbreviate(b)),
+        (DiffStatus::Removed, Some(a), _) => format!("{path}: removed {}", abbreviate(a)),
+        (_, Some(a), Some(b)) => format!("{path}: {} -> {}", abbreviate(a), abbreviate(b)),
+        _ => format!("{path}: changed"),
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui::os::OperatingSystem;
+use serde_json::Value as Json;
+use toml_edit::{DocumentMut, Item, Table, Value as Toml};
+
+use crate::settings::AppSettings;
+
+/// Where the config file is: `config.toml` in the platform's config directory, if it has one.
+///
+/// Settings in the config file take the place of those saved with the session, so that they can be
+/// kept under version control and shared across machines.
+pub fn path() -> Option<PathBuf> {
+    let var = |name| {
+        std::env::var_os(name)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+    };
+    let directory = match OperatingSystem::from_target_os() {
+        OperatingSystem::Nix => var("XDG_CONFIG_HOME")
+            .or_else(|| var("HOME").map(|home| home.join(".config")))?
+            .join("msgpack-differ"),
+        OperatingSystem::Mac => var("HOME")?
+            .join("Library")
+            .join("Application Support")
+            .join("MsgPack-Differ"),
+        OperatingSystem::Windows => var("APPDATA")?.join("MsgPack Differ"),
+        OperatingSystem::Unknown | OperatingSystem::Android | OperatingSystem::IOS => return None,
+    };
+    Some(directory.join("config.toml"))
+}
+
+/// `settings` with whatever the config file at `path` sets in place of their own values. Tables in
+/// the file only replace the settings they mention, e.g. `[display]` with just `sort_keys = true`
+/// leaves the rest of the display options alone.
+pub fn apply(
+    path: &Path,
+    settings: &AppSettings,
+) -> Result<AppSettings, Box<dyn std::error::Error>> {
+    let document: DocumentMut = std::fs::read_to_string(path)?.parse()?;
+    let mut merged = serde_json::to_value(settings)?;
+    for key in document.as_table().iter().map(|(key, _)| key) {
+        if merged.get(key).is_none() {
+            log::warn!("Unknown setting {key} in {}", path.display());
+        }
+    }
+    merge(&mut merged, table_to_json(document.as_table()));
+    let mut settings: AppSettings = serde_json::from_value(merged)?;
+    if document.contains_key("ext_types_file") {
+        // so that the config directory can be shared along with the extension types file in it
+        if let (Some(file), Some(directory)) = (&mut settings.ext_types_file, path.parent()) {
+            *file = directory.join(&*file);
+        }
+    }
+    Ok(settings)
+}
+
+/// Writes all of `settings` to the config file at `path`, creating its directory if need be.
+pub fn save(path: &Path, settings: &AppSettings) -> Result<(), Box<dyn std::error::Error>> {
+    let mut document = DocumentMut::new();
+    if let Json::Object(entries) = serde_json::to_value(settings)? {
+        for (key, value) in &entries {
+            let item = match json_to_toml(value) {
+                Some(Toml::InlineTable(table)) => Item::Table(table.into_table()),
+                Some(value) => Item::Value(value),
+                // e.g. no extension types file
+                None => continue,
+            };
+            document.insert(key, item);
+        }
+    }
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    std::fs::write(path, document.to_string())?;
+    Ok(())
+}
+
+/// Sets everything in `overrides` in `value`, going into objects rather than replacing them whole.
+fn merge(value: &mut Json, overrides: Json) {
+    match (value, overrides) {
+        (Json::Object(entries), Json::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge(entries.entry(key).or_insert(Json::Null), value);
+            }
+        }
+        (value, overrides) => *value = overrides,
+    }
+}
+
+fn table_to_json(table: &Table) -> Json {
+    table
+        .iter()
+        .filter_map(|(key, item)| Some((key, item_to_json(item)?)))
+        .collect()
+}
+
+fn item_to_json(item: &Item) -> Option<Json> {
+    Some(match item {
+        Item::None => return None,
+        Item::Value(value) => toml_to_json(value),
+        Item::Table(table) => table_to_json(table),
+        Item::ArrayOfTables(tables) => tables.iter().map(table_to_json).collect(),
+    })
+}
+
+fn toml_to_json(value: &Toml) -> Json {
+    match value {
+        Toml::String(text) => Json::from(text.value().as_str()),
+        Toml::Integer(integer) => Json::from(*integer.value()),
+        Toml::Float(float) => Json::from(*float.value()),
+        Toml::Boolean(boolean) => Json::from(*boolean.value()),
+        Toml::Datetime(datetime) => Json::from(datetime.value().to_string()),
+        Toml::Array(items) => items.iter().map(toml_to_json).collect(),
+        Toml::InlineTable(table) => table
+            .iter()
+            .map(|(key, value)| (key, toml_to_json(value)))
+            .collect(),
+    }
+}
+
+/// `value` as TOML, or `None` if it's null, which TOML has nothing like.
+fn json_to_toml(value: &Json) -> Option<Toml> {
+    Some(match value {
+        Json::Null => return None,
+        Json::Bool(boolean) => Toml::from(*boolean),
+        Json::Number(number) => match number.as_i64() {
+            Some(integer) => Toml::from(integer),
+            None => Toml::from(number.as_f64()?),
+        },
+        Json::String(text) => Toml::from(text.as_str()),
+        Json::Array(items) => Toml::Array(items.iter().filter_map(json_to_toml).collect()),
+        Json::Object(entries) => Toml::InlineTable(
+            entries
+                .iter()
+                .filter_map(|(key, value)| Some((key.as_str(), json_to_toml(value)?)))
+                .collect(),
+        ),
+    })
+}
@@ -0,0 +1,1702 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::accessibility;
+use crate::annotations::{show_note_marker, Annotations};
+use crate::diff::{DiffEntry, DiffNode, DiffStatus};
+use crate::display::{DisplayOptions, Palette, StringStyle};
+use crate::edit::Edit;
+use crate::image_preview;
+use crate::node_menu::{
+    copy_bytes_buttons, copy_path_buttons, copy_value_buttons, decode_nested_button,
+    save_value_button, NodeAction,
+};
+use crate::path::{PathSegment, ValuePath};
+use crate::search::{search, SearchMatch};
+use crate::tree_view::{show_duplicate_key, Expansion};
+use crate::value::{compare_keys, describe_children, find_duplicate_keys, key_counts};
+
+/// How [`DiffView`] lays out a diff, much like the two ways text diff tools can.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum DiffLayout {
+    /// A table with a row per node, with A's value on the left and B's on the right.
+    #[default]
+    SideBySide,
+    /// One merged tree, with both values shown inline where they differ.
+    Unified,
+    /// Columns of each node's whole path, its values and its status, without indentation, which
+    /// can be sorted by any of them.
+    Table,
+}
+
+/// A column of [`DiffLayout::Table`] that its rows can be sorted by.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TableColumn {
+    Path,
+    A,
+    B,
+    Status,
+}
+
+/// How the rows of [`DiffLayout::Table`] are ordered, rather than as they are in the tree.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TableSort {
+    pub column: TableColumn,
+    pub descending: bool,
+}
+
+/// Which kinds of nodes [`DiffView`] shows, along with whatever holds them.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusFilter {
+    pub added: bool,
+    pub removed: bool,
+    pub changed: bool,
+    pub unchanged: bool,
+}
+
+impl Default for StatusFilter {
+    fn default() -> Self {
+        Self {
+            added: true,
+            removed: true,
+            changed: true,
+            unchanged: true,
+        }
+    }
+}
+
+impl StatusFilter {
+    fn shows_everything(self) -> bool {
+        self == Self::default()
+    }
+
+    /// Whether nodes with `status` are shown even if nothing within them is.
+    fn shows(self, status: DiffStatus) -> bool {
+        match status {
+            DiffStatus::Added => self.added,
+            DiffStatus::Removed => self.removed,
+            DiffStatus::Changed => self.changed,
+            DiffStatus::Unchanged | DiffStatus::Ignored => self.unchanged,
+            DiffStatus::ContainsChanges => self.shows_everything(),
+        }
+    }
+
+    /// Whether `entry` or anything within it is shown.
+    fn shows_any(self, entry: DiffEntry) -> bool {
+        self.shows(entry.node.status)
+            || (0..entry.node.children.len()).any(|i| self.shows_any(entry.child(i).1))
+    }
+}
+
+/// The central view of a diff, as a tree of nodes aligned between A and B.
+///
+/// The expanded part of the tree is flattened into rows, which are only rebuilt when the diff or the
+/// expansion changes, and only the rows scrolled into view get laid out.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffView {
+    pub layout: DiffLayout,
+    /// Widths of the path, A and B columns of [`DiffLayout::Table`], once they're resized by hand.
+    table_widths: Option<[f32; 3]>,
+    table_sort: Option<TableSort>,
+    pub filter: StatusFilter,
+    /// How string values are shown; they're never shown in full, so never wrapped.
+    pub strings: StringStyle,
+    /// Containers that were expanded or collapsed by hand, by path. Others follow `expansion`, which
+    /// by default expands exactly those that contain changes or search matches.
+    expanded: HashMap<String, bool>,
+    expansion: Expansion,
+    /// Containers whose runs of unchanged children are all shown, by path.
+    unfolded: HashSet<String>,
+    #[serde(skip)]
+    rows: Vec<Row>,
+    /// Revision of the diff that `rows` were built for, or `None` if they need rebuilding.
+    #[serde(skip)]
+    rows_revision: Option<u64>,
+    /// Whether `rows` show map entries sorted by key, see [`DisplayOptions::sort_keys`].
+    #[serde(skip)]
+    sort_keys: bool,
+    /// How `rows` are ordered, if they were sorted for [`DiffLayout::Table`].
+    #[serde(skip)]
+    rows_sort: Option<TableSort>,
+    /// Filter that `rows` and `markers` were built with.
+    #[serde(skip)]
+    rows_filter: StatusFilter,
+    #[serde(skip)]
+    search: Search,
+    /// Index of a row to scroll to once the rows are next shown.
+    #[serde(skip)]
+    scroll_to_row: Option<usize>,
+    /// Path to navigate to once the diff is next shown, see [`DiffView::go_to`].
+    #[serde(skip)]
+    go_to: Option<ValuePath>,
+    /// Node that was last navigated to, which is highlighted.
+    #[serde(skip)]
+    revealed: Option<Vec<usize>>,
+    /// Positions of a node to scroll to once the rows are rebuilt with it visible.
+    #[serde(skip)]
+    reveal: Option<Vec<usize>>,
+    /// Node under the pointer as of the last frame, whose path is shown in the breadcrumb bar.
+    #[serde(skip)]
+    hovered: Option<Vec<usize>>,
+    /// Where the differences are within the whole diff, see [`minimap_markers`].
+    #[serde(skip)]
+    markers: Vec<Marker>,
+    #[serde(skip)]
+    markers_revision: Option<u64>,
+    /// Maps with duplicate keys in A and in B, see [`find_duplicate_keys`].
+    #[serde(skip)]
+    duplicate_keys: [Vec<(ValuePath, usize)>; 2],
+    #[serde(skip)]
+    duplicate_keys_revision: Option<u64>,
+}
+
+/// A difference shown in the minimap.
+struct Marker {
+    /// How far into the diff the node is, from 0 at the root to 1 at the very end.
+    position: f32,
+    indexes: Vec<usize>,
+    status: DiffStatus,
+}
+
+#[derive(Default)]
+struct Search {
+    query: String,
+    /// Revision of the diff and query that `matches` were found for.
+    searched: Option<(u64, String)>,
+    matches: Vec<SearchMatch>,
+    matching: HashSet<Vec<usize>>,
+    /// Paths of the containers holding matches, which are expanded unless collapsed by hand.
+    ancestors: HashSet<String>,
+    current: Option<usize>,
+}
+
+/// What happened to the rows shown in a frame.
+#[derive(Default)]
+struct RowsResponse {
+    /// Path of a row whose expansion was toggled, and whether it's now expanded.
+    toggled: Option<(ValuePath, bool)>,
+    /// Path of a container whose unchanged children should all be shown.
+    unfolded: Option<String>,
+    hovered: Option<Vec<usize>>,
+    /// Positions of a node whose whole subtree should be expanded or collapsed.
+    subtree_expanded: Option<(Vec<usize>, bool)>,
+    action: Option<NodeAction>,
+}
+
+/// What happened in [`DiffView::show`] that the app needs to know about.
+#[derive(Default)]
+pub struct DiffViewResponse {
+    /// Path of a node that was navigated to, for the panels to follow.
+    pub navigated: Option<ValuePath>,
+    /// Path of a node that was expanded or collapsed by hand, and whether it's now expanded.
+    pub toggled: Option<(ValuePath, bool)>,
+    /// What was picked from a node's context menu for the app to do.
+    pub action: Option<NodeAction>,
+}
+
+/// Describes where the node at a path within the diff's root is encoded in B if the flag is set, or
+/// else in A, see [`crate::loader::LoadedFile::describe_span`].
+pub type DescribeSpan<'a> = &'a dyn Fn(&egui::Context, bool, &ValuePath) -> Option<String>;
+
+/// A visible node of the diff.
+struct Row {
+    path: ValuePath,
+    /// Positions of the children leading from the root to this node, see [`DiffView::entry_at`].
+    indexes: Vec<usize>,
+    is_expanded: bool,
+    /// For a row standing in for a run of unchanged siblings, starting with this node, how many of
+    /// them there are.
+    folded: usize,
+    /// How many entries of the map holding this node in A or B have its key, if more than one.
+    duplicate_key: Option<usize>,
+}
+
+impl Row {
+    fn depth(&self) -> usize {
+        self.indexes.len()
+    }
+}
+
+impl DiffView {
+    const INDENT: f32 = 16.0;
+    const MINIMAP_WIDTH: f32 = 10.0;
+    /// Unchanged siblings shown before and after a difference, like the context lines of a text diff.
+    const CONTEXT: usize = 2;
+    /// Runs of unchanged siblings are only folded away if that hides at least this many of them.
+    const MIN_FOLDED: usize = 3;
+    /// Bound on the number of search matches, as each of them gets its ancestors expanded.
+    const MAX_MATCHES: usize = 10_000;
+
+    fn is_expanded(&self, path: &ValuePath, depth: usize, node: &DiffNode) -> bool {
+        let path = path.to_string();
+        self.expanded.get(&path).copied().unwrap_or_else(|| {
+            let by_default = node.status == DiffStatus::ContainsChanges;
+            self.expansion.is_expanded(depth, by_default) || self.search.ancestors.contains(&path)
+        })
+    }
+
+    /// Expands or collapses everything, forgetting what was expanded or collapsed by hand.
+    pub fn set_expansion(&mut self, expansion: Expansion) {
+        self.expansion = expansion;
+        self.expanded.clear();
+        self.unfolded.clear();
+        self.rows_revision = None;
+    }
+
+    /// Expands or collapses the container at `path`, as if by hand.
+    pub fn set_expanded(&mut self, path: &ValuePath, expanded: bool) {
+        self.expanded.insert(path.to_string(), expanded);
+        self.rows_revision = None;
+    }
+
+    /// The containers that were expanded or collapsed by hand, by path, to save in a project.
+    pub fn expanded_by_hand(&self) -> BTreeMap<String, bool> {
+        self.expanded.clone().into_iter().collect()
+    }
+
+    /// Forgets what was expanded or collapsed by hand, then expands and collapses `expanded` as if
+    /// by hand instead.
+    pub fn restore_expanded(&mut self, expanded: BTreeMap<String, bool>) {
+        self.expanded = expanded.into_iter().collect();
+        self.rows_revision = None;
+    }
+
+    /// Expands the container at `path` and shows every child of it, to make one of them visible.
+    fn open(&mut self, path: &ValuePath) {
+        let path = path.to_string();
+        self.expanded.insert(path.clone(), true);
+        self.unfolded.insert(path);
+    }
+
+    fn build_rows(&mut self, root: DiffEntry) {
+        let mut rows = Vec::new();
+        let mut path = ValuePath::root();
+        let mut indexes = Vec::new();
+        self.push_rows(root, None, &mut path, &mut indexes, &mut rows);
+        if let Some(sort) = self.rows_sort {
+            let rank = |row: &Row| {
+                let entry = Self::entry_at(root, &row.indexes);
+                (entry, status_rank(entry.node.status))
+            };
+            rows.sort_by(|x, y| {
+                let ((x_entry, x_status), (y_entry, y_status)) = (rank(x), rank(y));
+                let by_value = |x: Option<&rmpv::Value>, y: Option<&rmpv::Value>| match (x, y) {
+                    (Some(x), Some(y)) => compare_keys(x, y),
+                    (x, y) => x.is_some().cmp(&y.is_some()),
+                };
+                let order = match sort.column {
+                    TableColumn::Path => x.path.to_string().cmp(&y.path.to_string()),
+                    TableColumn::A => by_value(x_entry.a, y_entry.a),
+                    TableColumn::B => by_value(x_entry.b, y_entry.b),
+                    TableColumn::Status => x_status.cmp(&y_status),
+                };
+                if sort.descending {
+                    order.reverse()
+                } else {
+                    order
+                }
+            });
+        }
+        self.rows = rows;
+    }
+
+    /// Adds the rows of `entry` and whatever of it is expanded, unless the filter hides all of them
+    /// (the root is always shown); returns whether it did.
+    fn push_rows(
+        &self,
+        entry: DiffEntry,
+        duplicate_key: Option<usize>,
+        path: &mut ValuePath,
+        indexes: &mut Vec<usize>,
+        rows: &mut Vec<Row>,
+    ) -> bool {
+        let is_expanded =
+            !entry.node.children.is_empty() && self.is_expanded(path, indexes.len(), entry.node);
+        let row_index = rows.len();
+        rows.push(Row {
+            path: path.clone(),
+            indexes: indexes.clone(),
+            is_expanded,
+            folded: 0,
+            duplicate_key,
+        });
+        let mut shown = indexes.is_empty() || self.filter.shows(entry.node.status);
+        if is_expanded {
+            let children = &entry.node.children;
+            let fold = self.filter.unchanged
+                && entry.node.status == DiffStatus::ContainsChanges
+                && !self.unfolded.contains(&path.to_string());
+            let mut order = (0..children.len()).collect::<Vec<_>>();
+            if self.sort_keys {
+                let segments = order.iter().map(|&i| entry.child(i).0).collect::<Vec<_>>();
+                order.sort_by(|&i, &j| match (&segments[i], &segments[j]) {
+                    (PathSegment::Key(a), PathSegment::Key(b)) => compare_keys(a, b),
+                    // the children of an array, which stay in order
+                    _ => i.cmp(&j),
+                });
+            }
+            let [a_key_counts, b_key_counts] = [entry.a, entry.b].map(|value| match value {
+                Some(rmpv::Value::Map(entries)) => key_counts(entries),
+                _ => Vec::new(),
+            });
+            let mut push_child = |i: usize, folded: usize, rows: &mut Vec<Row>| {
+                let (segment, child) = entry.child(i);
+                let count_of = |counts: &[usize], index: Option<usize>| {
+                    index.and_then(|index| counts.get(index).copied())
+                };
+                let duplicate_key = count_of(&a_key_counts, child.node.a_index)
+                    .max(count_of(&b_key_counts, child.node.b_index))
+                    .filter(|&count| count > 1);
+                path.push(segment);
+                indexes.push(i);
+                if folded > 0 {
+                    rows.push(Row {
+                        path: path.clone(),
+                        indexes: indexes.clone(),
+                        is_expanded: false,
+                        folded,
+                        duplicate_key,
+                    });
+                } else {
+                    shown |= self.push_rows(child, duplicate_key, path, indexes, rows);
+                }
+                indexes.pop();
+                path.pop();
+            };
+            let mut i = 0;
+            while i < children.len() {
+                let run_end = (i..children.len())
+                    .find(|&j| children[order[j]].status != DiffStatus::Unchanged)
+                    .unwrap_or(children.len());
+                // keep some context next to the differences before and after the run
+                let fold_start = if i == 0 { 0 } else { i + Self::CONTEXT };
+                let fold_end = if run_end == children.len() {
+                    run_end
+                } else {
+                    run_end.saturating_sub(Self::CONTEXT)
+                };
+                if fold && fold_end >= fold_start + Self::MIN_FOLDED {
+                    for &j in &order[i..fold_start] {
+                        push_child(j, 0, rows);
+                    }
+                    push_child(order[fold_start], fold_end - fold_start, rows);
+                    for &j in &order[fold_end..run_end] {
+                        push_child(j, 0, rows);
+                    }
+                    i = run_end;
+                } else {
+                    push_child(order[i], 0, rows);
+                    i += 1;
+                }
+            }
+        } else if !shown {
+            shown = self.filter.shows_any(entry);
+        }
+        if !shown {
+            rows.truncate(row_index);
+        }
+        shown
+    }
+
+    /// Expands everything holding the node at `path` and scrolls to it, once the diff is next shown.
+    pub fn go_to(&mut self, path: ValuePath) {
+        self.go_to = Some(path);
+    }
+
+    /// The path of the node last navigated to, if it's still shown.
+    pub fn revealed_path(&self) -> Option<&ValuePath> {
+        let revealed = self.revealed.as_ref()?;
+        let row = self.rows.iter().find(|row| &row.indexes == revealed)?;
+        Some(&row.path)
+    }
+
+    /// Marks the node at `path` as revealed, expanding its ancestors, if the diff has one.
+    fn reveal_path(&mut self, root: DiffEntry, path: &ValuePath) {
+        let mut entry = root;
+        let mut indexes = Vec::new();
+        let mut ancestor = ValuePath::root();
+        for segment in path.segments() {
+            let found = (0..entry.node.children.len())
+                .map(|i| (i, entry.child(i)))
+                .find(|(_, (child_segment, _))| child_segment == segment);
+            let Some((i, (_, child))) = found else {
+                return;
+            };
+            self.open(&ancestor);
+            ancestor.push(segment.clone());
+            indexes.push(i);
+            entry = child;
+        }
+        self.reveal = Some(indexes);
+        self.rows_revision = None;
+    }
+
+    /// The rows as they're shown, as indented text to paste into a terminal or a ticket, with a
+    /// column marking what's added, removed or changed before them if `markers` is set.
+    pub fn visible_text(&self, root: DiffEntry, display: &DisplayOptions, markers: bool) -> String {
+        let mut text = String::new();
+        for row in &self.rows {
+            let entry = Self::entry_at(root, &row.indexes);
+            if markers {
+                text.push_str(match entry.node.status {
+                    _ if row.folded > 0 => "  ",
+                    DiffStatus::Added => "+ ",
+                    DiffStatus::Removed => "- ",
+                    DiffStatus::Changed => "~ ",
+                    _ => "  ",
+                });
+            }
+            text.push_str(&"  ".repeat(row.depth()));
+            let value = entry.b.or(entry.a);
+            if row.folded > 0 {
+                let parent = Self::entry_at(root, &row.indexes[..row.depth() - 1]);
+                let noun = match parent.a.or(parent.b) {
+                    Some(rmpv::Value::Map(_)) => "entries",
+                    _ => "items",
+                };
+                text.push_str(&format!("… {} identical {noun} …", row.folded));
+            } else if let Some(children) = value.and_then(describe_children) {
+                text.push_str(&format!("{} {children}", row_label(row)));
+            } else if entry.node.status == DiffStatus::Changed {
+                text.push_str(&format!(
+                    "{}: {} → {}",
+                    row_label(row),
+                    summarize(entry.a, display),
+                    summarize(entry.b, display)
+                ));
+            } else {
+                text.push_str(&format!(
+                    "{}: {}",
+                    row_label(row),
+                    summarize(value, display)
+                ));
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn entry_at<'a>(root: DiffEntry<'a>, indexes: &[usize]) -> DiffEntry<'a> {
+        indexes
+            .iter()
+            .fold(root, |entry, &index| entry.child(index).1)
+    }
+
+    /// Shows `diff`, whose `revision` changes whenever it's recomputed.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        root: DiffEntry,
+        revision: u64,
+        display: &DisplayOptions,
+        describe_span: DescribeSpan,
+        notes: &Annotations,
+    ) -> DiffViewResponse {
+        let mut go_to_difference = ui.input_mut(|i| {
+            if i.consume_key(egui::Modifiers::SHIFT, egui::Key::F7) {
+                Some(false)
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::F7) {
+                Some(true)
+            } else {
+                None
+            }
+        });
+        // whether to copy the rows as text once they're built, and with markers if so
+        let mut copy_text = None;
+        ui.horizontal(|ui| {
+            match root.node.difference_count {
+                0 => ui.label("No differences"),
+                1 => ui.label("1 difference"),
+                n => ui.label(format!("{n} differences")),
+            };
+            let has_differences = root.node.difference_count > 0;
+            if ui
+                .add_enabled(has_differences, egui::Button::new("▲").small())
+                .on_hover_text("Previous difference (Shift+F7)")
+                .clicked()
+            {
+                go_to_difference = Some(false);
+            }
+            if ui
+                .add_enabled(has_differences, egui::Button::new("▼").small())
+                .on_hover_text("Next difference (F7)")
+                .clicked()
+            {
+                go_to_difference = Some(true);
+            }
+            ui.menu_button("📋", |ui| {
+                if ui.button("Copy visible tree as text").clicked() {
+                    ui.close_menu();
+                    copy_text = Some(false);
+                }
+                if ui.button("Copy visible tree with diff markers").clicked() {
+                    ui.close_menu();
+                    copy_text = Some(true);
+                }
+            })
+            .response
+            .on_hover_text("Copy what's expanded as indented text");
+            ui.separator();
+            ui.selectable_value(&mut self.layout, DiffLayout::SideBySide, "Side by side");
+            ui.selectable_value(&mut self.layout, DiffLayout::Unified, "Unified");
+            ui.selectable_value(&mut self.layout, DiffLayout::Table, "Table");
+            ui.separator();
+            ui.toggle_value(&mut self.filter.added, "+ Added");
+            ui.toggle_value(&mut self.filter.removed, "− Removed");
+            ui.toggle_value(&mut self.filter.changed, "~ Changed");
+            ui.toggle_value(&mut self.filter.unchanged, "Unchanged");
+            ui.separator();
+            self.strings.show_toggles(ui, false);
+            ui.separator();
+            self.show_search(ui, root, revision);
+        });
+        let display = &DisplayOptions {
+            strings: self.strings,
+            ..display.clone()
+        };
+        if let Some(forwards) = go_to_difference {
+            self.go_to_difference(root, forwards);
+        }
+        self.show_breadcrumbs(ui, root);
+        if self.duplicate_keys_revision != Some(revision) {
+            self.duplicate_keys = [root.a, root.b].map(|value| {
+                let mut found = Vec::new();
+                if let Some(value) = value {
+                    find_duplicate_keys(value, &mut ValuePath::root(), &mut found);
+                }
+                found
+            });
+            self.duplicate_keys_revision = Some(revision);
+        }
+        self.show_duplicate_keys(ui);
+
+        if let Some(path) = self.go_to.take() {
+            self.reveal_path(root, &path);
+        }
+        // the filter can also be changed from the menu bar
+        if self.rows_filter != self.filter {
+            self.rows_filter = self.filter;
+            self.rows_revision = None;
+            self.markers_revision = None;
+        }
+        if self.sort_keys != display.sort_keys {
+            self.sort_keys = display.sort_keys;
+            self.rows_revision = None;
+        }
+        let sort = self.table_sort.filter(|_| self.layout == DiffLayout::Table);
+        if self.rows_sort != sort {
+            self.rows_sort = sort;
+            self.rows_revision = None;
+        }
+        if self.rows_revision != Some(revision) {
+            self.build_rows(root);
+            self.rows_revision = Some(revision);
+        }
+        if let Some(markers) = copy_text {
+            ui.ctx()
+                .copy_text(self.visible_text(root, display, markers));
+        }
+        let mut navigated = None;
+        if let Some(indexes) = self.reveal.take() {
+            self.scroll_to_row = self.rows.iter().position(|row| row.indexes == indexes);
+            navigated = self.scroll_to_row.map(|row| self.rows[row].path.clone());
+            self.revealed = Some(indexes);
+        }
+
+        if self.markers_revision != Some(revision) {
+            self.markers = minimap_markers(root, self.filter);
+            self.markers_revision = Some(revision);
+        }
+        let available = ui.available_rect_before_wrap();
+        let minimap_rect = available.with_min_x(available.right() - Self::MINIMAP_WIDTH);
+        let content_rect = available.with_max_x(minimap_rect.left() - ui.spacing().item_spacing.x);
+        let response = ui
+            .allocate_new_ui(
+                egui::UiBuilder::new().max_rect(content_rect),
+                |ui| match self.layout {
+                    DiffLayout::SideBySide => {
+                        self.show_side_by_side(ui, root, display, describe_span, notes)
+                    }
+                    DiffLayout::Unified => {
+                        self.show_unified(ui, root, display, describe_span, notes)
+                    }
+                    DiffLayout::Table => self.show_table(ui, root, display, describe_span, notes),
+                },
+            )
+            .inner;
+        self.show_minimap(ui, minimap_rect, root, &display.palette);
+        self.scroll_to_row = None;
+        self.hovered = response.hovered;
+        if let Some((path, expanded)) = &response.toggled {
+            self.set_expanded(path, *expanded);
+        }
+        if let Some(path) = response.unfolded {
+            self.unfolded.insert(path);
+            self.rows_revision = None;
+        }
+        if let Some((indexes, expanded)) = response.subtree_expanded {
+            let mut path = ValuePath::root();
+            let mut entry = root;
+            for &index in &indexes {
+                let (segment, child) = entry.child(index);
+                path.push(segment);
+                entry = child;
+            }
+            self.set_subtree_expanded(entry, &mut path, expanded);
+            self.rows_revision = None;
+        }
+        DiffViewResponse {
+            navigated,
+            toggled: response.toggled,
+            action: response.action,
+        }
+    }
+
+    /// Expands or collapses the node at `path` along with everything within it.
+    fn set_subtree_expanded(&mut self, entry: DiffEntry, path: &mut ValuePath, expanded: bool) {
+        if entry.node.children.is_empty() {
+            return;
+        }
+        if expanded {
+            self.open(path);
+        } else {
+            self.expanded.insert(path.to_string(), false);
+        }
+        for i in 0..entry.node.children.len() {
+            let (segment, child) = entry.child(i);
+            path.push(segment);
+            self.set_subtree_expanded(child, path, expanded);
+            path.pop();
+        }
+    }
+
+    fn show_node_menu(
+        &self,
+        ui: &mut egui::Ui,
+        root: DiffEntry,
+        row: &Row,
+        entry: DiffEntry,
+        response: &mut RowsResponse,
+    ) {
+        copy_path_buttons(ui, &row.path);
+        match (entry.a, entry.b) {
+            (Some(a), Some(b)) if a == b => copy_value_buttons(ui, a, ""),
+            (a, b) => {
+                for (value, name) in [(a, " of A"), (b, " of B")] {
+                    if let Some(value) = value {
+                        copy_value_buttons(ui, value, name);
+                    }
+                }
+            }
+        }
+        // equal values can still be encoded differently, so there's always a choice of bytes
+        for (value, name, in_b) in [(entry.a, " of A", false), (entry.b, " of B", true)] {
+            if value.is_some() {
+                if let Some(format) = copy_bytes_buttons(ui, name) {
+                    response.action = Some(NodeAction::CopyRawBytes {
+                        in_b,
+                        path: row.path.clone(),
+                        format,
+                    });
+                }
+            }
+        }
+        let to_save = match (entry.a, entry.b) {
+            (Some(a), Some(b)) if a == b => vec![(a, "")],
+            (a, b) => [(a, " of A"), (b, " of B")]
+                .into_iter()
+                .filter_map(|(value, name)| Some((value?, name)))
+                .collect(),
+        };
+        for (value, name) in to_save {
+            if let Some(save) = save_value_button(ui, value, &row.path, name) {
+                response.action = Some(save);
+            }
+        }
+        if decode_nested_button(ui, entry.a)
+            || decode_nested_button(ui, entry.b.filter(|_| entry.a != entry.b))
+        {
+            response.action = Some(NodeAction::DecodeNested);
+        }
+        if entry.node.status.is_difference() {
+            ui.separator();
+            for to_b in [true, false] {
+                let edit = take_across(root, &row.indexes, to_b);
+                let button = egui::Button::new(take_across_name(entry, to_b));
+                if ui
+                    .add_enabled(edit.is_some(), button)
+                    .on_disabled_hover_text("What holds it isn't there")
+                    .clicked()
+                {
+                    response.action = edit.map(|edit| NodeAction::TakeAcross { to_b, edit });
+                    ui.close_menu();
+                }
+            }
+        }
+        if !entry.node.children.is_empty() {
+            ui.separator();
+            if ui.button("Expand subtree").clicked() {
+                response.subtree_expanded = Some((row.indexes.clone(), true));
+                ui.close_menu();
+            }
+            if ui.button("Collapse subtree").clicked() {
+                response.subtree_expanded = Some((row.indexes.clone(), false));
+                ui.close_menu();
+            }
+        }
+        if row.depth() > 0 {
+            ui.separator();
+            if ui.button("Bookmark").clicked() {
+                response.action = Some(NodeAction::Bookmark(row.path.clone()));
+                ui.close_menu();
+            }
+            if ui.button("Note…").clicked() {
+                response.action = Some(NodeAction::Annotate(row.path.clone()));
+                ui.close_menu();
+            }
+            if ui.button("Ignore this path").clicked() {
+                response.action = Some(NodeAction::Ignore(row.path.clone()));
+                ui.close_menu();
+            }
+            if ui.button("Compare only this subtree").clicked() {
+                response.action = Some(NodeAction::CompareSubtree(row.path.clone()));
+                ui.close_menu();
+            }
+        }
+    }
+
+    /// Warns about the maps with duplicate keys if there are any, with a menu of them to go to.
+    fn show_duplicate_keys(&mut self, ui: &mut egui::Ui) {
+        let [a, b] = &self.duplicate_keys;
+        if a.is_empty() && b.is_empty() {
+            return;
+        }
+        let mut clicked = None;
+        ui.horizontal(|ui| {
+            let text = egui::RichText::new(format!(
+                "⚠ Duplicate keys in {} maps of A and {} of B",
+                a.len(),
+                b.len()
+            ))
+            .color(ui.visuals().warn_fg_color);
+            ui.menu_button(text, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for (side, found) in [("A", a), ("B", b)] {
+                            for (path, count) in found {
+                                if ui
+                                    .button(format!("{path} in {side}: {count} entries"))
+                                    .clicked()
+                                {
+                                    clicked = Some(path.clone());
+                                    ui.close_menu();
+                                }
+                            }
+                        }
+                    });
+            })
+            .response
+            .on_hover_text(
+                "Msgpack allows a map to have the same key more than once, but it's almost \
+                 always a bug in whatever encoded it",
+            );
+        });
+        if let Some(path) = clicked {
+            self.go_to(path);
+        }
+    }
+
+    /// Shows the path of the node under the pointer, or else the one last navigated to, with a link
+    /// to each of its ancestors.
+    fn show_breadcrumbs(&mut self, ui: &mut egui::Ui, root: DiffEntry) {
+        let target = self
+            .hovered
+            .as_deref()
+            .or(self.revealed.as_deref())
+            .unwrap_or_default();
+        // the diff may have changed since, so stop at the first position that's gone
+        let mut crumbs = vec![("(root)".to_owned(), Vec::new())];
+        let mut entry = root;
+        for (depth, &index) in target.iter().enumerate() {
+            if index >= entry.node.children.len() {
+                break;
+            }
+            let (segment, child) = entry.child(index);
+            crumbs.push((segment.to_string(), target[..=depth].to_vec()));
+            entry = child;
+        }
+
+        let mut clicked = None;
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 4.0;
+            for (i, (label, indexes)) in crumbs.iter().enumerate() {
+                if i > 0 {
+                    ui.weak("›");
+                }
+                if ui.link(label).clicked() {
+                    clicked = Some(indexes.clone());
+                }
+            }
+        });
+        if let Some(indexes) = clicked {
+            self.reveal_indexes(root, indexes);
+        }
+    }
+
+    /// Expands everything holding the node at `indexes` and scrolls to it.
+    fn reveal_indexes(&mut self, root: DiffEntry, indexes: Vec<usize>) {
+        let mut path = ValuePath::root();
+        let mut entry = root;
+        for &index in &indexes {
+            self.open(&path);
+            let (segment, child) = entry.child(index);
+            path.push(segment);
+            entry = child;
+        }
+        self.reveal = Some(indexes);
+        self.rows_revision = None;
+    }
+
+    /// Goes to the first difference after the node last navigated to, or the last one before it if
+    /// not `forwards`, wrapping around.
+    fn go_to_difference(&mut self, root: DiffEntry, forwards: bool) {
+        let mut differences = Vec::new();
+        collect_differences(root, self.filter, &mut Vec::new(), &mut differences);
+        // positions compare in document order, as a node's come before those of its descendants
+        let current = self.revealed.as_ref();
+        let next = if forwards {
+            differences
+                .iter()
+                .find(|indexes| current.is_none_or(|current| *indexes > current))
+                .or(differences.first())
+        } else {
+            differences
+                .iter()
+                .rev()
+                .find(|indexes| current.is_none_or(|current| *indexes < current))
+                .or(differences.last())
+        };
+        if let Some(indexes) = next {
+            self.reveal_indexes(root, indexes.clone());
+        }
+    }
+
+    /// Shows a strip marking where the differences are within the whole diff, expanded or not, which
+    /// can be clicked to go to the nearest one.
+    fn show_minimap(
+        &mut self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        root: DiffEntry,
+        palette: &Palette,
+    ) {
+        let response = ui.interact(rect, ui.id().with("minimap"), egui::Sense::click());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+        let y_of = |marker: &Marker| rect.top() + marker.position * rect.height();
+        let mut last_y = None;
+        for marker in &self.markers {
+            // many differences can share a pixel, and only one of them needs painting
+            let y = y_of(marker).round();
+            if last_y == Some(y) {
+                continue;
+            }
+            last_y = Some(y);
+            let color = status_color(marker.status, palette, ui.visuals())
+                .unwrap_or(ui.visuals().text_color());
+            let marker_rect = egui::Rect::from_min_max(
+                egui::pos2(rect.left(), y - 1.0),
+                egui::pos2(rect.right(), y + 1.0),
+            );
+            painter.rect_filled(marker_rect, 0.0, color);
+        }
+
+        let Some(pointer) = response
+            .interact_pointer_pos()
+            .filter(|_| response.clicked())
+        else {
+            return;
+        };
+        let nearest = self.markers.iter().min_by(|x, y| {
+            let distance = |marker| (y_of(marker) - pointer.y).abs();
+            distance(x).total_cmp(&distance(y))
+        });
+        if let Some(marker) = nearest {
+            self.reveal_indexes(root, marker.indexes.clone());
+            ui.ctx().request_repaint();
+        }
+    }
+
+    /// Shows the search box and the position among its matches, finding them again if the query or
+    /// the diff changed.
+    fn show_search(&mut self, ui: &mut egui::Ui, root: DiffEntry, revision: u64) {
+        let focus = ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::F));
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.search.query)
+                .hint_text("Search (Ctrl+F)")
+                .desired_width(160.0),
+        );
+        if focus {
+            response.request_focus();
+        }
+        let mut step = None;
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            step = Some(if ui.input(|i| i.modifiers.shift) {
+                -1
+            } else {
+                1
+            });
+            response.request_focus();
+        }
+
+        let searched = Some((revision, self.search.query.clone()));
+        if self.search.searched != searched {
+            let query_changed =
+                self.search.searched.as_ref().map(|(_, query)| query) != Some(&self.search.query);
+            self.find_matches(root);
+            self.search.searched = searched;
+            if query_changed {
+                self.search.current = None;
+                if !self.search.matches.is_empty() {
+                    step = Some(1);
+                }
+            }
+        }
+
+        if self.search.query.is_empty() {
+            return;
+        }
+        let count = self.search.matches.len();
+        let more = if count >= Self::MAX_MATCHES { "+" } else { "" };
+        match self.search.current {
+            _ if count == 0 => ui.label("No matches"),
+            Some(current) => ui.label(format!("{} of {count}{more}", current + 1)),
+            None => ui.label(format!("{count}{more} matches")),
+        };
+        if ui
+            .add_enabled(count > 0, egui::Button::new("▲").small())
+            .on_hover_text("Previous match (Shift+Enter)")
+            .clicked()
+        {
+            step = Some(-1);
+        }
+        if ui
+            .add_enabled(count > 0, egui::Button::new("▼").small())
+            .on_hover_text("Next match (Enter)")
+            .clicked()
+        {
+            step = Some(1);
+        }
+        if let Some(step) = step {
+            self.go_to_match(step);
+        }
+    }
+
+    fn find_matches(&mut self, root: DiffEntry) {
+        let matches = search(root, &self.search.query, Self::MAX_MATCHES);
+        let mut ancestors = HashSet::new();
+        for found in &matches {
+            let mut path = found.path.clone();
+            while path.pop().is_some() {
+                ancestors.insert(path.to_string());
+            }
+        }
+        self.search.matching = matches.iter().map(|found| found.indexes.clone()).collect();
+        self.search.current = self
+            .search
+            .current
+            .filter(|&current| current < matches.len());
+        self.search.matches = matches;
+        self.search.ancestors = ancestors;
+        self.rows_revision = None;
+    }
+
+    /// Moves `step` matches forwards or backwards, wrapping around, and scrolls to that match after
+    /// expanding everything holding it.
+    fn go_to_match(&mut self, step: isize) {
+        let count = self.search.matches.len();
+        if count == 0 {
+            return;
+        }
+        let current = match self.search.current {
+            Some(current) => (current as isize + step).rem_euclid(count as isize) as usize,
+            None if step < 0 => count - 1,
+            None => 0,
+        };
+        self.search.current = Some(current);
+        let found = self.search.matches[current].clone();
+        let mut path = found.path;
+        while path.pop().is_some() {
+            self.open(&path);
+        }
+        self.reveal = Some(found.indexes);
+        self.rows_revision = None;
+    }
+
+    /// Shows the rows in view, calling `show_row` for each of them, which returns whether the row's
+    /// expansion was toggled.
+    fn show_rows(
+        &self,
+        ui: &mut egui::Ui,
+        root: DiffEntry,
+        display: &DisplayOptions,
+        notes: &Annotations,
+        mut show_row: impl FnMut(&mut egui::Ui, &Row, DiffEntry) -> bool,
+    ) -> RowsResponse {
+        let mut response = RowsResponse::default();
+        let row_height = ui.spacing().interact_size.y;
+        let mut scroll_area = egui::ScrollArea::both().auto_shrink(false);
+        if let Some(index) = self.scroll_to_row {
+            // a few rows above the target for context
+            let spaced_height = row_height + ui.spacing().item_spacing.y;
+            let offset = index.saturating_sub(3) as f32 * spaced_height;
+            scroll_area = scroll_area.vertical_scroll_offset(offset);
+        }
+        let current_match = self
+            .search
+            .current
+            .and_then(|current| self.search.matches.get(current));
+        accessibility::tree(ui, ui.id().with("diff tree"), "Differences", |ui| {
+            scroll_area.show_rows(ui, row_height, self.rows.len(), |ui, range| {
+                let first = range.start;
+                for (i, row) in self.rows[range].iter().enumerate() {
+                    let rect = egui::Rect::from_min_size(
+                        ui.cursor().min,
+                        egui::vec2(ui.available_width(), row_height),
+                    );
+                    if self.layout == DiffLayout::Table && (first + i) % 2 == 1 {
+                        ui.painter()
+                            .rect_filled(rect, 0.0, ui.visuals().faint_bg_color);
+                    }
+                    if ui.rect_contains_pointer(rect) {
+                        response.hovered = Some(row.indexes.clone());
+                    }
+                    let is_revealed = self.revealed.as_ref() == Some(&row.indexes);
+                    if is_revealed || self.search.matching.contains(&row.indexes) {
+                        let is_current = is_revealed
+                            || current_match.is_some_and(|found| found.indexes == row.indexes);
+                        let fill = ui.visuals().selection.bg_fill;
+                        let fill = if is_current {
+                            fill
+                        } else {
+                            fill.gamma_multiply(0.35)
+                        };
+                        ui.painter().rect_filled(rect, 2.0, fill);
+                    }
+                    if row.folded > 0 {
+                        ui.horizontal(|ui| {
+                            ui.set_min_height(row_height);
+                            let indent = row.depth() as f32 * Self::INDENT;
+                            ui.add_space(indent + ui.spacing().interact_size.y);
+                            let parent = Self::entry_at(root, &row.indexes[..row.depth() - 1]);
+                            let noun = match parent.a.or(parent.b) {
+                                Some(rmpv::Value::Map(_)) => "entries",
+                                _ => "items",
+                            };
+                            let text = format!("… {} identical {noun} …", row.folded);
+                            if ui.link(text).on_hover_text("Show them").clicked() {
+                                let mut parent_path = row.path.clone();
+                                parent_path.pop();
+                                response.unfolded = Some(parent_path.to_string());
+                            }
+                        });
+                        continue;
+                    }
+                    let entry = Self::entry_at(root, &row.indexes);
+                    // interacted with before the row's contents so that those get clicks first
+                    let row_response =
+                        ui.interact(rect, ui.id().with(&row.indexes), egui::Sense::click());
+                    let is_expandable = !entry.node.children.is_empty();
+                    if row_response.has_focus() {
+                        // left and right expand and collapse the focused row, while up and down move
+                        // the focus between the rows as they do between any widgets
+                        let filter = egui::EventFilter {
+                            horizontal_arrows: true,
+                            ..Default::default()
+                        };
+                        ui.memory_mut(|memory| {
+                            memory.set_focus_lock_filter(row_response.id, filter)
+                        });
+                        let key = match row.is_expanded {
+                            true => egui::Key::ArrowLeft,
+                            false => egui::Key::ArrowRight,
+                        };
+                        if is_expandable && ui.input(|i| i.key_pressed(key)) {
+                            response.toggled = Some((row.path.clone(), !row.is_expanded));
+                        }
+                    }
+                    let name = || {
+                        let segment = row.path.segments().last();
+                        let status = entry.node.status;
+                        let name = accessibility::describe_diff_entry(
+                            segment, entry.a, entry.b, status, display,
+                        );
+                        accessibility::with_note(name, notes.get(&row.path))
+                    };
+                    let expanded = is_expandable.then_some(row.is_expanded);
+                    let toggled = accessibility::tree_item(
+                        ui,
+                        &row_response,
+                        row.depth(),
+                        expanded,
+                        is_revealed,
+                        name,
+                        |ui| show_row(ui, row, entry),
+                    );
+                    if toggled {
+                        response.toggled = Some((row.path.clone(), !row.is_expanded));
+                    }
+                    if ui.rect_contains_pointer(rect)
+                        && (image_preview::is_image(entry.a) || image_preview::is_image(entry.b))
+                    {
+                        let id = ui.id().with(("image preview", &row.indexes));
+                        egui::show_tooltip_at_pointer(ui.ctx(), ui.layer_id(), id, |ui| {
+                            image_preview::show_side_by_side(ui, entry.a, entry.b);
+                        });
+                    }
+                    let is_hovered = ui.rect_contains_pointer(rect);
+                    if entry.node.status.is_difference() && (is_hovered || is_revealed) {
+                        if let Some(action) = show_take_across(ui, rect, root, row, entry) {
+                            response.action = Some(action);
+                        }
+                    }
+                    row_response.context_menu(|ui| {
+                        self.show_node_menu(ui, root, row, entry, &mut response)
+                    });
+                }
+            });
+            // the rows are focused themselves, rather than the tree
+            ((), None)
+        });
+        response
+    }
+
+    fn show_side_by_side(
+        &self,
+        ui: &mut egui::Ui,
+        root: DiffEntry,
+        display: &DisplayOptions,
+        describe_span: DescribeSpan,
+        notes: &Annotations,
+    ) -> RowsResponse {
+        let row_height = ui.spacing().interact_size.y;
+        let status_width = 16.0;
+        let column_widths = |ui: &egui::Ui| {
+            let width = ui.available_width();
+            let path_width = (width * 0.3).max(120.0);
+            let value_width = ((width - path_width - status_width) / 2.0).max(60.0);
+            [path_width, status_width, value_width, value_width]
+        };
+        let [path_width, _, value_width, _] = column_widths(ui);
+        ui.horizontal(|ui| {
+            cell(ui, path_width, row_height, |ui| ui.strong("Path"));
+            cell(ui, status_width, row_height, |ui| ui.label(""));
+            cell(ui, value_width, row_height, |ui| ui.strong("A"));
+            cell(ui, value_width, row_height, |ui| ui.strong("B"));
+        });
+        ui.separator();
+        self.show_rows(ui, root, display, notes, |ui, row, entry| {
+            let [path_width, _, value_width, _] = column_widths(ui);
+            let color = status_color(entry.node.status, &display.palette, ui.visuals());
+            let colored = |text: egui::RichText| match color {
+                Some(color) => text.color(color),
+                None => text,
+            };
+            let mut toggled = false;
+            ui.horizontal(|ui| {
+                cell(ui, path_width, row_height, |ui| {
+                    toggled = show_toggle(ui, row, entry);
+                    show_duplicate_key(ui, row.duplicate_key);
+                    show_note_marker(ui, notes.get(&row.path));
+                    ui.add(egui::Label::new(colored(row_label(row).into())).truncate())
+                        .on_hover_ui(|ui| {
+                            ui.label(row.path.to_string());
+                            show_spans(ui, row, entry, describe_span);
+                        });
+                });
+                cell(ui, status_width, row_height, |ui| {
+                    ui.label(colored(status_symbol(entry.node.status).into()))
+                        .on_hover_text(status_name(entry.node.status));
+                });
+                for value in [entry.a, entry.b] {
+                    cell(ui, value_width, row_height, |ui| {
+                        if let Some(rmpv::Value::Binary(bytes)) = value {
+                            image_preview::show_thumbnail(ui, bytes);
+                        }
+                        ui.add(egui::Label::new(colored(summary_text(value, display))).truncate());
+                    });
+                }
+            });
+            toggled
+        })
+    }
+
+    /// Like [`Self::show_side_by_side`], but with whole paths rather than indentation, striped rows
+    /// and a header for resizing the columns and sorting by them.
+    fn show_table(
+        &mut self,
+        ui: &mut egui::Ui,
+        root: DiffEntry,
+        display: &DisplayOptions,
+        describe_span: DescribeSpan,
+        notes: &Annotations,
+    ) -> RowsResponse {
+        const MIN_WIDTH: f32 = 40.0;
+        let row_height = ui.spacing().interact_size.y;
+        let width = ui.available_width();
+        let mut widths = self.table_widths.unwrap_or({
+            let path_width = (width * 0.35).max(120.0);
+            let value_width = ((width - path_width) * 0.4).max(60.0);
+            [path_width, value_width, value_width]
+        });
+        let headers = [
+            (TableColumn::Path, "Path"),
+            (TableColumn::A, "A"),
+            (TableColumn::B, "B"),
+            (TableColumn::Status, "Status"),
+        ];
+        let mut resized = false;
+        ui.horizontal(|ui| {
+            for (i, (column, name)) in headers.into_iter().enumerate() {
+                let width = widths.get(i).copied().unwrap_or(MIN_WIDTH * 2.0);
+                cell(ui, width, row_height, |ui| {
+                    let arrow = match self.table_sort {
+                        Some(sort) if sort.column == column && sort.descending => " ⏷",
+                        Some(sort) if sort.column == column => " ⏶",
+                        _ => "",
+                    };
+                    let header = ui
+                        .add(
+                            egui::Button::new(
+                                egui::RichText::new(format!("{name}{arrow}")).strong(),
+                            )
+                            .frame(false),
+                        )
+                        .on_hover_text("Sort by this, then the other way, then as in the tree");
+                    if header.clicked() {
+                        self.table_sort = match self.table_sort {
+                            Some(sort) if sort.column == column && sort.descending => None,
+                            Some(sort) if sort.column == column => Some(TableSort {
+                                column,
+                                descending: true,
+                            }),
+                            _ => Some(TableSort {
+                                column,
+                                descending: false,
+                            }),
+                        };
+                    }
+                });
+                // a handle for resizing every column but the last, which takes what's left
+                if let Some(width) = widths.get_mut(i) {
+                    let (rect, response) =
+                        ui.allocate_exact_size(egui::vec2(6.0, row_height), egui::Sense::drag());
+                    let stroke = match response.hovered() || response.dragged() {
+                        true => ui.visuals().widgets.hovered.fg_stroke,
+                        false => ui.visuals().widgets.noninteractive.bg_stroke,
+                    };
+                    ui.painter().vline(rect.center().x, rect.y_range(), stroke);
+                    if response.dragged() {
+                        *width = (*width + response.drag_delta().x).max(MIN_WIDTH);
+                        resized = true;
+                    }
+                    response.on_hover_cursor(egui::CursorIcon::ResizeHorizontal);
+                }
+            }
+        });
+        if resized {
+            self.table_widths = Some(widths);
+        }
+        ui.separator();
+        // the handles between the columns of the header take up space too
+        let gap = 6.0 + ui.spacing().item_spacing.x;
+        let [path_width, a_width, b_width] = widths.map(|w| w + gap);
+        self.show_rows(ui, root, display, notes, |ui, row, entry| {
+            let color = status_color(entry.node.status, &display.palette, ui.visuals());
+            let colored = |text: egui::RichText| match color {
+                Some(color) => text.color(color),
+                None => text,
+            };
+            let mut toggled = false;
+            ui.horizontal(|ui| {
+                cell(ui, path_width, row_height, |ui| {
+                    toggled = show_toggle_unindented(ui, row, entry);
+                    show_duplicate_key(ui, row.duplicate_key);
+                    show_note_marker(ui, notes.get(&row.path));
+                    ui.add(egui::Label::new(colored(row.path.to_string().into())).truncate())
+                        .on_hover_ui(|ui| {
+                            ui.label(row.path.to_string());
+                            show_spans(ui, row, entry, describe_span);
+                        });
+                });
+                for (value, width) in [(entry.a, a_width), (entry.b, b_width)] {
+                    cell(ui, width, row_height, |ui| {
+                        if let Some(rmpv::Value::Binary(bytes)) = value {
+                            image_preview::show_thumbnail(ui, bytes);
+                        }
+                        ui.add(egui::Label::new(colored(summary_text(value, display))).truncate());
+                    });
+                }
+                let status = entry.node.status;
+                let symbol = status_symbol(status);
+                ui.label(colored(
+                    format!("{symbol} {}", status_name(status)).trim().into(),
+                ));
+            });
+            toggled
+        })
+    }
+
+    /// Like [`Self::show_side_by_side`], but as a single tree with the values of both sides inline.
+    fn show_unified(
+        &self,
+        ui: &mut egui::Ui,
+        root: DiffEntry,
+        display: &DisplayOptions,
+        describe_span: DescribeSpan,
+        notes: &Annotations,
+    ) -> RowsResponse {
+        self.show_rows(ui, root, display, notes, |ui, row, entry| {
+            let mut toggled = false;
+            ui.horizontal(|ui| {
+                ui.spacing_mut().item_spacing.x = 4.0;
+                toggled = show_toggle(ui, row, entry);
+                let status = entry.node.status;
+                let color = status_color(status, &display.palette, ui.visuals());
+                let mut label = egui::RichText::new(row_label(row));
+                match status {
+                    DiffStatus::Removed => label = label.strikethrough(),
+                    DiffStatus::Added => {
+                        label = label.background_color(ui.visuals().selection.bg_fill)
+                    }
+                    _ => {}
+                }
+                if let Some(color) = color {
+                    label = label.color(color);
+                }
+                ui.label(label).on_hover_ui(|ui| {
+                    ui.label(format!("{} ({})", row.path, status_name(status)));
+                    show_spans(ui, row, entry, describe_span);
+                });
+                show_duplicate_key(ui, row.duplicate_key);
+                show_note_marker(ui, notes.get(&row.path));
+                ui.label(":");
+                let removed_color = display.palette.removed;
+                match (status, entry.a, entry.b) {
+                    (DiffStatus::Changed, a, b) => {
+                        ui.label(summary_text(a, display).color(removed_color));
+                        ui.label("→");
+                        ui.label(summary_text(b, display).color(display.palette.added));
+                    }
+                    (DiffStatus::Removed, a, _) => {
+                        ui.label(
+                            summary_text(a, display)
+                                .strikethrough()
+                                .color(removed_color),
+                        );
+                    }
+                    (_, a, b) => {
+                        ui.label(
+                            summary_text(b.or(a), display)
+                                .color(color.unwrap_or(ui.visuals().text_color())),
+                        );
+                    }
+                }
+            });
+            toggled
+        })
+    }
+}
+
+/// Collects the positions of the nodes that differ in a way `filter` shows, but not of those within
+/// them, in document order.
+fn collect_differences(
+    entry: DiffEntry,
+    filter: StatusFilter,
+    indexes: &mut Vec<usize>,
+    differences: &mut Vec<Vec<usize>>,
+) {
+    if entry.node.status.is_difference() {
+        if filter.shows(entry.node.status) {
+            differences.push(indexes.clone());
+        }
+        return;
+    }
+    if entry.node.difference_count == 0 {
+        return;
+    }
+    for i in 0..entry.node.children.len() {
+        indexes.push(i);
+        collect_differences(entry.child(i).1, filter, indexes, differences);
+        indexes.pop();
+    }
+}
+
+/// Finds the differences like [`collect_differences`], placing each by how many nodes come before
+/// it in the whole diff.
+fn minimap_markers(root: DiffEntry, filter: StatusFilter) -> Vec<Marker> {
+    fn visit(
+        entry: DiffEntry,
+        filter: StatusFilter,
+        within_difference: bool,
+        indexes: &mut Vec<usize>,
+        count: &mut usize,
+        markers: &mut Vec<Marker>,
+    ) {
+        let is_difference = !within_difference && entry.node.status.is_difference();
+        if is_difference && filter.shows(entry.node.status) {
+            markers.push(Marker {
+                position: *count as f32,
+                indexes: indexes.clone(),
+                status: entry.node.status,
+            });
+        }
+        *count += 1;
+        for i in 0..entry.node.children.len() {
+            indexes.push(i);
+            let within_difference = within_difference || is_difference;
+            visit(
+                entry.child(i).1,
+                filter,
+                within_difference,
+                indexes,
+                count,
+                markers,
+            );
+            indexes.pop();
+        }
+    }
+
+    let mut markers = Vec::new();
+    let mut count = 0;
+    visit(
+        root,
+        filter,
+        false,
+        &mut Vec::new(),
+        &mut count,
+        &mut markers,
+    );
+    for marker in &mut markers {
+        marker.position /= count as f32;
+    }
+    markers
+}
+
+/// Lays out a fixed-size table cell.
+fn cell<R>(
+    ui: &mut egui::Ui,
+    width: f32,
+    height: f32,
+    add_contents: impl FnOnce(&mut egui::Ui) -> R,
+) {
+    ui.allocate_ui_with_layout(
+        egui::vec2(width, height),
+        egui::Layout::left_to_right(egui::Align::Center),
+        |ui| {
+            ui.set_min_size(egui::vec2(width, height));
+            ui.set_max_width(width);
+            add_contents(ui);
+        },
+    );
+}
+
+/// Indents a row according to its depth and shows its expand/collapse button, returning whether
+/// that was clicked.
+fn show_toggle(ui: &mut egui::Ui, row: &Row, entry: DiffEntry) -> bool {
+    ui.add_space(row.depth() as f32 * DiffView::INDENT);
+    show_toggle_unindented(ui, row, entry)
+}
+
+/// Like [`show_toggle`], for a row that isn't indented by its depth.
+fn show_toggle_unindented(ui: &mut egui::Ui, row: &Row, entry: DiffEntry) -> bool {
+    if entry.node.children.is_empty() {
+        ui.add_space(ui.spacing().interact_size.y);
+        return false;
+    }
+    accessibility::show_expander(ui, row.is_expanded)
+}
+
+/// Shows where the node of `row` is encoded on each side it's on.
+fn show_spans(ui: &mut egui::Ui, row: &Row, entry: DiffEntry, describe_span: DescribeSpan) {
+    for (name, value, in_b) in [("A", entry.a, false), ("B", entry.b, true)] {
+        if value.is_none() {
+            continue;
+        }
+        if let Some(span) = describe_span(ui.ctx(), in_b, &row.path) {
+            ui.weak(format!("{name}: {span}"));
+        }
+    }
+}
+
+/// The last segment of the row's path, or `(root)`.
+/// Shows the buttons for taking the value of a difference from one side to the other at the end of
+/// its row, over whatever is there, returning the action of the one clicked.
+fn show_take_across(
+    ui: &mut egui::Ui,
+    rect: egui::Rect,
+    root: DiffEntry,
+    row: &Row,
+    entry: DiffEntry,
+) -> Option<NodeAction> {
+    let area = egui::Rect::from_min_max(egui::pos2(rect.right() - 60.0, rect.top()), rect.max);
+    let layout = egui::Layout::right_to_left(egui::Align::Center);
+    let mut ui = ui.new_child(egui::UiBuilder::new().max_rect(area).layout(layout));
+    let mut picked = None;
+    // right to left, so B's button comes first
+    for (to_b, icon) in [(false, "⬅"), (true, "➡")] {
+        let Some(edit) = take_across(root, &row.indexes, to_b) else {
+            continue;
+        };
+        if accessibility::icon_button(&mut ui, icon, take_across_name(entry, to_b)).clicked() {
+            picked = Some(NodeAction::TakeAcross { to_b, edit });
+        }
+    }
+    picked
+}
+
+/// What taking the value of a difference across into B if `to_b` is set, or else into A, does.
+fn take_across_name(entry: DiffEntry, to_b: bool) -> &'static str {
+    match (to_b, entry.a.is_some(), entry.b.is_some()) {
+        (true, true, _) => "Copy A's value into B",
+        (true, false, _) => "Remove it from B",
+        (false, _, true) => "Copy B's value into A",
+        (false, _, false) => "Remove it from A",
+    }
+}
+
+/// The edit making the node at `indexes` the same in B as it's in A if `to_b` is set, or the other
+/// way around, with positions within the value compared on that side; `None` if what would hold
+/// it isn't there.
+fn take_across<'a>(root: DiffEntry<'a>, indexes: &[usize], to_b: bool) -> Option<Edit> {
+    let index_of = |node: &DiffNode| if to_b { node.b_index } else { node.a_index };
+    let value_of = |entry: DiffEntry<'a>| if to_b { entry.a } else { entry.b };
+    let Some((&last, parent_indexes)) = indexes.split_last() else {
+        return Some(Edit::Set {
+            indexes: Vec::new(),
+            value: value_of(root)?.clone(),
+        });
+    };
+    let mut target = Vec::with_capacity(indexes.len());
+    let mut parent = root.node;
+    for &index in parent_indexes {
+        parent = &parent.children[index];
+        target.push(index_of(parent)?);
+    }
+    let (segment, entry) = DiffView::entry_at(root, parent_indexes).child(last);
+    let edit = match (value_of(entry), index_of(entry.node)) {
+        (Some(value), Some(index)) => Edit::Set {
+            indexes: [target, vec![index]].concat(),
+            value: value.clone(),
+        },
+        (None, Some(index)) => Edit::Remove {
+            indexes: [target, vec![index]].concat(),
+        },
+        (Some(value), None) => {
+            // right after the nearest sibling before it that's on that side too
+            let position = parent.children[..last]
+                .iter()
+                .rev()
+                .find_map(index_of)
+                .map_or(0, |index| index + 1);
+            let key = match segment {
+                PathSegment::Key(key) => Some(key),
+                PathSegment::Index(_) => None,
+            };
+            Edit::Insert {
+                indexes: [target, vec![position]].concat(),
+                key,
+                value: value.clone(),
+            }
+        }
+        (None, None) => return None,
+    };
+    Some(edit)
+}
+
+fn row_label(row: &Row) -> String {
+    match row.path.segments().last() {
+        Some(segment) => segment.to_string(),
+        None => row.path.to_string(),
+    }
+}
+
+pub fn status_color(
+    status: DiffStatus,
+    palette: &Palette,
+    visuals: &egui::Visuals,
+) -> Option<egui::Color32> {
+    match status {
+        DiffStatus::Unchanged | DiffStatus::ContainsChanges => None,
+        DiffStatus::Added => Some(palette.added),
+        DiffStatus::Removed => Some(palette.removed),
+        DiffStatus::Changed => Some(palette.changed),
+        DiffStatus::Ignored => Some(visuals.weak_text_color()),
+    }
+}
+
+fn status_symbol(status: DiffStatus) -> &'static str {
+    match status {
+        DiffStatus::Unchanged => "",
+        DiffStatus::Added => "+",
+        DiffStatus::Removed => "−",
+        DiffStatus::Changed => "~",
+        DiffStatus::ContainsChanges => "•",
+        DiffStatus::Ignored => "🚫",
+    }
+}
+
+/// Where nodes with `status` come when sorting by status, differences first.
+fn status_rank(status: DiffStatus) -> u8 {
+    match status {
+        DiffStatus::Removed => 0,
+        DiffStatus::Added => 1,
+        DiffStatus::Changed => 2,
+        DiffStatus::ContainsChanges => 3,
+        DiffStatus::Unchanged => 4,
+        DiffStatus::Ignored => 5,
+    }
+}
+
+pub fn status_name(status: DiffStatus) -> &'static str {
+    match status {
+        DiffStatus::Unchanged => "unchanged",
+        DiffStatus::Added => "added",
+        DiffStatus::Removed => "removed",
+        DiffStatus::Changed => "changed",
+        DiffStatus::ContainsChanges => "contains changes",
+        DiffStatus::Ignored => "ignored",
+    }
+}
+
+/// [`summarize`] as text to show, in the font picked for strings if it's one.
+fn summary_text(value: Option<&rmpv::Value>, display: &DisplayOptions) -> egui::RichText {
+    let text = summarize(value, display);
+    match value {
+        Some(rmpv::Value::String(_)) => display.strings.text(text),
+        _ => egui::RichText::new(text),
+    }
+}
+
+/// One-line summary of a value for a table cell; containers are summarized by their size.
+pub fn summarize(value: Option<&rmpv::Value>, display: &DisplayOptions) -> String {
+    match value {
+        None => String::new(),
+        Some(rmpv::Value::Array(items)) => format!("[{} items]", items.len()),
+        Some(rmpv::Value::Map(entries)) => format!("{{{} entries}}", entries.len()),
+        Some(rmpv::Value::Binary(bytes)) => {
+            let format = display.binary_format;
+            if format.shows_as_text(bytes) {
+                format!("{} (shown as text)", format.summarize(bytes))
+            } else {
+                format.summarize(bytes)
+            }
+        }
+        Some(value) => display.abbreviate(value),
+    }
+}
@@ -0,0 +1,452 @@
+use base64::Engine as _;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::ext::ExtRegistry;
+use crate::ext_types;
+use crate::hex_dump;
+use crate::value::{abbreviate, binary_as_text};
+
+/// How values are rendered in the trees and the diff.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayOptions {
+    pub integer_base: IntegerBase,
+    /// Whether decimal integers have their digits grouped in thousands, e.g. `16,777,216`.
+    pub thousands_separators: bool,
+    pub float_notation: FloatNotation,
+    /// Digits after the decimal point for [`FloatNotation::Fixed`] and
+    /// [`FloatNotation::Scientific`].
+    pub float_precision: usize,
+    /// How binary values are shown unless picked for them in particular.
+    pub binary_format: BinaryFormat,
+    /// Whether to show map entries in the order of their keys (see [`crate::value::compare_keys`])
+    /// rather than the order they're encoded in.
+    pub sort_keys: bool,
+    /// Strings longer than this many characters, or over several lines, are cut short unless shown
+    /// in full by hand.
+    pub max_string_chars: usize,
+    /// Colors that differences are highlighted in.
+    pub palette: Palette,
+    /// Set by each view from its own [`StringStyle`] rather than saved with the rest.
+    #[serde(skip)]
+    pub strings: StringStyle,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            integer_base: IntegerBase::default(),
+            thousands_separators: false,
+            float_notation: FloatNotation::default(),
+            float_precision: 6,
+            binary_format: BinaryFormat::default(),
+            sort_keys: false,
+            max_string_chars: 200,
+            palette: Palette::default(),
+            strings: StringStyle::default(),
+        }
+    }
+}
+
+/// Colors that the diff highlights what was added, removed and changed in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    pub added: egui::Color32,
+    pub removed: egui::Color32,
+    pub changed: egui::Color32,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::PRESETS[0].1
+    }
+}
+
+impl Palette {
+    /// Palettes to start from, with what they're called. The color-blind safe ones are from the
+    /// Okabe-Ito and IBM design palettes, whose colors stay distinct with the common kinds of color
+    /// blindness.
+    pub const PRESETS: [(&'static str, Palette); 3] = [
+        (
+            "Green, red and orange",
+            Palette {
+                added: egui::Color32::from_rgb(0x4c, 0xaf, 0x50),
+                removed: egui::Color32::from_rgb(0xff, 0x00, 0x00),
+                changed: egui::Color32::from_rgb(0xff, 0x8f, 0x00),
+            },
+        ),
+        (
+            "Blue, orange and purple (color-blind safe)",
+            Palette {
+                added: egui::Color32::from_rgb(0x56, 0xb4, 0xe9),
+                removed: egui::Color32::from_rgb(0xe6, 0x9f, 0x00),
+                changed: egui::Color32::from_rgb(0xcc, 0x79, 0xa7),
+            },
+        ),
+        (
+            "Blue, magenta and yellow (color-blind safe)",
+            Palette {
+                added: egui::Color32::from_rgb(0x64, 0x8f, 0xff),
+                removed: egui::Color32::from_rgb(0xdc, 0x26, 0x7f),
+                changed: egui::Color32::from_rgb(0xff, 0xb0, 0x00),
+            },
+        ),
+    ];
+}
+
+/// How a view shows string values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StringStyle {
+    /// For strings holding code, JSON or log lines, where alignment matters.
+    pub monospace: bool,
+    /// Whether the lines of a string shown in full wrap after [`DisplayOptions::max_string_chars`]
+    /// characters, rather than being scrolled sideways.
+    pub wrap: bool,
+}
+
+impl Default for StringStyle {
+    fn default() -> Self {
+        Self {
+            monospace: false,
+            wrap: true,
+        }
+    }
+}
+
+impl StringStyle {
+    /// Shows toggles for the options, leaving out wrapping for a view that doesn't show strings in
+    /// full.
+    pub fn show_toggles(&mut self, ui: &mut egui::Ui, can_wrap: bool) {
+        ui.toggle_value(&mut self.monospace, "Monospace")
+            .on_hover_text("Show strings in a monospace font");
+        if can_wrap {
+            ui.toggle_value(&mut self.wrap, "Wrap").on_hover_text(
+                "Wrap the long lines of strings shown in full, rather than scrolling sideways",
+            );
+        }
+    }
+
+    /// `text` from a string value, in a monospace font if that's picked.
+    pub fn text(self, text: impl Into<String>) -> egui::RichText {
+        let text = egui::RichText::new(text);
+        match self.monospace {
+            true => text.monospace(),
+            false => text,
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IntegerBase {
+    #[default]
+    Decimal,
+    Hex,
+    /// With the digits in groups of four, as that's how bitmasks are read.
+    Binary,
+}
+
+impl IntegerBase {
+    pub const ALL: [IntegerBase; 3] = [IntegerBase::Decimal, IntegerBase::Hex, IntegerBase::Binary];
+}
+
+impl std::fmt::Display for IntegerBase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            IntegerBase::Decimal => "decimal",
+            IntegerBase::Hex => "hex",
+            IntegerBase::Binary => "binary",
+        })
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FloatNotation {
+    /// As few digits as it takes to tell the float apart from every other, as Rust prints them.
+    #[default]
+    Shortest,
+    Fixed,
+    Scientific,
+    /// The bits of the float in hex, for telling apart floats that print the same, like `0.0` and
+    /// `-0.0` or different NaNs.
+    Bits,
+}
+
+impl FloatNotation {
+    pub const ALL: [FloatNotation; 4] = [
+        FloatNotation::Shortest,
+        FloatNotation::Fixed,
+        FloatNotation::Scientific,
+        FloatNotation::Bits,
+    ];
+
+    /// Whether floats are shown with [`DisplayOptions::float_precision`] digits.
+    pub fn has_precision(self) -> bool {
+        matches!(self, FloatNotation::Fixed | FloatNotation::Scientific)
+    }
+}
+
+impl std::fmt::Display for FloatNotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FloatNotation::Shortest => "shortest",
+            FloatNotation::Fixed => "fixed",
+            FloatNotation::Scientific => "scientific",
+            FloatNotation::Bits => "raw bits",
+        })
+    }
+}
+
+impl DisplayOptions {
+    /// Whether `text` is cut short when it's shown on one row.
+    pub fn is_cut_short(&self, text: &str) -> bool {
+        text.contains('\n') || text.chars().nth(self.max_string_chars).is_some()
+    }
+
+    /// `text` as shown on one row: its first line, if need be cut short with a `…`.
+    pub fn cut_short(&self, text: &str) -> String {
+        if !self.is_cut_short(text) {
+            return text.to_owned();
+        }
+        let first_line = text.lines().next().unwrap_or_default();
+        let mut cut = match first_line.char_indices().nth(self.max_string_chars) {
+            Some((end, _)) => first_line[..end].to_owned(),
+            None => first_line.to_owned(),
+        };
+        cut.push('…');
+        cut
+    }
+
+    /// Where each line of `text` starts when it's shown in full, lines longer than
+    /// [`Self::max_string_chars`] being wrapped if [`StringStyle::wrap`] is picked.
+    pub fn string_line_starts(&self, text: &str) -> Vec<usize> {
+        let max = self.wrap_chars();
+        let mut starts = vec![0];
+        let mut chars = 0;
+        for (i, c) in text.char_indices() {
+            if c == '\n' {
+                starts.push(i + 1);
+                chars = 0;
+            } else if chars == max {
+                starts.push(i);
+                chars = 1;
+            } else {
+                chars += 1;
+            }
+        }
+        starts
+    }
+
+    /// The line of `text` starting at `start` (see [`Self::string_line_starts`]).
+    pub fn string_line<'a>(&self, text: &'a str, start: usize) -> &'a str {
+        let max = self.wrap_chars();
+        let line = &text[start..];
+        let end = line
+            .char_indices()
+            .enumerate()
+            .find(|&(n, (_, c))| c == '\n' || n == max)
+            .map_or(line.len(), |(_, (i, _))| i);
+        &line[..end]
+    }
+
+    fn wrap_chars(&self) -> usize {
+        match self.strings.wrap {
+            true => self.max_string_chars.max(1),
+            false => usize::MAX,
+        }
+    }
+
+    /// Formats an integer in the chosen base, e.g. `-0x1f` or `0b10_1100`.
+    pub fn format_integer(&self, integer: rmpv::Integer) -> String {
+        // every msgpack integer fits in one or the other
+        let (negative, magnitude) = match (integer.as_u64(), integer.as_i64()) {
+            (Some(u), _) => (false, u),
+            (_, Some(i)) => (true, i.unsigned_abs()),
+            _ => return integer.to_string(),
+        };
+        let sign = if negative { "-" } else { "" };
+        match self.integer_base {
+            IntegerBase::Decimal if self.thousands_separators => {
+                format!("{sign}{}", group_digits(&magnitude.to_string(), 3, ','))
+            }
+            IntegerBase::Decimal => integer.to_string(),
+            IntegerBase::Hex => format!("{sign}0x{magnitude:x}"),
+            IntegerBase::Binary => {
+                format!(
+                    "{sign}0b{}",
+                    group_digits(&format!("{magnitude:b}"), 4, '_')
+                )
+            }
+        }
+    }
+
+    pub fn format_f32(&self, float: f32) -> String {
+        self.format_float(float, || format!("0x{:08x}", float.to_bits()))
+    }
+
+    pub fn format_f64(&self, float: f64) -> String {
+        self.format_float(float, || format!("0x{:016x}", float.to_bits()))
+    }
+
+    /// Formats `float` in the chosen notation, `bits` giving its bits in hex, which depend on its
+    /// width.
+    fn format_float<F>(&self, float: F, bits: impl FnOnce() -> String) -> String
+    where
+        F: std::fmt::Display + std::fmt::LowerExp,
+    {
+        let precision = self.float_precision;
+        match self.float_notation {
+            FloatNotation::Shortest => float.to_string(),
+            FloatNotation::Fixed => format!("{float:.precision$}"),
+            FloatNotation::Scientific => format!("{float:.precision$e}"),
+            FloatNotation::Bits => bits(),
+        }
+    }
+
+    /// Formats a scalar for a one-line summary, like [`crate::value::abbreviate`] but numbers
+    /// formatted as chosen.
+    pub fn abbreviate(&self, value: &rmpv::Value) -> String {
+        match value {
+            rmpv::Value::Integer(integer) => self.format_integer(*integer),
+            rmpv::Value::F32(float) => self.format_f32(*float),
+            rmpv::Value::F64(float) => self.format_f64(*float),
+            value => abbreviate(value, &ext_types::registry()),
+        }
+    }
+}
+
+/// `digits` split into groups of `size` from the right by `separator`, e.g. `1,000,000`.
+fn group_digits(digits: &str, size: usize, separator: char) -> String {
+    let mut grouped = String::with_capacity(digits.len() * (size + 1) / size);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(size) {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// How the contents of binary values are shown.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryFormat {
+    /// As text if they hold text (see [`binary_as_text`]), or else as hex, and expanded into a hex
+    /// dump either way.
+    #[default]
+    Auto,
+    Hex,
+    Base64,
+    /// As ASCII, with everything else escaped like in a byte string, e.g. `GIF89a\x01\x00`.
+    Ascii,
+}
+
+impl BinaryFormat {
+    pub const ALL: [BinaryFormat; 4] = [
+        BinaryFormat::Auto,
+        BinaryFormat::Hex,
+        BinaryFormat::Base64,
+        BinaryFormat::Ascii,
+    ];
+
+    /// How many bytes are shown at the start of a summary; a multiple of 3 so that base64 doesn't
+    /// end in padding.
+    const SUMMARY_BYTES: usize = 24;
+
+    fn bytes_per_line(self) -> usize {
+        match self {
+            BinaryFormat::Auto | BinaryFormat::Hex => hex_dump::BYTES_PER_LINE,
+            // 64 characters, and a multiple of 3 so that the lines join up into the whole encoding
+            BinaryFormat::Base64 => 48,
+            BinaryFormat::Ascii => 32,
+        }
+    }
+
+    /// What `bytes` hold as text, if they're shown as text.
+    fn text(self, bytes: &[u8]) -> Option<String> {
+        match self {
+            BinaryFormat::Auto => binary_as_text(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn shows_as_text(self, bytes: &[u8]) -> bool {
+        self.text(bytes).is_some()
+    }
+
+    /// What `bytes` are shown as, e.g. `text` for text shown automatically.
+    pub fn name_for(self, bytes: &[u8]) -> &'static str {
+        match self {
+            BinaryFormat::Auto if self.shows_as_text(bytes) => "text",
+            BinaryFormat::Auto | BinaryFormat::Hex => "hex",
+            BinaryFormat::Base64 => "base64",
+            BinaryFormat::Ascii => "ASCII",
+        }
+    }
+
+    /// Formats `bytes` for a one-line summary: the start of them along with their size, or just the
+    /// start of their text.
+    pub fn summarize(self, bytes: &[u8]) -> String {
+        if let Some(text) = self.text(bytes) {
+            return abbreviate(&text.into(), ExtRegistry::builtin());
+        }
+        let start = &bytes[..bytes.len().min(Self::SUMMARY_BYTES)];
+        let ellipsis = if start.len() < bytes.len() { "…" } else { "" };
+        let formatted = match self {
+            BinaryFormat::Auto | BinaryFormat::Hex => hex_dump::encode_spaced(start),
+            BinaryFormat::Base64 => base64::engine::general_purpose::STANDARD.encode(start),
+            BinaryFormat::Ascii => start.escape_ascii().to_string(),
+        };
+        format!("{} bytes: {formatted}{ellipsis}", bytes.len())
+    }
+
+    /// Number of lines it takes to show `len` bytes when expanded.
+    pub fn line_count(self, len: usize) -> usize {
+        len.div_ceil(self.bytes_per_line())
+    }
+
+    /// Formats the `line`th line of `bytes` expanded.
+    pub fn format_line(self, bytes: &[u8], line: usize) -> String {
+        let per_line = self.bytes_per_line();
+        let start = (line * per_line).min(bytes.len());
+        let chunk = &bytes[start..(start + per_line).min(bytes.len())];
+        match self {
+            BinaryFormat::Auto | BinaryFormat::Hex => hex_dump::format_line(bytes, line),
+            BinaryFormat::Base64 => base64::engine::general_purpose::STANDARD.encode(chunk),
+            BinaryFormat::Ascii => chunk.escape_ascii().to_string(),
+        }
+    }
+
+    /// Shows the `line`th line of `bytes` expanded, without wrapping it.
+    pub fn show_line(self, ui: &mut egui::Ui, bytes: &[u8], line: usize) {
+        let text = egui::RichText::new(self.format_line(bytes, line)).monospace();
+        ui.add(egui::Label::new(text).extend());
+    }
+
+    /// Shows all of `bytes` expanded, laying out only the lines scrolled into view so that big blobs
+    /// stay responsive.
+    pub fn show_all(self, ui: &mut egui::Ui, bytes: &[u8]) {
+        ui.weak(format!("{} bytes", bytes.len()));
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::both()
+            .id_salt("binary contents")
+            .auto_shrink(false)
+            .show_rows(ui, row_height, self.line_count(bytes.len()), |ui, range| {
+                for line in range {
+                    self.show_line(ui, bytes, line);
+                }
+            });
+    }
+}
+
+impl std::fmt::Display for BinaryFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BinaryFormat::Auto => "text or hex",
+            BinaryFormat::Hex => "hex",
+            BinaryFormat::Base64 => "base64",
+            BinaryFormat::Ascii => "ASCII",
+        })
+    }
+}
@@ -0,0 +1,27 @@
+use std::sync::{Arc, LazyLock, RwLock};
+
+use crate::ext::{DecodedExt, ExtRegistry};
+
+/// The registry in use, which is shared as values are rendered and compared all over the place.
+static REGISTRY: LazyLock<RwLock<Arc<ExtRegistry>>> = LazyLock::new(Default::default);
+
+/// Starts using `registry` to decode extension values.
+pub fn set_registry(registry: ExtRegistry) {
+    *REGISTRY.write().unwrap_or_else(|err| err.into_inner()) = Arc::new(registry);
+}
+
+/// The registry in use.
+pub fn registry() -> Arc<ExtRegistry> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone()
+}
+
+/// Decodes `value` with the registry in use, see [`ExtRegistry::decode`].
+pub fn decode(value: &rmpv::Value) -> Option<DecodedExt> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .decode(value)
+}
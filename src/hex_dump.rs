@@ -0,0 +1,40 @@
+use std::fmt::Write;
+
+pub const BYTES_PER_LINE: usize = 16;
+
+/// Formats the `line`th line of a dump of `bytes`, as its offset, its bytes in hex and then as ASCII
+/// with a `.` for anything unprintable, e.g. `00000010  68 65 6c 6c 6f 00  |hello.|`.
+pub fn format_line(bytes: &[u8], line: usize) -> String {
+    let start = (line * BYTES_PER_LINE).min(bytes.len());
+    let chunk = &bytes[start..(start + BYTES_PER_LINE).min(bytes.len())];
+    let mut text = format!("{start:08x} ");
+    for i in 0..BYTES_PER_LINE {
+        // an extra space halfway along to make counting easier
+        if i == BYTES_PER_LINE / 2 {
+            text.push(' ');
+        }
+        match chunk.get(i) {
+            Some(byte) => write!(text, " {byte:02x}").expect("writing to a String can't fail"),
+            None => text.push_str("   "),
+        }
+    }
+    text.push_str("  |");
+    text.extend(chunk.iter().map(|&byte| match byte {
+        0x20..=0x7e => byte as char,
+        _ => '.',
+    }));
+    text.push('|');
+    text
+}
+
+/// Formats bytes as hex with a space between each, e.g. `68 65 6c`.
+pub fn encode_spaced(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len() * 3);
+    for byte in bytes {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        write!(text, "{byte:02x}").expect("writing to a String can't fail");
+    }
+    text
+}
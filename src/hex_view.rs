@@ -0,0 +1,149 @@
+use std::ops::Range;
+
+use eframe::egui;
+use egui::text::LayoutJob;
+
+use crate::hex_dump::BYTES_PER_LINE;
+use crate::loader::LoadedFile;
+use crate::spans::SpanTree;
+
+/// A hex dump of a whole file that highlights the bytes of the value selected in its tree, and
+/// selects the value holding whatever byte is clicked.
+#[derive(Default)]
+pub struct HexView {
+    /// Where the values within the file are, along with when the file was loaded.
+    spans: Option<(jiff::Timestamp, Option<SpanTree>)>,
+    /// Selection that was last scrolled to, so that it's scrolled to only when it changes.
+    scrolled_to: Option<Vec<usize>>,
+}
+
+impl HexView {
+    /// Shows `file`, with the bytes of the value at `selected` highlighted, returning the positions
+    /// of the value holding the byte clicked, if one was.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        file: &LoadedFile,
+        selected: Option<&[usize]>,
+    ) -> Option<Vec<usize>> {
+        if self.spans.as_ref().map(|(time, _)| *time) != Some(file.load_time) {
+            self.spans = Some((file.load_time, file.span_tree()));
+        }
+        let spans = self.spans.as_ref().and_then(|(_, spans)| spans.as_ref());
+        let highlighted = selected
+            .and_then(|indexes| Some(spans?.get(indexes)?.span.clone()))
+            .unwrap_or_default();
+        let font = egui::TextStyle::Monospace.resolve(ui.style());
+        let char_width = ui.fonts(|fonts| fonts.glyph_width(&font, '0'));
+        let row_height = ui.fonts(|fonts| fonts.row_height(&font));
+        let spaced_height = row_height + ui.spacing().item_spacing.y;
+        let lines = file.data.len().div_ceil(BYTES_PER_LINE);
+
+        let mut scroll_area = egui::ScrollArea::both()
+            .id_salt("hex view")
+            .auto_shrink(false);
+        if selected != self.scrolled_to.as_deref() {
+            if !highlighted.is_empty() {
+                // a couple of lines above it for context
+                let line = (highlighted.start / BYTES_PER_LINE).saturating_sub(2);
+                scroll_area = scroll_area.vertical_scroll_offset(line as f32 * spaced_height);
+            }
+            self.scrolled_to = selected.map(<[usize]>::to_vec);
+        }
+        let mut clicked = None;
+        scroll_area.show_rows(ui, row_height, lines, |ui, range| {
+            for line in range {
+                let start = line * BYTES_PER_LINE;
+                let bytes = &file.data[start..(start + BYTES_PER_LINE).min(file.data.len())];
+                let job = line_layout(ui, &font, start, bytes, &highlighted);
+                let label = egui::Label::new(job).extend().sense(egui::Sense::click());
+                let response = ui.add(label);
+                if !response.clicked() {
+                    continue;
+                }
+                let Some(pointer) = response.interact_pointer_pos() else {
+                    continue;
+                };
+                let column = ((pointer.x - response.rect.left()) / char_width) as usize;
+                if let Some(i) = byte_at_column(column).filter(|&i| i < bytes.len()) {
+                    // the file as a whole isn't a row of the tree to select
+                    clicked = spans
+                        .and_then(|spans| spans.enclosing(start + i))
+                        .filter(|indexes| !indexes.is_empty());
+                }
+            }
+        });
+        if clicked.is_some() {
+            // it's in view already
+            self.scrolled_to.clone_from(&clicked);
+        }
+        clicked
+    }
+}
+
+/// Lays out a line of the dump like [`crate::hex_dump::format_line`] does, with the bytes within
+/// `highlighted` highlighted.
+fn line_layout(
+    ui: &egui::Ui,
+    font: &egui::FontId,
+    start: usize,
+    bytes: &[u8],
+    highlighted: &Range<usize>,
+) -> LayoutJob {
+    let normal = egui::TextFormat::simple(font.clone(), ui.visuals().text_color());
+    let weak = egui::TextFormat::simple(font.clone(), ui.visuals().weak_text_color());
+    let selected = egui::TextFormat {
+        background: ui.visuals().selection.bg_fill,
+        color: ui.visuals().strong_text_color(),
+        ..normal.clone()
+    };
+    let format_of = |i: usize| match highlighted.contains(&(start + i)) {
+        true => selected.clone(),
+        false => normal.clone(),
+    };
+    let mut job = LayoutJob::default();
+    job.append(&format!("{start:08x} "), 0.0, weak.clone());
+    for i in 0..BYTES_PER_LINE {
+        if i == BYTES_PER_LINE / 2 {
+            job.append(" ", 0.0, normal.clone());
+        }
+        match bytes.get(i) {
+            // the space before a byte is highlighted if it's between two highlighted ones
+            Some(byte) => {
+                let space = match i > 0 && highlighted.contains(&(start + i - 1)) {
+                    true => format_of(i),
+                    false => normal.clone(),
+                };
+                job.append(" ", 0.0, space);
+                job.append(&format!("{byte:02x}"), 0.0, format_of(i));
+            }
+            None => job.append("   ", 0.0, normal.clone()),
+        }
+    }
+    job.append("  |", 0.0, weak.clone());
+    for (i, &byte) in bytes.iter().enumerate() {
+        let c = match byte {
+            0x20..=0x7e => byte as char,
+            _ => '.',
+        };
+        job.append(&c.to_string(), 0.0, format_of(i));
+    }
+    job.append("|", 0.0, weak);
+    job
+}
+
+/// Which byte of a line the character at `column` of it stands for, if any.
+fn byte_at_column(column: usize) -> Option<usize> {
+    // laid out as the offset and a space, then a space and two digits for each byte with an extra
+    // space halfway along, then two spaces and a bar before the bytes as ASCII
+    const HEX_START: usize = 9;
+    const ASCII_START: usize = HEX_START + 3 * BYTES_PER_LINE + 1 + 3;
+    if column >= ASCII_START {
+        return Some(column - ASCII_START).filter(|&i| i < BYTES_PER_LINE);
+    }
+    let mut hex = column.checked_sub(HEX_START)?;
+    if hex >= 3 * BYTES_PER_LINE / 2 {
+        hex = hex.checked_sub(1)?;
+    }
+    Some(hex / 3).filter(|&i| i < BYTES_PER_LINE)
+}
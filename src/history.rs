@@ -0,0 +1,119 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::loader::{LoadedFile, Source};
+
+/// One loaded version of a file.
+#[derive(Clone)]
+pub struct Version {
+    /// Counts up from 1 with every distinct version loaded from the same source.
+    pub number: usize,
+    pub file: LoadedFile,
+}
+
+/// Previously loaded versions of each file, so that any two of them can be compared.
+pub struct History {
+    sources: HashMap<Source, SourceHistory>,
+    /// Total size of all versions kept, see [`History::max_bytes`].
+    bytes: usize,
+    max_versions: usize,
+    /// Bound on the size of the data of all versions kept, though the latest version of each file
+    /// is kept regardless. The decoded values take up memory too, but roughly in proportion to that.
+    max_bytes: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            sources: HashMap::new(),
+            bytes: 0,
+            max_versions: Self::DEFAULT_MAX_VERSIONS,
+            max_bytes: Self::DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
+#[derive(Default)]
+struct SourceHistory {
+    versions: VecDeque<Version>,
+    loaded_count: usize,
+}
+
+impl History {
+    pub const DEFAULT_MAX_VERSIONS: usize = 20;
+    pub const DEFAULT_MAX_BYTES: usize = 256 * 1024 * 1024;
+
+    /// Changes how many versions of each file are kept and how much data they take up in all,
+    /// dropping the oldest versions if there are too many now.
+    pub fn set_limits(&mut self, max_versions: usize, max_bytes: usize) {
+        if (max_versions, max_bytes) == (self.max_versions, self.max_bytes) {
+            return;
+        }
+        (self.max_versions, self.max_bytes) = (max_versions.max(1), max_bytes);
+        for history in self.sources.values_mut() {
+            while history.versions.len() > self.max_versions {
+                let evicted = history.versions.pop_front().expect("just checked length");
+                self.bytes -= evicted.file.data.len();
+            }
+        }
+        self.evict_to_fit();
+    }
+
+    /// Adds `file` as the latest version of its source, unless its contents are the same as the
+    /// latest version already.
+    pub fn record(&mut self, file: &LoadedFile) {
+        let history = self.sources.entry(file.source.clone()).or_default();
+        if history.versions.back().is_some_and(|latest| {
+            latest.file.crc32 == file.crc32 && latest.file.data.len() == file.data.len()
+        }) {
+            return;
+        }
+        history.loaded_count += 1;
+        history.versions.push_back(Version {
+            number: history.loaded_count,
+            file: file.clone(),
+        });
+        self.bytes += file.data.len();
+        if history.versions.len() > self.max_versions {
+            let evicted = history.versions.pop_front().expect("just checked length");
+            self.bytes -= evicted.file.data.len();
+        }
+        self.evict_to_fit();
+    }
+
+    /// Drops the oldest versions until everything fits into [`Self::max_bytes`], but never the latest
+    /// version of any file, which is what's shown of it.
+    fn evict_to_fit(&mut self) {
+        while self.bytes > self.max_bytes {
+            let oldest = self
+                .sources
+                .values_mut()
+                .filter(|history| history.versions.len() > 1)
+                .filter_map(|history| {
+                    let load_time = history.versions.front()?.file.load_time;
+                    Some((load_time, history))
+                })
+                .min_by_key(|(load_time, _)| *load_time);
+            let Some((_, history)) = oldest else {
+                break;
+            };
+            let evicted = history
+                .versions
+                .pop_front()
+                .expect("only non-empty ones are picked");
+            self.bytes -= evicted.file.data.len();
+        }
+    }
+
+    /// Versions of `source` that are still kept, oldest first.
+    pub fn versions(&self, source: &Source) -> impl Iterator<Item = &Version> {
+        self.sources
+            .get(source)
+            .into_iter()
+            .flat_map(|history| &history.versions)
+    }
+
+    pub fn version(&self, source: &Source, number: usize) -> Option<&Version> {
+        self.versions(source)
+            .find(|version| version.number == number)
+    }
+}
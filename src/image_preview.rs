@@ -0,0 +1,175 @@
+use eframe::egui;
+
+/// Image formats recognized by their magic bytes. Only those that can be decoded are, so that there's
+/// a preview of every image found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+}
+
+impl ImageFormat {
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(ImageFormat::Jpeg)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ImageFormat::Png => "PNG",
+            ImageFormat::Jpeg => "JPEG",
+        })
+    }
+}
+
+/// Whether `value` is binary data holding an image.
+pub fn is_image(value: Option<&rmpv::Value>) -> bool {
+    matches!(value, Some(rmpv::Value::Binary(bytes)) if ImageFormat::detect(bytes).is_some())
+}
+
+/// An image decoded for previewing, or why it couldn't be.
+#[derive(Clone)]
+struct Preview {
+    format: ImageFormat,
+    result: Result<(egui::TextureHandle, [usize; 2]), String>,
+}
+
+/// Decodes the image in `bytes` if there is one, keeping its texture around in egui's memory so that
+/// it's only decoded once.
+fn preview(ctx: &egui::Context, bytes: &[u8]) -> Option<Preview> {
+    let format = ImageFormat::detect(bytes)?;
+    let id = egui::Id::new(("image preview", crc32fast::hash(bytes), bytes.len()));
+    if let Some(preview) = ctx.data(|data| data.get_temp::<Preview>(id)) {
+        return Some(preview);
+    }
+    let max_side = ctx.input(|i| i.max_texture_side);
+    let result = decode(format, bytes, max_side).map(|image| {
+        let size = image.size;
+        let texture = ctx.load_texture("image preview", image, egui::TextureOptions::LINEAR);
+        (texture, size)
+    });
+    let preview = Preview { format, result };
+    ctx.data_mut(|data| data.insert_temp(id, preview.clone()));
+    Some(preview)
+}
+
+/// The most pixels an image can have to be previewed, as its header can claim any size and decoding
+/// it takes memory for all of them.
+const MAX_PIXELS: usize = 16 * 1024 * 1024;
+
+/// Decodes an image, unless its header says it's bigger than `max_side` pixels across or than
+/// [`MAX_PIXELS`] in all.
+fn decode(format: ImageFormat, bytes: &[u8], max_side: usize) -> Result<egui::ColorImage, String> {
+    let check_size = |width: u32, height: u32| {
+        let (width, height) = (width as usize, height as usize);
+        if width.max(height) > max_side || width.saturating_mul(height) > MAX_PIXELS {
+            return Err(format!("{width}×{height} is too big to preview"));
+        }
+        Ok(())
+    };
+    match format {
+        ImageFormat::Png => decode_png(bytes, check_size),
+        ImageFormat::Jpeg => decode_jpeg(bytes, check_size),
+    }
+}
+
+fn decode_png(
+    bytes: &[u8],
+    check_size: impl Fn(u32, u32) -> Result<(), String>,
+) -> Result<egui::ColorImage, String> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    check_size(reader.info().width, reader.info().height)?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let frame = reader
+        .next_frame(&mut pixels)
+        .map_err(|err| err.to_string())?;
+    pixels.truncate(frame.buffer_size());
+    let size = [frame.width as usize, frame.height as usize];
+    let rgba = match frame.color_type {
+        png::ColorType::Rgba => pixels,
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|la| [la[0], la[0], la[0], la[1]])
+            .collect(),
+        // palettes are expanded to RGB(A) by the transformations, so only grayscale gets here
+        png::ColorType::Grayscale | png::ColorType::Indexed => {
+            pixels.iter().flat_map(|&l| [l, l, l, 255]).collect()
+        }
+    };
+    Ok(egui::ColorImage::from_rgba_unmultiplied(size, &rgba))
+}
+
+fn decode_jpeg(
+    bytes: &[u8],
+    check_size: impl Fn(u32, u32) -> Result<(), String>,
+) -> Result<egui::ColorImage, String> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    decoder.read_info().map_err(|err| err.to_string())?;
+    let info = decoder.info().ok_or("JPEG has no frame")?;
+    check_size(info.width.into(), info.height.into())?;
+    let pixels = decoder.decode().map_err(|err| err.to_string())?;
+    let size = [info.width as usize, info.height as usize];
+    match info.pixel_format {
+        jpeg_decoder::PixelFormat::RGB24 => Ok(egui::ColorImage::from_rgb(size, &pixels)),
+        jpeg_decoder::PixelFormat::L8 => Ok(egui::ColorImage::from_gray(size, &pixels)),
+        other => Err(format!("{other:?} JPEGs can't be previewed")),
+    }
+}
+
+/// Shows a thumbnail of the image in `bytes` as tall as a row, if there is one that can be decoded.
+pub fn show_thumbnail(ui: &mut egui::Ui, bytes: &[u8]) -> Option<egui::Response> {
+    let (texture, _) = preview(ui.ctx(), bytes)?.result.ok()?;
+    let height = ui.spacing().interact_size.y;
+    Some(ui.add(egui::Image::new(&texture).max_height(height)))
+}
+
+/// Shows the image in `bytes` at a size that fits in a tooltip, along with its format and size.
+pub fn show_preview(ui: &mut egui::Ui, bytes: &[u8]) {
+    const MAX_SIZE: f32 = 256.0;
+    let Some(preview) = preview(ui.ctx(), bytes) else {
+        ui.weak("Not an image");
+        return;
+    };
+    match preview.result {
+        Ok((texture, [width, height])) => {
+            ui.label(format!("{} image, {width}×{height}", preview.format));
+            ui.add(egui::Image::new(&texture).max_size(egui::Vec2::splat(MAX_SIZE)));
+        }
+        Err(err) => {
+            ui.label(format!("{} image: {err}", preview.format));
+        }
+    }
+}
+
+/// Shows the images in A and B next to each other, or that there isn't one on a side.
+pub fn show_side_by_side(ui: &mut egui::Ui, a: Option<&rmpv::Value>, b: Option<&rmpv::Value>) {
+    ui.horizontal_top(|ui| {
+        for (name, value) in [("A", a), ("B", b)] {
+            ui.vertical(|ui| {
+                ui.strong(name);
+                match value {
+                    Some(rmpv::Value::Binary(bytes)) => show_preview(ui, bytes),
+                    Some(_) => {
+                        ui.weak("Not an image");
+                    }
+                    None => {
+                        ui.weak("Not present");
+                    }
+                }
+            });
+        }
+    });
+}
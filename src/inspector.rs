@@ -0,0 +1,168 @@
+use eframe::egui;
+
+use crate::display::DisplayOptions;
+use crate::hex_dump;
+use crate::loader::LoadedFile;
+use crate::size_view::format_size;
+use crate::spans;
+use crate::tree_view::path_of;
+
+/// Lines of the raw bytes shown at most, as a dump of a huge value would be slow to lay out.
+const MAX_DUMP_LINES: usize = 64;
+
+/// Shows everything there is to know about the value at `indexes` within `file`: where it is and
+/// how it's encoded, what it decodes to, and what's at the same path in `other`, the file it's
+/// compared with, named by `other_name`.
+pub fn show_inspector(
+    ui: &mut egui::Ui,
+    file: &LoadedFile,
+    indexes: &[usize],
+    other: Option<(&str, &LoadedFile)>,
+    display: &DisplayOptions,
+) {
+    let path = path_of(&file.parsed, indexes);
+    let Some(value) = path.resolve(&file.parsed) else {
+        ui.weak("The selected value is no longer in the file.");
+        return;
+    };
+    let span = file.value_span(indexes);
+    egui::Grid::new("inspector")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Path:");
+            ui.horizontal(|ui| {
+                ui.monospace(path.to_string());
+                if ui.small_button("Copy").clicked() {
+                    ui.output_mut(|output| output.copied_text = path.to_string());
+                }
+            });
+            ui.end_row();
+            ui.label("Type:");
+            ui.label(type_name(value));
+            ui.end_row();
+            match &span {
+                Some(span) => {
+                    ui.label("Encoded as:");
+                    let encoding = spans::describe_encoding(&file.data, span.start);
+                    ui.label(encoding.unwrap_or_default());
+                    ui.end_row();
+                    ui.label("Size:");
+                    ui.label(format!(
+                        "{} ({} bytes)",
+                        format_size(span.len()),
+                        span.len()
+                    ));
+                    ui.end_row();
+                    ui.label("Offset:");
+                    let within = match file.compression {
+                        Some((compression, _)) => {
+                            format!(" of the data decompressed from {compression}")
+                        }
+                        None => String::new(),
+                    };
+                    ui.label(format!("0x{:X} ({}){within}", span.start, span.start));
+                    ui.end_row();
+                }
+                None => {
+                    ui.label("Offset:");
+                    ui.weak("unknown, as decoding failed within it");
+                    ui.end_row();
+                }
+            }
+            if let rmpv::Value::Ext(type_id, data) = value {
+                ui.label("Extension:");
+                match crate::ext_types::decode(value) {
+                    Some(decoded) => ui.label(format!(
+                        "{} (type {type_id}, {} bytes): {}",
+                        decoded.name,
+                        data.len(),
+                        decoded.text
+                    )),
+                    None => ui.weak(format!(
+                        "type {type_id}, {} bytes, which there's no decoder for",
+                        data.len()
+                    )),
+                };
+                ui.end_row();
+            }
+            if !matches!(value, rmpv::Value::Array(_) | rmpv::Value::Map(_)) {
+                ui.label("Value:");
+                ui.horizontal_wrapped(|ui| crate::render_rmpv(ui, value, display));
+                ui.end_row();
+            }
+            if let Some((other_name, other)) = other {
+                ui.label(format!("In {other_name}:"));
+                match path.resolve(&other.parsed) {
+                    Some(counterpart) if counterpart == value => {
+                        ui.label("the same");
+                    }
+                    Some(counterpart) => {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.colored_label(ui.visuals().warn_fg_color, "different:");
+                            match counterpart {
+                                rmpv::Value::Array(items) => {
+                                    ui.label(format!("an array of {} items", items.len()))
+                                }
+                                rmpv::Value::Map(entries) => {
+                                    ui.label(format!("a map of {} entries", entries.len()))
+                                }
+                                _ => {
+                                    crate::render_rmpv(ui, counterpart, display);
+                                    ui.weak(format!("({})", type_name(counterpart)))
+                                }
+                            };
+                        });
+                    }
+                    None => {
+                        ui.weak("not there");
+                    }
+                }
+                ui.end_row();
+            }
+        });
+    if let Some(bytes) = span.and_then(|span| file.data.get(span)) {
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.strong("Raw bytes");
+            if ui.small_button("Copy as hex").clicked() {
+                ui.output_mut(|output| output.copied_text = hex_dump::encode_spaced(bytes));
+            }
+        });
+        let lines = bytes.len().div_ceil(hex_dump::BYTES_PER_LINE);
+        egui::ScrollArea::vertical()
+            .id_salt("inspector bytes")
+            .max_height(160.0)
+            .show(ui, |ui| {
+                for line in 0..lines.min(MAX_DUMP_LINES) {
+                    ui.monospace(hex_dump::format_line(bytes, line));
+                }
+                if lines > MAX_DUMP_LINES {
+                    let shown = MAX_DUMP_LINES * hex_dump::BYTES_PER_LINE;
+                    ui.weak(format!("…and {} more bytes", bytes.len() - shown));
+                }
+            });
+    }
+}
+
+/// Names the kind of value `value` is as rmpv has it, with its length if it has one.
+fn type_name(value: &rmpv::Value) -> String {
+    match value {
+        rmpv::Value::Nil => "nil".to_owned(),
+        rmpv::Value::Boolean(_) => "boolean".to_owned(),
+        rmpv::Value::Integer(n) if n.as_i64().is_some_and(|n| n < 0) => {
+            "negative integer".to_owned()
+        }
+        rmpv::Value::Integer(_) => "integer".to_owned(),
+        rmpv::Value::F32(_) => "32-bit float".to_owned(),
+        rmpv::Value::F64(_) => "64-bit float".to_owned(),
+        rmpv::Value::String(text) => match text.as_str() {
+            Some(text) => format!("string of {} bytes", text.len()),
+            None => format!("string of {} bytes, not valid UTF-8", text.as_bytes().len()),
+        },
+        rmpv::Value::Binary(bytes) => format!("binary of {} bytes", bytes.len()),
+        rmpv::Value::Array(items) => format!("array of {} items", items.len()),
+        rmpv::Value::Map(entries) => format!("map of {} entries", entries.len()),
+        rmpv::Value::Ext(type_id, _) => format!("extension of type {type_id}"),
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+use std::sync::{LazyLock, Mutex};
+
+use eframe::egui;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// A message that was logged, as kept to be shown in the app.
+struct LogRecord {
+    level: Level,
+    target: String,
+    message: String,
+    time: jiff::Timestamp,
+}
+
+/// The most recent messages, along with how many warnings and errors there have been in all.
+#[derive(Default)]
+struct Captured {
+    records: VecDeque<LogRecord>,
+    warnings: usize,
+}
+
+static CAPTURED: LazyLock<Mutex<Captured>> = LazyLock::new(Default::default);
+
+/// Logs to stderr as `RUST_LOG` says, like `env_logger` on its own does, while also keeping
+/// messages to show in [`LogView`]: the app's own from info up, and everyone else's warnings and
+/// errors. Those would otherwise go unseen without a console.
+struct AppLogger {
+    stderr: env_logger::Logger,
+}
+
+impl AppLogger {
+    const MAX_RECORDS: usize = 1000;
+
+    fn captures(metadata: &Metadata) -> bool {
+        let is_own = metadata.target().starts_with(env!("CARGO_CRATE_NAME"));
+        metadata.level() <= if is_own { Level::Info } else { Level::Warn }
+    }
+}
+
+impl Log for AppLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.stderr.enabled(metadata) || Self::captures(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.stderr.matches(record) {
+            self.stderr.log(record);
+        }
+        if !Self::captures(record.metadata()) {
+            return;
+        }
+        let mut captured = CAPTURED.lock().unwrap_or_else(|err| err.into_inner());
+        captured.records.push_back(LogRecord {
+            level: record.level(),
+            target: record.target().to_owned(),
+            message: record.args().to_string(),
+            time: jiff::Timestamp::now(),
+        });
+        if captured.records.len() > Self::MAX_RECORDS {
+            captured.records.pop_front();
+        }
+        if record.level() <= Level::Warn {
+            captured.warnings += 1;
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+    }
+}
+
+/// Starts logging; see [`AppLogger`].
+pub fn init() {
+    let stderr = env_logger::Builder::from_default_env().build();
+    let max_level = stderr.filter().max(LevelFilter::Info);
+    if log::set_boxed_logger(Box::new(AppLogger { stderr })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// A window listing what was logged, which can be filtered by level and text.
+pub struct LogView {
+    pub open: bool,
+    /// Least severe level shown.
+    level: Level,
+    filter: String,
+    /// How many warnings and errors had been logged when the window was last shown.
+    seen_warnings: usize,
+}
+
+impl Default for LogView {
+    fn default() -> Self {
+        Self {
+            open: false,
+            level: Level::Info,
+            filter: String::new(),
+            seen_warnings: 0,
+        }
+    }
+}
+
+impl LogView {
+    /// How many warnings and errors were logged since the window was last shown.
+    pub fn unseen_warnings(&self) -> usize {
+        let captured = CAPTURED.lock().unwrap_or_else(|err| err.into_inner());
+        captured.warnings - self.seen_warnings
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        egui::Window::new("Log")
+            .open(&mut open)
+            .default_size([640.0, 320.0])
+            .show(ctx, |ui| self.show_contents(ui));
+        self.open = open;
+    }
+
+    fn show_contents(&mut self, ui: &mut egui::Ui) {
+        let mut captured = CAPTURED.lock().unwrap_or_else(|err| err.into_inner());
+        self.seen_warnings = captured.warnings;
+        ui.horizontal(|ui| {
+            ui.label("Show:");
+            for (level, name) in [
+                (Level::Error, "Errors"),
+                (Level::Warn, "Warnings"),
+                (Level::Info, "Info"),
+            ] {
+                ui.selectable_value(&mut self.level, level, name);
+            }
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter)
+                    .hint_text("Filter")
+                    .desired_width(160.0),
+            );
+            if ui.button("Clear").clicked() {
+                captured.records.clear();
+            }
+        });
+        ui.separator();
+        let filter = self.filter.to_lowercase();
+        let shown = captured.records.iter().filter(|record| {
+            record.level <= self.level
+                && (filter.is_empty() || record.message.to_lowercase().contains(&filter))
+        });
+        egui::ScrollArea::both()
+            .auto_shrink(false)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for record in shown {
+                    let color = match record.level {
+                        Level::Error => ui.visuals().error_fg_color,
+                        Level::Warn => ui.visuals().warn_fg_color,
+                        _ => ui.visuals().text_color(),
+                    };
+                    let text = format!(
+                        "{} {:5} {}",
+                        record.time.strftime("%H:%M:%S"),
+                        record.level,
+                        record.message
+                    );
+                    ui.label(egui::RichText::new(text).monospace().color(color))
+                        .on_hover_text(&record.target);
+                }
+            });
+    }
+}
@@ -1,14 +1,90 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 #![allow(rustdoc::missing_crate_level_docs)] // it's an example
 
-use std::path::PathBuf;
+mod accessibility;
+mod annotations;
+mod cli;
+mod config;
+mod diff_view;
+mod display;
+mod ext_types;
+mod hex_dump;
+mod hex_view;
+mod history;
+mod image_preview;
+mod inspector;
+mod log_view;
+mod node_menu;
+mod pairing;
+mod project;
+mod recent;
+mod report;
+mod search;
+mod settings;
+mod size_view;
+mod toasts;
+mod tree_view;
+mod watch;
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use eframe::egui;
 use log::{error, warn};
+use msgpack_differ_core::{
+    diff, edit, ext, framing, json, loader, nested, patch, path, payload, recover, spans, value,
+    yaml,
+};
 use serde::{Deserialize, Serialize};
 
+use crate::annotations::Annotations;
+use crate::diff::{diff_values_with, DiffEntry, DiffNode, DiffOptions};
+use crate::diff_view::{DiffLayout, DiffView, StatusFilter};
+use crate::display::{
+    BinaryFormat, DisplayOptions, FloatNotation, IntegerBase, Palette, StringStyle,
+};
+use crate::edit::Edit;
+use crate::ext::ExtRegistry;
+use crate::framing::Framing;
+use crate::hex_view::HexView;
+use crate::history::{History, Version};
+use crate::json::{json_key, msgpack_to_json_with, BinaryConversion, ExtConversion};
+use crate::loader::{
+    BackgroundLoad, FileBytes, LoadError, LoadOptions, LoadProgress, LoadResult, LoadStage,
+    LoadedFile, Source,
+};
+use crate::log_view::LogView;
+use crate::node_menu::NodeAction;
+use crate::pairing::{pair_across, pair_within, FilePair, PairPattern};
+use crate::path::{ParsePathError, PathSegment, ValuePath};
+use crate::payload::decode_payload;
+use crate::project::{Project, ProjectSide};
+use crate::recent::RecentFiles;
+use crate::recover::DecodeFailure;
+use crate::report::ReportFile;
+use crate::settings::AppSettings;
+use crate::size_view::{format_size, SizeView};
+use crate::toasts::Toasts;
+use crate::tree_view::{describe_span, path_of, Expansion, TreeView};
+use crate::value::{describe_children, HashableValue};
+use crate::watch::FileWatcher;
+
 fn main() -> eframe::Result {
-    env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
+    log_view::init(); // Log to stderr (if you run with `RUST_LOG=debug`), and the log window.
+
+    let args = std::env::args_os().skip(1).collect::<Vec<_>>();
+    if let Some(exit_code) = cli::run_subcommand(&args) {
+        std::process::exit(exit_code);
+    }
+    // any other arguments are files (or URLs) to open, e.g. when launched via "Open With" or as a difftool
+    let sources = args
+        .into_iter()
+        .map(|arg| match arg.to_str() {
+            Some(text) => Source::parse(text),
+            None => Source::File(PathBuf::from(arg)),
+        })
+        .collect::<Vec<_>>();
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_title("MsgPack Differ"),
         ..Default::default()
@@ -16,225 +92,3729 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "MsgPack Differ",
         options,
-        Box::new(|_cc| Ok(Box::<MsgPackDifferApp>::default())),
+        Box::new(|cc| Ok(Box::new(MsgPackDifferApp::new(cc, sources)))),
     )
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-#[repr(transparent)]
-pub struct Crc32 {
-    result: u32,
+/// A file picked for one side of the comparison, along with everything loaded from it.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct FileSlot {
+    source: Option<Source>,
+    #[serde(skip)]
+    loaded: Option<LoadResult>,
+    #[serde(skip)]
+    loading: Option<BackgroundLoad>,
+    /// An earlier version of the file to show and compare instead of the latest one.
+    #[serde(skip)]
+    pinned: Option<Version>,
+    tree: TreeView,
+    /// Whether to show a hex dump of the file below its tree.
+    show_hex: bool,
+    #[serde(skip)]
+    hex_view: HexView,
+    auto_reload: bool,
+    /// Which value of a multi-value file to compare, or `None` to compare all of them.
+    record: Option<usize>,
+    /// How to interpret the file's bytes; changing these reloads it.
+    load_options: LoadOptions,
+    /// Path of the subtree to compare (relative to the selected record), or empty for all of it.
+    subtree: String,
+    /// How the tree shows strings.
+    string_style: StringStyle,
+    #[serde(skip)]
+    watcher: Option<FileWatcher>,
+    /// When the watcher last reported a change that hasn't been reloaded yet.
+    #[serde(skip)]
+    pending_change: Option<Instant>,
+    /// Whether the file is being reloaded because it changed.
+    #[serde(skip)]
+    reloading_automatically: bool,
+    /// Whether reloading the file because it changed worked, once it's done and until that's been
+    /// noted with [`Self::take_auto_reloaded`].
+    #[serde(skip)]
+    auto_reloaded: Option<bool>,
+    /// Whether the file is being loaded from the bytes of an edit made to it, which aren't a
+    /// version of the file to keep in the history.
+    #[serde(skip)]
+    loading_edited: bool,
+    /// Whether a load of the file other than from the bytes of an edit finished since
+    /// [`Self::take_newly_loaded`] was last called.
+    #[serde(skip)]
+    newly_loaded: bool,
+    /// The file's bytes from before it was first edited by hand, if it's been edited since it was
+    /// last read or saved.
+    #[serde(skip)]
+    unedited: Option<FileBytes>,
+    /// Edits made by hand that can be undone, the latest last. Only so many are kept, see
+    /// [`EditStep::push`].
+    #[serde(skip)]
+    undo: Vec<EditStep>,
+    /// Edits that were undone and can be redone, the latest undone last.
+    #[serde(skip)]
+    redo: Vec<EditStep>,
+}
+
+/// A step back or forward through the edits made to a file, see [`FileSlot::undo`].
+struct EditStep {
+    /// The file's bytes as of the other end of the step.
+    bytes: FileBytes,
+    /// The edit taking the file there, for moving what's kept for its entries along with them.
+    edit: Edit,
+    /// When the step was made, for undoing the latest one in either file first.
+    made: Instant,
 }
 
-impl std::fmt::Display for Crc32 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "CRC32: {:08x}", self.result)
+impl EditStep {
+    const MAX_STEPS: usize = 100;
+    /// Bound on the size of the bytes of all steps kept in one direction, though the latest step
+    /// is kept regardless, as each step keeps a whole copy of the file.
+    const MAX_BYTES: usize = 256 * 1024 * 1024;
+
+    /// Adds `step` as the latest of `steps`, dropping the oldest ones if there are too many or
+    /// they take up too much memory.
+    fn push(steps: &mut Vec<EditStep>, step: EditStep) {
+        steps.push(step);
+        let mut bytes = steps.iter().map(|step| step.bytes.len()).sum::<usize>();
+        let mut dropped = 0;
+        while steps.len() - dropped > 1
+            && (steps.len() - dropped > Self::MAX_STEPS || bytes > Self::MAX_BYTES)
+        {
+            bytes -= steps[dropped].bytes.len();
+            dropped += 1;
+        }
+        steps.drain(..dropped);
+    }
+}
+
+impl FileSlot {
+    /// Starts (re)loading the file in the background. Until that's done, whatever was loaded
+    /// previously stays available.
+    ///
+    /// Downloads are only reloaded from the bytes downloaded before; see [`Self::refetch`].
+    fn reload(&mut self, ctx: &egui::Context) {
+        let cached = match (&self.source, self.loaded_file()) {
+            (
+                Some(Source::Url(_) | Source::Pasted { .. } | Source::Snapshot { .. }),
+                Some(file),
+            ) => Some(file.raw.clone()),
+            _ => None,
+        };
+        self.start_loading(ctx, cached);
+    }
+
+    /// Like [`Self::reload`], but always reads or downloads the file afresh.
+    fn refetch(&mut self, ctx: &egui::Context) {
+        self.start_loading(ctx, None);
+    }
+
+    fn start_loading(&mut self, ctx: &egui::Context, cached: Option<FileBytes>) {
+        if cached.is_none() {
+            // edits only ever exist as the bytes cached from making them
+            self.unedited = None;
+            self.undo.clear();
+            self.redo.clear();
+        }
+        self.loading_edited = false;
+        let repaint_ctx = ctx.clone();
+        self.loading = self.source.clone().map(|source| {
+            BackgroundLoad::start(source, cached, self.load_options.clone(), move || {
+                repaint_ctx.request_repaint()
+            })
+        });
+    }
+
+    /// Makes `edit` to the file's document, and loads that encoded the way the file is in place of
+    /// the file, keeping the bytes from before it to undo it with.
+    fn edit(&mut self, ctx: &egui::Context, edit: Edit) {
+        if self.pinned.is_some() {
+            warn!("Can't edit an earlier version of a file; show the latest one to edit it");
+            return;
+        }
+        if self.loading.is_some() {
+            // edits are made to the file as it is once the previous one is loaded
+            warn!("Can't edit a file while it's loading; try again once it's loaded");
+            return;
+        }
+        let Some(file) = self.loaded_file() else {
+            return;
+        };
+        let mut document = rmpv::Value::clone(&file.parsed);
+        if let Err(err) = edit.apply(&mut document) {
+            warn!("Can't edit {}: {err}", file.source);
+            return;
+        }
+        let nested = file
+            .nested
+            .iter()
+            .flat_map(|indexes| edit.moved(indexes))
+            .collect();
+        let encoded = match file.encode_document(&document, &nested) {
+            Ok(encoded) => FileBytes::from(encoded),
+            Err(err) => {
+                warn!("Can't edit {}: {err}", file.source);
+                return;
+            }
+        };
+        if let Some(inverse) = edit.inverse(&file.parsed) {
+            let step = EditStep {
+                bytes: file.raw.clone(),
+                edit: inverse,
+                made: Instant::now(),
+            };
+            EditStep::push(&mut self.undo, step);
+        }
+        self.redo.clear();
+        self.tree.follow_edit(&edit);
+        self.load_edited(ctx, encoded);
+    }
+
+    /// Undoes the latest edit, or redoes the latest edit undone if `redo` is set.
+    fn undo(&mut self, ctx: &egui::Context, redo: bool) {
+        let Some(file) = self
+            .loaded_file()
+            .filter(|_| self.loading.is_none() && self.pinned.is_none())
+        else {
+            return;
+        };
+        let steps = if redo { &self.redo } else { &self.undo };
+        let Some(step) = steps.last() else {
+            return;
+        };
+        let back = step.edit.inverse(&file.parsed).map(|edit| EditStep {
+            bytes: file.raw.clone(),
+            edit,
+            made: Instant::now(),
+        });
+        let (steps, opposite) = match redo {
+            true => (&mut self.redo, &mut self.undo),
+            false => (&mut self.undo, &mut self.redo),
+        };
+        let step = steps.pop().expect("just checked there's one");
+        if let Some(back) = back {
+            EditStep::push(opposite, back);
+        }
+        self.tree.follow_edit(&step.edit);
+        self.load_edited(ctx, step.bytes);
+    }
+
+    /// When the latest edit that [`Self::undo`] would undo or redo was made or undone.
+    fn latest_step(&self, redo: bool) -> Option<Instant> {
+        let steps = if redo { &self.redo } else { &self.undo };
+        steps.last().map(|step| step.made)
+    }
+
+    /// Loads `bytes` from editing the file in place of it, noting whether they're the bytes that
+    /// were last read or saved, which undoing every edit goes back to.
+    fn load_edited(&mut self, ctx: &egui::Context, bytes: FileBytes) {
+        let Some(file) = self.loaded_file() else {
+            return;
+        };
+        let raw = file.raw.clone();
+        let unedited = self.unedited.take().unwrap_or(raw);
+        if *bytes != *unedited {
+            self.unedited = Some(unedited);
+        }
+        self.start_loading(ctx, Some(bytes));
+        self.loading_edited = true;
+    }
+
+    fn is_edited(&self) -> bool {
+        self.unedited.is_some()
+    }
+
+    /// Goes back to the file as it was before it was edited, which can't be undone.
+    fn discard_edits(&mut self, ctx: &egui::Context) {
+        if let Some(unedited) = self.unedited.take() {
+            self.undo.clear();
+            self.redo.clear();
+            self.start_loading(ctx, Some(unedited));
+        }
+    }
+
+    fn loaded_file(&self) -> Option<&LoadedFile> {
+        self.loaded.as_ref()?.as_ref().ok()
+    }
+
+    /// The pinned version of the file if there is one, or else the latest one.
+    fn shown_file(&self) -> Option<&LoadedFile> {
+        match &self.pinned {
+            Some(version) => Some(&version.file),
+            None => self.loaded_file(),
+        }
+    }
+
+    /// The selected record of a multi-value file, or else the whole document.
+    fn record_root(&self) -> Option<&rmpv::Value> {
+        select_record(self.shown_file()?, self.record)
+    }
+
+    /// The value to diff: the selected subtree of [`Self::record_root`].
+    fn diff_root(&self) -> Option<&rmpv::Value> {
+        self.subtree_path().ok()?.resolve(self.record_root()?)
+    }
+
+    fn subtree_path(&self) -> Result<ValuePath, ParsePathError> {
+        self.subtree.parse()
+    }
+
+    /// Why the selected subtree can't be compared, if it can't.
+    fn subtree_error(&self) -> Option<String> {
+        subtree_error(self.record_root()?, &self.subtree)
+    }
+
+    /// Path of [`Self::diff_root`] within the whole file, as shown by the tree.
+    fn diff_root_path(&self) -> Option<ValuePath> {
+        let file = self.shown_file()?;
+        let record = self.record.filter(|_| file.is_multi_value());
+        let subtree_path = self.subtree_path().ok()?;
+        let segments = subtree_path.segments().iter().cloned();
+        Some(
+            record
+                .map(PathSegment::Index)
+                .into_iter()
+                .chain(segments)
+                .collect(),
+        )
+    }
+
+    /// Turns a path within the whole file into one within [`Self::diff_root`], if it's in there.
+    fn path_within_diff_root(&self, full_path: &ValuePath) -> Option<ValuePath> {
+        let root_path = self.diff_root_path()?;
+        let rest = full_path.segments().strip_prefix(root_path.segments())?;
+        Some(rest.iter().cloned().collect())
+    }
+
+    /// Expands the tree to the value at `path` within [`Self::diff_root`] and scrolls to it; returns
+    /// whether there is one.
+    fn go_to(&mut self, path: &ValuePath) -> bool {
+        // the tree shows the whole file, so the path starts from its root rather than the diff's
+        let Some(indexes) = self
+            .shown_file()
+            .and_then(|file| self.path_from_file_root(path)?.indexes(&file.parsed))
+        else {
+            return false;
+        };
+        self.tree.reveal(indexes);
+        true
+    }
+
+    /// The path within the whole file of the value at `path` within the diff's root.
+    fn path_from_file_root(&self, path: &ValuePath) -> Option<ValuePath> {
+        let root_path = self.diff_root_path()?;
+        Some(
+            root_path
+                .segments()
+                .iter()
+                .chain(path.segments())
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// The path within the whole file of the entry at the top of the tree, if it was just scrolled
+    /// by hand.
+    fn scrolled_path(&self) -> Option<ValuePath> {
+        let indexes = self.tree.scrolled_to()?;
+        Some(path_of(&self.shown_file()?.parsed, indexes))
+    }
+
+    /// The path within the whole file of the entry that was just expanded or collapsed by hand in
+    /// the tree, and whether it's now expanded.
+    fn toggled_path(&self) -> Option<(ValuePath, bool)> {
+        let (indexes, expanded) = self.tree.toggled()?;
+        Some((path_of(&self.shown_file()?.parsed, indexes), expanded))
+    }
+
+    /// Expands or collapses the value at `path` within the whole file in the tree, if there is one.
+    fn set_expanded(&mut self, path: &ValuePath, expanded: bool) {
+        let indexes = self
+            .shown_file()
+            .and_then(|file| path.indexes(&file.parsed));
+        if let Some(indexes) = indexes {
+            self.tree.set_expanded(indexes, expanded);
+        }
+    }
+
+    /// Scrolls the tree to the value at `path` within the whole file, or as close to it as there is.
+    fn scroll_to(&mut self, path: &ValuePath) {
+        let Some(file) = self.shown_file() else {
+            return;
+        };
+        let segments = path.segments();
+        let indexes = (0..=segments.len()).rev().find_map(|len| {
+            segments[..len]
+                .iter()
+                .cloned()
+                .collect::<ValuePath>()
+                .indexes(&file.parsed)
+        });
+        if let Some(indexes) = indexes {
+            self.tree.scroll_to(indexes);
+        }
+    }
+
+    /// The bytes that the value at `path` within the diff's root was decoded from.
+    fn raw_bytes(&self, path: &ValuePath) -> Option<&[u8]> {
+        let file = self.shown_file()?;
+        let indexes = self.path_from_file_root(path)?.indexes(&file.parsed)?;
+        file.value_bytes(&indexes)
+    }
+
+    /// Compares just the value at `full_path` within the whole file, picking the value it's in for a
+    /// file with several of them.
+    fn compare_subtree(&mut self, full_path: &ValuePath) {
+        let is_multi_value = self.shown_file().is_some_and(LoadedFile::is_multi_value);
+        let segments = match full_path.segments().split_first() {
+            Some((PathSegment::Index(record), rest)) if is_multi_value => {
+                self.record = Some(*record);
+                rest
+            }
+            _ => full_path.segments(),
+        };
+        self.subtree = segments.iter().cloned().collect::<ValuePath>().to_string();
+    }
+
+    fn diff_inputs(&self) -> Option<SlotDiffInputs> {
+        let file = self.shown_file()?;
+        Some((
+            file.load_time,
+            self.record.filter(|_| file.is_multi_value()),
+            self.subtree.trim().to_owned(),
+        ))
+    }
+
+    /// Whether the file was reloaded because it changed and whether that worked, if it was since this
+    /// was last called.
+    fn take_auto_reloaded(&mut self) -> Option<bool> {
+        self.auto_reloaded.take()
+    }
+
+    /// The file, if it finished loading since this was last called.
+    fn take_newly_loaded(&mut self) -> Option<&LoadedFile> {
+        if !std::mem::take(&mut self.newly_loaded) {
+            return None;
+        }
+        self.loaded_file()
+    }
+
+    /// Keeps loading and watching the file. Once it changes, it's reloaded after `reload_delay`
+    /// has passed without further changes.
+    fn tick(&mut self, ctx: &egui::Context, reload_delay: Duration) {
+        let Some(source) = self.source.clone() else {
+            self.loaded = None;
+            self.loading = None;
+            self.pinned = None;
+            self.watcher = None;
+            self.pending_change = None;
+            return;
+        };
+        if self
+            .pinned
+            .as_ref()
+            .is_some_and(|version| version.file.source != source)
+        {
+            self.pinned = None;
+        }
+        if let Some(result) = self.loading.as_ref().and_then(BackgroundLoad::poll) {
+            self.loading = None;
+            let automatically = std::mem::take(&mut self.reloading_automatically);
+            let edited = std::mem::take(&mut self.loading_edited);
+            match result {
+                // a cancelled reload leaves the previously loaded version in place
+                Err(LoadError::Cancelled) if matches!(self.loaded, Some(Ok(_))) => {}
+                Err(err) => {
+                    warn!("Failed to load {source}: {err}");
+                    self.loaded = Some(Err(err));
+                    self.auto_reloaded = automatically.then_some(false);
+                }
+                Ok(file) => {
+                    self.loaded = Some(Ok(file));
+                    self.newly_loaded = !edited;
+                    self.auto_reloaded = automatically.then_some(true);
+                }
+            }
+        }
+        let needs_load = match (&self.loading, &self.loaded) {
+            (Some(loading), _) => *loading.source() != source,
+            (None, Some(Ok(file))) => file.source != source,
+            (None, Some(Err(_))) => false,
+            (None, None) => true,
+        };
+        if needs_load {
+            self.refetch(ctx);
+        }
+
+        // only local files can be watched, and reloading an edited one would throw the edits away
+        let Some(picked_path) = source
+            .as_path()
+            .filter(|_| self.auto_reload && !self.is_edited())
+        else {
+            self.watcher = None;
+            self.pending_change = None;
+            return;
+        };
+        let watching_picked_path = self.watcher.as_ref().is_some_and(|watcher| {
+            std::path::absolute(picked_path).is_ok_and(|p| watcher.path() == p)
+        });
+        if !watching_picked_path {
+            let repaint_ctx = ctx.clone();
+            match FileWatcher::new(picked_path, move || repaint_ctx.request_repaint()) {
+                Ok(watcher) => self.watcher = Some(watcher),
+                Err(err) => {
+                    error!("Could not watch {}: {err}", picked_path.display());
+                    self.auto_reload = false;
+                    self.watcher = None;
+                    return;
+                }
+            }
+        }
+        if self.watcher.as_ref().is_some_and(FileWatcher::has_changed) {
+            self.pending_change = Some(Instant::now());
+        }
+        if let Some(changed_at) = self.pending_change {
+            let elapsed = changed_at.elapsed();
+            if elapsed >= reload_delay {
+                self.pending_change = None;
+                self.reload(ctx);
+                self.reloading_automatically = true;
+            } else {
+                ctx.request_repaint_after(reload_delay - elapsed);
+            }
+        }
+    }
+}
+
+/// Everything that isn't skipped is saved as the session, and restored on the next launch.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct MsgPackDifferApp {
+    /// The comparison in the tab that's shown.
+    tab: Comparison,
+    /// Every tab's comparison, except that the shown one's is in [`Self::tab`] and a default one is
+    /// left in its place.
+    tabs: Vec<Comparison>,
+    /// Which of `tabs` is shown.
+    tab_index: usize,
+    settings: AppSettings,
+    /// Directory the file dialog opened files from most recently.
+    last_directory: Option<PathBuf>,
+    #[serde(skip)]
+    settings_open: bool,
+    /// Text of the settings window's extension list while it's being edited.
+    #[serde(skip)]
+    extensions_input: Option<String>,
+    /// Why the extension types file couldn't be loaded, if it couldn't.
+    #[serde(skip)]
+    ext_types_error: Option<String>,
+    /// Watcher of the config file, so that its settings are applied again whenever it changes.
+    #[serde(skip)]
+    config_watcher: Option<FileWatcher>,
+    /// Why the config file couldn't be loaded, if it couldn't.
+    #[serde(skip)]
+    config_error: Option<String>,
+    #[serde(skip)]
+    recent_files: RecentFiles,
+    #[serde(skip)]
+    history: History,
+    /// Pairs of files found in directories dropped onto the window, to pick from.
+    #[serde(skip)]
+    file_pairs: Option<FilePairs>,
+    detached: Detached,
+    /// Whether to show the details of the value last selected in either tree below them.
+    show_inspector: bool,
+    /// Whether to show the list of the tab's bookmarks at the side of the window.
+    show_bookmarks: bool,
+    /// Which file's tree a value was last selected in, which the inspector shows.
+    #[serde(skip)]
+    inspected: Option<Side>,
+    /// Path whose note is being edited, and the note's text so far.
+    #[serde(skip)]
+    annotating: Option<(String, String)>,
+    /// Number of diffs computed so far, in any tab.
+    #[serde(skip)]
+    diff_count: u64,
+    #[serde(skip)]
+    url_input: String,
+    #[serde(skip)]
+    paste_error: Option<String>,
+    /// Number of payloads pasted so far, for naming them.
+    #[serde(skip)]
+    paste_count: usize,
+    #[serde(skip)]
+    toasts: Toasts,
+    #[serde(skip)]
+    log_view: LogView,
+    /// Zoom factor last set from the settings, to tell when egui's own shortcuts changed it.
+    #[serde(skip)]
+    applied_zoom: Option<f32>,
+}
+
+/// A pair of files being compared, along with how the diff of them is shown, of which there's one
+/// per tab.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Comparison {
+    a: FileSlot,
+    b: FileSlot,
+    /// Paths within the compared values to leave out of the diff.
+    ignored_paths: Vec<String>,
+    /// Paths within the compared values that were bookmarked to come back to.
+    bookmarks: Vec<String>,
+    annotations: Annotations,
+    #[serde(skip)]
+    diff: Option<CachedDiff>,
+    diff_view: DiffView,
+    #[serde(skip)]
+    go_to_input: String,
+    /// Outcome of the last jump to a path, if it wasn't found everywhere.
+    #[serde(skip)]
+    go_to_note: Option<String>,
+    /// Text of the diff menu's box for ignoring another path.
+    #[serde(skip)]
+    ignore_input: String,
+    #[serde(skip)]
+    size_view: SizeView,
+    /// Sides that were reloaded because they changed, which is noted along with how the diff changed
+    /// once it's computed again.
+    #[serde(skip)]
+    reloaded: Vec<Side>,
+}
+
+impl Comparison {
+    /// What the tab is called, after the files in it.
+    fn name(&self) -> String {
+        let name = |slot: &FileSlot| slot.source.as_ref().map(Source::short_name);
+        match (name(&self.a), name(&self.b)) {
+            (Some(a), Some(b)) => format!("{a} ↔ {b}"),
+            (Some(name), None) | (None, Some(name)) => name.to_string(),
+            (None, None) => "New comparison".to_owned(),
+        }
+    }
+}
+
+/// Formats the diff can be exported in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum DiffExport {
+    /// See [`report::html_report`].
+    Report,
+    /// See [`report::markdown_summary`].
+    Markdown,
+    /// See [`patch::json_patch`].
+    JsonPatch,
+    /// See [`patch::json_merge_patch`].
+    MergePatch,
+    /// See [`report::csv`].
+    Csv,
+    /// See [`patch::msgpack_patch`].
+    MsgpackPatch,
+}
+
+impl DiffExport {
+    const ALL: [DiffExport; 6] = [
+        DiffExport::Report,
+        DiffExport::Markdown,
+        DiffExport::JsonPatch,
+        DiffExport::MergePatch,
+        DiffExport::Csv,
+        DiffExport::MsgpackPatch,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            DiffExport::Report => "HTML report…",
+            DiffExport::Markdown => "Markdown summary…",
+            DiffExport::JsonPatch => "JSON Patch…",
+            DiffExport::MergePatch => "JSON Merge Patch…",
+            DiffExport::Csv => "CSV…",
+            DiffExport::MsgpackPatch => "Msgpack patch…",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            DiffExport::Report => {
+                "A web page showing the diff and what the files are, which can be looked at \
+                 without this app"
+            }
+            DiffExport::Markdown => {
+                "How many differences there are and what they are, to paste into a pull request"
+            }
+            DiffExport::JsonPatch => {
+                "The operations that turn A into B, as RFC 6902 has them, which apply-patch can \
+                 apply again"
+            }
+            DiffExport::MergePatch => {
+                "What changed as a single JSON document, as RFC 7396 has it; arrays that changed \
+                 are replaced whole"
+            }
+            DiffExport::Csv => {
+                "Each difference as a row with its path, what it was and became and by how much, \
+                 for a spreadsheet"
+            }
+            DiffExport::MsgpackPatch => {
+                "The changes that turn A into B as compact msgpack, keeping values exactly as they \
+                 are, which apply-patch can apply again"
+            }
+        }
+    }
+
+    fn file_stem(self) -> &'static str {
+        match self {
+            DiffExport::Report => "diff report",
+            DiffExport::Markdown => "diff summary",
+            DiffExport::JsonPatch => "diff.patch",
+            DiffExport::MergePatch => "diff.merge-patch",
+            DiffExport::Csv => "differences",
+            DiffExport::MsgpackPatch => "diff.patch",
+        }
+    }
+
+    /// What the file dialog calls files of the format, and their extension.
+    fn filter(self) -> (&'static str, &'static str) {
+        match self {
+            DiffExport::Report => ("HTML", "html"),
+            DiffExport::Markdown => ("Markdown", "md"),
+            DiffExport::JsonPatch | DiffExport::MergePatch => ("JSON", "json"),
+            DiffExport::Csv => ("CSV", "csv"),
+            DiffExport::MsgpackPatch => ("msgpack", "msgpack"),
+        }
+    }
+}
+
+/// Which panels are shown in windows of their own rather than in the main one.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Detached {
+    a: bool,
+    b: bool,
+    diff: bool,
+}
+
+/// Shows `add_contents` in a window of its own, or in one within the main window where there can't
+/// be more than one, returning whether it's still open.
+fn show_detached(
+    ctx: &egui::Context,
+    id: impl std::hash::Hash,
+    title: String,
+    mut add_contents: impl FnMut(&mut egui::Ui),
+) -> bool {
+    let mut open = true;
+    let builder = egui::ViewportBuilder::default()
+        .with_title(title.clone())
+        .with_inner_size([600.0, 800.0]);
+    let viewport_id = egui::ViewportId::from_hash_of(("detached", id));
+    ctx.show_viewport_immediate(viewport_id, builder, |ctx, class| {
+        if class == egui::ViewportClass::Embedded {
+            egui::Window::new(&title)
+                .open(&mut open)
+                .show(ctx, |ui| add_contents(ui));
+        } else {
+            egui::CentralPanel::default().show(ctx, |ui| add_contents(ui));
+            open = !ctx.input(|i| i.viewport().close_requested());
+        }
+    });
+    open
+}
+
+/// Expansion picked with a keyboard shortcut this frame, if any.
+fn expansion_shortcut(ctx: &egui::Context) -> Option<Expansion> {
+    const DIGITS: [egui::Key; 9] = [
+        egui::Key::Num1,
+        egui::Key::Num2,
+        egui::Key::Num3,
+        egui::Key::Num4,
+        egui::Key::Num5,
+        egui::Key::Num6,
+        egui::Key::Num7,
+        egui::Key::Num8,
+        egui::Key::Num9,
+    ];
+    ctx.input_mut(|i| {
+        // shift first, as shortcuts without it match regardless
+        if i.consume_key(
+            egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+            egui::Key::E,
+        ) {
+            return Some(Expansion::ToDepth(1));
+        }
+        if i.consume_key(egui::Modifiers::COMMAND, egui::Key::E) {
+            return Some(Expansion::All);
+        }
+        (1..).zip(DIGITS).find_map(|(depth, key)| {
+            i.consume_key(egui::Modifiers::COMMAND, key)
+                .then_some(Expansion::ToDepth(depth))
+        })
+    })
+}
+
+/// Notes that the file on `side`, or on both sides, was reloaded because it changed, and how the
+/// number of differences changed from `previous` to `count`.
+fn reload_note(side: Side, both: bool, previous: Option<usize>, count: usize) -> String {
+    let reloaded = match both {
+        true => "A and B reloaded".to_owned(),
+        false => format!("{side} reloaded"),
+    };
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    match previous {
+        Some(previous) if count > previous => {
+            let new = count - previous;
+            format!("{reloaded}: {new} new difference{}", plural(new))
+        }
+        Some(previous) if count < previous => {
+            let gone = previous - count;
+            format!("{reloaded}: {gone} fewer difference{}", plural(gone))
+        }
+        _ => format!("{reloaded}: {count} difference{}", plural(count)),
+    }
+}
+
+fn select_record(file: &LoadedFile, record: Option<usize>) -> Option<&rmpv::Value> {
+    match record {
+        Some(record) if file.is_multi_value() => file.parsed.as_array()?.get(record),
+        _ => Some(&file.parsed),
+    }
+}
+
+fn subtree_error(root: &rmpv::Value, subtree: &str) -> Option<String> {
+    match subtree.parse::<ValuePath>() {
+        Ok(path) if path.resolve(root).is_none() => Some(format!("nothing at {path}")),
+        Ok(_) => None,
+        Err(err) => Some(err.to_string()),
     }
 }
 
-impl Crc32 {
-    pub fn calculate_hash_of(data: &[u8]) -> Self {
-        let mut hasher = crc32fast::Hasher::new();
-        hasher.update(&data);
-        let result = hasher.finalize();
-        Self { result }
+struct FilePairs {
+    /// Where the files were found, e.g. `in /some/directory`.
+    description: String,
+    pairs: Result<Vec<FilePair>, String>,
+}
+
+/// Diff of the values selected for comparison in A and B, kept until either selection changes.
+struct CachedDiff {
+    inputs: DiffInputs,
+    ignored_paths: Vec<String>,
+    root: DiffNode,
+    /// Distinguishes this diff from all those computed before it.
+    revision: u64,
+    /// How long computing it took.
+    duration: Duration,
+}
+
+type DiffInputs = (SlotDiffInputs, SlotDiffInputs);
+
+/// When one side was loaded, which of its records was selected, and the subtree path.
+type SlotDiffInputs = (jiff::Timestamp, Option<usize>, String);
+
+impl eframe::App for MsgPackDifferApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, self);
+        eframe::set_value(storage, RecentFiles::STORAGE_KEY, &self.recent_files);
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if ctx.options(|options| options.theme_preference) != self.settings.theme {
+            ctx.set_theme(self.settings.theme);
+        }
+        if self
+            .config_watcher
+            .as_ref()
+            .is_some_and(FileWatcher::has_changed)
+            && self.apply_config()
+        {
+            self.toasts
+                .push("Reloaded settings from the config file".to_owned());
+        }
+        self.sync_zoom(ctx);
+        self.handle_dropped_files(ctx);
+        let reload_delay = Duration::from_millis(self.settings.reload_delay_ms);
+        self.history.set_limits(
+            self.settings.history_versions,
+            self.settings.history_mib * 1024 * 1024,
+        );
+        // files in the other tabs keep loading and reloading too
+        let is_shown = std::iter::once(true).chain((0..self.tabs.len()).map(|_| false));
+        for (tab, is_shown) in std::iter::once(&mut self.tab)
+            .chain(&mut self.tabs)
+            .zip(is_shown)
+        {
+            for side in [Side::A, Side::B] {
+                let slot = match side {
+                    Side::A => &mut tab.a,
+                    Side::B => &mut tab.b,
+                };
+                slot.tick(ctx, reload_delay);
+                if let Some(file) = slot.take_newly_loaded() {
+                    self.history.record(file);
+                }
+                match slot.take_auto_reloaded() {
+                    Some(false) => self.toasts.push(format!("{side} couldn't be reloaded")),
+                    // the diff is computed again only once it's shown
+                    Some(true) if is_shown => tab.reloaded.push(side),
+                    Some(true) => self
+                        .toasts
+                        .push(format!("{side} reloaded in {}", tab.name())),
+                    None => {}
+                }
+            }
+        }
+
+        if let Some(expansion) = expansion_shortcut(ctx) {
+            self.set_expansion(expansion);
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&Self::OPEN_SHORTCUT)) {
+            self.pick_files();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&Self::NEW_TAB_SHORTCUT)) {
+            self.new_tab();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&Self::CLOSE_TAB_SHORTCUT)) {
+            self.close_tab(self.tab_index);
+        }
+        // text boxes undo what's typed in them themselves
+        if !ctx.wants_keyboard_input() {
+            let redo = ctx.input_mut(|i| {
+                // before plain undo, which would take it too
+                i.consume_shortcut(&Self::REDO_SHIFT_SHORTCUT)
+                    || i.consume_shortcut(&Self::REDO_SHORTCUT)
+            });
+            if redo {
+                self.undo_edit(ctx, true);
+            } else if ctx.input_mut(|i| i.consume_shortcut(&Self::UNDO_SHORTCUT)) {
+                self.undo_edit(ctx, false);
+            }
+        }
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| self.render_file_menu(ui));
+                ui.menu_button("View", |ui| self.render_view_menu(ui));
+                ui.menu_button("Diff", |ui| self.render_diff_menu(ui));
+            });
+        });
+        egui::TopBottomPanel::top("tab_bar").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| self.render_tab_bar(ui));
+        });
+        if self.tab.a.source.is_some() || self.tab.b.source.is_some() {
+            egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    self.render_go_to(ui);
+                    ui.separator();
+                    self.render_expansion_buttons(ui);
+                });
+            });
+        }
+
+        if self.tab.a.source.is_some() || self.tab.b.source.is_some() {
+            egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+                ui.horizontal(|ui| self.render_status_bar(ui));
+            });
+        }
+
+        if ctx.input_mut(|i| i.consume_shortcut(&Self::BOOKMARK_SHORTCUT)) {
+            self.bookmark_selected();
+        }
+        if self.show_bookmarks {
+            egui::SidePanel::left("bookmarks")
+                .resizable(true)
+                .default_width(180.0)
+                .show(ctx, |ui| self.render_bookmarks(ui));
+        }
+        if self.show_inspector {
+            egui::TopBottomPanel::bottom("inspector")
+                .resizable(true)
+                .default_height(200.0)
+                .show(ctx, |ui| self.render_inspector(ui));
+        }
+
+        let width = ctx.available_rect().width();
+        let request = [Side::A, Side::B]
+            .into_iter()
+            .filter_map(|side| Some((side, self.render_file_panel(ctx, side, width)?)))
+            .last();
+
+        match request {
+            Some((side, SlotRequest::Paste)) => self.paste_payload(side, ctx),
+            Some((_, SlotRequest::Snapshot)) => self.snapshot_a(),
+            Some((side, SlotRequest::SaveAs(format))) => self.save_as_text(side, format),
+            Some((side, SlotRequest::SaveEdits { pick_path })) => self.save_edits(side, pick_path),
+            Some((side, SlotRequest::Node(action))) => {
+                // the tree's paths are within the whole file, but the diff's are within its root
+                let slot = match side {
+                    Side::A => &self.tab.a,
+                    Side::B => &self.tab.b,
+                };
+                let action = match action {
+                    NodeAction::Ignore(path) => match slot.path_within_diff_root(&path) {
+                        Some(path) => Some(NodeAction::Ignore(path)),
+                        None => {
+                            warn!("Can't ignore {path}, as it's outside what's being compared");
+                            None
+                        }
+                    },
+                    NodeAction::Bookmark(path) => match slot.path_within_diff_root(&path) {
+                        Some(path) => Some(NodeAction::Bookmark(path)),
+                        None => {
+                            warn!("Can't bookmark {path}, as it's outside what's being compared");
+                            None
+                        }
+                    },
+                    NodeAction::Annotate(path) => match slot.path_within_diff_root(&path) {
+                        Some(path) => Some(NodeAction::Annotate(path)),
+                        None => {
+                            warn!("Can't note {path}, as it's outside what's being compared");
+                            None
+                        }
+                    },
+                    NodeAction::CompareSubtree(path) => {
+                        self.tab.a.compare_subtree(&path);
+                        self.tab.b.compare_subtree(&path);
+                        None
+                    }
+                    // the path only names the saved file, so it can be within the whole file
+                    action @ (NodeAction::DecodeNested | NodeAction::SaveValue { .. }) => {
+                        Some(action)
+                    }
+                    // the tree copies those itself, as it has the file at hand, and only the diff
+                    // takes values across
+                    NodeAction::CopyRawBytes { .. } | NodeAction::TakeAcross { .. } => None,
+                };
+                if let Some(action) = action {
+                    self.apply_node_action(ctx, action);
+                }
+            }
+            None => {}
+        }
+
+        if self.settings.sync_scrolling {
+            self.sync_scroll(ctx);
+        }
+        if self.settings.sync_expanding {
+            self.sync_tree_expansion(ctx);
+        }
+
+        if self.detached.diff {
+            let title = "Diff - MsgPack Differ".to_owned();
+            self.detached.diff =
+                show_detached(ctx, "diff", title, |ui| self.render_msg_pack_diff(ui));
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.weak("The diff is in a window of its own.");
+                if ui.button("Bring it back").clicked() {
+                    self.detached.diff = false;
+                }
+            });
+        } else {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                self.render_msg_pack_diff(ui);
+            });
+        }
+
+        self.render_settings_window(ctx);
+        self.render_note_window(ctx);
+        self.render_file_pairs_window(ctx);
+        if self.tab.size_view.open {
+            let files = [&self.tab.a, &self.tab.b]
+                .map(|slot| Some((slot.shown_file()?, slot.diff_root_path()?)));
+            self.tab.size_view.show(ctx, files);
+        }
+        if self.log_view.open {
+            self.log_view.show(ctx);
+        }
+        Self::render_drop_hint(ctx);
+        self.toasts.show(ctx);
+    }
+}
+
+impl MsgPackDifferApp {
+    fn new(cc: &eframe::CreationContext<'_>, sources: Vec<Source>) -> Self {
+        // the window's size and position are restored by eframe itself
+        let mut app = Self::default();
+        if let Some(storage) = cc.storage {
+            let session = eframe::get_value::<Self>(storage, eframe::APP_KEY);
+            if session.is_none() && storage.get_string(eframe::APP_KEY).is_some() {
+                warn!("Couldn't restore the last session, so starting a new one");
+            }
+            match session {
+                Some(session) if sources.is_empty() => app = session,
+                // files given on the command line start a new session rather than restoring the
+                // last one, but the settings carry over, as otherwise they'd be lost on exit
+                Some(session) => {
+                    app.settings = session.settings;
+                    app.last_directory = session.last_directory;
+                }
+                None => {}
+            }
+            app.recent_files =
+                eframe::get_value(storage, RecentFiles::STORAGE_KEY).unwrap_or_default();
+        }
+        if app.tab_index >= app.tabs.len() {
+            app.tabs = vec![Comparison::default()];
+            app.tab_index = 0;
+        }
+        for tab in std::iter::once(&mut app.tab).chain(&mut app.tabs) {
+            for slot in [&mut tab.a, &mut tab.b] {
+                // pasted data and snapshots aren't saved with the session, so there's nothing to
+                // restore
+                if matches!(
+                    slot.source,
+                    Some(Source::Pasted { .. } | Source::Snapshot { .. })
+                ) {
+                    *slot = FileSlot::default();
+                }
+            }
+        }
+        app.load_ext_types();
+        app.watch_config(&cc.egui_ctx);
+        app.apply_config();
+        if !sources.is_empty() {
+            app.open_sources(sources);
+        }
+        app
+    }
+
+    /// Starts watching the config file for changes, if its directory exists.
+    fn watch_config(&mut self, ctx: &egui::Context) {
+        let Some(path) = config::path() else {
+            return;
+        };
+        if !path.parent().is_some_and(Path::is_dir) {
+            return;
+        }
+        let repaint_ctx = ctx.clone();
+        match FileWatcher::new(&path, move || repaint_ctx.request_repaint()) {
+            Ok(watcher) => self.config_watcher = Some(watcher),
+            Err(err) => warn!("Could not watch {}: {err}", path.display()),
+        }
+    }
+
+    /// Replaces the settings with those in the config file, if there is one, returning whether that
+    /// changed any.
+    fn apply_config(&mut self) -> bool {
+        self.config_error = None;
+        let Some(path) = config::path().filter(|path| path.is_file()) else {
+            return false;
+        };
+        let settings = match config::apply(&path, &self.settings) {
+            Ok(settings) => settings,
+            Err(err) => {
+                warn!("Failed to load config from {}: {err}", path.display());
+                self.config_error = Some(err.to_string());
+                return false;
+            }
+        };
+        let as_json = |settings: &AppSettings| serde_json::to_value(settings).ok();
+        if as_json(&settings) == as_json(&self.settings) {
+            return false;
+        }
+        let ext_types_changed = settings.ext_types_file != self.settings.ext_types_file;
+        self.settings = settings;
+        self.extensions_input = None;
+        if ext_types_changed {
+            self.load_ext_types();
+        }
+        true
+    }
+
+    /// Writes the settings to the config file, so that they're used from then on.
+    fn save_config(&mut self, ctx: &egui::Context) {
+        let Some(path) = config::path() else {
+            return;
+        };
+        match config::save(&path, &self.settings) {
+            Ok(()) => {
+                self.config_error = None;
+                if self.config_watcher.is_none() {
+                    self.watch_config(ctx);
+                }
+                self.toasts
+                    .push(format!("Saved settings to {}", path.display()));
+            }
+            Err(err) => warn!("Failed to save config to {}: {err}", path.display()),
+        }
+    }
+
+    /// Starts decoding extension values as the extension types file in the settings says.
+    fn load_ext_types(&mut self) {
+        let registry = match &self.settings.ext_types_file {
+            Some(path) => match ExtRegistry::load(path) {
+                Ok(registry) => {
+                    self.ext_types_error = None;
+                    registry
+                }
+                Err(err) => {
+                    warn!(
+                        "Failed to load extension types from {}: {err}",
+                        path.display()
+                    );
+                    self.ext_types_error = Some(err.to_string());
+                    ExtRegistry::default()
+                }
+            },
+            None => {
+                self.ext_types_error = None;
+                ExtRegistry::default()
+            }
+        };
+        ext_types::set_registry(registry);
+        // values may compare differently now, in the tabs in the background too
+        for tab in std::iter::once(&mut self.tab).chain(&mut self.tabs) {
+            tab.diff = None;
+        }
+    }
+
+    /// Shows the panel of the file on `side`, at that side of the window or in a window of its own,
+    /// if there's a file there.
+    fn render_file_panel(
+        &mut self,
+        ctx: &egui::Context,
+        side: Side,
+        window_width: f32,
+    ) -> Option<SlotRequest> {
+        let (slot, detached, panel) = match side {
+            Side::A => (
+                &mut self.tab.a,
+                &mut self.detached.a,
+                egui::SidePanel::left("path_a"),
+            ),
+            Side::B => (
+                &mut self.tab.b,
+                &mut self.detached.b,
+                egui::SidePanel::right("path_b"),
+            ),
+        };
+        let title = format!(
+            "{side}: {} - MsgPack Differ",
+            slot.source.as_ref()?.short_name()
+        );
+        let mut request = None;
+        let selected = slot.tree.selected().map(<[usize]>::to_vec);
+        let contents = |ui: &mut egui::Ui| {
+            request = Self::render_msg_pack_file(
+                slot,
+                side,
+                &self.history,
+                &self.settings.display,
+                &self.tab.annotations,
+                ui,
+            );
+        };
+        if *detached {
+            *detached = show_detached(ctx, side, title, contents);
+        } else {
+            panel
+                .min_width(window_width / 4.0)
+                .resizable(true)
+                .show(ctx, contents);
+        }
+        if slot.tree.selected() != selected.as_deref() {
+            self.inspected = Some(side);
+        }
+        request
+    }
+
+    /// Shows the details of the value last selected in either tree, see [`inspector::show_inspector`].
+    fn render_inspector(&self, ui: &mut egui::Ui) {
+        let (slot, other_side, other) = match self.inspected {
+            Some(Side::A) => (&self.tab.a, Side::B, &self.tab.b),
+            Some(Side::B) => (&self.tab.b, Side::A, &self.tab.a),
+            None => (&self.tab.a, Side::B, &self.tab.b),
+        };
+        let (Some(file), Some(indexes)) = (slot.shown_file(), slot.tree.selected()) else {
+            ui.weak("Select a value in either file's tree to see its details here.");
+            return;
+        };
+        let other_name = other_side.to_string();
+        let other = other.shown_file().map(|file| (other_name.as_str(), file));
+        let display = DisplayOptions {
+            strings: slot.string_style,
+            ..self.settings.display.clone()
+        };
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                inspector::show_inspector(ui, file, indexes, other, &display);
+            });
+    }
+
+    fn render_msg_pack_file(
+        slot: &mut FileSlot,
+        side: Side,
+        history: &History,
+        display: &DisplayOptions,
+        notes: &Annotations,
+        ui: &mut egui::Ui,
+    ) -> Option<SlotRequest> {
+        enum Operation {
+            Reload,
+            Refetch,
+            Unload,
+            /// Show the given version rather than the latest one, or the latest one if `None`.
+            Pin(Option<usize>),
+            Edit(Edit),
+            /// Undo the latest edit, or redo the latest edit undone if set.
+            Undo {
+                redo: bool,
+            },
+            DiscardEdits,
+        }
+        let mut request = None;
+        let mut operation = None;
+        let can_snapshot = side == Side::A && slot.shown_file().is_some();
+        let can_save = slot.shown_file().is_some();
+        if let Some(source) = &slot.source {
+            ui.horizontal(|ui| {
+                ui.heading(source.short_name())
+                    .on_hover_text(source.to_string());
+                if slot.unedited.is_some() {
+                    ui.colored_label(ui.visuals().warn_fg_color, "✏ edited")
+                        .on_hover_text(
+                            "Values were edited by hand, and the edits aren't saved yet",
+                        );
+                    if source.as_path().is_some()
+                        && ui
+                            .button("Save")
+                            .on_hover_text("Write the edited file over the file, uncompressed")
+                            .clicked()
+                    {
+                        request = Some(SlotRequest::SaveEdits { pick_path: false });
+                    }
+                    if ui
+                        .button("Save edited as…")
+                        .on_hover_text("Write the edited file to a file of its own, uncompressed")
+                        .clicked()
+                    {
+                        request = Some(SlotRequest::SaveEdits { pick_path: true });
+                    }
+                    if ui.button("Discard edits").clicked() {
+                        operation = Some(Operation::DiscardEdits);
+                    }
+                }
+                if !slot.undo.is_empty()
+                    && accessibility::icon_button(ui, "⟲", "Undo the latest edit (Ctrl+Z)")
+                        .clicked()
+                {
+                    operation = Some(Operation::Undo { redo: false });
+                }
+                if !slot.redo.is_empty()
+                    && accessibility::icon_button(ui, "⟳", "Redo the latest edit undone (Ctrl+Y)")
+                        .clicked()
+                {
+                    operation = Some(Operation::Undo { redo: true });
+                }
+
+                if ui.button("Reload").clicked() {
+                    operation = Some(Operation::Reload);
+                } else if ui.button("X").clicked() {
+                    operation = Some(Operation::Unload);
+                }
+                if ui
+                    .button("Paste")
+                    .on_hover_text("Replace with a hex or base64 payload from the clipboard")
+                    .clicked()
+                {
+                    request = Some(SlotRequest::Paste);
+                }
+                if can_snapshot
+                    && ui
+                        .button("Snapshot")
+                        .on_hover_text(
+                            "Freeze this file as it is now, and compare it as B against the frozen \
+                             copy as A from now on",
+                        )
+                        .clicked()
+                {
+                    request = Some(SlotRequest::Snapshot);
+                }
+                if can_save {
+                    ui.menu_button("Save as", |ui| {
+                        for format in TextFormat::ALL {
+                            if ui
+                                .button(format!("{format}…"))
+                                .on_hover_text(
+                                    "Binary and extension values are converted as picked in the \
+                                     settings",
+                                )
+                                .clicked()
+                            {
+                                ui.close_menu();
+                                request = Some(SlotRequest::SaveAs(format));
+                            }
+                        }
+                    });
+                }
+                match source {
+                    Source::File(_) => {
+                        ui.checkbox(&mut slot.auto_reload, "Auto-reload")
+                            .on_hover_text("Reload automatically when the file changes on disk");
+                    }
+                    Source::Pasted { .. } | Source::Snapshot { .. } => {}
+                    Source::Url(_) => {
+                        if ui
+                            .button("Download again")
+                            .on_hover_text("Reload re-parses the bytes downloaded previously")
+                            .clicked()
+                        {
+                            operation = Some(Operation::Refetch);
+                        }
+                    }
+                }
+                if let Some(loading) = &slot.loading {
+                    ui.spinner().on_hover_text("Loading…");
+                    if ui.button("Cancel").clicked() {
+                        loading.progress().cancel();
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Framing:");
+                let framing = &mut slot.load_options.framing;
+                egui::ComboBox::from_id_salt("framing")
+                    .selected_text(framing.to_string())
+                    .show_ui(ui, |ui| {
+                        for option in Framing::ALL {
+                            if ui
+                                .selectable_value(framing, option, option.to_string())
+                                .changed()
+                            {
+                                operation = Some(Operation::Reload);
+                            }
+                        }
+                    });
+            });
+            ui.horizontal(|ui| {
+                let options = &mut slot.load_options;
+                ui.label("Skip:");
+                let skip = ui.add(egui::DragValue::new(&mut options.skip_bytes).suffix(" bytes"));
+                let scan = ui
+                    .checkbox(
+                        &mut options.scan_for_start,
+                        "then find where the msgpack starts",
+                    )
+                    .on_hover_text("For files with a header before the msgpack data");
+                // don't reload for every step of a drag
+                if (skip.changed() && !skip.dragged()) || skip.drag_stopped() || scan.changed() {
+                    operation = Some(Operation::Reload);
+                }
+            });
+            if ui
+                .checkbox(
+                    &mut slot.load_options.decode_nested,
+                    "Decode msgpack within binary values",
+                )
+                .on_hover_text("Show and compare binary values holding msgpack as what they hold")
+                .changed()
+            {
+                operation = Some(Operation::Reload);
+            }
+        } else {
+            ui.heading(format!("File {side}"));
+        }
+        if let Some(loading) = &slot.loading {
+            render_load_progress(ui, loading.progress());
+        }
+        let shown = match (&slot.pinned, &slot.loaded) {
+            (Some(version), _) => Some(Ok(&version.file)),
+            (None, Some(Ok(file))) => Some(Ok(file)),
+            (None, Some(Err(err))) => Some(Err(err)),
+            (None, None) => None,
+        };
+        match shown {
+            Some(Ok(file)) => {
+                ui.label(format!(
+                    "Loaded at: {}",
+                    file.load_time.strftime("%Y-%m-%d %H:%M:%S")
+                ));
+                let versions = history.versions(&file.source).collect::<Vec<_>>();
+                if versions.len() > 1 {
+                    ui.horizontal(|ui| {
+                        ui.label("Version:");
+                        let describe = |version: &Version| {
+                            format!(
+                                "#{} loaded at {}",
+                                version.number,
+                                version.file.load_time.strftime("%H:%M:%S")
+                            )
+                        };
+                        let pinned_number = slot.pinned.as_ref().map(|version| version.number);
+                        let selected = match &slot.pinned {
+                            Some(version) => describe(version),
+                            None => "latest".to_owned(),
+                        };
+                        egui::ComboBox::from_id_salt("version")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                if ui
+                                    .selectable_label(pinned_number.is_none(), "latest")
+                                    .clicked()
+                                {
+                                    operation = Some(Operation::Pin(None));
+                                }
+                                for version in versions.iter().rev() {
+                                    let is_pinned = pinned_number == Some(version.number);
+                                    if ui.selectable_label(is_pinned, describe(version)).clicked() {
+                                        operation = Some(Operation::Pin(Some(version.number)));
+                                    }
+                                }
+                            });
+                    });
+                }
+                if file.data.is_mapped() {
+                    ui.weak("(memory-mapped)").on_hover_text(
+                        "The file is large, so it was mapped into memory rather than read",
+                    );
+                }
+                match file.compression {
+                    Some((compression, file_size)) => ui.label(format!(
+                        "{file_size} bytes {compression} → {} bytes, crc32={:08x}",
+                        file.data.len(),
+                        file.crc32.result,
+                    )),
+                    None => ui.label(format!(
+                        "{} bytes, crc32={:08x}",
+                        file.data.len(),
+                        file.crc32.result,
+                    )),
+                };
+                if file.payload_offset > 0 {
+                    ui.label(format!("msgpack starts at byte {}", file.payload_offset));
+                }
+                if let Some(failure) = &file.decode_failure {
+                    let decoded = match file.value_count {
+                        1 => "What was decoded before it is shown below.".to_owned(),
+                        n => format!("The {n} values decoded before it are shown below."),
+                    };
+                    let color = ui.visuals().warn_fg_color;
+                    let heading = "Only partially decoded";
+                    render_decode_failure(ui, heading, color, failure, &file.data, &decoded);
+                }
+                let record_root = select_record(file, slot.record);
+                ui.horizontal(|ui| {
+                    ui.label("Compare subtree:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut slot.subtree)
+                            .hint_text("(root)")
+                            .desired_width(160.0),
+                    )
+                    .on_hover_text("Path to diff instead of the whole value, e.g. snapshots[0]");
+                    if let Some(err) =
+                        record_root.and_then(|root| subtree_error(root, &slot.subtree))
+                    {
+                        ui.colored_label(ui.visuals().error_fg_color, err);
+                    }
+                });
+                if file.is_multi_value() {
+                    match file.options.framing {
+                        Framing::Concatenated => {
+                            ui.label(format!("{} concatenated values", file.value_count))
+                        }
+                        framing => ui.label(format!("{} {framing} messages", file.value_count)),
+                    };
+                    ui.horizontal(|ui| {
+                        ui.label("Compare:");
+                        if ui.radio(slot.record.is_none(), "all values").clicked() {
+                            slot.record = None;
+                        }
+                        let mut record = slot.record.unwrap_or(0).min(file.value_count - 1);
+                        if ui.radio(slot.record.is_some(), "value").clicked() {
+                            slot.record = Some(record);
+                        }
+                        ui.add_enabled(
+                            slot.record.is_some(),
+                            egui::DragValue::new(&mut record).range(0..=file.value_count - 1),
+                        );
+                        // keeps the selection in range if the file shrank on reload
+                        if slot.record.is_some() {
+                            slot.record = Some(record);
+                        }
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Strings:");
+                    slot.string_style.show_toggles(ui, true);
+                    ui.separator();
+                    ui.checkbox(&mut slot.show_hex, "Hex dump").on_hover_text(
+                        "Show the file's bytes, with those of the selected value highlighted",
+                    );
+                });
+                if slot.show_hex {
+                    egui::TopBottomPanel::bottom(ui.id().with("hex dump"))
+                        .resizable(true)
+                        .default_height(200.0)
+                        .show_inside(ui, |ui| {
+                            let selected = slot.tree.selected();
+                            if let Some(indexes) = slot.hex_view.show(ui, file, selected) {
+                                slot.tree.reveal(indexes);
+                            }
+                        });
+                }
+                let display = DisplayOptions {
+                    strings: slot.string_style,
+                    ..display.clone()
+                };
+                let notes_root = slot.diff_root_path();
+                if let Some(action) = slot
+                    .tree
+                    .show(ui, file, &display, notes, notes_root.as_ref())
+                {
+                    request = Some(SlotRequest::Node(action));
+                }
+                if let Some(edit) = slot.tree.take_edit() {
+                    operation = Some(Operation::Edit(edit));
+                }
+            }
+            Some(Err(LoadError::DecodeError { details, .. })) => {
+                let color = ui.visuals().error_fg_color;
+                let heading = "Nothing could be decoded";
+                let decoded = "Not even the start of the first value could be made sense of.";
+                render_decode_failure(ui, heading, color, &details.failure, &details.data, decoded);
+            }
+            Some(Err(err)) => {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("Error loading file: {err}"),
+                );
+                // some are down to how the file is loaded, which its load options change
+                let hint = match err {
+                    LoadError::SkippedPastEnd { .. } => Some("Try skipping fewer bytes."),
+                    LoadError::NoMsgpackFound { .. } => {
+                        Some("Try skipping to where the msgpack starts by hand instead.")
+                    }
+                    LoadError::NotRpcMessage { .. } => Some("Try another framing."),
+                    LoadError::UnsupportedFormat { .. } => {
+                        Some("Try extracting or decompressing it first.")
+                    }
+                    _ => None,
+                };
+                if let Some(hint) = hint {
+                    ui.weak(hint);
+                }
+            }
+            None => {}
+        }
+        if let Some(operation) = operation {
+            match operation {
+                Operation::Reload => {
+                    slot.reload(ui.ctx());
+                }
+                Operation::Refetch => {
+                    slot.refetch(ui.ctx());
+                }
+                Operation::Unload => {
+                    slot.source = None;
+                }
+                Operation::Pin(number) => {
+                    slot.pinned = number.and_then(|number| {
+                        let source = slot.source.as_ref()?;
+                        history.version(source, number).cloned()
+                    });
+                }
+                Operation::Edit(edit) => slot.edit(ui.ctx(), edit),
+                Operation::Undo { redo } => slot.undo(ui.ctx(), redo),
+                Operation::DiscardEdits => slot.discard_edits(ui.ctx()),
+            }
+        }
+        request
+    }
+
+    /// Replaces A with a frozen copy of it, moving the live file to B.
+    fn snapshot_a(&mut self) {
+        let Some(file) = self.tab.a.shown_file() else {
+            return;
+        };
+        let name = format!(
+            "{} as of {}",
+            file.source.short_name(),
+            file.load_time.strftime("%H:%M:%S")
+        );
+        let source = Source::Snapshot { name };
+        let snapshot = FileSlot {
+            source: Some(source.clone()),
+            loaded: Some(Ok(LoadedFile {
+                source,
+                ..file.clone()
+            })),
+            load_options: self.tab.a.load_options.clone(),
+            record: self.tab.a.record,
+            subtree: self.tab.a.subtree.clone(),
+            ..FileSlot::default()
+        };
+        self.tab.b = std::mem::replace(&mut self.tab.a, snapshot);
+        self.tab.diff = None;
+    }
+
+    /// Swaps the files in A and B along with how each is loaded and shown, so that the diff is
+    /// from what was in B to what was in A.
+    fn swap_sides(&mut self) {
+        std::mem::swap(&mut self.tab.a, &mut self.tab.b);
+        self.tab.diff = None;
+    }
+
+    /// Loads a hex or base64 payload from the clipboard into `side`, as if it were a file.
+    fn paste_payload(&mut self, side: Side, ctx: &egui::Context) {
+        let pasted = arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .map_err(|err| format!("Could not read the clipboard: {err}"))
+            .and_then(|text| decode_payload(&text));
+        let (bytes, encoding) = match pasted {
+            Ok(pasted) => pasted,
+            Err(err) => {
+                self.paste_error = Some(err);
+                return;
+            }
+        };
+        self.paste_error = None;
+        self.paste_count += 1;
+        let name = format!("Pasted {encoding} #{}", self.paste_count);
+        self.open_source(side, Source::Pasted { name });
+        let slot = match side {
+            Side::A => &mut self.tab.a,
+            Side::B => &mut self.tab.b,
+        };
+        slot.start_loading(ctx, Some(FileBytes::from(bytes)));
+    }
+
+    fn render_msg_pack_diff(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Diff");
+        if let Some(err) = &self.paste_error {
+            let mut dismissed = false;
+            ui.horizontal(|ui| {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+                dismissed = ui.small_button("OK").clicked();
+            });
+            if dismissed {
+                self.paste_error = None;
+            }
+        }
+        if self.tab.a.source.is_none() && self.tab.b.source.is_none() {
+            self.render_welcome(ui);
+            return;
+        }
+        let prompt = if self.tab.a.source.is_none() {
+            Some("Select file A to compare with file B")
+        } else if self.tab.b.source.is_none() {
+            Some("Select file B to compare with file A")
+        } else {
+            None
+        };
+        if let Some(prompt) = prompt {
+            ui.label(prompt);
+            ui.horizontal(|ui| {
+                if ui.button("Open file(s)…").clicked() {
+                    self.pick_files();
+                }
+                if ui.button("Settings…").clicked() {
+                    self.settings_open = true;
+                }
+            });
+            ui.label("…or drag and drop them onto this window.");
+            ui.horizontal(|ui| self.render_url_input(ui));
+            if ui
+                .button("Paste payload")
+                .on_hover_text("Load a hex or base64 payload from the clipboard")
+                .clicked()
+            {
+                let side = if self.tab.a.source.is_none() {
+                    Side::A
+                } else {
+                    Side::B
+                };
+                self.paste_payload(side, ui.ctx());
+            }
+            let loaded_source = self
+                .tab
+                .a
+                .source
+                .as_ref()
+                .or(self.tab.b.source.as_ref())
+                .cloned();
+            if let Some(source) = loaded_source {
+                if ui
+                    .button("Compare with itself")
+                    .on_hover_text(
+                        "Open the same file on the other side too, to compare subtrees of it",
+                    )
+                    .clicked()
+                {
+                    self.open_sources(vec![source]);
+                }
+            }
+            let mut picked_path = None;
+            ui.menu_button("Open recent", |ui| {
+                picked_path = self.render_recent_files(ui);
+                if picked_path.is_some() {
+                    ui.close_menu();
+                }
+            });
+            if let Some(path) = picked_path {
+                self.open_paths(vec![path]);
+            }
+            return;
+        }
+
+        let (Some(inputs_a), Some(inputs_b)) = (self.tab.a.diff_inputs(), self.tab.b.diff_inputs())
+        else {
+            ui.label("Both files need to load successfully before they can be compared.");
+            return;
+        };
+        let subtree_errors = [
+            ("A", self.tab.a.subtree_error()),
+            ("B", self.tab.b.subtree_error()),
+        ];
+        let mut subtrees_valid = true;
+        for (label, err) in subtree_errors {
+            if let Some(err) = err {
+                ui.label(format!("Can't compare subtree of file {label}: {err}"));
+                subtrees_valid = false;
+            }
+        }
+        if !subtrees_valid {
+            return;
+        }
+        let mut unignored = None;
+        if !self.tab.ignored_paths.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Ignoring:");
+                for (i, path) in self.tab.ignored_paths.iter().enumerate() {
+                    ui.monospace(path);
+                    if accessibility::icon_button(ui, "✕", "Compare it again").clicked() {
+                        unignored = Some(i);
+                    }
+                }
+            });
+        }
+        if let Some(i) = unignored {
+            self.tab.ignored_paths.remove(i);
+        }
+
+        let inputs = (inputs_a, inputs_b);
+        let (a, b) = (self.tab.a.diff_root(), self.tab.b.diff_root());
+        if self.tab.diff.as_ref().is_none_or(|diff| {
+            diff.inputs != inputs || diff.ignored_paths != self.tab.ignored_paths
+        }) {
+            let ignored = self
+                .tab
+                .ignored_paths
+                .iter()
+                .filter_map(|path| path.parse().ok())
+                .collect::<Vec<_>>();
+            self.diff_count += 1;
+            let previous_count = self
+                .tab
+                .diff
+                .as_ref()
+                .map(|diff| diff.root.difference_count);
+            let started = Instant::now();
+            let options = DiffOptions {
+                ignored: &ignored,
+                ext_types: &ext_types::registry(),
+            };
+            let root = diff_values_with(a, b, &options);
+            let count = root.difference_count;
+            self.tab.diff = Some(CachedDiff {
+                inputs,
+                ignored_paths: self.tab.ignored_paths.clone(),
+                root,
+                revision: self.diff_count,
+                duration: started.elapsed(),
+            });
+            let reloaded = std::mem::take(&mut self.tab.reloaded);
+            if let Some(&side) = reloaded.first() {
+                let both = reloaded.contains(&Side::A) && reloaded.contains(&Side::B);
+                self.toasts
+                    .push(reload_note(side, both, previous_count, count));
+            }
+        }
+        let diff = self.tab.diff.as_ref().expect("just computed");
+        let root = DiffEntry {
+            node: &diff.root,
+            a,
+            b,
+        };
+        let describe_span = |ctx: &egui::Context, in_b: bool, path: &ValuePath| {
+            let slot = if in_b { &self.tab.b } else { &self.tab.a };
+            let file = slot.shown_file()?;
+            let indexes = slot.path_from_file_root(path)?.indexes(&file.parsed)?;
+            Some(describe_span(ctx, file, &indexes))
+        };
+        let response = self.tab.diff_view.show(
+            ui,
+            root,
+            diff.revision,
+            &self.settings.display,
+            &describe_span,
+            &self.tab.annotations,
+        );
+        if let Some(path) = response.navigated {
+            self.tab.a.go_to(&path);
+            self.tab.b.go_to(&path);
+        }
+        if let Some((path, expanded)) = response.toggled.filter(|_| self.settings.sync_expanding) {
+            for slot in [&mut self.tab.a, &mut self.tab.b] {
+                if let Some(full_path) = slot.path_from_file_root(&path) {
+                    slot.set_expanded(&full_path, expanded);
+                }
+            }
+        }
+        if let Some(action) = response.action {
+            self.apply_node_action(&ui.ctx().clone(), action);
+        }
+    }
+
+    /// Shows what can be done when nothing is open: the ways of opening files, and those that were
+    /// opened recently.
+    fn render_welcome(&mut self, ui: &mut egui::Ui) {
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() * 0.1);
+            ui.heading("Compare two msgpack files");
+            ui.label(
+                "Open them as A and B, or drag and drop them onto this window. Dropping a \
+                 directory, or two, pairs up the files in them.",
+            );
+        });
+        ui.add_space(16.0);
+        let mut picked = None;
+        ui.columns(2, |columns| {
+            let ui = &mut columns[0];
+            ui.strong("Open");
+            let open = egui::Button::new("Open file(s)…")
+                .shortcut_text(ui.ctx().format_shortcut(&Self::OPEN_SHORTCUT));
+            if ui.add(open).clicked() {
+                self.pick_files();
+            }
+            ui.horizontal(|ui| self.render_url_input(ui));
+            if ui
+                .button("Paste payload")
+                .on_hover_text("Load a hex or base64 payload from the clipboard as A")
+                .clicked()
+            {
+                self.paste_payload(Side::A, ui.ctx());
+            }
+            ui.add_space(8.0);
+            if ui.button("Settings…").clicked() {
+                self.settings_open = true;
+            }
+
+            let ui = &mut columns[1];
+            ui.strong("Recent comparisons");
+            for (a, b) in self.recent_files.pairs() {
+                let name = |path: &Path| {
+                    let name = path.file_name().unwrap_or(path.as_os_str());
+                    name.to_string_lossy().into_owned()
+                };
+                if ui
+                    .link(format!("{} ↔ {}", name(a), name(b)))
+                    .on_hover_text(format!("A: {}\nB: {}", a.display(), b.display()))
+                    .clicked()
+                {
+                    picked = Some(vec![a.to_owned(), b.to_owned()]);
+                }
+            }
+            if self.recent_files.pairs().next().is_none() {
+                ui.weak("No recent comparisons");
+            } else if ui.small_button("Clear list").clicked() {
+                self.recent_files.clear_pairs();
+            }
+            ui.add_space(8.0);
+            ui.strong("Recent files");
+            if let Some(path) = self.render_recent_files(ui) {
+                picked = Some(vec![path]);
+            }
+        });
+        if let Some(paths) = picked {
+            self.open_paths(paths);
+        }
+    }
+
+    /// Shows a box for a URL to download a file from, and a button to open it.
+    fn render_url_input(&mut self, ui: &mut egui::Ui) {
+        let url_input = ui.add(
+            egui::TextEdit::singleline(&mut self.url_input)
+                .hint_text("https://example.com/file.msgpack"),
+        );
+        let submitted = url_input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        let url = self.url_input.trim();
+        let valid = url.starts_with("http://") || url.starts_with("https://");
+        if (ui
+            .add_enabled(valid, egui::Button::new("Open URL"))
+            .clicked()
+            || submitted)
+            && valid
+        {
+            let url = std::mem::take(&mut self.url_input);
+            self.open_sources(vec![Source::Url(url.trim().to_owned())]);
+        }
+    }
+
+    /// Carries out an action picked for the node at the path within the diff's root.
+    fn apply_node_action(&mut self, ctx: &egui::Context, action: NodeAction) {
+        match action {
+            NodeAction::Ignore(path) => {
+                let path = path.to_string();
+                if !self.tab.ignored_paths.contains(&path) {
+                    self.tab.ignored_paths.push(path);
+                }
+            }
+            NodeAction::Bookmark(path) => self.add_bookmark(&path),
+            NodeAction::Annotate(path) => {
+                let note = self.tab.annotations.get(&path).unwrap_or_default();
+                self.annotating = Some((path.to_string(), note.to_owned()));
+            }
+            NodeAction::CompareSubtree(path) => {
+                for slot in [&mut self.tab.a, &mut self.tab.b] {
+                    if let Some(full_path) = slot.path_from_file_root(&path) {
+                        slot.compare_subtree(&full_path);
+                    }
+                }
+            }
+            NodeAction::DecodeNested => {
+                for slot in [&mut self.tab.a, &mut self.tab.b] {
+                    if !slot.load_options.decode_nested {
+                        slot.load_options.decode_nested = true;
+                        slot.reload(ctx);
+                    }
+                }
+            }
+            NodeAction::CopyRawBytes { in_b, path, format } => {
+                let slot = if in_b { &self.tab.b } else { &self.tab.a };
+                match slot.raw_bytes(&path) {
+                    Some(bytes) => ctx.copy_text(format.encode(bytes)),
+                    None => warn!("Can't find the bytes of {path}, as decoding failed within it"),
+                }
+            }
+            NodeAction::SaveValue { value, path } => self.save_value(&value, &path),
+            NodeAction::TakeAcross { to_b, edit } => {
+                let slot = if to_b {
+                    &mut self.tab.b
+                } else {
+                    &mut self.tab.a
+                };
+                let root = slot
+                    .loaded_file()
+                    .zip(slot.diff_root_path())
+                    .and_then(|(file, path)| path.indexes(&file.parsed));
+                match root {
+                    Some(root) => slot.edit(ctx, edit.within(&root)),
+                    None => warn!("Can't find what's compared in the file to change"),
+                }
+            }
+        }
+    }
+
+    /// Writes the edited file in `side` over the file, or to a file picked if `pick_path` is set (or
+    /// there's no file to write over), which it's then the file of.
+    fn save_edits(&mut self, side: Side, pick_path: bool) {
+        let slot = match side {
+            Side::A => &self.tab.a,
+            Side::B => &self.tab.b,
+        };
+        let Some(file) = slot.loaded_file() else {
+            return;
+        };
+        let path = match slot.source.as_ref().and_then(Source::as_path) {
+            Some(path) if !pick_path => path.to_owned(),
+            _ => {
+                let Some(path) = self
+                    .file_dialog()
+                    .set_file_name(file.source.short_name())
+                    .save_file()
+                else {
+                    return;
+                };
+                path
+            }
+        };
+        if let Err(err) = std::fs::write(&path, &*file.raw) {
+            warn!("Can't save the edited file to {}: {err}", path.display());
+            return;
+        }
+        self.toasts
+            .push(format!("Saved the edited file to {}", path.display()));
+        let slot = match side {
+            Side::A => &mut self.tab.a,
+            Side::B => &mut self.tab.b,
+        };
+        let source = Source::File(path);
+        if slot.source.as_ref() == Some(&source) {
+            slot.unedited = None;
+        } else {
+            // which is then read back in as the file saved
+            self.open_source(side, source);
+        }
+    }
+
+    /// Saves the value shown for `side` converted to JSON or YAML, see [`msgpack_to_json_with`].
+    fn save_as_text(&mut self, side: Side, format: TextFormat) {
+        let slot = match side {
+            Side::A => &self.tab.a,
+            Side::B => &self.tab.b,
+        };
+        let Some(file) = slot.shown_file() else {
+            return;
+        };
+        let name = file.source.short_name();
+        let stem = Path::new(&name)
+            .file_stem()
+            .map_or(name.clone(), |stem| stem.to_string_lossy().into_owned());
+        let extension = format.extension();
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(format.to_string(), &[extension])
+            .set_file_name(format!("{stem}.{extension}"))
+            .save_file()
+        else {
+            return;
+        };
+        let json = msgpack_to_json_with(
+            &file.parsed,
+            self.settings.json_conversion,
+            &ext_types::registry(),
+        );
+        let text = match format {
+            TextFormat::Json => {
+                serde_json::to_string_pretty(&json).expect("JSON values always serialize")
+            }
+            TextFormat::Yaml => yaml::to_yaml(&json),
+        };
+        match std::fs::write(&path, text) {
+            Ok(()) => self
+                .toasts
+                .push(format!("Saved {name} as {format} to {}", path.display())),
+            Err(err) => warn!("Can't save {name} to {}: {err}", path.display()),
+        }
+    }
+
+    /// Saves `value` as a msgpack file of its own, encoded afresh, offering to name it after the
+    /// last part of `path`.
+    fn save_value(&mut self, value: &rmpv::Value, path: &ValuePath) {
+        let stem = match path.segments().last() {
+            Some(PathSegment::Key(key)) => json_key(key)
+                .chars()
+                .map(|c| match c.is_alphanumeric() || "-_.".contains(c) {
+                    true => c,
+                    false => '_',
+                })
+                .collect(),
+            Some(PathSegment::Index(i)) => format!("item {i}"),
+            None => "value".to_owned(),
+        };
+        let Some(file) = self
+            .file_dialog()
+            .set_file_name(format!("{stem}.msgpack"))
+            .save_file()
+        else {
+            return;
+        };
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, value).expect("writing to a Vec can't fail");
+        match std::fs::write(&file, bytes) {
+            Ok(()) => self
+                .toasts
+                .push(format!("Saved {path} to {}", file.display())),
+            Err(err) => warn!("Can't save {path} to {}: {err}", file.display()),
+        }
+    }
+
+    const OPEN_SHORTCUT: egui::KeyboardShortcut =
+        egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::O);
+    const BOOKMARK_SHORTCUT: egui::KeyboardShortcut =
+        egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::D);
+    const NEW_TAB_SHORTCUT: egui::KeyboardShortcut =
+        egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::T);
+    const CLOSE_TAB_SHORTCUT: egui::KeyboardShortcut =
+        egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::W);
+    const UNDO_SHORTCUT: egui::KeyboardShortcut =
+        egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+    const REDO_SHORTCUT: egui::KeyboardShortcut =
+        egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+    const REDO_SHIFT_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+        egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+        egui::Key::Z,
+    );
+
+    /// Undoes the latest edit made to either file of the tab, or redoes the latest one undone if
+    /// `redo` is set.
+    fn undo_edit(&mut self, ctx: &egui::Context, redo: bool) {
+        let latest = [&mut self.tab.a, &mut self.tab.b]
+            .into_iter()
+            .filter(|slot| slot.latest_step(redo).is_some())
+            .max_by_key(|slot| slot.latest_step(redo));
+        if let Some(slot) = latest {
+            slot.undo(ctx, redo);
+        }
+    }
+
+    fn render_tab_bar(&mut self, ui: &mut egui::Ui) {
+        let mut picked = None;
+        let mut closed = None;
+        for i in 0..self.tabs.len() {
+            let is_shown = i == self.tab_index;
+            let tab = if is_shown { &self.tab } else { &self.tabs[i] };
+            if ui.selectable_label(is_shown, tab.name()).clicked() {
+                picked = Some(i);
+            }
+            if self.tabs.len() > 1 && accessibility::icon_button(ui, "✕", "Close the tab").clicked()
+            {
+                closed = Some(i);
+            }
+            ui.separator();
+        }
+        let shortcut = ui.ctx().format_shortcut(&Self::NEW_TAB_SHORTCUT);
+        let new_tab =
+            accessibility::icon_button(ui, "+", format!("New tab ({shortcut})")).clicked();
+        if let Some(i) = picked {
+            self.switch_tab(i);
+        }
+        if let Some(i) = closed {
+            self.close_tab(i);
+        }
+        if new_tab {
+            self.new_tab();
+        }
+    }
+
+    fn switch_tab(&mut self, index: usize) {
+        std::mem::swap(&mut self.tab, &mut self.tabs[self.tab_index]);
+        std::mem::swap(&mut self.tab, &mut self.tabs[index]);
+        self.tab_index = index;
+    }
+
+    fn new_tab(&mut self) {
+        self.tabs.push(Comparison::default());
+        self.switch_tab(self.tabs.len() - 1);
+    }
+
+    /// Closes the tab at `index`, or just empties it if it's the only one.
+    fn close_tab(&mut self, index: usize) {
+        if self.tabs.len() == 1 {
+            self.tab = Comparison::default();
+            return;
+        }
+        if index == self.tab_index {
+            // the one after it is shown instead, or the one before if it's the last
+            self.switch_tab(if index + 1 < self.tabs.len() {
+                index + 1
+            } else {
+                index - 1
+            });
+        }
+        self.tabs.remove(index);
+        if self.tab_index > index {
+            self.tab_index -= 1;
+        }
+    }
+
+    /// Asks for files to open with the file dialog, and opens them.
+    fn pick_files(&mut self) {
+        if let Some(picked_paths) = self.file_dialog().pick_files() {
+            self.last_directory = picked_paths
+                .first()
+                .and_then(|path| path.parent())
+                .map(Path::to_owned);
+            self.open_paths(picked_paths);
+        }
+    }
+
+    fn render_file_menu(&mut self, ui: &mut egui::Ui) {
+        let open = egui::Button::new("Open file(s)…")
+            .shortcut_text(ui.ctx().format_shortcut(&Self::OPEN_SHORTCUT));
+        if ui.add(open).clicked() {
+            ui.close_menu();
+            self.pick_files();
+        }
+        ui.menu_button("Open recent", |ui| {
+            if let Some(path) = self.render_recent_files(ui) {
+                ui.close_menu();
+                self.open_paths(vec![path]);
+            }
+        });
+        for side in [Side::A, Side::B] {
+            if ui
+                .button(format!("Paste payload as {side}"))
+                .on_hover_text("Load a hex or base64 payload from the clipboard")
+                .clicked()
+            {
+                ui.close_menu();
+                self.paste_payload(side, ui.ctx());
+            }
+        }
+        ui.separator();
+        for (side, slot) in [(Side::A, &mut self.tab.a), (Side::B, &mut self.tab.b)] {
+            let is_open = slot.source.is_some();
+            if ui
+                .add_enabled(is_open, egui::Button::new(format!("Reload {side}")))
+                .clicked()
+            {
+                ui.close_menu();
+                slot.reload(ui.ctx());
+            }
+            if ui
+                .add_enabled(is_open, egui::Button::new(format!("Close {side}")))
+                .clicked()
+            {
+                ui.close_menu();
+                slot.source = None;
+            }
+        }
+        ui.separator();
+        let can_export = self.tab.diff.is_some()
+            && self.tab.a.shown_file().is_some()
+            && self.tab.b.shown_file().is_some();
+        ui.add_enabled_ui(can_export, |ui| {
+            ui.menu_button("Export diff", |ui| {
+                for export in DiffExport::ALL {
+                    if ui
+                        .button(export.label())
+                        .on_hover_text(export.description())
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        self.export_diff(export);
+                    }
+                }
+            });
+        });
+        ui.separator();
+        if ui
+            .button("Open project…")
+            .on_hover_text("Open a comparison saved as a project in a tab of its own")
+            .clicked()
+        {
+            ui.close_menu();
+            self.open_project(ui.ctx());
+        }
+        let can_save = self.tab.a.source.is_some() || self.tab.b.source.is_some();
+        ui.add_enabled_ui(can_save, |ui| {
+            if ui
+                .button("Save project…")
+                .on_hover_text(
+                    "Save which files are compared and how, along with what's ignored, \
+                     bookmarked, noted and expanded, for someone else to open",
+                )
+                .clicked()
+            {
+                ui.close_menu();
+                self.save_project(false);
+            }
+            if ui
+                .button("Save project with files embedded…")
+                .on_hover_text(
+                    "Like saving a project, but with the files as they are now saved in it, so \
+                     that it can be opened where they aren't",
+                )
+                .clicked()
+            {
+                ui.close_menu();
+                self.save_project(true);
+            }
+        });
+        ui.separator();
+        let new_tab = egui::Button::new("New tab")
+            .shortcut_text(ui.ctx().format_shortcut(&Self::NEW_TAB_SHORTCUT));
+        if ui.add(new_tab).clicked() {
+            ui.close_menu();
+            self.new_tab();
+        }
+        let close_tab = egui::Button::new("Close tab")
+            .shortcut_text(ui.ctx().format_shortcut(&Self::CLOSE_TAB_SHORTCUT));
+        if ui.add(close_tab).clicked() {
+            ui.close_menu();
+            self.close_tab(self.tab_index);
+        }
+        ui.separator();
+        if ui.button("Settings…").clicked() {
+            ui.close_menu();
+            self.settings_open = true;
+        }
+        if ui.button("Quit").clicked() {
+            ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+
+    fn render_view_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Theme", |ui| self.render_theme_options(ui));
+        ui.menu_button("Zoom", egui::gui_zoom::zoom_menu_buttons);
+        ui.separator();
+        ui.menu_button("Integers", |ui| {
+            for option in IntegerBase::ALL {
+                ui.radio_value(
+                    &mut self.settings.display.integer_base,
+                    option,
+                    option.to_string(),
+                );
+            }
+            ui.separator();
+            ui.checkbox(
+                &mut self.settings.display.thousands_separators,
+                "Thousands separators",
+            );
+        });
+        ui.menu_button("Floats", |ui| {
+            for option in FloatNotation::ALL {
+                ui.radio_value(
+                    &mut self.settings.display.float_notation,
+                    option,
+                    option.to_string(),
+                );
+            }
+        });
+        ui.menu_button("Binary values", |ui| {
+            for option in BinaryFormat::ALL {
+                ui.radio_value(
+                    &mut self.settings.display.binary_format,
+                    option,
+                    option.to_string(),
+                );
+            }
+        })
+        .response
+        .on_hover_text("How to show binary values, unless picked for one in particular");
+        ui.checkbox(&mut self.settings.display.sort_keys, "Sort keys")
+            .on_hover_text("Show map entries in the order of their keys rather than as encoded");
+        ui.separator();
+        ui.checkbox(&mut self.settings.sync_scrolling, "Sync scrolling")
+            .on_hover_text("Scroll each file's tree to where the other was scrolled to");
+        ui.checkbox(&mut self.settings.sync_expanding, "Sync expanding")
+            .on_hover_text(
+                "Expand or collapse the same thing in the other file's tree and the diff",
+            );
+        ui.checkbox(&mut self.show_bookmarks, "Bookmarks")
+            .on_hover_text("List the values bookmarked to come back to (Ctrl+D)");
+        ui.checkbox(&mut self.show_inspector, "Inspector")
+            .on_hover_text("Show the details of the value selected in either file's tree");
+        ui.separator();
+        ui.checkbox(&mut self.detached.a, "A in its own window");
+        ui.checkbox(&mut self.detached.b, "B in its own window");
+        ui.checkbox(&mut self.detached.diff, "Diff in its own window");
+        ui.separator();
+        if ui
+            .button("Sizes…")
+            .on_hover_text("Which parts of the files take up the most bytes")
+            .clicked()
+        {
+            ui.close_menu();
+            self.tab.size_view.open = true;
+        }
+        if ui
+            .button("Log…")
+            .on_hover_text("Warnings and other messages")
+            .clicked()
+        {
+            ui.close_menu();
+            self.log_view.open = true;
+        }
+    }
+
+    /// Zooms in or out as the settings say, or updates them if zoomed with the keyboard, which egui
+    /// does itself at the end of each frame.
+    fn sync_zoom(&mut self, ctx: &egui::Context) {
+        let zoom = ctx.zoom_factor();
+        if self.applied_zoom.is_some_and(|applied| applied != zoom) {
+            self.settings.zoom = zoom;
+        } else if zoom != self.settings.zoom {
+            ctx.set_zoom_factor(self.settings.zoom);
+        }
+        self.applied_zoom = Some(self.settings.zoom);
+    }
+
+    fn render_theme_options(&mut self, ui: &mut egui::Ui) {
+        for (theme, name) in AppSettings::THEMES {
+            ui.radio_value(&mut self.settings.theme, theme, name);
+        }
+    }
+
+    fn render_diff_menu(&mut self, ui: &mut egui::Ui) {
+        if ui
+            .button("⇄ Swap A and B")
+            .on_hover_text("Diff from what's in B to what's in A instead")
+            .clicked()
+        {
+            ui.close_menu();
+            self.swap_sides();
+        }
+        ui.separator();
+        let view = &mut self.tab.diff_view;
+        ui.radio_value(&mut view.layout, DiffLayout::SideBySide, "Side by side");
+        ui.radio_value(&mut view.layout, DiffLayout::Unified, "Unified");
+        ui.radio_value(&mut view.layout, DiffLayout::Table, "Table");
+        ui.separator();
+        ui.label("Show:");
+        ui.checkbox(&mut view.filter.added, "Added");
+        ui.checkbox(&mut view.filter.removed, "Removed");
+        ui.checkbox(&mut view.filter.changed, "Changed");
+        ui.checkbox(&mut view.filter.unchanged, "Unchanged");
+        let only_differences = StatusFilter {
+            unchanged: false,
+            ..StatusFilter::default()
+        };
+        if ui
+            .add_enabled(
+                view.filter != only_differences,
+                egui::Button::new("Only differences"),
+            )
+            .clicked()
+        {
+            view.filter = only_differences;
+        }
+        ui.separator();
+        ui.menu_button("Ignored paths", |ui| {
+            let mut unignored = None;
+            for (i, path) in self.tab.ignored_paths.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.monospace(path);
+                    if accessibility::icon_button(ui, "✕", "Compare it again").clicked() {
+                        unignored = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = unignored {
+                self.tab.ignored_paths.remove(i);
+            }
+            if self.tab.ignored_paths.is_empty() {
+                ui.weak("Nothing is ignored");
+            } else if ui.button("Compare everything again").clicked() {
+                self.tab.ignored_paths.clear();
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Ignore:");
+                let input = ui.add(
+                    egui::TextEdit::singleline(&mut self.tab.ignore_input)
+                        .hint_text("players[3].last_seen")
+                        .desired_width(160.0),
+                );
+                let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                let path = self.tab.ignore_input.parse::<ValuePath>().ok();
+                let add = ui.add_enabled(path.is_some(), egui::Button::new("Add"));
+                if let Some(path) = path.filter(|_| add.clicked() || submitted) {
+                    self.tab.ignore_input.clear();
+                    self.apply_node_action(ui.ctx(), NodeAction::Ignore(path));
+                }
+            });
+        });
+    }
+
+    /// Shows what was loaded on each side and how long that took, the same for the diff, and
+    /// whether there were any warnings.
+    fn render_status_bar(&mut self, ui: &mut egui::Ui) {
+        let warnings = self.log_view.unseen_warnings();
+        if warnings > 0
+            && ui
+                .button(
+                    egui::RichText::new(format!("⚠ {warnings}")).color(ui.visuals().warn_fg_color),
+                )
+                .on_hover_text("Warnings and errors since the log was last looked at")
+                .clicked()
+        {
+            self.log_view.open = true;
+        }
+        for (side, slot) in [(Side::A, &self.tab.a), (Side::B, &self.tab.b)] {
+            let Some(source) = &slot.source else {
+                continue;
+            };
+            ui.label(format!("{side}: {}", source.short_name()))
+                .on_hover_text(source.to_string());
+            match &slot.loaded {
+                _ if slot.loading.is_some() => {
+                    ui.weak("loading…");
+                }
+                Some(Ok(_)) | None => {}
+                Some(Err(_)) => {
+                    ui.colored_label(ui.visuals().error_fg_color, "failed to load");
+                }
+            }
+            if let Some(file) = slot.shown_file() {
+                let size = match file.compression {
+                    Some((compression, file_size)) => format!(
+                        "{} {compression} → {}",
+                        format_size(file_size),
+                        format_size(file.data.len())
+                    ),
+                    None => format_size(file.data.len()),
+                };
+                ui.weak(format!(
+                    "{size}, crc32={:08x}, parsed in {:.1?}",
+                    file.crc32.result, file.parse_duration
+                ));
+            }
+            ui.separator();
+        }
+        // the diff is kept when a file is closed, but it's out of date then
+        let both_shown = self.tab.a.shown_file().is_some() && self.tab.b.shown_file().is_some();
+        if let Some(diff) = self.tab.diff.as_ref().filter(|_| both_shown) {
+            let differences = match diff.root.difference_count {
+                1 => "1 difference".to_owned(),
+                n => format!("{n} differences"),
+            };
+            ui.label(differences);
+            ui.weak(format!("computed in {:.1?}", diff.duration));
+            if accessibility::icon_button(ui, "📋", "Copy a summary of the diff").clicked() {
+                self.copy_summary(ui.ctx());
+            }
+        }
+    }
+
+    /// Saves the tab's comparison as a project, with the files' bytes in it if `embed` is set.
+    /// Pasted payloads and snapshots only exist in memory, so they're embedded either way.
+    fn save_project(&mut self, embed: bool) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Comparison project", &[project::EXTENSION])
+            .set_file_name(format!("{}.{}", self.tab.name(), project::EXTENSION))
+            .save_file()
+        else {
+            return;
+        };
+        let side = |slot: &FileSlot| {
+            let mut side = ProjectSide {
+                source: slot.source.clone(),
+                embedded: None,
+                load_options: slot.load_options.clone(),
+                record: slot.record,
+                subtree: slot.subtree.clone(),
+            };
+            let in_memory = matches!(
+                slot.source,
+                Some(Source::Pasted { .. } | Source::Snapshot { .. })
+            );
+            if let Some(file) = slot.shown_file().filter(|_| embed || in_memory) {
+                side.embed(&file.raw);
+            }
+            side
+        };
+        let project = Project {
+            a: side(&self.tab.a),
+            b: side(&self.tab.b),
+            ignored_paths: self.tab.ignored_paths.clone(),
+            bookmarks: self.tab.bookmarks.clone(),
+            annotations: self.tab.annotations.clone(),
+            expanded: self.tab.diff_view.expanded_by_hand(),
+        };
+        match project.save(&path) {
+            Ok(()) => self
+                .toasts
+                .push(format!("Saved the project to {}", path.display())),
+            Err(err) => warn!("Can't save the project to {}: {err}", path.display()),
+        }
+    }
+
+    /// Asks for a project to open, and opens it in a new tab, or in this one if it's empty.
+    fn open_project(&mut self, ctx: &egui::Context) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Comparison project", &[project::EXTENSION])
+            .pick_file()
+        else {
+            return;
+        };
+        let project = match Project::load(&path) {
+            Ok(project) => project,
+            Err(err) => {
+                warn!("Can't open the project {}: {err}", path.display());
+                return;
+            }
+        };
+        let mut slot = |side: ProjectSide| {
+            let embedded = match side.embedded_bytes() {
+                Ok(embedded) => embedded,
+                Err(err) => {
+                    warn!("Ignoring the file embedded in {}: {err}", path.display());
+                    None
+                }
+            };
+            let mut slot = FileSlot {
+                source: side.source,
+                load_options: side.load_options,
+                record: side.record,
+                subtree: side.subtree,
+                ..FileSlot::default()
+            };
+            match (embedded, &slot.source) {
+                (Some(bytes), Some(source)) => {
+                    // what's embedded is compared rather than whatever the file has become
+                    if !matches!(source, Source::Pasted { .. } | Source::Snapshot { .. }) {
+                        let name = format!("{} as saved", source.short_name());
+                        slot.source = Some(Source::Snapshot { name });
+                    }
+                    slot.start_loading(ctx, Some(FileBytes::from(bytes)));
+                }
+                (None, Some(source)) => {
+                    if let Some(path) = source.as_path() {
+                        self.recent_files.add(path);
+                    }
+                }
+                (_, None) => {}
+            }
+            slot
+        };
+        let (a, b) = (slot(project.a), slot(project.b));
+        let mut comparison = Comparison {
+            a,
+            b,
+            ignored_paths: project.ignored_paths,
+            bookmarks: project.bookmarks,
+            annotations: project.annotations,
+            ..Comparison::default()
+        };
+        comparison.diff_view.restore_expanded(project.expanded);
+        if self.tab.a.source.is_some() || self.tab.b.source.is_some() {
+            self.new_tab();
+        }
+        self.tab = comparison;
+        self.remember_pair();
+    }
+
+    /// Copies a line summing up the tab's diff, to paste into a chat.
+    fn copy_summary(&mut self, ctx: &egui::Context) {
+        let (Some(diff), Some(file_a), Some(file_b)) = (
+            &self.tab.diff,
+            self.tab.a.shown_file(),
+            self.tab.b.shown_file(),
+        ) else {
+            return;
+        };
+        let root = DiffEntry {
+            node: &diff.root,
+            a: self.tab.a.diff_root(),
+            b: self.tab.b.diff_root(),
+        };
+        let report_file = |slot: &FileSlot, file| ReportFile {
+            file,
+            compared: slot.diff_root_path().unwrap_or_default(),
+        };
+        ctx.copy_text(report::one_line_summary(
+            &report_file(&self.tab.a, file_a),
+            &report_file(&self.tab.b, file_b),
+            root,
+            &self.settings.display,
+        ));
+        self.toasts.push("Copied a summary of the diff".to_owned());
+    }
+
+    fn set_expansion(&mut self, expansion: Expansion) {
+        self.tab.a.tree.set_expansion(expansion);
+        self.tab.b.tree.set_expansion(expansion);
+        self.tab.diff_view.set_expansion(expansion);
+    }
+
+    /// Scrolls one file's tree to the same place as the other's, if that was just scrolled by hand.
+    /// Values being compared are matched up by their path within what's compared, like the diff
+    /// does, and others by their path from the root of the file.
+    fn sync_scroll(&mut self, ctx: &egui::Context) {
+        let (from, to) = match (self.tab.a.scrolled_path(), self.tab.b.scrolled_path()) {
+            (Some(path), _) => ((&self.tab.a, path), &mut self.tab.b),
+            (None, Some(path)) => ((&self.tab.b, path), &mut self.tab.a),
+            (None, None) => return,
+        };
+        let (from, full_path) = from;
+        let path = from
+            .path_within_diff_root(&full_path)
+            .and_then(|path| to.path_from_file_root(&path))
+            .unwrap_or(full_path);
+        to.scroll_to(&path);
+        ctx.request_repaint();
+    }
+
+    /// Expands or collapses what was just expanded or collapsed by hand in one file's tree in the
+    /// other's and the diff too.
+    fn sync_tree_expansion(&mut self, ctx: &egui::Context) {
+        let toggled = [self.tab.a.toggled_path(), self.tab.b.toggled_path()];
+        for (i, toggled) in toggled.into_iter().enumerate() {
+            let Some((full_path, expanded)) = toggled else {
+                continue;
+            };
+            let (from, to) = match i {
+                0 => (&self.tab.a, &mut self.tab.b),
+                _ => (&self.tab.b, &mut self.tab.a),
+            };
+            let path = from.path_within_diff_root(&full_path);
+            let other_path = path
+                .as_ref()
+                .and_then(|path| to.path_from_file_root(path))
+                .unwrap_or(full_path);
+            to.set_expanded(&other_path, expanded);
+            if let Some(path) = path {
+                self.tab.diff_view.set_expanded(&path, expanded);
+            }
+            ctx.request_repaint();
+        }
+    }
+
+    fn render_expansion_buttons(&mut self, ui: &mut egui::Ui) {
+        let mut expansion = None;
+        if ui.button("Expand all").on_hover_text("Ctrl+E").clicked() {
+            expansion = Some(Expansion::All);
+        }
+        if ui
+            .button("Collapse all")
+            .on_hover_text("Ctrl+Shift+E")
+            .clicked()
+        {
+            expansion = Some(Expansion::ToDepth(1));
+        }
+        ui.menu_button("Expand to depth", |ui| {
+            for depth in 1..=9 {
+                if ui.button(format!("{depth}\tCtrl+{depth}")).clicked() {
+                    expansion = Some(Expansion::ToDepth(depth));
+                    ui.close_menu();
+                }
+            }
+        });
+        if ui
+            .button("Reset")
+            .on_hover_text("Go back to expanding what contains changes")
+            .clicked()
+        {
+            expansion = Some(Expansion::Default);
+        }
+        if let Some(expansion) = expansion {
+            self.set_expansion(expansion);
+        }
+    }
+
+    /// Shows the box for jumping to a path, which is relative to the values being compared.
+    fn render_go_to(&mut self, ui: &mut egui::Ui) {
+        ui.label("Go to:");
+        let focus = ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::G));
+        let input = ui.add(
+            egui::TextEdit::singleline(&mut self.tab.go_to_input)
+                .hint_text("players[3].name (Ctrl+G)")
+                .desired_width(240.0),
+        );
+        if focus {
+            input.request_focus();
+        }
+        let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if ui.button("Go").clicked() || submitted {
+            match self.tab.go_to_input.parse::<ValuePath>() {
+                Ok(path) => self.go_to(path),
+                Err(err) => self.tab.go_to_note = Some(err.to_string()),
+            }
+        }
+        if let Some(note) = &self.tab.go_to_note {
+            ui.weak(note);
+        }
+    }
+
+    /// Jumps to the value at `path` within the compared values, in both trees and the diff.
+    fn go_to(&mut self, path: ValuePath) {
+        let in_a = self.tab.a.go_to(&path);
+        let in_b = self.tab.b.go_to(&path);
+        self.tab.go_to_note = match (in_a, in_b) {
+            (true, true) => None,
+            (true, false) => Some(format!("{path} is only in A")),
+            (false, true) => Some(format!("{path} is only in B")),
+            (false, false) => Some(format!("nothing at {path}")),
+        };
+        self.tab.diff_view.go_to(path);
+    }
+
+    /// Bookmarks the node last navigated to in the diff, or else the one selected in a tree.
+    fn bookmark_selected(&mut self) {
+        let in_tree = || {
+            let slot = match self.inspected? {
+                Side::A => &self.tab.a,
+                Side::B => &self.tab.b,
+            };
+            let path = path_of(&slot.shown_file()?.parsed, slot.tree.selected()?);
+            slot.path_within_diff_root(&path)
+        };
+        let path = self.tab.diff_view.revealed_path().cloned().or_else(in_tree);
+        match path {
+            Some(path) => self.add_bookmark(&path),
+            None => warn!("Nothing is selected to bookmark"),
+        }
+    }
+
+    fn add_bookmark(&mut self, path: &ValuePath) {
+        let path = path.to_string();
+        if !self.tab.bookmarks.contains(&path) {
+            self.tab.bookmarks.push(path);
+        }
+        self.show_bookmarks = true;
     }
-}
 
-struct LoadedFile {
-    path: PathBuf,
-    data: Vec<u8>,
-    crc32: Crc32,
-    parsed: rmpv::Value,
-    load_time: jiff::Timestamp,
-}
-impl LoadedFile {
-    fn load_from(path: &PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
-        let data = std::fs::read(path)?;
-        let crc32 = Crc32::calculate_hash_of(&data);
-
-        let parsed = rmpv::decode::read_value(&mut data.as_slice())?;
-        Ok(Self {
-            path: path.clone(),
-            data,
-            crc32,
-            parsed,
-            load_time: jiff::Timestamp::now(),
-        })
+    /// Shows the window for editing the note on a path, while one is being edited.
+    fn render_note_window(&mut self, ctx: &egui::Context) {
+        let Some((path, note)) = &mut self.annotating else {
+            return;
+        };
+        let mut open = true;
+        let mut done = false;
+        egui::Window::new(format!("Note on {path}"))
+            .id(egui::Id::new("note window"))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(note)
+                        .hint_text("e.g. expected, as schema v12 adds this")
+                        .desired_rows(4),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        done = true;
+                    }
+                    if ui
+                        .button("Remove")
+                        .on_hover_text("Remove the note from this path")
+                        .clicked()
+                    {
+                        note.clear();
+                        done = true;
+                    }
+                });
+            });
+        if done {
+            let (path, note) = self.annotating.take().expect("a note is being edited");
+            self.tab.annotations.set(path, note);
+        } else if !open {
+            self.annotating = None;
+        }
     }
-}
 
-#[derive(Default, Serialize, Deserialize)]
-struct MsgPackDifferApp {
-    path_a: Option<PathBuf>,
-    #[serde(skip)]
-    loaded_a: Option<Result<LoadedFile, Box<dyn std::error::Error>>>,
-    path_b: Option<PathBuf>,
-    #[serde(skip)]
-    loaded_b: Option<Result<LoadedFile, Box<dyn std::error::Error>>>,
-}
+    fn render_bookmarks(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("Bookmarks");
+            if !self.tab.bookmarks.is_empty() && ui.small_button("Clear").clicked() {
+                self.tab.bookmarks.clear();
+            }
+        });
+        if self.tab.bookmarks.is_empty() {
+            ui.weak("Bookmark values from their context menu or with Ctrl+D to come back to them.");
+        }
+        let mut opened = None;
+        let mut removed = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (i, path) in self.tab.bookmarks.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if accessibility::icon_button(ui, "✕", "Remove it").clicked() {
+                        removed = Some(i);
+                    }
+                    let link = ui.link(egui::RichText::new(path).monospace());
+                    let note = path
+                        .parse::<ValuePath>()
+                        .ok()
+                        .and_then(|path| self.tab.annotations.get(&path));
+                    annotations::show_note_marker(ui, note);
+                    if link.clicked() {
+                        opened = Some(path.clone());
+                    }
+                });
+            }
+            if !self.tab.annotations.is_empty() {
+                ui.separator();
+                ui.heading("Notes");
+                for (path, note) in self.tab.annotations.iter() {
+                    if ui
+                        .link(egui::RichText::new(path).monospace())
+                        .on_hover_text(note)
+                        .clicked()
+                    {
+                        opened = Some(path.to_owned());
+                    }
+                    let first_line = note.lines().next().unwrap_or_default();
+                    ui.add(egui::Label::new(egui::RichText::new(first_line).weak()).truncate());
+                }
+            }
+        });
+        if let Some(i) = removed {
+            self.tab.bookmarks.remove(i);
+        }
+        if let Some(path) = opened {
+            match path.parse::<ValuePath>() {
+                Ok(path) => self.go_to(path),
+                Err(err) => warn!("Can't go to bookmark {path}: {err}"),
+            }
+        }
+    }
 
-impl eframe::App for MsgPackDifferApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        tick_files(&mut self.path_a, &mut self.loaded_a);
-        tick_files(&mut self.path_b, &mut self.loaded_b);
+    /// Saves the tab's diff in the format picked.
+    fn export_diff(&mut self, export: DiffExport) {
+        let (Some(diff), Some(file_a), Some(file_b)) = (
+            &self.tab.diff,
+            self.tab.a.shown_file(),
+            self.tab.b.shown_file(),
+        ) else {
+            return;
+        };
+        // the patch is checked against the bytes A was decoded from before it's applied
+        let base = self.tab.a.raw_bytes(&ValuePath::root()).unwrap_or_default();
+        if export == DiffExport::MsgpackPatch && base.is_empty() {
+            warn!("Can't export a msgpack patch, as where A is encoded isn't known");
+            return;
+        }
+        let (filter, extension) = export.filter();
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(filter, &[extension])
+            .set_file_name(format!("{}.{extension}", export.file_stem()))
+            .save_file()
+        else {
+            return;
+        };
+        let root = DiffEntry {
+            node: &diff.root,
+            a: self.tab.a.diff_root(),
+            b: self.tab.b.diff_root(),
+        };
+        let report_file = |slot: &FileSlot, file| ReportFile {
+            file,
+            compared: slot.diff_root_path().unwrap_or_default(),
+        };
+        let contents: Vec<u8> = match export {
+            DiffExport::Report => report::html_report(
+                &report_file(&self.tab.a, file_a),
+                &report_file(&self.tab.b, file_b),
+                root,
+                &self.settings.display,
+                &self.tab.annotations,
+            )
+            .into(),
+            DiffExport::Markdown => report::markdown_summary(
+                &report_file(&self.tab.a, file_a),
+                &report_file(&self.tab.b, file_b),
+                root,
+                &self.settings.display,
+            )
+            .into(),
+            DiffExport::JsonPatch => serde_json::to_vec_pretty(&patch::json_patch(root))
+                .expect("patches always serialize"),
+            DiffExport::MergePatch => serde_json::to_vec_pretty(&patch::json_merge_patch(root))
+                .expect("JSON values always serialize"),
+            DiffExport::Csv => report::csv(root, &self.settings.display).into(),
+            DiffExport::MsgpackPatch => {
+                let decode_nested = file_a.options.decode_nested;
+                let patch = patch::msgpack_patch(root, base, decode_nested);
+                patch::encode_msgpack_patch(&patch)
+            }
+        };
+        let what = export.label().trim_end_matches('…');
+        match std::fs::write(&path, contents) {
+            Ok(()) => self
+                .toasts
+                .push(format!("Exported the {what} to {}", path.display())),
+            Err(err) => warn!("Can't write the {what} to {}: {err}", path.display()),
+        }
+    }
 
-        let width = ctx.available_rect().width();
-        if self.path_a.is_some() {
-            egui::SidePanel::left("path_a")
-                .min_width(width / 4.0)
-                .resizable(true)
-                .show(ctx, |ui| {
-                    egui::ScrollArea::vertical().show(ui, |ui| {
-                        Self::render_msg_pack_file(&mut self.path_a, &mut self.loaded_a, "A", ui);
-                    });
-                });
+    fn file_dialog(&self) -> rfd::FileDialog {
+        let extensions = &self.settings.file_extensions;
+        let mut dialog = rfd::FileDialog::new();
+        if !extensions.is_empty() {
+            let name = extensions
+                .iter()
+                .map(|ext| format!("*.{ext}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            dialog = dialog.add_filter(format!("msgpack files ({name})"), extensions);
         }
+        dialog = dialog.add_filter("All files", &["*"]);
+        match &self.last_directory {
+            Some(directory) => dialog.set_directory(directory),
+            None => dialog,
+        }
+    }
 
-        if self.path_b.is_some() {
-            egui::SidePanel::right("path_b")
-                .min_width(width / 4.0)
-                .resizable(true)
-                .show(ctx, |ui| {
-                    egui::ScrollArea::vertical().show(ui, |ui| {
-                        Self::render_msg_pack_file(&mut self.path_b, &mut self.loaded_b, "B", ui);
-                    });
+    fn render_settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.settings_open;
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .default_size([420.0, 480.0])
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    self.render_config_settings(ui);
+                    egui::CollapsingHeader::new("Appearance")
+                        .default_open(true)
+                        .show(ui, |ui| self.render_appearance_settings(ui));
+                    egui::CollapsingHeader::new("Values")
+                        .default_open(true)
+                        .show(ui, |ui| self.render_value_settings(ui));
+                    egui::CollapsingHeader::new("Diff")
+                        .default_open(true)
+                        .show(ui, |ui| self.render_diff_settings(ui));
+                    egui::CollapsingHeader::new("Files")
+                        .default_open(true)
+                        .show(ui, |ui| self.render_file_settings(ui));
+                    egui::CollapsingHeader::new("Performance")
+                        .default_open(true)
+                        .show(ui, |ui| self.render_performance_settings(ui));
+                    ui.separator();
+                    if ui.button("Restore defaults").clicked() {
+                        self.settings = AppSettings::default();
+                        self.extensions_input = None;
+                        self.load_ext_types();
+                        self.apply_config();
+                    }
                 });
+            });
+        self.settings_open = open;
+        if !open {
+            self.extensions_input = None;
         }
+    }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            egui::ScrollArea::both().show(ui, |ui| {
-                self.render_msg_pack_diff(ui);
-            });
+    fn render_appearance_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Theme:");
+            self.render_theme_options(ui);
         });
+        ui.horizontal(|ui| {
+            ui.label("Zoom:");
+            let mut zoom = self.settings.zoom;
+            let slider = ui.add(
+                egui::Slider::new(&mut zoom, 0.5..=3.0)
+                    .step_by(0.1)
+                    .custom_formatter(|zoom, _| format!("{:.0}%", zoom * 100.0))
+                    .custom_parser(|text| {
+                        let percent = text.trim().trim_end_matches('%').trim();
+                        percent.parse::<f64>().ok().map(|percent| percent / 100.0)
+                    }),
+            );
+            // zooming moves the slider, so only once it's let go of
+            if (slider.changed() && !slider.dragged()) || slider.drag_stopped() {
+                self.settings.zoom = zoom;
+            }
+        })
+        .response
+        .on_hover_text("Also Ctrl+= and Ctrl+-, and Ctrl+0 to reset it");
     }
-}
-
-impl MsgPackDifferApp {
-    fn render_msg_pack_file(
-        picked_path: &mut Option<PathBuf>,
-        loaded_file: &mut Option<Result<LoadedFile, Box<dyn std::error::Error>>>,
-        label: &str,
-        ui: &mut egui::Ui,
-    ) {
-        enum Operation {
-            Reload(PathBuf),
-            Unload,
-        }
-        let mut operation = None;
-        if let Some(file) = loaded_file {
-            match file {
-                Ok(file) => {
-                    ui.horizontal(|ui| {
-                        ui.heading(file.path.file_name().unwrap().to_string_lossy())
-                            .on_hover_text(file.path.to_string_lossy());
 
-                        if ui.button("Reload").clicked() {
-                            operation = Some(Operation::Reload(file.path.clone()));
-                        } else if ui.button("X").clicked() {
-                            operation = Some(Operation::Unload);
+    fn render_value_settings(&mut self, ui: &mut egui::Ui) {
+        let display = &mut self.settings.display;
+        egui::Grid::new("value_settings").show(ui, |ui| {
+            ui.label("Integers:");
+            egui::ComboBox::from_id_salt("integer_base")
+                .selected_text(display.integer_base.to_string())
+                .show_ui(ui, |ui| {
+                    for option in IntegerBase::ALL {
+                        ui.selectable_value(&mut display.integer_base, option, option.to_string());
+                    }
+                });
+            ui.end_row();
+            ui.label("");
+            ui.add_enabled(
+                display.integer_base == IntegerBase::Decimal,
+                egui::Checkbox::new(&mut display.thousands_separators, "Thousands separators"),
+            )
+            .on_hover_text("Group the digits of decimal integers in thousands, e.g. 16,777,216");
+            ui.end_row();
+            ui.label("Floats:");
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("float_notation")
+                    .selected_text(display.float_notation.to_string())
+                    .show_ui(ui, |ui| {
+                        for option in FloatNotation::ALL {
+                            ui.selectable_value(
+                                &mut display.float_notation,
+                                option,
+                                option.to_string(),
+                            );
                         }
                     });
-                    ui.label(format!(
-                        "Loaded at: {}",
-                        file.load_time.strftime("%Y-%m-%d %H:%M:%S")
-                    ));
-                    ui.label(format!(
-                        "{} bytes, crc32={:08x}",
-                        file.data.len(),
-                        file.crc32.result,
-                    ));
-                    render_rmpv(ui, &file.parsed);
+                if display.float_notation.has_precision() {
+                    ui.add(egui::DragValue::new(&mut display.float_precision).range(0..=17));
+                    ui.label("digits after the point");
                 }
-                Err(err) => {
-                    ui.label(format!("Error loading file: {}", err));
+            });
+            ui.end_row();
+            ui.label("Binary values:")
+                .on_hover_text("Unless picked for one in particular");
+            egui::ComboBox::from_id_salt("binary_format")
+                .selected_text(display.binary_format.to_string())
+                .show_ui(ui, |ui| {
+                    for option in BinaryFormat::ALL {
+                        ui.selectable_value(&mut display.binary_format, option, option.to_string());
+                    }
+                });
+            ui.end_row();
+        });
+        ui.checkbox(&mut display.sort_keys, "Sort map entries by key")
+            .on_hover_text("Rather than showing them in the order they're encoded in");
+        ui.horizontal(|ui| {
+            ui.label("Cut strings short after");
+            ui.add(egui::DragValue::new(&mut display.max_string_chars).range(20..=100_000));
+            ui.label("characters");
+        })
+        .response
+        .on_hover_text("Or at their first line break. They can still be shown in full.");
+        ui.add_space(4.0);
+        ui.label("When saving a file as JSON or YAML, write");
+        let conversion = &mut self.settings.json_conversion;
+        egui::Grid::new("json_conversion_settings").show(ui, |ui| {
+            ui.label("binary values as");
+            egui::ComboBox::from_id_salt("binary_conversion")
+                .selected_text(conversion.binary.to_string())
+                .show_ui(ui, |ui| {
+                    for option in BinaryConversion::ALL {
+                        ui.selectable_value(&mut conversion.binary, option, option.to_string());
+                    }
+                });
+            ui.end_row();
+            ui.label("extension values as");
+            egui::ComboBox::from_id_salt("ext_conversion")
+                .selected_text(conversion.ext.to_string())
+                .show_ui(ui, |ui| {
+                    for option in ExtConversion::ALL {
+                        ui.selectable_value(&mut conversion.ext, option, option.to_string());
+                    }
+                });
+            ui.end_row();
+        });
+        self.render_ext_types_settings(ui);
+    }
+
+    fn render_diff_settings(&mut self, ui: &mut egui::Ui) {
+        self.render_palette_settings(ui);
+        ui.checkbox(&mut self.settings.sync_scrolling, "Sync scrolling")
+            .on_hover_text("Scroll each file's tree to where the other was scrolled to");
+        ui.checkbox(&mut self.settings.sync_expanding, "Sync expanding")
+            .on_hover_text(
+                "Expand or collapse the same thing in the other file's tree and the diff",
+            );
+    }
+
+    fn render_file_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label("File extensions to show in the open dialog:");
+        let extensions = self
+            .extensions_input
+            .get_or_insert_with(|| self.settings.file_extensions.join(", "));
+        let response = ui.add(egui::TextEdit::singleline(extensions).hint_text("msgpack, mp, bin"));
+        if response.changed() {
+            self.settings.file_extensions = AppSettings::parse_extensions(extensions);
+        }
+        ui.label("Patterns for pairing up the files in a dropped directory:")
+            .on_hover_text("Files whose names match the same text for * are compared");
+        let patterns = &mut self.settings.pair_patterns;
+        let mut removed = None;
+        egui::Grid::new("pair_patterns").show(ui, |ui| {
+            for (i, pattern) in patterns.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(&mut pattern.a).desired_width(140.0));
+                ui.label("↔");
+                ui.add(egui::TextEdit::singleline(&mut pattern.b).desired_width(140.0));
+                if accessibility::icon_button(ui, "✕", "Remove it").clicked() {
+                    removed = Some(i);
                 }
+                ui.end_row();
             }
+        });
+        if let Some(i) = removed {
+            patterns.remove(i);
+        }
+        if ui.small_button("Add pattern").clicked() {
+            patterns.push(PairPattern::new("*_a.msgpack", "*_b.msgpack"));
+        }
+        ui.horizontal(|ui| {
+            ui.label("Reload changed files after");
+            ui.add(egui::DragValue::new(&mut self.settings.reload_delay_ms).range(0..=10_000));
+            ui.label("ms");
+        })
+        .response
+        .on_hover_text("When watching for changes, wait this long for writing to settle down");
+    }
+
+    /// Shows where the config file is and whether its settings are in use.
+    fn render_config_settings(&mut self, ui: &mut egui::Ui) {
+        let Some(path) = config::path() else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.label("Config file:");
+            ui.monospace(path.display().to_string());
+        });
+        if path.is_file() {
+            ui.weak(
+                "Its settings are used in place of these ones, and it's reloaded when it changes",
+            );
         } else {
-            ui.heading(format!("File {label}"));
+            ui.weak("There's none, so these settings are only saved along with the session");
         }
-        if let Some(operation) = operation {
-            match operation {
-                Operation::Reload(path) => {
-                    *loaded_file = Some(LoadedFile::load_from(&path));
+        if let Some(err) = &self.config_error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+        if ui
+            .button("Save these settings to it")
+            .on_hover_text("So that they can be put under version control or shared")
+            .clicked()
+        {
+            self.save_config(ui.ctx());
+        }
+        ui.separator();
+    }
+
+    fn render_performance_settings(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("performance_settings").show(ui, |ui| {
+            ui.label("Earlier versions kept per file:");
+            ui.add(egui::DragValue::new(&mut self.settings.history_versions).range(1..=1000));
+            ui.end_row();
+            ui.label("Memory for earlier versions:")
+                .on_hover_text("Beyond this, the oldest versions of any file are dropped");
+            ui.add(
+                egui::DragValue::new(&mut self.settings.history_mib)
+                    .range(16..=16 * 1024)
+                    .suffix(" MiB"),
+            );
+            ui.end_row();
+        });
+    }
+
+    fn render_palette_settings(&mut self, ui: &mut egui::Ui) {
+        let palette = &mut self.settings.display.palette;
+        ui.horizontal(|ui| {
+            ui.label("Diff colors:");
+            let preset = Palette::PRESETS
+                .iter()
+                .find(|(_, preset)| preset == palette)
+                .map_or("Custom", |(name, _)| name);
+            egui::ComboBox::from_id_salt("palette")
+                .selected_text(preset)
+                .show_ui(ui, |ui| {
+                    for (name, preset) in Palette::PRESETS {
+                        ui.selectable_value(palette, preset, name);
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            for (name, color) in [
+                ("Added", &mut palette.added),
+                ("Removed", &mut palette.removed),
+                ("Changed", &mut palette.changed),
+            ] {
+                ui.color_edit_button_srgba(color);
+                ui.label(name);
+            }
+        });
+    }
+
+    fn render_ext_types_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label("Decoders for extension types:").on_hover_text(
+            "A JSON list such as [{\"type\": 1, \"name\": \"UUID\", \"decoder\": \"uuid\"}], \
+             with decoders timestamp, uuid, decimal, text, integer, duration-nanos, \
+             duration-micros, duration-millis and duration-seconds",
+        );
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            match &self.settings.ext_types_file {
+                Some(path) => {
+                    ui.monospace(path.display().to_string());
+                    if ui.small_button("Reload").clicked() {
+                        changed = true;
+                    }
+                    if accessibility::icon_button(ui, "✕", "Stop using it").clicked() {
+                        self.settings.ext_types_file = None;
+                        changed = true;
+                    }
                 }
-                Operation::Unload => {
-                    *picked_path = None;
+                None => {
+                    ui.weak("No file, so only timestamps are decoded");
+                }
+            }
+            if ui.button("Choose file…").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .pick_file()
+                {
+                    self.settings.ext_types_file = Some(path);
+                    changed = true;
+                }
+            }
+        });
+        if changed {
+            self.load_ext_types();
+        }
+        if let Some(err) = &self.ext_types_error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+        egui::Grid::new("ext_types").show(ui, |ui| {
+            for mapping in ext_types::registry().mappings() {
+                ui.label(format!("Type {}", mapping.type_id));
+                ui.label(&mapping.name);
+                ui.weak(format!("{:?}", mapping.decoder));
+                ui.end_row();
+            }
+        });
+    }
+
+    /// Lists recently opened files, returning whichever one was clicked.
+    fn render_recent_files(&mut self, ui: &mut egui::Ui) -> Option<PathBuf> {
+        let mut picked_path = None;
+        for path in self.recent_files.iter() {
+            let file_name = path.file_name().unwrap_or(path.as_os_str());
+            if ui
+                .link(file_name.to_string_lossy())
+                .on_hover_text(path.to_string_lossy())
+                .clicked()
+            {
+                picked_path = Some(path.to_owned());
+            }
+        }
+        if self.recent_files.is_empty() {
+            ui.weak("No recent files");
+        } else if ui.small_button("Clear list").clicked() {
+            self.recent_files.clear();
+        }
+        picked_path
+    }
+
+    fn open_source(&mut self, side: Side, source: Source) {
+        let slot = match side {
+            Side::A => &mut self.tab.a,
+            Side::B => &mut self.tab.b,
+        };
+        if let Some(path) = source.as_path() {
+            self.recent_files.add(path);
+        }
+        slot.source = Some(source);
+        // clear out any previous load so that it's retried even if the path is unchanged
+        slot.loaded = None;
+    }
+
+    /// Adds the files in A and B to the recent comparisons, if they're both local files.
+    fn remember_pair(&mut self) {
+        let (Some(a), Some(b)) = (&self.tab.a.source, &self.tab.b.source) else {
+            return;
+        };
+        if let (Some(a), Some(b)) = (a.as_path(), b.as_path()) {
+            self.recent_files.add_pair(a, b);
+        }
+    }
+
+    fn open_paths(&mut self, paths: Vec<PathBuf>) {
+        self.open_sources(paths.into_iter().map(Source::File).collect());
+    }
+
+    /// Opens the first two sources as A and B, or a single one into whichever side is still empty.
+    fn open_sources(&mut self, sources: Vec<Source>) {
+        let mut sources = sources.into_iter();
+        match (sources.next(), sources.next()) {
+            (Some(a), Some(b)) => {
+                self.open_source(Side::A, a);
+                self.open_source(Side::B, b);
+                let extra_sources = sources.collect::<Vec<_>>();
+                if !extra_sources.is_empty() {
+                    warn!(
+                        "Ignoring extra files: {}",
+                        extra_sources
+                            .iter()
+                            .map(Source::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
                 }
             }
+            (Some(source), None) => {
+                let side = if self.tab.a.source.is_none() {
+                    Side::A
+                } else {
+                    Side::B
+                };
+                self.open_source(side, source);
+            }
+            _ => {
+                error!("No files selected somehow");
+            }
         }
+        self.remember_pair();
     }
 
-    fn render_msg_pack_diff(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Diff");
-        let prompt = if self.path_a.is_none() && self.path_b.is_none() {
-            Some("Select files A and B to compare them")
-        } else if self.path_a.is_none() {
-            Some("Select file A to compare with file B")
-        } else if self.path_b.is_none() {
-            Some("Select file B to compare with file A")
-        } else {
-            None
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let (dropped_files, pointer_pos) =
+            ctx.input(|i| (i.raw.dropped_files.clone(), i.pointer.latest_pos()));
+        let (directories, paths): (Vec<_>, Vec<_>) = dropped_files
+            .into_iter()
+            .filter_map(|file| file.path)
+            .partition(|path| path.is_dir());
+        if !directories.is_empty() {
+            if !paths.is_empty() {
+                warn!("Ignoring files dropped along with directories: {paths:?}");
+            }
+            self.pair_directories(&directories);
+            return;
+        }
+        if paths.is_empty() {
+            return;
+        }
+        match pointer_pos {
+            // a single file goes to whichever half of the window it was dropped on
+            Some(pos) if paths.len() == 1 => {
+                let side = if pos.x < ctx.screen_rect().center().x {
+                    Side::A
+                } else {
+                    Side::B
+                };
+                self.open_source(side, Source::File(paths[0].clone()));
+                self.remember_pair();
+            }
+            _ => self.open_paths(paths),
+        }
+    }
+
+    /// Finds pairs of files to compare within a single directory, or across two of them.
+    fn pair_directories(&mut self, directories: &[PathBuf]) {
+        let (description, pairs) = match directories {
+            [directory] => (
+                format!("in {}", directory.display()),
+                pair_within(directory, &self.settings.pair_patterns),
+            ),
+            [a, b, rest @ ..] => {
+                if !rest.is_empty() {
+                    warn!("Only pairing the first two dropped directories, ignoring {rest:?}");
+                }
+                (
+                    format!("in {} and {}", a.display(), b.display()),
+                    pair_across(a, b),
+                )
+            }
+            [] => return,
         };
-        if let Some(prompt) = prompt {
-            ui.label(prompt);
-            if ui.button("Open file(s)…").clicked() {
-                if let Some(picked_paths) = rfd::FileDialog::new()
-                    .add_filter("*.msgpack files", &["msgpack"])
-                    .pick_files()
-                {
-                    if picked_paths.len() >= 2 {
-                        self.path_a = Some(picked_paths[0].clone());
-                        self.path_b = Some(picked_paths[1].clone());
-                        if picked_paths.len() > 2 {
-                            warn!(
-                                "Ignoring extra files: {}",
-                                picked_paths[2..]
-                                    .iter()
-                                    .map(|p| p.to_string_lossy())
-                                    .collect::<Vec<_>>()
-                                    .join(", ")
-                            );
-                        }
-                    } else if picked_paths.len() == 1 {
-                        if self.path_a.is_none() {
-                            self.path_a = Some(picked_paths[0].clone());
-                        } else {
-                            self.path_b = Some(picked_paths[0].clone());
-                        }
-                    } else {
-                        error!("No files selected somehow");
+        self.file_pairs = Some(FilePairs {
+            description,
+            pairs: pairs.map_err(|err| err.to_string()),
+        });
+    }
+
+    fn render_file_pairs_window(&mut self, ctx: &egui::Context) {
+        let Some(file_pairs) = &self.file_pairs else {
+            return;
+        };
+        let mut open = true;
+        let mut picked = None;
+        egui::Window::new("File pairs")
+            .open(&mut open)
+            .default_height(300.0)
+            .show(ctx, |ui| {
+                ui.label(format!("Files to compare {}:", file_pairs.description));
+                match &file_pairs.pairs {
+                    Ok(pairs) if pairs.is_empty() => {
+                        ui.weak("No pairs found. The patterns to pair by can be changed in the settings.");
+                    }
+                    Ok(pairs) => {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            for pair in pairs {
+                                let loaded = self.tab.a.source.as_ref().and_then(Source::as_path)
+                                    == Some(&pair.a)
+                                    && self.tab.b.source.as_ref().and_then(Source::as_path)
+                                        == Some(&pair.b);
+                                if ui
+                                    .selectable_label(loaded, &pair.name)
+                                    .on_hover_text(format!(
+                                        "A: {}\nB: {}",
+                                        pair.a.display(),
+                                        pair.b.display()
+                                    ))
+                                    .clicked()
+                                {
+                                    picked = Some(pair.clone());
+                                }
+                            }
+                        });
+                    }
+                    Err(err) => {
+                        ui.colored_label(ui.visuals().error_fg_color, err);
                     }
                 }
-            }
+            });
+        if let Some(pair) = picked {
+            self.open_paths(vec![pair.a, pair.b]);
+        }
+        if !open {
+            self.file_pairs = None;
+        }
+    }
 
+    fn render_drop_hint(ctx: &egui::Context) {
+        let hovered_count = ctx.input(|i| i.raw.hovered_files.len());
+        if hovered_count == 0 {
             return;
         }
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("drop_hint"),
+        ));
+        let screen_rect = ctx.screen_rect();
+        painter.rect_filled(screen_rect, 0.0, egui::Color32::from_black_alpha(160));
+        let font = egui::TextStyle::Heading.resolve(&ctx.style());
+        if hovered_count >= 2 {
+            painter.text(
+                screen_rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "Drop to open as A and B",
+                font,
+                egui::Color32::WHITE,
+            );
+        } else {
+            let (left, right) = screen_rect.split_left_right_at_fraction(0.5);
+            painter.vline(
+                screen_rect.center().x,
+                screen_rect.y_range(),
+                egui::Stroke::new(2.0, egui::Color32::GRAY),
+            );
+            for (half, text) in [
+                (left, "Drop here to open as A"),
+                (right, "Drop here to open as B"),
+            ] {
+                painter.text(
+                    half.center(),
+                    egui::Align2::CENTER_CENTER,
+                    text,
+                    font.clone(),
+                    egui::Color32::WHITE,
+                );
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+enum Side {
+    A,
+    B,
+}
+
+impl std::fmt::Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Side::A => "A",
+            Side::B => "B",
+        })
+    }
+}
+
+/// Something a file's panel asks of the app as a whole.
+enum SlotRequest {
+    Paste,
+    Snapshot,
+    /// Save the edited file, to a file picked for it if set.
+    SaveEdits {
+        pick_path: bool,
+    },
+    SaveAs(TextFormat),
+    /// Picked from the context menu of an entry of the tree.
+    Node(NodeAction),
+}
+
+/// Formats a file can be saved as to read it without this app.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum TextFormat {
+    Json,
+    Yaml,
+}
+
+impl TextFormat {
+    const ALL: [TextFormat; 2] = [TextFormat::Json, TextFormat::Yaml];
+
+    fn extension(self) -> &'static str {
+        match self {
+            TextFormat::Json => "json",
+            TextFormat::Yaml => "yaml",
+        }
+    }
+}
+
+impl std::fmt::Display for TextFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TextFormat::Json => "JSON",
+            TextFormat::Yaml => "YAML",
+        })
     }
 }
 
-fn tick_files(
-    picked_path: &mut Option<PathBuf>,
-    loaded_file: &mut Option<Result<LoadedFile, Box<dyn std::error::Error>>>,
+/// Shows where and why decoding stopped, with a hex dump of the bytes around that point, and what
+/// was `decoded` before it.
+fn render_decode_failure(
+    ui: &mut egui::Ui,
+    heading: &str,
+    color: egui::Color32,
+    failure: &DecodeFailure,
+    data: &[u8],
+    decoded: &str,
 ) {
-    if let Some(picked_path) = picked_path {
-        match loaded_file {
-            Some(Ok(file)) => {
-                if file.path != *picked_path {
-                    *loaded_file = Some(LoadedFile::load_from(picked_path));
-                }
+    // lines of the dump shown before and after the one with the failure
+    const CONTEXT_LINES: usize = 3;
+    let frame = egui::Frame::group(ui.style()).stroke(egui::Stroke::new(1.0, color));
+    frame.show(ui, |ui| {
+        ui.colored_label(color, egui::RichText::new(heading).strong());
+        ui.label(&failure.message);
+        let offset = failure.offset;
+        ui.label(format!(
+            "At byte offset {offset} (0x{offset:x}) of {}",
+            data.len()
+        ));
+        let failed_line = offset / hex_dump::BYTES_PER_LINE;
+        let first_line = failed_line.saturating_sub(CONTEXT_LINES);
+        let line_count = data.len().div_ceil(hex_dump::BYTES_PER_LINE);
+        for line in first_line..=failed_line + CONTEXT_LINES {
+            if line >= line_count && line != failed_line {
+                break;
             }
-            None => {
-                *loaded_file = Some(LoadedFile::load_from(picked_path));
+            let text = egui::RichText::new(hex_dump::format_line(data, line)).monospace();
+            if line != failed_line {
+                ui.label(text);
+                continue;
+            }
+            ui.label(text.color(color));
+            // a caret under the byte, or past the last one if the data ended early
+            let column = offset % hex_dump::BYTES_PER_LINE;
+            let indent = 9 + 3 * column + usize::from(column >= hex_dump::BYTES_PER_LINE / 2);
+            let caret = format!("{}^^", " ".repeat(indent + 1));
+            ui.label(egui::RichText::new(caret).monospace().color(color));
+        }
+        ui.label(decoded);
+    });
+}
+
+fn render_load_progress(ui: &mut egui::Ui, progress: &LoadProgress) {
+    match progress.stage() {
+        LoadStage::Reading => {
+            ui.label("Reading file…");
+        }
+        LoadStage::Downloading => match progress.download() {
+            (downloaded, Some(size)) => {
+                ui.add(
+                    egui::ProgressBar::new(downloaded as f32 / size.max(1) as f32)
+                        .show_percentage(),
+                );
+                ui.label(format!("Downloaded {downloaded} of {size} bytes"));
+            }
+            (downloaded, None) => {
+                ui.label(format!("Downloaded {downloaded} bytes"));
             }
-            _ => {}
+        },
+        LoadStage::Decompressing => {
+            ui.label(format!(
+                "Decompressed {} bytes…",
+                progress.bytes_decompressed()
+            ));
+        }
+        LoadStage::Parsing => {
+            let (bytes_parsed, total_bytes) = progress.bytes();
+            ui.add(
+                egui::ProgressBar::new(bytes_parsed as f32 / total_bytes.max(1) as f32)
+                    .show_percentage(),
+            );
+            ui.label(format!(
+                "Parsed {bytes_parsed} of {total_bytes} bytes ({} values)",
+                progress.values_parsed()
+            ));
         }
-    } else {
-        *loaded_file = None;
     }
+    // the loading thread only asks for a repaint once it's done, so keep the progress ticking over
+    ui.ctx().request_repaint_after(Duration::from_millis(100));
+}
+
+/// Points out that a string isn't valid UTF-8, which msgpack allows, with its raw bytes on hover as
+/// the text shown has what isn't replaced.
+fn show_invalid_utf8_badge(ui: &mut egui::Ui, bytes: &[u8]) {
+    const MAX_LINES: usize = 16;
+    ui.colored_label(ui.visuals().warn_fg_color, "⚠ invalid UTF-8")
+        .on_hover_ui(|ui| {
+            ui.label("This string isn't valid UTF-8, so what isn't is shown as �. Its bytes are:");
+            let lines = bytes.len().div_ceil(hex_dump::BYTES_PER_LINE);
+            for line in 0..lines.min(MAX_LINES) {
+                ui.monospace(hex_dump::format_line(bytes, line));
+            }
+            if lines > MAX_LINES {
+                ui.weak("…");
+            }
+        });
 }
 
-fn render_rmpv(ui: &mut egui::Ui, value: &rmpv::Value) {
+fn render_rmpv(ui: &mut egui::Ui, value: &rmpv::Value, display: &DisplayOptions) {
     match value {
         rmpv::Value::Nil => {
             ui.label("nil");
@@ -243,19 +3823,26 @@ fn render_rmpv(ui: &mut egui::Ui, value: &rmpv::Value) {
             ui.label(if *b { "true" } else { "false" });
         }
         rmpv::Value::Integer(i) => {
-            ui.label(i.to_string());
+            ui.label(display.format_integer(*i));
         }
         rmpv::Value::F32(f) => {
-            ui.label(f.to_string());
+            ui.label(display.format_f32(*f));
         }
         rmpv::Value::F64(f) => {
-            ui.label(f.to_string());
-        }
-        rmpv::Value::String(s) => {
-            ui.label(s.as_str().expect("should be valid string"));
+            ui.label(display.format_f64(*f));
         }
+        rmpv::Value::String(s) => match s.as_str() {
+            Some(text) => {
+                ui.label(display.strings.text(display.cut_short(text)));
+            }
+            None => {
+                let text = String::from_utf8_lossy(s.as_bytes());
+                ui.label(display.strings.text(display.cut_short(&text)));
+                show_invalid_utf8_badge(ui, s.as_bytes());
+            }
+        },
         rmpv::Value::Binary(b) => {
-            ui.label(format!("{} bytes", b.len()));
+            ui.label(display.binary_format.summarize(b));
         }
         rmpv::Value::Array(a) => {
             ui.vertical(|ui| {
@@ -263,10 +3850,14 @@ fn render_rmpv(ui: &mut egui::Ui, value: &rmpv::Value) {
                     ui.horizontal(|ui| {
                         // ui.label(format!("[{i}]"));
                         ui.push_id(i, |ui| {
-                            egui::CollapsingHeader::new(format!("array[{i}]"))
+                            let header = match describe_children(array_item) {
+                                Some(children) => format!("array[{i}] {children}"),
+                                None => format!("array[{i}]"),
+                            };
+                            egui::CollapsingHeader::new(header)
                                 .default_open(false)
                                 .show(ui, |ui| {
-                                    render_rmpv(ui, array_item);
+                                    render_rmpv(ui, array_item, display);
                                 });
                         });
                     });
@@ -277,7 +3868,11 @@ fn render_rmpv(ui: &mut egui::Ui, value: &rmpv::Value) {
             ui.vertical(|ui| {
                 for (key, value) in m.iter() {
                     ui.push_id(HashableValue(key), |ui| {
-                        egui::CollapsingHeader::new(format!("map[{}]", key))
+                        let header = match describe_children(value) {
+                            Some(children) => format!("map[{key}] {children}"),
+                            None => format!("map[{key}]"),
+                        };
+                        egui::CollapsingHeader::new(header)
                             .default_open(true)
                             .show(ui, |ui| {
                                 if matches!(
@@ -289,13 +3884,13 @@ fn render_rmpv(ui: &mut egui::Ui, value: &rmpv::Value) {
                                         | rmpv::Value::F32(_)
                                 ) {
                                     // already rendered the key in the map[{}] header so skip it
-                                    render_rmpv(ui, value);
+                                    render_rmpv(ui, value, display);
                                 } else {
                                     ui.label(format!("[FYI! {}]", type_name_of(key)));
                                     ui.horizontal(|ui| {
-                                        render_rmpv(ui, key);
+                                        render_rmpv(ui, key, display);
                                         ui.label("->");
-                                        render_rmpv(ui, value);
+                                        render_rmpv(ui, value, display);
                                     });
                                 }
                             })
@@ -305,9 +3900,18 @@ fn render_rmpv(ui: &mut egui::Ui, value: &rmpv::Value) {
                 }
             });
         }
-        rmpv::Value::Ext(i8, bytes) => {
-            ui.label(format!("External {}, {} bytes", i8, bytes.len()));
-        }
+        rmpv::Value::Ext(i8, bytes) => match ext_types::decode(value) {
+            Some(decoded) => {
+                ui.label(decoded.text).on_hover_text(format!(
+                    "{} (external {i8}, {} bytes)",
+                    decoded.name,
+                    bytes.len()
+                ));
+            }
+            None => {
+                ui.label(format!("External {}, {} bytes", i8, bytes.len()));
+            }
+        },
     }
 }
 
@@ -325,68 +3929,3 @@ fn type_name_of(value: &rmpv::Value) -> &'static str {
         rmpv::Value::Ext(_, _) => "Key type: Ext",
     }
 }
-
-struct HashableValue<'a>(&'a rmpv::Value);
-impl<'a> std::hash::Hash for HashableValue<'a> {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        match self.0 {
-            rmpv::Value::Nil => {
-                state.write_u8(0);
-            }
-            rmpv::Value::Boolean(b) => {
-                (*b).hash(state);
-            }
-            rmpv::Value::Integer(i) => {
-                if let Some(i) = i.as_i64() {
-                    i.hash(state);
-                } else if let Some(i) = i.as_u64() {
-                    i.hash(state);
-                } else if let Some(i) = i.as_f64() {
-                    i.to_bits().hash(state);
-                } else {
-                    panic!("unsupported rmpv integer type");
-                }
-            }
-            rmpv::Value::F32(f) => {
-                f.to_bits().hash(state);
-            }
-            rmpv::Value::F64(f) => {
-                f.to_bits().hash(state);
-            }
-            rmpv::Value::String(s) => {
-                s.as_bytes().hash(state);
-            }
-            rmpv::Value::Binary(b) => {
-                state.write_u8(6);
-                state.write_u64(b.len() as u64);
-                state.write(b);
-            }
-            rmpv::Value::Array(a) => {
-                state.write_u8(7);
-                state.write_u64(a.len() as u64);
-                for item in a {
-                    HashableValue(item).hash(state);
-                }
-            }
-            rmpv::Value::Map(m) => {
-                state.write_u8(8);
-                state.write_u64(m.len() as u64);
-                for (key, value) in m {
-                    HashableValue(key).hash(state);
-                    HashableValue(value).hash(state);
-                }
-            }
-            rmpv::Value::Ext(i8, bytes) => {
-                state.write_u8(9);
-                state.write_i8(*i8);
-                state.write_u64(bytes.len() as u64);
-                state.write(bytes);
-            }
-        }
-    }
-}
-impl<'a> PartialEq for HashableValue<'a> {
-    fn eq(&self, other: &Self) -> bool {
-        self.0.eq(other.0)
-    }
-}
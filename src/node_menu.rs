@@ -0,0 +1,159 @@
+use base64::Engine as _;
+use eframe::egui;
+
+use crate::edit::Edit;
+use crate::json::{json_conversion_losses, msgpack_to_json};
+use crate::nested::decode_msgpack;
+use crate::path::ValuePath;
+use crate::payload::encode_hex;
+
+/// Something asked for from a node's context menu that the app rather than the view showing the
+/// node takes care of.
+pub enum NodeAction {
+    /// Leave the node out of the comparison.
+    Ignore(ValuePath),
+    /// Add the node to the tab's bookmarks.
+    Bookmark(ValuePath),
+    /// Attach a note to the node, or edit the one attached.
+    Annotate(ValuePath),
+    /// Compare only the node, rather than the whole document.
+    CompareSubtree(ValuePath),
+    /// Show binary values that hold msgpack as what they decode to, in both files.
+    DecodeNested,
+    /// Copy the bytes the node was decoded from, in A or in B.
+    CopyRawBytes {
+        in_b: bool,
+        path: ValuePath,
+        format: ByteFormat,
+    },
+    /// Make the node the same in B as it's in A if `to_b` is set, or the other way around, with
+    /// `edit` made to the value compared on that side.
+    TakeAcross { to_b: bool, edit: Edit },
+    /// Save a value as a msgpack file of its own, along with the path it's at to name the file
+    /// after.
+    SaveValue { value: rmpv::Value, path: ValuePath },
+}
+
+/// How to write bytes as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteFormat {
+    Hex,
+    Base64,
+}
+
+impl ByteFormat {
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            ByteFormat::Hex => encode_hex(bytes),
+            ByteFormat::Base64 => base64::engine::general_purpose::STANDARD.encode(bytes),
+        }
+    }
+}
+
+/// Adds the context menu items for copying `path` in the various syntaxes it can be written in.
+pub fn copy_path_buttons(ui: &mut egui::Ui, path: &ValuePath) {
+    let formats = [
+        ("Copy path", path.to_string()),
+        ("Copy path as JSON Pointer", path.to_json_pointer()),
+        ("Copy path as jq expression", path.to_jq()),
+    ];
+    for (label, text) in formats {
+        if ui.button(label).on_hover_text(&text).clicked() {
+            ui.ctx().copy_text(text);
+            ui.close_menu();
+        }
+    }
+}
+
+/// Adds the context menu items for copying `value`, whose name is appended to them if there's more
+/// than one value to pick from, e.g. ` of A`.
+pub fn copy_value_buttons(ui: &mut egui::Ui, value: &rmpv::Value, name: &str) {
+    if let Some(text) = value.as_str() {
+        if ui
+            .button(format!("Copy text{name}"))
+            .on_hover_text("All of it, even where it's cut short")
+            .clicked()
+        {
+            ui.ctx().copy_text(text.to_owned());
+            ui.close_menu();
+        }
+    }
+    // working out what converts badly means going through the whole value, so only when asked
+    let show_losses = |ui: &mut egui::Ui| {
+        let losses = json_conversion_losses(value);
+        if losses.is_empty() {
+            ui.label("Converts to JSON exactly");
+        }
+        for loss in losses {
+            ui.label(format!("Note: {loss}"));
+        }
+    };
+    let pretty = ui
+        .button(format!("Copy value{name} as JSON"))
+        .on_hover_ui(show_losses);
+    let compact = ui
+        .button(format!("Copy value{name} as one-line JSON"))
+        .on_hover_ui(show_losses);
+    if pretty.clicked() || compact.clicked() {
+        let json = msgpack_to_json(value);
+        let text = if pretty.clicked() {
+            serde_json::to_string_pretty(&json)
+        } else {
+            serde_json::to_string(&json)
+        };
+        ui.ctx()
+            .copy_text(text.expect("JSON values always serialize"));
+        ui.close_menu();
+    }
+}
+
+/// Adds the context menu items for copying the bytes a value was decoded from, returning the format
+/// asked for. The value's name is appended to them as for [`copy_value_buttons`].
+pub fn copy_bytes_buttons(ui: &mut egui::Ui, name: &str) -> Option<ByteFormat> {
+    let mut picked = None;
+    for (label, format) in [("hex", ByteFormat::Hex), ("base64", ByteFormat::Base64)] {
+        if ui
+            .button(format!("Copy raw bytes{name} as {label}"))
+            .on_hover_text("Exactly as encoded in the file")
+            .clicked()
+        {
+            picked = Some(format);
+            ui.close_menu();
+        }
+    }
+    picked
+}
+
+/// Adds the context menu item for saving `value`, which is at `path`, as a msgpack file of its own,
+/// returning the action to do that if it was picked. The value's name is appended to it as for
+/// [`copy_value_buttons`].
+pub fn save_value_button(
+    ui: &mut egui::Ui,
+    value: &rmpv::Value,
+    path: &ValuePath,
+    name: &str,
+) -> Option<NodeAction> {
+    let button = ui
+        .button(format!("Save value{name} as msgpack…"))
+        .on_hover_text("Encoded afresh, e.g. to make a smaller test file out of a big one");
+    if !button.clicked() {
+        return None;
+    }
+    ui.close_menu();
+    Some(NodeAction::SaveValue {
+        value: value.clone(),
+        path: path.clone(),
+    })
+}
+
+/// Adds the context menu item for decoding binary values that hold msgpack, if `value` is one,
+/// returning whether it was picked.
+pub fn decode_nested_button(ui: &mut egui::Ui, value: Option<&rmpv::Value>) -> bool {
+    let holds_msgpack =
+        matches!(value, Some(rmpv::Value::Binary(bytes)) if decode_msgpack(bytes).is_some());
+    if holds_msgpack && ui.button("Decode as msgpack").clicked() {
+        ui.close_menu();
+        return true;
+    }
+    false
+}
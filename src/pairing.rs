@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Two file name patterns, each with a single `*` wildcard, e.g. `*_before.msgpack` and
+/// `*_after.msgpack`. Files whose names match them with the same text for the wildcard form a pair.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PairPattern {
+    pub a: String,
+    pub b: String,
+}
+
+impl PairPattern {
+    pub fn new(a: &str, b: &str) -> Self {
+        Self {
+            a: a.to_owned(),
+            b: b.to_owned(),
+        }
+    }
+
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::new("*_before.msgpack", "*_after.msgpack"),
+            Self::new("*_old.msgpack", "*_new.msgpack"),
+            Self::new("*_expected.msgpack", "*_actual.msgpack"),
+            Self::new("*.a.msgpack", "*.b.msgpack"),
+        ]
+    }
+}
+
+/// What `name` has in place of the wildcard of `pattern`, if it matches it.
+fn match_wildcard<'a>(pattern: &str, name: &'a str) -> Option<&'a str> {
+    let (prefix, suffix) = pattern.split_once('*')?;
+    name.strip_prefix(prefix)?.strip_suffix(suffix)
+}
+
+/// Two files to compare.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePair {
+    /// What the files have in common, e.g. the text matched by a pattern's wildcard.
+    pub name: String,
+    pub a: PathBuf,
+    pub b: PathBuf,
+}
+
+fn file_names(directory: &Path) -> std::io::Result<Vec<(String, PathBuf)>> {
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            if let Some(name) = entry.file_name().to_str() {
+                names.push((name.to_owned(), entry.path()));
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Pairs up the files in `directory` according to the first of `patterns` that they match, sorted
+/// by name.
+pub fn pair_within(directory: &Path, patterns: &[PairPattern]) -> std::io::Result<Vec<FilePair>> {
+    let names = file_names(directory)?;
+    let mut pairs = BTreeMap::new();
+    for pattern in patterns {
+        let b_by_stem = names
+            .iter()
+            .filter_map(|(name, path)| Some((match_wildcard(&pattern.b, name)?, path)))
+            .collect::<BTreeMap<_, _>>();
+        for (name, a_path) in &names {
+            let Some(stem) = match_wildcard(&pattern.a, name) else {
+                continue;
+            };
+            if let Some(b_path) = b_by_stem.get(stem) {
+                pairs.entry(stem.to_owned()).or_insert_with(|| FilePair {
+                    name: stem.to_owned(),
+                    a: a_path.clone(),
+                    b: (*b_path).clone(),
+                });
+            }
+        }
+    }
+    Ok(pairs.into_values().collect())
+}
+
+/// Pairs up files with the same name in `directory_a` and `directory_b`, sorted by name.
+pub fn pair_across(directory_a: &Path, directory_b: &Path) -> std::io::Result<Vec<FilePair>> {
+    let b_by_name = file_names(directory_b)?
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+    let mut pairs = file_names(directory_a)?
+        .into_iter()
+        .filter_map(|(name, a)| {
+            let b = b_by_name.get(&name)?.clone();
+            Some(FilePair { name, a, b })
+        })
+        .collect::<Vec<_>>();
+    pairs.sort_by(|x, y| x.name.cmp(&y.name));
+    Ok(pairs)
+}
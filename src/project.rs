@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+
+use crate::annotations::Annotations;
+use crate::loader::{LoadOptions, Source};
+
+/// Extension of project files, without the leading dot.
+pub const EXTENSION: &str = "msgpackdiff";
+
+/// A comparison saved to a file of its own, so that someone else can open exactly the comparison
+/// that was being looked at: which files are compared and how, along with what was ignored,
+/// bookmarked, noted and expanded. It's written as JSON so that it can be read and diffed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Project {
+    pub a: ProjectSide,
+    pub b: ProjectSide,
+    /// Paths within the compared values to leave out of the diff.
+    pub ignored_paths: Vec<String>,
+    pub bookmarks: Vec<String>,
+    pub annotations: Annotations,
+    /// Containers of the diff that were expanded or collapsed by hand, by path.
+    pub expanded: BTreeMap<String, bool>,
+}
+
+/// One of the files of a [`Project`] and how it's loaded.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectSide {
+    /// Where the file is, with paths within the project's directory written relative to it so that
+    /// the project can be moved along with the files.
+    pub source: Option<Source>,
+    /// The file's bytes as they were when the project was saved, in base64, if they're embedded.
+    /// They're compared instead of whatever is at `source` by then.
+    pub embedded: Option<String>,
+    pub load_options: LoadOptions,
+    pub record: Option<usize>,
+    pub subtree: String,
+}
+
+impl ProjectSide {
+    /// The bytes of the file embedded in the project, if there are any.
+    pub fn embedded_bytes(&self) -> Result<Option<Vec<u8>>, base64::DecodeError> {
+        self.embedded
+            .as_deref()
+            .map(|embedded| base64::engine::general_purpose::STANDARD.decode(embedded))
+            .transpose()
+    }
+
+    pub fn embed(&mut self, bytes: &[u8]) {
+        self.embedded = Some(base64::engine::general_purpose::STANDARD.encode(bytes));
+    }
+}
+
+impl Project {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut project: Self = serde_json::from_slice(&std::fs::read(path)?)?;
+        if let Some(directory) = path.parent() {
+            for side in [&mut project.a, &mut project.b] {
+                if let Some(Source::File(file)) = &mut side.source {
+                    *file = directory.join(&*file);
+                }
+            }
+        }
+        Ok(project)
+    }
+
+    pub fn save(mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(directory) = path.parent() {
+            for side in [&mut self.a, &mut self.b] {
+                if let Some(Source::File(file)) = &mut side.source {
+                    *file = relative_to(file, directory);
+                }
+            }
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self)?)?;
+        Ok(())
+    }
+}
+
+/// `path` relative to `directory` if it's within it, or else as it is.
+fn relative_to(path: &Path, directory: &Path) -> PathBuf {
+    let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+    match absolute(path).strip_prefix(absolute(directory)) {
+        Ok(relative) => relative.to_owned(),
+        Err(_) => path.to_owned(),
+    }
+}
@@ -0,0 +1,53 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Most-recently-used list of opened files, newest first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentFiles {
+    paths: VecDeque<PathBuf>,
+    /// Files that were compared with each other, as A and B.
+    pairs: VecDeque<(PathBuf, PathBuf)>,
+}
+
+impl RecentFiles {
+    pub const STORAGE_KEY: &'static str = "recent_files";
+    const MAX_LEN: usize = 10;
+
+    pub fn add(&mut self, path: &Path) {
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+        self.paths.retain(|p| *p != path);
+        self.paths.push_front(path);
+        self.paths.truncate(Self::MAX_LEN);
+    }
+
+    pub fn add_pair(&mut self, a: &Path, b: &Path) {
+        let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+        let pair = (absolute(a), absolute(b));
+        self.pairs.retain(|p| *p != pair);
+        self.pairs.push_front(pair);
+        self.pairs.truncate(Self::MAX_LEN);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Path> {
+        self.paths.iter().map(PathBuf::as_path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+    }
+
+    pub fn pairs(&self) -> impl Iterator<Item = (&Path, &Path)> {
+        self.pairs.iter().map(|(a, b)| (a.as_path(), b.as_path()))
+    }
+
+    pub fn clear_pairs(&mut self) {
+        self.pairs.clear();
+    }
+}
@@ -0,0 +1,421 @@
+use std::fmt::Write as _;
+
+use crate::annotations::Annotations;
+use crate::diff::{children, differences, DiffEntry, DiffStatus, Difference};
+use crate::diff_view::{status_name, summarize};
+use crate::display::DisplayOptions;
+use crate::loader::LoadedFile;
+use crate::path::{PathSegment, ValuePath};
+
+/// One of the files a report compares, along with the path of the value within it that's compared.
+pub struct ReportFile<'a> {
+    pub file: &'a LoadedFile,
+    pub compared: ValuePath,
+}
+
+/// Runs of more unchanged siblings than this are left out of the report, other than a couple at
+/// either end of them, so that a change in a huge array doesn't come with all the rest of it.
+const MAX_UNCHANGED_RUN: usize = 7;
+/// Unchanged siblings kept at either end of a run that's left out.
+const CONTEXT: usize = 2;
+
+/// Writes a standalone HTML page showing the diff at `root` of the files `a` and `b` as a tree to
+/// expand and collapse, colored as the app would color it, along with what the files are and how
+/// many differences there are.
+pub fn html_report(
+    a: &ReportFile,
+    b: &ReportFile,
+    root: DiffEntry,
+    display: &DisplayOptions,
+    notes: &Annotations,
+) -> String {
+    let (name_a, name_b) = (a.file.source.short_name(), b.file.source.short_name());
+    let palette = display.palette;
+    let css_color = |color: eframe::egui::Color32| {
+        format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+    };
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{} ↔ {}</title>\n<style>\n{}\n.added {{ color: {}; }}\n\
+         .removed {{ color: {}; text-decoration: line-through; }}\n.changed {{ color: {}; }}\n\
+         </style>\n</head>\n<body>\n",
+        escape(&name_a),
+        escape(&name_b),
+        STYLE,
+        css_color(palette.added),
+        css_color(palette.removed),
+        css_color(palette.changed),
+    );
+    let _ = writeln!(html, "<h1>{} ↔ {}</h1>", escape(&name_a), escape(&name_b));
+    let _ = writeln!(
+        html,
+        "<p class=\"weak\">Made by msgpack-differ on {}</p>",
+        jiff::Zoned::now().strftime("%Y-%m-%d %H:%M:%S %Z")
+    );
+    write_files_table(&mut html, a, b);
+    write_statistics(&mut html, root);
+    html.push_str("<div class=\"tree\">\n");
+    let mut path = ValuePath::root();
+    write_node(&mut html, root, "(root)", &mut path, display, notes);
+    html.push_str("</div>\n</body>\n</html>\n");
+    html
+}
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table.files { border-collapse: collapse; margin-bottom: 1em; }
+table.files th, table.files td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }
+.tree { font-family: monospace; }
+.tree details > :not(summary) { margin-left: 1.5em; }
+.tree .leaf { margin-left: 1.1em; }
+.weak { color: #888; }
+.note { background: #fff3b0; color: #333; border-radius: 3px; padding: 0 0.3em; }";
+
+fn write_files_table(html: &mut String, a: &ReportFile, b: &ReportFile) {
+    html.push_str("<table class=\"files\">\n<tr><th></th><th>A</th><th>B</th></tr>\n");
+    let mut row = |name: &str, of: &dyn Fn(&ReportFile) -> String| {
+        let _ = writeln!(
+            html,
+            "<tr><th>{name}</th><td>{}</td><td>{}</td></tr>",
+            escape(&of(a)),
+            escape(&of(b))
+        );
+    };
+    row("File", &|side| side.file.source.to_string());
+    row("Size", &|side| match side.file.compression {
+        Some((compression, file_size)) => format!(
+            "{file_size} bytes, {compression} of {} bytes",
+            side.file.data.len()
+        ),
+        None => format!("{} bytes", side.file.data.len()),
+    });
+    row("CRC32", &|side| format!("{:08x}", side.file.crc32.result));
+    row("Values", &|side| side.file.value_count.to_string());
+    row("Compared", &|side| side.compared.to_string());
+    html.push_str("</table>\n");
+}
+
+/// How many of the differences in a diff are of each kind.
+struct Statistics {
+    added: usize,
+    removed: usize,
+    changed: usize,
+}
+
+impl Statistics {
+    fn of(differences: &[Difference]) -> Self {
+        let count = |status| {
+            differences
+                .iter()
+                .filter(|found| found.status == status)
+                .count()
+        };
+        Self {
+            added: count(DiffStatus::Added),
+            removed: count(DiffStatus::Removed),
+            changed: count(DiffStatus::Changed),
+        }
+    }
+
+    fn total(&self) -> String {
+        match self.added + self.removed + self.changed {
+            0 => "No differences".to_owned(),
+            1 => "1 difference".to_owned(),
+            n => format!("{n} differences"),
+        }
+    }
+}
+
+fn write_statistics(html: &mut String, root: DiffEntry) {
+    let statistics = Statistics::of(&differences(root.node, root.a, root.b));
+    let _ = writeln!(
+        html,
+        "<p>{}: <span class=\"added\">{} added</span>, \
+         <span class=\"removed\">{} removed</span>, <span class=\"changed\">{} changed</span></p>",
+        statistics.total(),
+        statistics.added,
+        statistics.removed,
+        statistics.changed
+    );
+}
+
+/// Writes the node `entry`, reached by `label`, and what's within it if it holds any differences.
+fn write_node(
+    html: &mut String,
+    entry: DiffEntry,
+    label: &str,
+    path: &mut ValuePath,
+    display: &DisplayOptions,
+    notes: &Annotations,
+) {
+    let status = entry.node.status;
+    let class = match status {
+        DiffStatus::Added => " added",
+        DiffStatus::Removed => " removed",
+        DiffStatus::Changed => " changed",
+        DiffStatus::Ignored => " weak",
+        DiffStatus::Unchanged | DiffStatus::ContainsChanges => "",
+    };
+    let mut line = format!("<span class=\"key{class}\">{}</span>: ", escape(label));
+    match (status, entry.a, entry.b) {
+        (DiffStatus::Changed, a, b) => {
+            let _ = write!(
+                line,
+                "<span class=\"removed\">{}</span> → <span class=\"added\">{}</span>",
+                escape(&summarize(a, display)),
+                escape(&summarize(b, display))
+            );
+        }
+        (_, a, b) => {
+            let _ = write!(
+                line,
+                "<span class=\"{}\">{}</span>",
+                class.trim(),
+                escape(&summarize(b.or(a), display))
+            );
+        }
+    }
+    if status != DiffStatus::Unchanged {
+        let _ = write!(
+            line,
+            " <span class=\"weak\">({})</span>",
+            status_name(status)
+        );
+    }
+    if let Some(note) = notes.get(path) {
+        let _ = write!(line, " <span class=\"note\">🗒 {}</span>", escape(note));
+    }
+    if entry.node.difference_count == 0 || entry.node.children.is_empty() {
+        let _ = writeln!(html, "<div class=\"leaf\">{line}</div>");
+        return;
+    }
+    let _ = writeln!(html, "<details open>\n<summary>{line}</summary>");
+    let mut unchanged = Vec::new();
+    for (segment, child) in children(entry.node, entry.a, entry.b) {
+        if child.node.status == DiffStatus::Unchanged {
+            unchanged.push((segment, child));
+            continue;
+        }
+        write_unchanged(html, &mut unchanged, path, display, notes);
+        let label = segment.to_string();
+        path.push(segment);
+        write_node(html, child, &label, path, display, notes);
+        path.pop();
+    }
+    write_unchanged(html, &mut unchanged, path, display, notes);
+    html.push_str("</details>\n");
+}
+
+/// Writes a run of unchanged siblings, leaving out the middle of it if it's long, and empties it.
+fn write_unchanged(
+    html: &mut String,
+    run: &mut Vec<(PathSegment, DiffEntry)>,
+    path: &mut ValuePath,
+    display: &DisplayOptions,
+    notes: &Annotations,
+) {
+    let left_out = match run.len() > MAX_UNCHANGED_RUN {
+        true => CONTEXT..run.len() - CONTEXT,
+        false => 0..0,
+    };
+    for (i, (segment, child)) in run.drain(..).enumerate() {
+        if i == left_out.start && !left_out.is_empty() {
+            let _ = writeln!(
+                html,
+                "<div class=\"leaf weak\">… {} identical entries …</div>",
+                left_out.len()
+            );
+        }
+        if left_out.contains(&i) {
+            continue;
+        }
+        let label = segment.to_string();
+        path.push(segment);
+        write_node(html, child, &label, path, display, notes);
+        path.pop();
+    }
+}
+
+/// Differences listed at most in a Markdown summary, as it's meant to be read through.
+const MAX_MARKDOWN_DIFFERENCES: usize = 100;
+
+/// Writes a summary of the diff at `root` of the files `a` and `b` in Markdown, to paste into a
+/// pull request or an issue: a table of how many differences there are of each kind, then a list
+/// of them with what each value was and became, in a `diff` block so that they're colored.
+pub fn markdown_summary(
+    a: &ReportFile,
+    b: &ReportFile,
+    root: DiffEntry,
+    display: &DisplayOptions,
+) -> String {
+    let all = differences(root.node, root.a, root.b);
+    let statistics = Statistics::of(&all);
+    let mut markdown = String::new();
+    let describe = |side: &ReportFile| {
+        let compared = match side.compared.segments().is_empty() {
+            true => String::new(),
+            false => format!(" at `{}`", side.compared),
+        };
+        format!(
+            "`{}`{compared} ({} bytes, CRC32 {:08x})",
+            side.file.source.short_name(),
+            side.file.data.len(),
+            side.file.crc32.result
+        )
+    };
+    let _ = writeln!(markdown, "**A:** {}  ", describe(a));
+    let _ = writeln!(markdown, "**B:** {}\n", describe(b));
+    let _ = writeln!(
+        markdown,
+        "| | Count |\n|---|---:|\n| Added | {} |\n| Removed | {} |\n| Changed | {} |",
+        statistics.added, statistics.removed, statistics.changed
+    );
+    if all.is_empty() {
+        markdown.push_str("\nNo differences.\n");
+        return markdown;
+    }
+    markdown.push_str("\n```diff\n");
+    let summarize = |value| summarize(value, display).replace('\n', "\\n");
+    for difference in all.iter().take(MAX_MARKDOWN_DIFFERENCES) {
+        let path = &difference.path;
+        let (a, b) = (summarize(difference.a), summarize(difference.b));
+        let _ = match difference.status {
+            DiffStatus::Added => writeln!(markdown, "+ {path}: {b}"),
+            DiffStatus::Removed => writeln!(markdown, "- {path}: {a}"),
+            _ => writeln!(markdown, "- {path}: {a}\n+ {path}: {b}"),
+        };
+    }
+    markdown.push_str("```\n");
+    if let Some(rest) = all
+        .len()
+        .checked_sub(MAX_MARKDOWN_DIFFERENCES)
+        .filter(|&n| n > 0)
+    {
+        let _ = writeln!(markdown, "\n…and {rest} more.");
+    }
+    markdown
+}
+
+/// Differences named in a one-line summary, it being meant for a chat message.
+const MAX_NOTABLE_DIFFERENCES: usize = 3;
+
+/// Sums up the diff at `root` of the files `a` and `b` in a line, to paste into a chat, e.g.
+/// `a.msgpack vs b.msgpack: 5 added, 2 removed, 11 changed; notable: config.version 3→4, …`.
+pub fn one_line_summary(
+    a: &ReportFile,
+    b: &ReportFile,
+    root: DiffEntry,
+    display: &DisplayOptions,
+) -> String {
+    let all = differences(root.node, root.a, root.b);
+    let names = format!(
+        "{} vs {}",
+        a.file.source.short_name(),
+        b.file.source.short_name()
+    );
+    if all.is_empty() {
+        return format!("{names}: no differences");
+    }
+    let statistics = Statistics::of(&all);
+    let summarize = |value| summarize(value, display).replace('\n', "\\n");
+    let notable: Vec<_> = all
+        .iter()
+        .take(MAX_NOTABLE_DIFFERENCES)
+        .map(|difference| {
+            let path = &difference.path;
+            match difference.status {
+                DiffStatus::Added => format!("{path} added"),
+                DiffStatus::Removed => format!("{path} removed"),
+                _ => format!(
+                    "{path} {}→{}",
+                    summarize(difference.a),
+                    summarize(difference.b)
+                ),
+            }
+        })
+        .collect();
+    let more = match all.len() > MAX_NOTABLE_DIFFERENCES {
+        true => ", …",
+        false => "",
+    };
+    format!(
+        "{names}: {} added, {} removed, {} changed; notable: {}{more}",
+        statistics.added,
+        statistics.removed,
+        statistics.changed,
+        notable.join(", ")
+    )
+}
+
+/// Writes the differences of the diff at `root` as CSV, one row each, with what the value was and
+/// became and, for numbers, by how much it changed, to pivot on in a spreadsheet. Numbers are
+/// written plainly rather than as they're shown, so that they're read back as numbers.
+pub fn csv(root: DiffEntry, display: &DisplayOptions) -> String {
+    let mut csv = String::from("path,status,value_a,value_b,delta\r\n");
+    for difference in differences(root.node, root.a, root.b) {
+        let value = |value: Option<&rmpv::Value>| match value {
+            Some(rmpv::Value::Integer(i)) => i.to_string(),
+            Some(rmpv::Value::F32(f)) => f.to_string(),
+            Some(rmpv::Value::F64(f)) => f.to_string(),
+            Some(rmpv::Value::String(text)) => {
+                String::from_utf8_lossy(text.as_bytes()).into_owned()
+            }
+            value => summarize(value, display),
+        };
+        let delta = match (difference.a, difference.b) {
+            (Some(a), Some(b)) => delta(a, b).unwrap_or_default(),
+            _ => String::new(),
+        };
+        let fields = [
+            difference.path.to_string(),
+            status_name(difference.status).to_owned(),
+            value(difference.a),
+            value(difference.b),
+            delta,
+        ];
+        let row: Vec<_> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// How much the number `a` changed by to become `b`, exactly if both are integers.
+fn delta(a: &rmpv::Value, b: &rmpv::Value) -> Option<String> {
+    let wide = |i: &rmpv::Integer| {
+        i.as_i64()
+            .map_or(i.as_u64().map(i128::from), |i| Some(i.into()))
+    };
+    match (a, b) {
+        (rmpv::Value::Integer(a), rmpv::Value::Integer(b)) => {
+            Some((wide(b)? - wide(a)?).to_string())
+        }
+        (a, b) if a.is_number() && b.is_number() => Some((b.as_f64()? - a.as_f64()?).to_string()),
+        _ => None,
+    }
+}
+
+/// `field` quoted as RFC 4180 has it, if it needs to be.
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\r', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_owned(),
+    }
+}
+
+/// `text` with the characters that mean something in HTML escaped.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
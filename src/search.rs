@@ -0,0 +1,87 @@
+use crate::diff::DiffEntry;
+use crate::path::{PathSegment, ValuePath};
+
+/// A node of a diff that matches a search.
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    pub path: ValuePath,
+    /// Positions of the children leading from the root to the node.
+    pub indexes: Vec<usize>,
+}
+
+/// Finds the nodes of a diff whose key, value on either side, or path contains `query` (ignoring
+/// case), in document order, up to `limit` of them.
+///
+/// Only strings and numbers are searched, as they're rendered. A path only counts as matching at the
+/// node where the match ends, so that searching for `players[3]` doesn't also match everything below
+/// it.
+pub fn search(root: DiffEntry, query: &str, limit: usize) -> Vec<SearchMatch> {
+    let query = query.to_lowercase();
+    let mut matches = Vec::new();
+    if !query.is_empty() {
+        let mut path = ValuePath::root();
+        let mut indexes = Vec::new();
+        search_entry(
+            root,
+            &query,
+            limit,
+            false,
+            &mut path,
+            &mut indexes,
+            &mut matches,
+        );
+    }
+    matches
+}
+
+fn search_entry(
+    entry: DiffEntry,
+    query: &str,
+    limit: usize,
+    parent_path_matches: bool,
+    path: &mut ValuePath,
+    indexes: &mut Vec<usize>,
+    matches: &mut Vec<SearchMatch>,
+) {
+    if matches.len() >= limit {
+        return;
+    }
+    let path_matches = path.to_string().to_lowercase().contains(query);
+    let key_matches = match path.segments().last() {
+        Some(PathSegment::Key(key)) => value_matches(key, query),
+        _ => false,
+    };
+    let value_matches = [entry.a, entry.b]
+        .into_iter()
+        .flatten()
+        .any(|value| value_matches(value, query));
+    if key_matches || value_matches || (path_matches && !parent_path_matches) {
+        matches.push(SearchMatch {
+            path: path.clone(),
+            indexes: indexes.clone(),
+        });
+    }
+    for i in 0..entry.node.children.len() {
+        let (segment, child) = entry.child(i);
+        path.push(segment);
+        indexes.push(i);
+        search_entry(child, query, limit, path_matches, path, indexes, matches);
+        indexes.pop();
+        path.pop();
+    }
+}
+
+/// Whether a scalar's rendering contains `query`, which is expected to be lowercase.
+fn value_matches(value: &rmpv::Value, query: &str) -> bool {
+    let rendered = match value {
+        rmpv::Value::String(s) => match s.as_str() {
+            Some(s) => s.to_lowercase(),
+            None => String::from_utf8_lossy(s.as_bytes()).to_lowercase(),
+        },
+        rmpv::Value::Integer(i) => i.to_string(),
+        rmpv::Value::F32(f) => f.to_string(),
+        rmpv::Value::F64(f) => f.to_string(),
+        _ => return false,
+    };
+    rendered.contains(query)
+}
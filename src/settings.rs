@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use eframe::egui::ThemePreference;
+use serde::{Deserialize, Serialize};
+
+use crate::display::DisplayOptions;
+use crate::history::History;
+use crate::json::JsonConversion;
+use crate::pairing::PairPattern;
+
+/// User-configurable options, persisted along with the rest of the session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    /// Extensions (without the leading dot) offered by the file dialog's msgpack filter.
+    pub file_extensions: Vec<String>,
+    /// How to pair up the files in a directory dropped onto the window.
+    pub pair_patterns: Vec<PairPattern>,
+    /// File of decoders to use for extension types, see [`crate::ext::ExtRegistry`].
+    pub ext_types_file: Option<PathBuf>,
+    /// Whether the window is dark or light, or follows the system.
+    pub theme: ThemePreference,
+    /// How much bigger than normal everything is shown, also changed with Ctrl+= and Ctrl+-.
+    pub zoom: f32,
+    pub display: DisplayOptions,
+    /// How values JSON has nothing like are converted when saving a file as JSON or YAML.
+    pub json_conversion: JsonConversion,
+    /// Whether scrolling one file's tree scrolls the other's to the same place.
+    pub sync_scrolling: bool,
+    /// Whether expanding or collapsing something by hand in a tree or the diff does the same
+    /// everywhere else.
+    pub sync_expanding: bool,
+    /// How long to wait after a file changes before reloading it, as writers often touch a file
+    /// several times in quick succession.
+    pub reload_delay_ms: u64,
+    /// How many earlier versions of each file are kept to compare with.
+    pub history_versions: usize,
+    /// Bound on the size of the data of all versions kept, see [`History`].
+    pub history_mib: usize,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            file_extensions: ["msgpack", "mp", "bin", "pack"].map(str::to_owned).to_vec(),
+            pair_patterns: PairPattern::defaults(),
+            ext_types_file: None,
+            theme: ThemePreference::System,
+            zoom: 1.0,
+            display: DisplayOptions::default(),
+            json_conversion: JsonConversion::default(),
+            sync_scrolling: false,
+            sync_expanding: false,
+            reload_delay_ms: 250,
+            history_versions: History::DEFAULT_MAX_VERSIONS,
+            history_mib: History::DEFAULT_MAX_BYTES / (1024 * 1024),
+        }
+    }
+}
+
+impl AppSettings {
+    /// Each theme that can be picked, with what it's called.
+    pub const THEMES: [(ThemePreference, &'static str); 3] = [
+        (ThemePreference::System, "Follow system"),
+        (ThemePreference::Dark, "Dark"),
+        (ThemePreference::Light, "Light"),
+    ];
+
+    /// Parses a list of extensions separated by commas or whitespace, e.g. `*.msgpack, .mp bin`.
+    pub fn parse_extensions(text: &str) -> Vec<String> {
+        text.split(|c: char| c == ',' || c.is_whitespace())
+            .map(|ext| ext.trim_start_matches('*').trim_start_matches('.'))
+            .filter(|ext| !ext.is_empty())
+            .map(str::to_owned)
+            .collect()
+    }
+}
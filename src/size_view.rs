@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use eframe::egui;
+
+use crate::loader::LoadedFile;
+use crate::path::{PathSegment, ValuePath};
+use crate::spans::SizeTree;
+use crate::value::segment_of;
+
+/// How [`SizeView`] orders the children it lists.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum SizeOrder {
+    /// Biggest first, on whichever side it's bigger.
+    #[default]
+    Size,
+    /// Most grown or shrunk between A and B first.
+    Change,
+}
+
+/// What's compared on one side: the file, and the path of the value compared within it.
+pub type SizedFile<'a> = (&'a LoadedFile, ValuePath);
+
+/// A window ranking the children of a value by how many bytes they take to encode, and by how much
+/// that changed between A and B, which can be drilled down into.
+#[derive(Default)]
+pub struct SizeView {
+    pub open: bool,
+    /// Path within the compared values of the one whose children are listed.
+    path: ValuePath,
+    order: SizeOrder,
+    /// Sizes within A's and B's files, along with when the file was loaded.
+    sizes: [Option<(jiff::Timestamp, Option<SizeTree>)>; 2],
+    rows: Vec<SizeRow>,
+    /// The path, when the files were loaded and the order that `rows` were listed for.
+    rows_built_for: Option<(String, [Option<jiff::Timestamp>; 2], SizeOrder)>,
+}
+
+/// A child of the value being broken down, with its size on each side it's on.
+struct SizeRow {
+    segment: PathSegment,
+    sizes: [Option<usize>; 2],
+    is_container: bool,
+}
+
+impl SizeRow {
+    fn change(&self) -> i64 {
+        let [a, b] = self.sizes.map(|size| size.unwrap_or(0) as i64);
+        b - a
+    }
+
+    fn biggest(&self) -> usize {
+        self.sizes.into_iter().flatten().max().unwrap_or(0)
+    }
+}
+
+impl SizeView {
+    /// Rows beyond this many are left out, as laying out a row per child of a huge array is slow and
+    /// the smallest ones hardly matter.
+    const MAX_ROWS: usize = 500;
+
+    pub fn show(&mut self, ctx: &egui::Context, files: [Option<SizedFile>; 2]) {
+        let mut open = self.open;
+        egui::Window::new("Size breakdown")
+            .open(&mut open)
+            .default_size([520.0, 400.0])
+            .show(ctx, |ui| self.show_contents(ui, files));
+        self.open = open;
+    }
+
+    fn show_contents(&mut self, ui: &mut egui::Ui, files: [Option<SizedFile>; 2]) {
+        self.show_breadcrumbs(ui);
+        for (cached, file) in self.sizes.iter_mut().zip(&files) {
+            match file {
+                Some((file, _))
+                    if cached.as_ref().map(|(time, _)| *time) != Some(file.load_time) =>
+                {
+                    *cached = Some((file.load_time, file.size_tree()));
+                }
+                Some(_) => {}
+                None => *cached = None,
+            }
+        }
+        // the value being broken down on each side, and the sizes within it
+        let sides = [0, 1].map(|side| {
+            let (file, root_path) = files[side].as_ref()?;
+            let sizes = self.sizes[side].as_ref()?.1.as_ref()?;
+            let path = root_path
+                .segments()
+                .iter()
+                .chain(self.path.segments())
+                .cloned()
+                .collect::<ValuePath>();
+            let value = path.resolve(&file.parsed)?;
+            Some((value, sizes.get(&path.indexes(&file.parsed)?)?))
+        });
+        let is_diff = files.iter().all(Option::is_some);
+
+        ui.horizontal(|ui| {
+            for (name, side) in ["A", "B"].into_iter().zip(&sides) {
+                if let Some((_, sizes)) = side {
+                    ui.label(format!("{name}: {}", format_size(sizes.size)));
+                }
+            }
+            if let [Some((_, a)), Some((_, b))] = &sides {
+                ui.label(format_change(a.size, b.size));
+            }
+        });
+        if sides.iter().all(Option::is_none) {
+            ui.weak("Nothing here to break down, as decoding failed within it or it's not in either file");
+            return;
+        }
+        if is_diff {
+            ui.horizontal(|ui| {
+                ui.label("Order by:");
+                ui.selectable_value(&mut self.order, SizeOrder::Size, "Size");
+                ui.selectable_value(&mut self.order, SizeOrder::Change, "Change");
+            });
+        }
+        ui.separator();
+
+        let built_for = (
+            self.path.to_string(),
+            files
+                .each_ref()
+                .map(|file| file.as_ref().map(|(file, _)| file.load_time)),
+            self.order,
+        );
+        if self.rows_built_for.as_ref() != Some(&built_for) {
+            self.rows = size_rows(sides);
+            match self.order {
+                SizeOrder::Change if is_diff => self
+                    .rows
+                    .sort_by_key(|row| std::cmp::Reverse(row.change().unsigned_abs())),
+                _ => self
+                    .rows
+                    .sort_by_key(|row| std::cmp::Reverse(row.biggest())),
+            }
+            self.rows_built_for = Some(built_for);
+        }
+        let rows = &self.rows;
+        let totals = sides.map(|side| side.map(|(_, sizes)| sizes.size));
+        let mut opened = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("sizes").striped(true).show(ui, |ui| {
+                ui.strong("Child");
+                for (name, total) in ["A", "B"].into_iter().zip(totals) {
+                    if total.is_some() {
+                        ui.strong(name);
+                    }
+                }
+                if is_diff {
+                    ui.strong("Change");
+                }
+                ui.strong("Share");
+                ui.end_row();
+                for row in rows.iter().take(Self::MAX_ROWS) {
+                    let name = row.segment.to_string();
+                    if row.is_container {
+                        if ui.link(name).on_hover_text("Break it down").clicked() {
+                            opened = Some(row.segment.clone());
+                        }
+                    } else {
+                        ui.label(name);
+                    }
+                    for (size, total) in row.sizes.into_iter().zip(totals) {
+                        if total.is_some() {
+                            ui.label(size.map_or("—".to_owned(), format_size));
+                        }
+                    }
+                    if is_diff {
+                        let [a, b] = row.sizes.map(|size| size.unwrap_or(0));
+                        ui.label(format_change(a, b));
+                    }
+                    // of whichever side it's biggest on, as that's what the rows are ordered by
+                    let share = row
+                        .sizes
+                        .into_iter()
+                        .zip(totals)
+                        .filter_map(|(size, total)| Some(size? as f32 / total?.max(1) as f32))
+                        .fold(0.0, f32::max);
+                    ui.add(
+                        egui::ProgressBar::new(share)
+                            .desired_width(100.0)
+                            .text(format!("{:.1}%", share * 100.0)),
+                    );
+                    ui.end_row();
+                }
+            });
+            if rows.len() > Self::MAX_ROWS {
+                ui.weak(format!("…and {} smaller ones", rows.len() - Self::MAX_ROWS));
+            }
+        });
+        if let Some(segment) = opened {
+            self.path.push(segment);
+        }
+    }
+
+    /// Shows the path of the value being broken down, with a link to each of its ancestors.
+    fn show_breadcrumbs(&mut self, ui: &mut egui::Ui) {
+        let mut clicked = None;
+        ui.horizontal_wrapped(|ui| {
+            let depth = self.path.segments().len();
+            if ui
+                .add_enabled(depth > 0, egui::Link::new("(root)"))
+                .clicked()
+            {
+                clicked = Some(0);
+            }
+            for (i, segment) in self.path.segments().iter().enumerate() {
+                ui.label("›");
+                if ui
+                    .add_enabled(i + 1 < depth, egui::Link::new(segment.to_string()))
+                    .clicked()
+                {
+                    clicked = Some(i + 1);
+                }
+            }
+        });
+        if let Some(depth) = clicked {
+            self.path = self.path.segments()[..depth].iter().cloned().collect();
+        }
+    }
+}
+
+/// Lists the children of the value on each side, those of A first and then those only in B, with
+/// map entries paired up by key.
+fn size_rows(sides: [Option<(&rmpv::Value, &SizeTree)>; 2]) -> Vec<SizeRow> {
+    let mut rows = Vec::new();
+    // keys are paired up by how they're written, as values can't be hashed as they are
+    let mut row_of = HashMap::new();
+    for (side, found) in sides.iter().enumerate() {
+        let Some((value, sizes)) = found else {
+            continue;
+        };
+        for (i, child_sizes) in sizes.children.iter().enumerate() {
+            let Some(segment) = segment_of(value, i) else {
+                break;
+            };
+            let row = *row_of.entry(segment.to_string()).or_insert_with(|| {
+                rows.push(SizeRow {
+                    segment,
+                    sizes: [None, None],
+                    is_container: false,
+                });
+                rows.len() - 1
+            });
+            // a duplicate key adds up with the entries before it
+            let size = rows[row].sizes[side].get_or_insert(0);
+            *size += child_sizes.size;
+            rows[row].is_container |= !child_sizes.children.is_empty();
+        }
+    }
+    rows
+}
+
+/// Formats a number of bytes, e.g. `87 B` or `1.4 MiB`.
+pub fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Formats how a size changed from `a` to `b`, e.g. `+1.2 KiB (+30%)`.
+fn format_change(a: usize, b: usize) -> String {
+    let sign = if b >= a { "+" } else { "−" };
+    let change = format!("{sign}{}", format_size(a.abs_diff(b)));
+    match a {
+        0 => change,
+        _ => format!(
+            "{change} ({sign}{:.0}%)",
+            a.abs_diff(b) as f64 / a as f64 * 100.0
+        ),
+    }
+}
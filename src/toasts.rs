@@ -0,0 +1,46 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+
+/// Short messages shown for a few seconds in a corner of the window, for things that happen without
+/// being asked for there and then, such as a file being reloaded because it changed.
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<(String, Instant)>,
+}
+
+impl Toasts {
+    const DURATION: Duration = Duration::from_secs(4);
+    /// How long before it goes a toast starts fading out.
+    const FADE: Duration = Duration::from_millis(500);
+
+    pub fn push(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        log::info!("{text}");
+        self.toasts.push((text, Instant::now()));
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.toasts
+            .retain(|(_, shown_at)| shown_at.elapsed() < Self::DURATION);
+        if self.toasts.is_empty() {
+            return;
+        }
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -36.0])
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                for (text, shown_at) in &self.toasts {
+                    let remaining = Self::DURATION.saturating_sub(shown_at.elapsed());
+                    ui.scope(|ui| {
+                        let opacity = remaining.as_secs_f32() / Self::FADE.as_secs_f32();
+                        ui.multiply_opacity(opacity.min(1.0));
+                        egui::Frame::popup(ui.style()).show(ui, |ui| ui.label(text));
+                    });
+                }
+            });
+        // to fade the toasts out, and then get rid of them
+        ctx.request_repaint_after(Duration::from_millis(30));
+    }
+}
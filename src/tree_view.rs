@@ -0,0 +1,1565 @@
+use std::collections::{HashMap, HashSet};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::accessibility;
+use crate::annotations::{show_note_marker, Annotations};
+use crate::display::{BinaryFormat, DisplayOptions};
+use crate::edit::{Edit, ValueKind};
+use crate::framing::{describe_rpc_message, Framing};
+use crate::image_preview::{self, ImageFormat};
+use crate::loader::LoadedFile;
+use crate::nested::decode_msgpack;
+use crate::node_menu::{
+    copy_bytes_buttons, copy_path_buttons, copy_value_buttons, decode_nested_button,
+    save_value_button, NodeAction,
+};
+use crate::path::ValuePath;
+use crate::value::{
+    child_of, compare_keys, count_values, describe_children, key_counts, segment_of,
+};
+
+/// How containers are expanded unless they were expanded or collapsed by hand.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Expansion {
+    /// Each view's own defaults.
+    #[default]
+    Default,
+    All,
+    /// Shows this many levels of nesting below the root, so `ToDepth(1)` collapses everything.
+    ToDepth(usize),
+}
+
+impl Expansion {
+    /// Whether a container `depth` levels below the root is expanded, if it's not by default.
+    pub fn is_expanded(self, depth: usize, by_default: bool) -> bool {
+        match self {
+            Expansion::Default => by_default,
+            Expansion::All => true,
+            Expansion::ToDepth(max_depth) => depth < max_depth,
+        }
+    }
+}
+
+/// Tree of a single document, as shown in a file's panel.
+///
+/// Like [`crate::diff_view::DiffView`], the expanded part of the tree is flattened into rows so that
+/// only the ones scrolled into view need laying out, which keeps huge arrays responsive.
+///
+/// Only what's expanded and how binary values are shown is saved with the session.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TreeView {
+    /// Entries that were expanded or collapsed by hand, by the positions leading to them. Others
+    /// follow `expansion`, which by default expands map entries but not array items or top-level
+    /// values.
+    expanded: HashMap<Vec<usize>, bool>,
+    expansion: Expansion,
+    #[serde(skip)]
+    rows: Vec<Row>,
+    /// When the file that `rows` were built for was loaded and how values were shown then, or `None`
+    /// if they need rebuilding.
+    #[serde(skip)]
+    rows_built_for: Option<(jiff::Timestamp, DisplayOptions)>,
+    /// Entry that was last navigated to or selected, which is highlighted and moved with the arrow
+    /// keys while the tree has keyboard focus.
+    #[serde(skip)]
+    revealed: Option<Vec<usize>>,
+    /// Whether to scroll [`Self::revealed`] into view, after it was moved with the keyboard.
+    #[serde(skip)]
+    scroll_to_selection: bool,
+    /// Rows that were in view as of the last frame.
+    #[serde(skip)]
+    visible_rows: std::ops::Range<usize>,
+    /// Whether to scroll to [`Self::revealed`] once the rows are rebuilt.
+    #[serde(skip)]
+    scroll_to_revealed: bool,
+    /// How binary values are shown where it was picked for them in particular.
+    binary_formats: HashMap<Vec<usize>, BinaryFormat>,
+    /// Pages of big arrays that are expanded, by the positions leading to the array and the page's
+    /// number; see [`Self::PAGE_SIZE`].
+    expanded_pages: HashSet<(Vec<usize>, usize)>,
+    /// Text of the "Go to index" box in the context menu of a big array.
+    #[serde(skip)]
+    index_input: String,
+    /// Entry to scroll to the top of the view once the tree is next shown, see [`Self::scroll_to`].
+    #[serde(skip)]
+    scroll_to: Option<Vec<usize>>,
+    /// How far down the rows were scrolled as of the last frame.
+    #[serde(skip)]
+    scroll_offset: f32,
+    /// Entry at the top of the view, if it was just scrolled to by hand.
+    #[serde(skip)]
+    scrolled_to: Option<Vec<usize>>,
+    /// Entry that was just expanded or collapsed by hand, and whether it's now expanded.
+    #[serde(skip)]
+    toggled: Option<(Vec<usize>, bool)>,
+    /// Text of the box above the tree for only showing map entries with matching keys.
+    #[serde(skip)]
+    key_filter: String,
+    /// Entries shown for [`Self::key_filter`] as of when the rows were built, if it's set.
+    #[serde(skip)]
+    filtered: Option<FilteredEntries>,
+    /// Entry being edited by hand.
+    #[serde(skip)]
+    editing: Option<Editor>,
+    /// Edit that was just made by hand, for the app to make to the file.
+    #[serde(skip)]
+    edit: Option<Edit>,
+}
+
+/// A value being typed in by hand: what type to give it, and the text to read it from.
+struct ValueInput {
+    kind: ValueKind,
+    text: String,
+}
+
+impl ValueInput {
+    /// An input holding `value`, if it's not an array or map.
+    fn of(value: &rmpv::Value) -> Option<Self> {
+        Some(Self {
+            kind: ValueKind::of(value)?,
+            text: ValueKind::text_of(value),
+        })
+    }
+
+    fn string() -> Self {
+        Self {
+            kind: ValueKind::String,
+            text: String::new(),
+        }
+    }
+
+    /// Shows the input, returning the value it reads as and whether Enter was pressed in it.
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        id_salt: impl std::hash::Hash,
+        focus: bool,
+    ) -> (Result<rmpv::Value, String>, bool) {
+        let parsed = self.kind.parse(&self.text);
+        egui::ComboBox::from_id_salt(id_salt)
+            .selected_text(self.kind.to_string())
+            .width(80.0)
+            .show_ui(ui, |ui| {
+                for kind in ValueKind::ALL {
+                    ui.selectable_value(&mut self.kind, kind, kind.to_string());
+                }
+            });
+        if !self.kind.takes_text() {
+            return (parsed, false);
+        }
+        let text_edit = egui::TextEdit::singleline(&mut self.text)
+            .desired_width(240.0)
+            .text_color_opt(parsed.is_err().then(|| ui.visuals().error_fg_color));
+        let response = ui.add(text_edit);
+        if focus {
+            response.request_focus();
+        }
+        let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if let Err(err) = &parsed {
+            response.on_hover_text(err);
+        }
+        (parsed, entered)
+    }
+}
+
+/// What's being typed in by hand for an entry.
+enum Editing {
+    /// A new value for it.
+    Value(ValueInput),
+    /// An entry to add to the end of it, with a key if it's a map.
+    Add {
+        key: Option<ValueInput>,
+        value: ValueInput,
+    },
+    /// The key of a copy of it, as an entry of a map, which goes right after it.
+    CopyKey(ValueInput),
+}
+
+/// The positions of an item of an array being dragged to move it within the array.
+struct DraggedItem(Vec<usize>);
+
+/// An edit being typed in for the entry at `indexes`, on its row, or for the root above the tree.
+struct Editor {
+    indexes: Vec<usize>,
+    editing: Editing,
+    /// Whether the first text box was focused, which it's as soon as it's first shown.
+    focused: bool,
+}
+
+impl Editor {
+    fn new(indexes: Vec<usize>, editing: Editing) -> Self {
+        Self {
+            indexes,
+            editing,
+            focused: false,
+        }
+    }
+
+    /// An editor adding an entry to `container`, the value at `indexes`.
+    fn add_to(indexes: Vec<usize>, container: &rmpv::Value) -> Self {
+        let key = matches!(container, rmpv::Value::Map(_)).then(ValueInput::string);
+        let value = ValueInput::string();
+        Self::new(indexes, Editing::Add { key, value })
+    }
+
+    /// Shows the editor, returning the edit made once it's entered with Enter, or `None` if editing
+    /// was cancelled with Escape; `Some(None)` until either happens. `value` is what's at
+    /// [`Self::indexes`].
+    fn show(&mut self, ui: &mut egui::Ui, value: &rmpv::Value) -> Option<Option<Edit>> {
+        let focus = !std::mem::replace(&mut self.focused, true);
+        let salt = |part: &str| (part.to_owned(), self.indexes.clone());
+        let (edit, entered) = match &mut self.editing {
+            Editing::Value(input) => {
+                let (parsed, entered) = input.show(ui, salt("value kind"), focus);
+                let edit = parsed.map(|value| Edit::Set {
+                    indexes: self.indexes.clone(),
+                    value,
+                });
+                (edit, entered)
+            }
+            Editing::Add { key, value: input } => {
+                let key = key.as_mut().map(|key| {
+                    ui.label("key");
+                    key.show(ui, salt("key kind"), focus)
+                });
+                ui.label("value");
+                let (parsed, value_entered) =
+                    input.show(ui, salt("value kind"), focus && key.is_none());
+                let len = entry_count(value);
+                let (key, key_entered) = match key {
+                    Some((key, entered)) => (key.map(Some), entered),
+                    None => (Ok(None), false),
+                };
+                let edit = key.and_then(|key| {
+                    Ok(Edit::Insert {
+                        indexes: [self.indexes.as_slice(), &[len]].concat(),
+                        key,
+                        value: parsed?,
+                    })
+                });
+                (edit, key_entered || value_entered)
+            }
+            Editing::CopyKey(input) => {
+                ui.label("copy as");
+                let (parsed, entered) = input.show(ui, salt("key kind"), focus);
+                let edit = parsed.map(|key| Edit::Duplicate {
+                    indexes: self.indexes.clone(),
+                    key: Some(key),
+                });
+                (edit, entered)
+            }
+        };
+        let done = ui
+            .add_enabled(edit.is_ok(), egui::Button::new("✔").small())
+            .on_hover_text("Make the edit (Enter)")
+            .on_disabled_hover_text(edit.as_ref().err().cloned().unwrap_or_default())
+            .clicked();
+        let cancelled = accessibility::icon_button(ui, "✕", "Cancel (Escape)").clicked()
+            || ui.input(|i| i.key_pressed(egui::Key::Escape));
+        match edit {
+            _ if cancelled => None,
+            Ok(edit) if done || entered => Some(Some(edit)),
+            _ => Some(None),
+        }
+    }
+}
+
+/// Shows the editor in `editing` if it's for the entry at `indexes`, which holds `value`, putting
+/// the edit made in `edited` once it's entered; returns whether any was shown.
+fn show_editor(
+    ui: &mut egui::Ui,
+    editing: &mut Option<Editor>,
+    edited: &mut Option<Edit>,
+    indexes: &[usize],
+    value: &rmpv::Value,
+) -> bool {
+    let Some(editor) = editing.as_mut().filter(|editor| editor.indexes == indexes) else {
+        return false;
+    };
+    match editor.show(ui, value) {
+        Some(None) => {}
+        Some(Some(edit)) => {
+            *edited = Some(edit);
+            *editing = None;
+        }
+        None => *editing = None,
+    }
+    true
+}
+
+/// Shows the buttons of an entry's context menu for editing it, returning what was picked: an
+/// editor to type in the rest of the edit with, or an edit that's made right away.
+fn edit_buttons(
+    ui: &mut egui::Ui,
+    file: &LoadedFile,
+    indexes: &[usize],
+    value: &rmpv::Value,
+) -> Option<Result<Editor, Edit>> {
+    let mut picked = None;
+    if let Some(input) = ValueInput::of(value) {
+        if ui
+            .button("Edit value…")
+            .on_hover_text("Or double-click it")
+            .clicked()
+        {
+            picked = Some(Ok(Editor::new(indexes.to_vec(), Editing::Value(input))));
+        }
+    }
+    let add = match value {
+        rmpv::Value::Map(_) => Some("Add entry…"),
+        rmpv::Value::Array(_) => Some("Add item…"),
+        _ => None,
+    };
+    if add.is_some_and(|add| ui.button(add).clicked()) {
+        picked = Some(Ok(Editor::add_to(indexes.to_vec(), value)));
+    }
+    match parent_map_entry(&file.parsed, indexes) {
+        Some((key, _)) => {
+            if ui
+                .button("Duplicate…")
+                .on_hover_text("Add a copy of it right after it, under a key of its own")
+                .clicked()
+            {
+                let input = ValueInput::of(key).unwrap_or_else(ValueInput::string);
+                picked = Some(Ok(Editor::new(indexes.to_vec(), Editing::CopyKey(input))));
+            }
+        }
+        None => {
+            if ui
+                .button("Duplicate")
+                .on_hover_text("Add a copy of it right after it")
+                .clicked()
+            {
+                let indexes = indexes.to_vec();
+                picked = Some(Err(Edit::Duplicate { indexes, key: None }));
+            }
+        }
+    }
+    if ui.button("Remove").clicked() {
+        picked = Some(Err(Edit::Remove {
+            indexes: indexes.to_vec(),
+        }));
+    }
+    picked
+}
+
+/// How many entries an array or map has.
+fn entry_count(container: &rmpv::Value) -> usize {
+    match container {
+        rmpv::Value::Array(items) => items.len(),
+        rmpv::Value::Map(entries) => entries.len(),
+        _ => 0,
+    }
+}
+
+/// Entries of a file that [`TreeView::key_filter`] keeps: those whose key matches it, with
+/// everything inside them, and those holding any of them.
+#[derive(Default)]
+struct FilteredEntries {
+    matching: HashSet<Vec<usize>>,
+    holding_matches: HashSet<Vec<usize>>,
+}
+
+impl FilteredEntries {
+    fn find(value: &rmpv::Value, filter: &str) -> Self {
+        let mut found = Self::default();
+        found.visit(value, &filter.to_lowercase(), &mut Vec::new());
+        found
+    }
+
+    /// Notes the entries within `value` that are kept, returning whether there are any.
+    fn visit(&mut self, value: &rmpv::Value, filter: &str, indexes: &mut Vec<usize>) -> bool {
+        let len = match value {
+            rmpv::Value::Array(items) => items.len(),
+            rmpv::Value::Map(entries) => entries.len(),
+            _ => 0,
+        };
+        let mut any = false;
+        for i in 0..len {
+            let child = child_of(value, i).expect("index is in range");
+            indexes.push(i);
+            let key_matches = value.as_map().is_some_and(|entries| {
+                let key = match &entries[i].0 {
+                    rmpv::Value::String(key) => String::from_utf8_lossy(key.as_bytes()).into(),
+                    key => key.to_string(),
+                };
+                matches_filter(filter, &key.to_lowercase())
+            });
+            if key_matches {
+                self.matching.insert(indexes.clone());
+                any = true;
+            } else if self.visit(child, filter, indexes) {
+                self.holding_matches.insert(indexes.clone());
+                any = true;
+            }
+            indexes.pop();
+        }
+        any
+    }
+
+    fn keeps(&self, indexes: &[usize]) -> bool {
+        self.holding_matches.contains(indexes)
+            || (1..=indexes.len()).any(|depth| self.matching.contains(&indexes[..depth]))
+    }
+}
+
+/// Whether `key` contains `filter`, or matches it if it has `*` wildcards in it, e.g. `render_*`.
+fn matches_filter(filter: &str, key: &str) -> bool {
+    let mut parts = filter.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(last) = parts.next_back() else {
+        return key.contains(filter);
+    };
+    let Some(mut rest) = key.strip_prefix(first) else {
+        return false;
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// A visible entry of an array or map, or a line of the contents of an expanded binary value.
+struct Row {
+    /// Positions of the children leading from the root to this entry, see [`child_of`].
+    indexes: Vec<usize>,
+    label: String,
+    is_expanded: bool,
+    /// Which line of the contents of the binary value at `indexes` this row shows if that's what it
+    /// is, or for a string, where the line starts.
+    line: Option<usize>,
+    /// Whether the entry is an expanded binary value that holds msgpack, which is worth pointing out.
+    holds_msgpack: bool,
+    /// How many entries of the map holding this entry have its key, if more than one.
+    duplicate_key: Option<usize>,
+    /// Which page of the items of the array at `indexes` this row stands for, if that's what it is.
+    page: Option<usize>,
+}
+
+impl Row {
+    /// Whether the row shows an entry, rather than a line of one or a page of an array.
+    fn is_entry(&self) -> bool {
+        self.line.is_none() && self.page.is_none()
+    }
+}
+
+/// What every row shown in a frame is shown with.
+struct RowContext<'a> {
+    file: &'a LoadedFile,
+    display: &'a DisplayOptions,
+    notes: &'a Annotations,
+    /// Where in the file the paths of `notes` start from, if they're of this file.
+    notes_root: Option<&'a ValuePath>,
+    /// Whether the file decoded in full, so that editing it won't write back a value cut short.
+    can_edit: bool,
+    /// Whether the tree has the keyboard focus.
+    has_focus: bool,
+    row_height: f32,
+}
+
+impl RowContext<'_> {
+    /// The note attached to the entry at `indexes`, if any.
+    fn note_of(&self, indexes: &[usize]) -> Option<&str> {
+        let root = self.notes_root.filter(|_| !self.notes.is_empty())?;
+        self.notes
+            .get_within(root, &path_of(&self.file.parsed, indexes))
+    }
+}
+
+/// What happened to the rows shown in a frame, along with the state of the tree that they change.
+#[derive(Default)]
+struct RowsResponse {
+    /// Positions of an entry whose expansion was toggled, and whether it's now expanded.
+    toggled: Option<(Vec<usize>, bool)>,
+    /// Positions of an array, one of its pages, and whether that's now expanded.
+    toggled_page: Option<(Vec<usize>, usize, bool)>,
+    /// Positions of an entry to go to, picked by its index in an array.
+    go_to_index: Option<Vec<usize>>,
+    /// Positions of a binary value and how it's now to be shown, or `None` for the default.
+    picked_format: Option<(Vec<usize>, Option<BinaryFormat>)>,
+    /// Positions of an entry whose whole subtree should be expanded or collapsed.
+    subtree_expanded: Option<(Vec<usize>, bool)>,
+    clicked: Option<Vec<usize>>,
+    /// The row of the selected entry, for the tree to report as its active descendant.
+    active_row: Option<egui::Id>,
+    editing: Option<Editor>,
+    edited: Option<Edit>,
+    /// What's typed into the "Go to index" field of an array's context menu.
+    index_input: String,
+    action: Option<NodeAction>,
+}
+
+impl TreeView {
+    const INDENT: f32 = 16.0;
+    /// Arrays with more items than this show them in pages of this many, which are collapsed unless
+    /// expanded by hand, so that a huge one doesn't bury everything after it.
+    const PAGE_SIZE: usize = 100;
+
+    /// Expands or collapses everything, forgetting what was expanded or collapsed by hand.
+    pub fn set_expansion(&mut self, expansion: Expansion) {
+        self.expansion = expansion;
+        self.expanded.clear();
+        self.expanded_pages.clear();
+        self.rows_built_for = None;
+    }
+
+    /// Scrolls so that the entry at `indexes` is at the top of the view, or else the closest of what
+    /// holds it that's visible, without expanding anything.
+    pub fn scroll_to(&mut self, indexes: Vec<usize>) {
+        self.scroll_to = Some(indexes);
+    }
+
+    /// The entry at the top of the view if it was scrolled by hand in the last frame, for syncing
+    /// up the other file's tree.
+    pub fn scrolled_to(&self) -> Option<&[usize]> {
+        self.scrolled_to.as_deref()
+    }
+
+    /// The entry that was expanded or collapsed by hand in the last frame if one was, and whether
+    /// it's now expanded.
+    pub fn toggled(&self) -> Option<(&[usize], bool)> {
+        self.toggled
+            .as_ref()
+            .map(|(indexes, expanded)| (indexes.as_slice(), *expanded))
+    }
+
+    /// Expands or collapses the entry at `indexes`, as if by hand.
+    pub fn set_expanded(&mut self, indexes: Vec<usize>, expanded: bool) {
+        self.expanded.insert(indexes, expanded);
+        self.rows_built_for = None;
+    }
+
+    /// The edit that was made by hand in the last frame, if one was.
+    pub fn take_edit(&mut self) -> Option<Edit> {
+        self.edit.take()
+    }
+
+    /// Moves what's kept for entries by their positions, such as whether they're expanded, to where
+    /// `edit` moves them, once it's made to the file.
+    pub fn follow_edit(&mut self, edit: &Edit) {
+        fn moved<T: Clone, C: FromIterator<(Vec<usize>, T)>>(
+            edit: &Edit,
+            entries: impl IntoIterator<Item = (Vec<usize>, T)>,
+        ) -> C {
+            entries
+                .into_iter()
+                .flat_map(|(indexes, kept)| {
+                    let moved = edit.moved(&indexes);
+                    moved
+                        .into_iter()
+                        .map(move |indexes| (indexes, kept.clone()))
+                })
+                .collect()
+        }
+        self.expanded = moved(edit, std::mem::take(&mut self.expanded));
+        self.binary_formats = moved(edit, std::mem::take(&mut self.binary_formats));
+        self.expanded_pages = moved(edit, std::mem::take(&mut self.expanded_pages));
+        self.revealed = self
+            .revealed
+            .take()
+            .and_then(|indexes| edit.moved(&indexes).into_iter().next());
+        self.rows_built_for = None;
+    }
+
+    /// The entry that was last navigated to or selected, if any.
+    pub fn selected(&self) -> Option<&[usize]> {
+        self.revealed.as_deref()
+    }
+
+    /// Expands everything holding the entry at `indexes` and scrolls to it.
+    pub fn reveal(&mut self, indexes: Vec<usize>) {
+        for depth in 1..indexes.len() {
+            self.expanded.insert(indexes[..depth].to_vec(), true);
+        }
+        // which only matters for the ones that are big arrays
+        for depth in 0..indexes.len() {
+            let page = indexes[depth] / Self::PAGE_SIZE;
+            self.expanded_pages
+                .insert((indexes[..depth].to_vec(), page));
+        }
+        self.revealed = Some(indexes);
+        self.scroll_to_revealed = true;
+        self.rows_built_for = None;
+    }
+
+    /// Shows `file`, returning what was picked from an entry's context menu for the app to do, with
+    /// the path of the entry within the whole file. Entries with notes attached are marked, the
+    /// notes being on paths within the value at `notes_root`.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        file: &LoadedFile,
+        display: &DisplayOptions,
+        notes: &Annotations,
+        notes_root: Option<&ValuePath>,
+    ) -> Option<NodeAction> {
+        self.toggled = None;
+        self.scrolled_to = None;
+        if let rmpv::Value::Binary(bytes) = &*file.parsed {
+            if ImageFormat::detect(bytes).is_some() {
+                image_preview::show_preview(ui, bytes);
+            }
+            display.binary_format.show_all(ui, bytes);
+            return None;
+        }
+        if !is_container(&file.parsed) {
+            crate::render_rmpv(ui, &file.parsed, display);
+            return None;
+        }
+        let mut copy_text = false;
+        let mut editing = self.editing.take();
+        let mut edited = None;
+        // what was decoded before a failure might be a value cut short, which writing back would
+        // write as if it were whole
+        let can_edit = file.decode_failure.is_none();
+        ui.horizontal(|ui| {
+            copy_text = accessibility::icon_button(ui, "📋", "Copy visible tree as text").clicked();
+            let add_name = match &*file.parsed {
+                rmpv::Value::Map(_) => "Add an entry to the root map",
+                _ if file.is_multi_value() => "Add a value to the end of the file",
+                _ => "Add an item to the root array",
+            };
+            let add = ui.add_enabled_ui(can_edit, |ui| {
+                accessibility::icon_button(ui, "➕", add_name)
+            });
+            if add.inner.clicked() {
+                editing = Some(Editor::add_to(Vec::new(), &file.parsed));
+            }
+            ui.label("Keys:");
+            let response = ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.key_filter)
+                        .hint_text("Filter, e.g. password or render_*")
+                        .desired_width(200.0),
+                )
+                .on_hover_text(
+                    "Only show map entries whose keys contain this, or match it with * standing \
+                     for anything, along with what holds them",
+                );
+            if response.changed() {
+                self.rows_built_for = None;
+            }
+            if !self.key_filter.is_empty() {
+                if accessibility::icon_button(ui, "✕", "Clear the filter").clicked() {
+                    self.key_filter.clear();
+                    self.rows_built_for = None;
+                }
+                if let Some(filtered) = &self.filtered {
+                    match filtered.matching.len() {
+                        0 => ui.weak("no matching keys"),
+                        1 => ui.weak("1 matching key"),
+                        count => ui.weak(format!("{count} matching keys")),
+                    };
+                }
+            }
+        });
+        if editing
+            .as_ref()
+            .is_some_and(|editor| editor.indexes.is_empty())
+        {
+            ui.horizontal(|ui| show_editor(ui, &mut editing, &mut edited, &[], &file.parsed));
+        }
+        let built_for = (file.load_time, display.clone());
+        if self.rows_built_for.as_ref() != Some(&built_for) {
+            self.build_rows(file, display);
+            self.rows_built_for = Some(built_for.clone());
+        }
+        if copy_text {
+            ui.ctx().copy_text(self.visible_text(file, display));
+        }
+        let focus_id = ui.id().with("tree focus");
+        let has_focus = ui.memory_mut(|memory| {
+            memory.interested_in_focus(focus_id);
+            let filter = egui::EventFilter {
+                horizontal_arrows: true,
+                vertical_arrows: true,
+                ..Default::default()
+            };
+            memory.set_focus_lock_filter(focus_id, filter);
+            memory.has_focus(focus_id)
+        });
+        if has_focus && self.handle_keys(ui, file, display) {
+            self.build_rows(file, display);
+            self.rows_built_for = Some(built_for);
+        }
+
+        let row_height = ui.spacing().interact_size.y;
+        let context = RowContext {
+            file,
+            display,
+            notes,
+            notes_root,
+            can_edit,
+            has_focus,
+            row_height,
+        };
+        let mut rows = RowsResponse {
+            editing,
+            edited,
+            index_input: std::mem::take(&mut self.index_input),
+            ..Default::default()
+        };
+        let spaced_height = row_height + ui.spacing().item_spacing.y;
+        let mut scroll_area = egui::ScrollArea::both().id_salt("tree").auto_shrink(false);
+        let mut scrolled_by_program = false;
+        let selected_row = self.revealed.as_ref().and_then(|selected| {
+            self.rows
+                .iter()
+                .position(|row| &row.indexes == selected && row.is_entry())
+        });
+        if let Some(index) = selected_row.filter(|_| std::mem::take(&mut self.scroll_to_selection))
+        {
+            // just far enough to bring it into view
+            let offset = if index <= self.visible_rows.start {
+                Some(index as f32 * spaced_height)
+            } else if index + 1 >= self.visible_rows.end {
+                Some(((index + 1) as f32 * spaced_height - ui.available_height()).max(0.0))
+            } else {
+                None
+            };
+            if let Some(offset) = offset {
+                scroll_area = scroll_area.vertical_scroll_offset(offset);
+                scrolled_by_program = true;
+            }
+        }
+        if let Some(target) = self.scroll_to.take() {
+            // the deepest of what holds the target that has a row, if the target itself doesn't
+            let target_row = (0..=target.len()).rev().find_map(|depth| {
+                let indexes = &target[..depth];
+                self.rows
+                    .iter()
+                    .position(|row| row.indexes == indexes && row.is_entry())
+            });
+            if let Some(index) = target_row {
+                scroll_area = scroll_area.vertical_scroll_offset(index as f32 * spaced_height);
+                scrolled_by_program = true;
+            }
+        }
+        if std::mem::take(&mut self.scroll_to_revealed) {
+            if let Some(index) = selected_row {
+                // a few rows above the target for context
+                let offset = index.saturating_sub(3) as f32 * spaced_height;
+                scroll_area = scroll_area.vertical_scroll_offset(offset);
+                scrolled_by_program = true;
+            }
+        }
+        let mut top_row = None;
+        let mut visible_rows = 0..0;
+        let tree_name = format!("Values of {}", file.source.short_name());
+        let output = accessibility::tree(ui, focus_id, &tree_name, |ui| {
+            let output = scroll_area.show_rows(ui, row_height, self.rows.len(), |ui, range| {
+                top_row = self.rows.get(range.start).map(|row| row.indexes.clone());
+                visible_rows = range.clone();
+                for row in &self.rows[range] {
+                    self.show_row(ui, &context, row, &mut rows);
+                }
+            });
+            (output, rows.active_row.filter(|_| has_focus))
+        });
+        if let Some((indexes, format)) = rows.picked_format {
+            match format {
+                Some(format) => self.binary_formats.insert(indexes, format),
+                None => self.binary_formats.remove(&indexes),
+            };
+            self.rows_built_for = None;
+        }
+        self.index_input = rows.index_input;
+        self.editing = rows.editing;
+        self.edit = rows.edited;
+        self.visible_rows = visible_rows;
+        if let Some(indexes) = rows.clicked {
+            self.revealed = Some(indexes);
+            ui.memory_mut(|memory| memory.request_focus(focus_id));
+        }
+        let offset = output.state.offset.y;
+        self.scrolled_to = top_row.filter(|_| offset != self.scroll_offset && !scrolled_by_program);
+        self.scroll_offset = offset;
+        if let Some((indexes, page, expanded)) = rows.toggled_page {
+            match expanded {
+                true => self.expanded_pages.insert((indexes, page)),
+                false => self.expanded_pages.remove(&(indexes, page)),
+            };
+            self.rows_built_for = None;
+        }
+        if let Some(indexes) = rows.go_to_index {
+            self.reveal(indexes);
+        }
+        self.toggled = rows.toggled.clone();
+        if let Some((indexes, expanded)) = rows.toggled {
+            self.set_expanded(indexes, expanded);
+        }
+        if let Some((mut indexes, expanded)) = rows.subtree_expanded {
+            if let Some(value) = value_at(&file.parsed, &indexes) {
+                self.set_subtree_expanded(value, &mut indexes, expanded);
+            }
+            self.rows_built_for = None;
+        }
+        rows.action
+    }
+
+    /// Shows `row`, one of those in view: a page of an array, a line of a string or binary value,
+    /// or an entry with the box around it that's clicked, dragged and right-clicked.
+    fn show_row(
+        &self,
+        ui: &mut egui::Ui,
+        context: &RowContext,
+        row: &Row,
+        response: &mut RowsResponse,
+    ) {
+        let (file, display, row_height) = (context.file, context.display, context.row_height);
+        let Some(value) = value_at(&file.parsed, &row.indexes) else {
+            return;
+        };
+        if let Some(page) = row.page {
+            ui.horizontal(|ui| {
+                ui.set_min_height(row_height);
+                ui.add_space(row.indexes.len() as f32 * Self::INDENT);
+                if accessibility::show_expander(ui, row.is_expanded) {
+                    response.toggled_page = Some((row.indexes.clone(), page, !row.is_expanded));
+                }
+                ui.weak(&row.label)
+                    .on_hover_text("Right-click the array to go to an index");
+            });
+            return;
+        }
+        if let (Some(line), rmpv::Value::Binary(bytes)) = (row.line, value) {
+            let format = self.binary_format(&row.indexes, display.binary_format);
+            ui.horizontal(|ui| {
+                ui.set_min_height(row_height);
+                ui.add_space(
+                    row.indexes.len() as f32 * Self::INDENT + ui.spacing().interact_size.y,
+                );
+                format.show_line(ui, bytes, line);
+            });
+            return;
+        }
+        if let (Some(start), Some(text)) = (row.line, value.as_str()) {
+            ui.horizontal(|ui| {
+                ui.set_min_height(row_height);
+                ui.add_space(
+                    row.indexes.len() as f32 * Self::INDENT + ui.spacing().interact_size.y,
+                );
+                ui.label(display.strings.text(display.string_line(text, start)));
+            });
+            return;
+        }
+        let rect = egui::Rect::from_min_size(
+            ui.cursor().min,
+            egui::vec2(ui.available_width(), row_height),
+        );
+        let is_selected = Some(&row.indexes) == self.revealed.as_ref();
+        if is_selected {
+            ui.painter()
+                .rect_filled(rect, 2.0, ui.visuals().selection.bg_fill);
+            if context.has_focus {
+                ui.painter()
+                    .rect_stroke(rect, 2.0, ui.visuals().selection.stroke);
+            }
+        }
+        // interacted with before the row's contents so that those get clicks first; rows
+        // aren't tabbed through, as the tree as a whole is focused to go through them
+        let row_response = ui.interact(
+            rect,
+            ui.id().with(&row.indexes),
+            accessibility::CLICK_WITHOUT_FOCUS,
+        );
+        if row_response.clicked() {
+            response.clicked = Some(row.indexes.clone());
+        }
+        if row_response.double_clicked() && context.can_edit {
+            if let Some(input) = ValueInput::of(value) {
+                response.editing = Some(Editor::new(row.indexes.clone(), Editing::Value(input)));
+            }
+        }
+        if is_selected {
+            response.active_row = Some(row_response.id);
+        }
+        let name = || {
+            let path = path_of(&file.parsed, &row.indexes);
+            let name = accessibility::describe_entry(path.segments().last(), Some(value), display);
+            accessibility::with_note(name, context.note_of(&row.indexes))
+        };
+        let expanded = is_expandable(value).then_some(row.is_expanded);
+        let depth = row.indexes.len();
+        let (&position, parent) = row.indexes.split_last().expect("rows are of entries");
+        let is_array_item = context.can_edit
+            && matches!(value_at(&file.parsed, parent), Some(rmpv::Value::Array(_)));
+        let contents = |ui: &mut egui::Ui| {
+            ui.horizontal(|ui| self.show_entry(ui, context, row, value, is_array_item, response))
+        };
+        accessibility::tree_item(
+            ui,
+            &row_response,
+            depth,
+            expanded,
+            is_selected,
+            name,
+            contents,
+        );
+        if is_array_item {
+            // only within the array it's in
+            let is_sibling = |dragged: &DraggedItem| {
+                dragged
+                    .0
+                    .split_last()
+                    .is_some_and(|(&from, dragged_parent)| {
+                        dragged_parent == parent && from != position
+                    })
+            };
+            if let Some(dragged) = row_response
+                .dnd_hover_payload::<DraggedItem>()
+                .filter(|dragged| is_sibling(dragged))
+            {
+                // where it ends up: after this item if it comes from before it
+                let y = match dragged.0[parent.len()] < position {
+                    true => rect.bottom(),
+                    false => rect.top(),
+                };
+                let stroke = egui::Stroke::new(2.0, ui.visuals().selection.stroke.color);
+                ui.painter().hline(rect.x_range(), y, stroke);
+            }
+            if let Some(dragged) = row_response
+                .dnd_release_payload::<DraggedItem>()
+                .filter(|dragged| is_sibling(dragged))
+            {
+                response.edited = Some(Edit::Move {
+                    indexes: dragged.0.clone(),
+                    to: position,
+                });
+            }
+        }
+        row_response.context_menu(|ui| self.show_row_menu(ui, context, row, value, response));
+    }
+
+    /// Shows the contents of the row of the entry `value`: its key or index, and then the value if
+    /// it's not a container, or else a summary of what it holds.
+    fn show_entry(
+        &self,
+        ui: &mut egui::Ui,
+        context: &RowContext,
+        row: &Row,
+        value: &rmpv::Value,
+        is_array_item: bool,
+        response: &mut RowsResponse,
+    ) {
+        let (file, display) = (context.file, context.display);
+        ui.set_min_height(context.row_height);
+        ui.add_space((row.indexes.len() - 1) as f32 * Self::INDENT);
+        if is_expandable(value) {
+            if accessibility::show_expander(ui, row.is_expanded) {
+                response.toggled = Some((row.indexes.clone(), !row.is_expanded));
+            }
+        } else {
+            ui.add_space(ui.spacing().interact_size.y);
+        }
+        if is_array_item {
+            let id = ui.id().with(("drag", &row.indexes));
+            let dragged = DraggedItem(row.indexes.clone());
+            ui.dnd_drag_source(id, dragged, |ui| ui.weak("☰"))
+                .response
+                .on_hover_text("Drag onto another item of the array to move it there");
+        }
+        let (editing, edited) = (&mut response.editing, &mut response.edited);
+        if !is_container(value) {
+            ui.label(format!("{}:", row.label)).on_hover_ui(|ui| {
+                ui.label(describe_span(ui.ctx(), file, &row.indexes));
+            });
+            show_duplicate_key(ui, row.duplicate_key);
+            show_note_marker(ui, context.note_of(&row.indexes));
+            if show_editor(ui, editing, edited, &row.indexes, value) {
+                return;
+            }
+            match value {
+                rmpv::Value::Binary(bytes) => {
+                    let format = self.binary_format(&row.indexes, display.binary_format);
+                    if let Some(picked) = show_binary(ui, bytes, format) {
+                        response.picked_format = Some((row.indexes.clone(), picked));
+                    }
+                }
+                _ => crate::render_rmpv(ui, value, display),
+            }
+            let is_cut_short = value
+                .as_str()
+                .is_some_and(|text| display.is_cut_short(text));
+            if is_cut_short {
+                let text = if row.is_expanded {
+                    "Show less"
+                } else {
+                    "Show more"
+                };
+                if ui.small_button(text).clicked() {
+                    response.toggled = Some((row.indexes.clone(), !row.is_expanded));
+                }
+            }
+            if let rmpv::Value::Binary(bytes) = value {
+                if let Some(thumbnail) = image_preview::show_thumbnail(ui, bytes) {
+                    thumbnail.on_hover_ui(|ui| image_preview::show_preview(ui, bytes));
+                }
+            }
+            let decode_hint = "Show binary values holding msgpack as what they hold, in both files";
+            if row.holds_msgpack
+                && ui
+                    .small_button("Decode as msgpack")
+                    .on_hover_text(decode_hint)
+                    .clicked()
+            {
+                response.action = Some(NodeAction::DecodeNested);
+            }
+            return;
+        }
+        ui.label(&row.label).on_hover_ui(|ui| {
+            if let Some((key, _)) = parent_map_entry(&file.parsed, &row.indexes) {
+                ui.label(crate::type_name_of(key));
+            }
+            ui.label(describe_span(ui.ctx(), file, &row.indexes));
+        });
+        show_duplicate_key(ui, row.duplicate_key);
+        show_note_marker(ui, context.note_of(&row.indexes));
+        let count = ui
+            .weak(describe_children(value).unwrap_or_default())
+            .on_hover_ui(|ui| {
+                let total = count_values_within(ui.ctx(), file, &row.indexes);
+                ui.label(format!("{total} values within it in all"));
+            });
+        if file.nested.contains(&row.indexes) {
+            ui.weak("(msgpack in binary)");
+            count.on_hover_text("Decoded from a binary value holding msgpack");
+        }
+        show_editor(ui, editing, edited, &row.indexes, value);
+    }
+
+    /// Shows the context menu of the row of the entry `value`.
+    fn show_row_menu(
+        &self,
+        ui: &mut egui::Ui,
+        context: &RowContext,
+        row: &Row,
+        value: &rmpv::Value,
+        response: &mut RowsResponse,
+    ) {
+        let file = context.file;
+        let path = path_of(&file.parsed, &row.indexes);
+        copy_path_buttons(ui, &path);
+        copy_value_buttons(ui, value, "");
+        if let Some(format) = copy_bytes_buttons(ui, "") {
+            match file.value_bytes(&row.indexes) {
+                Some(bytes) => ui.ctx().copy_text(format.encode(bytes)),
+                None => log::warn!("Can't find the bytes of {path}, as decoding failed within it"),
+            }
+        }
+        if let Some(save) = save_value_button(ui, value, &path, "") {
+            response.action = Some(save);
+        }
+        ui.separator();
+        ui.add_enabled_ui(context.can_edit, |ui| {
+            if let Some(picked) = edit_buttons(ui, file, &row.indexes, value) {
+                match picked {
+                    Ok(editor) => response.editing = Some(editor),
+                    Err(edit) => response.edited = Some(edit),
+                }
+                ui.close_menu();
+            }
+        })
+        .response
+        .on_disabled_hover_text("The file didn't decode in full, so it can't be written back");
+        ui.separator();
+        if decode_nested_button(ui, Some(value)) {
+            response.action = Some(NodeAction::DecodeNested);
+        }
+        if let rmpv::Value::Array(items) = value {
+            if items.len() > Self::PAGE_SIZE {
+                ui.horizontal(|ui| {
+                    ui.label("Go to index:");
+                    let index_input = &mut response.index_input;
+                    let input = ui.add(
+                        egui::TextEdit::singleline(index_input)
+                            .hint_text(format!("0–{}", items.len() - 1))
+                            .desired_width(80.0),
+                    );
+                    let submitted =
+                        input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    let index = index_input
+                        .trim()
+                        .replace(['_', ','], "")
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&index| index < items.len());
+                    if ui
+                        .add_enabled(index.is_some(), egui::Button::new("Go"))
+                        .clicked()
+                        || submitted
+                    {
+                        if let Some(index) = index {
+                            let mut indexes = row.indexes.clone();
+                            indexes.push(index);
+                            response.go_to_index = Some(indexes);
+                            ui.close_menu();
+                        }
+                    }
+                });
+            }
+        }
+        if is_container(value) {
+            ui.separator();
+            if ui.button("Expand subtree").clicked() {
+                response.subtree_expanded = Some((row.indexes.clone(), true));
+                ui.close_menu();
+            }
+            if ui.button("Collapse subtree").clicked() {
+                response.subtree_expanded = Some((row.indexes.clone(), false));
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        if ui.button("Bookmark").clicked() {
+            response.action = Some(NodeAction::Bookmark(path.clone()));
+            ui.close_menu();
+        }
+        if ui.button("Note…").clicked() {
+            response.action = Some(NodeAction::Annotate(path.clone()));
+            ui.close_menu();
+        }
+        if ui.button("Ignore this path").clicked() {
+            response.action = Some(NodeAction::Ignore(path.clone()));
+            ui.close_menu();
+        }
+        if ui.button("Compare only this subtree").clicked() {
+            response.action = Some(NodeAction::CompareSubtree(path));
+            ui.close_menu();
+        }
+    }
+
+    /// Moves the selection up and down with the arrow keys, and expands and collapses the selected
+    /// entry with left and right or Enter; returns whether anything was expanded or collapsed.
+    fn handle_keys(&mut self, ui: &egui::Ui, file: &LoadedFile, display: &DisplayOptions) -> bool {
+        use egui::Key;
+        let [up, down, left, right, enter] = [
+            Key::ArrowUp,
+            Key::ArrowDown,
+            Key::ArrowLeft,
+            Key::ArrowRight,
+            Key::Enter,
+        ]
+        .map(|key| ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key)));
+        let entries = (0..self.rows.len())
+            .filter(|&i| self.rows[i].is_entry())
+            .collect::<Vec<_>>();
+        let current = self.revealed.as_ref().and_then(|selected| {
+            entries
+                .iter()
+                .position(|&i| &self.rows[i].indexes == selected)
+        });
+        let select = |this: &mut Self, entry: usize| {
+            if let Some(&i) = entries.get(entry) {
+                this.revealed = Some(this.rows[i].indexes.clone());
+                this.scroll_to_selection = true;
+            }
+        };
+        if up || down {
+            let next = match current {
+                None => 0,
+                Some(current) if up => current.saturating_sub(1),
+                Some(current) => current + 1,
+            };
+            select(self, next);
+            return false;
+        }
+        let Some(current) = current else {
+            return false;
+        };
+        let row = &self.rows[entries[current]];
+        let (indexes, is_expanded) = (row.indexes.clone(), row.is_expanded);
+        let can_expand = value_at(&file.parsed, &indexes).is_some_and(|value| {
+            is_expandable(value)
+                || value
+                    .as_str()
+                    .is_some_and(|text| display.is_cut_short(text))
+        });
+        if can_expand && (enter || (right && !is_expanded) || (left && is_expanded)) {
+            self.toggled = Some((indexes.clone(), !is_expanded));
+            self.set_expanded(indexes, !is_expanded);
+            return true;
+        }
+        if right && is_expanded {
+            let is_child = entries
+                .get(current + 1)
+                .is_some_and(|&i| self.rows[i].indexes.starts_with(&indexes));
+            if is_child {
+                select(self, current + 1);
+            }
+        } else if left && indexes.len() > 1 {
+            let parent = entries
+                .iter()
+                .rposition(|&i| self.rows[i].indexes == indexes[..indexes.len() - 1]);
+            if let Some(parent) = parent {
+                select(self, parent);
+            }
+        }
+        false
+    }
+
+    /// The rows as they're shown, as indented text to paste into a terminal or a ticket.
+    pub fn visible_text(&self, file: &LoadedFile, display: &DisplayOptions) -> String {
+        let mut text = String::new();
+        for row in &self.rows {
+            let Some(value) = value_at(&file.parsed, &row.indexes) else {
+                continue;
+            };
+            let depth = row.indexes.len();
+            let line = match (row.page, row.line, value) {
+                (Some(_), _, _) => format!("{}{}", "  ".repeat(depth), row.label),
+                (None, Some(line), rmpv::Value::Binary(bytes)) => {
+                    let format = self.binary_format(&row.indexes, display.binary_format);
+                    format!("{}{}", "  ".repeat(depth), format.format_line(bytes, line))
+                }
+                (None, Some(start), value) => {
+                    let string = value.as_str().unwrap_or_default();
+                    format!(
+                        "{}{}",
+                        "  ".repeat(depth),
+                        display.string_line(string, start)
+                    )
+                }
+                (None, None, _) => {
+                    let indent = "  ".repeat(depth - 1);
+                    match (describe_children(value), value) {
+                        (Some(children), _) => format!("{indent}{} {children}", row.label),
+                        (None, rmpv::Value::Binary(bytes)) => {
+                            let format = self.binary_format(&row.indexes, display.binary_format);
+                            format!("{indent}{}: {}", row.label, format.summarize(bytes))
+                        }
+                        (None, value) => format!(
+                            "{indent}{}: {}",
+                            row.label,
+                            crate::diff_view::summarize(Some(value), display)
+                        ),
+                    }
+                }
+            };
+            text.push_str(&line);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// How the binary value at `indexes` is shown, given how binary values are shown by default.
+    fn binary_format(&self, indexes: &[usize], default: BinaryFormat) -> BinaryFormat {
+        self.binary_formats.get(indexes).copied().unwrap_or(default)
+    }
+
+    /// Expands or collapses the container at `indexes` along with every container within it.
+    fn set_subtree_expanded(
+        &mut self,
+        value: &rmpv::Value,
+        indexes: &mut Vec<usize>,
+        expanded: bool,
+    ) {
+        if !is_container(value) {
+            return;
+        }
+        self.expanded.insert(indexes.clone(), expanded);
+        let mut i = 0;
+        while let Some(child) = child_of(value, i) {
+            indexes.push(i);
+            self.set_subtree_expanded(child, indexes, expanded);
+            indexes.pop();
+            i += 1;
+        }
+    }
+
+    fn build_rows(&mut self, file: &LoadedFile, display: &DisplayOptions) {
+        self.filtered = match self.key_filter.trim() {
+            "" => None,
+            filter => Some(FilteredEntries::find(&file.parsed, filter)),
+        };
+        let mut rows = Vec::new();
+        let mut indexes = Vec::new();
+        if file.is_multi_value() {
+            let values = file
+                .parsed
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            for (i, value) in values.iter().enumerate() {
+                if self.is_filtered_out(&[i]) {
+                    continue;
+                }
+                let label = match file.options.framing {
+                    Framing::MsgpackRpc => match describe_rpc_message(value) {
+                        Some(description) => format!("value {i}: {description}"),
+                        None => format!("value {i}"),
+                    },
+                    _ => format!("value {i}"),
+                };
+                indexes.push(i);
+                self.push_row(value, label, false, display, &mut indexes, &mut rows);
+                indexes.pop();
+            }
+        } else {
+            self.push_children(&file.parsed, display, &mut indexes, &mut rows);
+        }
+        self.rows = rows;
+    }
+
+    /// Whether the key filter hides the entry at `indexes`.
+    fn is_filtered_out(&self, indexes: &[usize]) -> bool {
+        self.filtered
+            .as_ref()
+            .is_some_and(|filtered| !filtered.keeps(indexes))
+    }
+
+    fn push_children(
+        &self,
+        value: &rmpv::Value,
+        display: &DisplayOptions,
+        indexes: &mut Vec<usize>,
+        rows: &mut Vec<Row>,
+    ) {
+        let (len, open_by_default) = match value {
+            rmpv::Value::Array(items) => (items.len(), false),
+            rmpv::Value::Map(entries) => (entries.len(), true),
+            _ => return,
+        };
+        let mut order = (0..len).collect::<Vec<_>>();
+        if let (rmpv::Value::Map(entries), true) = (value, display.sort_keys) {
+            order.sort_by(|&i, &j| compare_keys(&entries[i].0, &entries[j].0));
+        }
+        let key_counts = match value {
+            rmpv::Value::Map(entries) => key_counts(entries),
+            _ => Vec::new(),
+        };
+        // what the filter keeps of a big array is shown as it is, as there's usually not much left
+        let is_filtering = self.filtered.is_some();
+        if matches!(value, rmpv::Value::Array(_)) && len > Self::PAGE_SIZE && !is_filtering {
+            for page in 0..len.div_ceil(Self::PAGE_SIZE) {
+                let start = page * Self::PAGE_SIZE;
+                let end = (start + Self::PAGE_SIZE).min(len);
+                let key = (indexes.clone(), page);
+                let is_expanded = self.expanded_pages.contains(&key);
+                rows.push(Row {
+                    indexes: key.0,
+                    label: format!("[{start}–{}]", end - 1),
+                    is_expanded,
+                    line: None,
+                    holds_msgpack: false,
+                    duplicate_key: None,
+                    page: Some(page),
+                });
+                if is_expanded {
+                    for i in start..end {
+                        let child = child_of(value, i).expect("index is in range");
+                        indexes.push(i);
+                        self.push_row(child, format!("array[{i}]"), false, display, indexes, rows);
+                        indexes.pop();
+                    }
+                }
+            }
+            return;
+        }
+        for i in order {
+            let child = child_of(value, i).expect("index is in range");
+            indexes.push(i);
+            let is_filtered_out = self.is_filtered_out(indexes);
+            indexes.pop();
+            if is_filtered_out {
+                continue;
+            }
+            let label = match value {
+                rmpv::Value::Map(entries) => format!("map[{}]", entries[i].0),
+                _ => format!("array[{i}]"),
+            };
+            indexes.push(i);
+            let row = rows.len();
+            self.push_row(child, label, open_by_default, display, indexes, rows);
+            rows[row].duplicate_key = key_counts.get(i).copied().filter(|&count| count > 1);
+            indexes.pop();
+        }
+    }
+
+    fn push_row(
+        &self,
+        value: &rmpv::Value,
+        label: String,
+        open_by_default: bool,
+        display: &DisplayOptions,
+        indexes: &mut Vec<usize>,
+        rows: &mut Vec<Row>,
+    ) {
+        let by_hand = self.expanded.get(indexes.as_slice()).copied();
+        let is_expanded = match value {
+            // dumps can be huge, so they're only ever expanded by hand, like strings that are cut short
+            rmpv::Value::Binary(_) => by_hand.unwrap_or(false),
+            rmpv::Value::String(text) => {
+                by_hand.unwrap_or(false)
+                    && text.as_str().is_some_and(|text| display.is_cut_short(text))
+            }
+            // so that what the filter matched is in view
+            _ if self
+                .filtered
+                .as_ref()
+                .is_some_and(|filtered| filtered.holding_matches.contains(indexes.as_slice())) =>
+            {
+                by_hand.unwrap_or(true)
+            }
+            _ => {
+                is_container(value)
+                    && by_hand.unwrap_or_else(|| {
+                        self.expansion.is_expanded(indexes.len(), open_by_default)
+                    })
+            }
+        };
+        let holds_msgpack = match value {
+            rmpv::Value::Binary(bytes) if is_expanded => decode_msgpack(bytes).is_some(),
+            _ => false,
+        };
+        rows.push(Row {
+            indexes: indexes.clone(),
+            label,
+            is_expanded,
+            line: None,
+            holds_msgpack,
+            duplicate_key: None,
+            page: None,
+        });
+        match value {
+            rmpv::Value::Binary(bytes) if is_expanded => {
+                let format = self.binary_format(indexes, display.binary_format);
+                rows.extend((0..format.line_count(bytes.len())).map(|line| Row {
+                    indexes: indexes.clone(),
+                    label: String::new(),
+                    is_expanded: false,
+                    line: Some(line),
+                    holds_msgpack: false,
+                    duplicate_key: None,
+                    page: None,
+                }));
+            }
+            rmpv::Value::String(text) if is_expanded => {
+                let text = text.as_str().expect("only valid strings are expanded");
+                rows.extend(
+                    display
+                        .string_line_starts(text)
+                        .into_iter()
+                        .map(|start| Row {
+                            indexes: indexes.clone(),
+                            label: String::new(),
+                            is_expanded: false,
+                            line: Some(start),
+                            holds_msgpack: false,
+                            duplicate_key: None,
+                            page: None,
+                        }),
+                );
+            }
+            _ if is_expanded => self.push_children(value, display, indexes, rows),
+            _ => {}
+        }
+    }
+}
+
+/// Shows a binary value in `format`, with a badge for picking how to show it instead, returning
+/// what was picked: a format, or `None` to go back to the default.
+fn show_binary(
+    ui: &mut egui::Ui,
+    bytes: &[u8],
+    format: BinaryFormat,
+) -> Option<Option<BinaryFormat>> {
+    ui.label(format.summarize(bytes));
+    let mut picked = None;
+    ui.menu_button(format!("shown as {}", format.name_for(bytes)), |ui| {
+        for option in BinaryFormat::ALL {
+            if ui
+                .selectable_label(option == format, option.to_string())
+                .clicked()
+            {
+                picked = Some(Some(option));
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        if ui.button("As set for all binary values").clicked() {
+            picked = Some(None);
+            ui.close_menu();
+        }
+    })
+    .response
+    .on_hover_text(format!("{} bytes of binary data", bytes.len()));
+    picked
+}
+
+fn is_container(value: &rmpv::Value) -> bool {
+    matches!(value, rmpv::Value::Array(_) | rmpv::Value::Map(_))
+}
+
+/// Whether the value's row has a button to show more of it below, for binary values their dump.
+fn is_expandable(value: &rmpv::Value) -> bool {
+    is_container(value) || matches!(value, rmpv::Value::Binary(_))
+}
+
+fn value_at<'a>(root: &'a rmpv::Value, indexes: &[usize]) -> Option<&'a rmpv::Value> {
+    indexes
+        .iter()
+        .try_fold(root, |value, &index| child_of(value, index))
+}
+
+/// The path of the value at `indexes` within `root`, or of as much of the way there as there is.
+pub fn path_of(root: &rmpv::Value, indexes: &[usize]) -> ValuePath {
+    let mut value = root;
+    let mut path = ValuePath::root();
+    for &index in indexes {
+        let (Some(segment), Some(child)) = (segment_of(value, index), child_of(value, index))
+        else {
+            break;
+        };
+        path.push(segment);
+        value = child;
+    }
+    path
+}
+
+/// The map entry holding the value at `indexes`, if its parent is a map.
+fn parent_map_entry<'a>(
+    root: &'a rmpv::Value,
+    indexes: &[usize],
+) -> Option<&'a (rmpv::Value, rmpv::Value)> {
+    let (&last, parent) = indexes.split_last()?;
+    value_at(root, parent)?.as_map()?.get(last)
+}
+
+/// Shows a warning next to an entry whose key occurs `count` times in its map, if it's duplicated.
+pub fn show_duplicate_key(ui: &mut egui::Ui, count: Option<usize>) {
+    if let Some(count) = count {
+        ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+            .on_hover_text(format!(
+            "This key occurs {count} times in the map, which is almost always a bug in whatever \
+                 encoded it"
+        ));
+    }
+}
+
+/// [`count_values`] of the value at `indexes`, kept in egui's memory while it's asked for as it means
+/// going through all of a possibly huge value.
+fn count_values_within(ctx: &egui::Context, file: &LoadedFile, indexes: &[usize]) -> usize {
+    let id = egui::Id::new(("value count", file.load_time.as_nanosecond(), indexes));
+    ctx.data_mut(|data| {
+        *data.get_temp_mut_or_insert_with(id, || {
+            value_at(&file.parsed, indexes).map_or(0, count_values)
+        })
+    })
+}
+
+/// [`LoadedFile::describe_span`], kept in egui's memory while it's asked for as finding the span can
+/// mean skipping over most of a big file.
+pub fn describe_span(ctx: &egui::Context, file: &LoadedFile, indexes: &[usize]) -> String {
+    let id = egui::Id::new(("span", file.load_time.as_nanosecond(), indexes));
+    ctx.data_mut(|data| {
+        data.get_temp_mut_or_insert_with(id, || file.describe_span(indexes))
+            .clone()
+    })
+}
@@ -0,0 +1,53 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use notify::Watcher as _;
+
+/// Watches a single file for changes on disk.
+///
+/// The parent directory is watched rather than the file itself, so that files which get replaced
+/// (rather than rewritten in place) by whatever produces them keep being picked up.
+pub struct FileWatcher {
+    path: PathBuf,
+    _watcher: notify::RecommendedWatcher,
+    changes: mpsc::Receiver<()>,
+}
+
+impl FileWatcher {
+    /// Starts watching `path`; `on_change` is called from the watcher's thread whenever it changes.
+    pub fn new(path: &Path, on_change: impl Fn() + Send + 'static) -> notify::Result<Self> {
+        let path = std::path::absolute(path)?;
+        let (sender, changes) = mpsc::channel();
+        let watched_path = path.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) => {
+                    let relevant = !event.kind.is_access() && event.paths.contains(&watched_path);
+                    if relevant && sender.send(()).is_ok() {
+                        on_change();
+                    }
+                }
+                Err(err) => log::warn!("Error watching {}: {err}", watched_path.display()),
+            })?;
+        let directory = path.parent().unwrap_or(&path);
+        watcher.watch(directory, notify::RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            path,
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file has changed since the last call.
+    pub fn has_changed(&self) -> bool {
+        let mut changed = false;
+        while self.changes.try_recv().is_ok() {
+            changed = true;
+        }
+        changed
+    }
+}